@@ -1,13 +1,19 @@
-use std::str::FromStr;
+use std::path::PathBuf;
 
-use bitcoin::{
-    key::{Keypair, Secp256k1},
-    secp256k1::SecretKey,
-};
 use clap::Parser;
 use ernest_oracle::{
-    mempool::MempoolClient, oracle::ErnestOracle, parlay, storage::PostgresStorage,
+    attestation::{self, AttestationDataOutcome},
+    audit::{self, SigningSource},
+    config::OracleConfig,
+    consistency, digits,
+    import::ImportResult,
+    keys, migrations, nostr,
+    oracle::{score_parlay_contract, ErnestOracle},
+    recovery::{self, NonceStatus},
+    storage::PostgresStorage,
+    triggers,
 };
+use kormir::storage::{OracleEventData, Storage};
 use sqlx::PgPool;
 
 #[derive(Debug, Clone, Parser)]
@@ -24,18 +30,90 @@ struct OracleAdminArgs {
     #[clap(short, long)]
     #[clap(default_value = "34d95a073eee38ecb968a0da8273926cda601802541a715c011fb340dd6d1706")]
     key: String,
+    /// BIP39 mnemonic to derive the oracle key from instead of `--key`
+    #[clap(long)]
+    mnemonic: Option<String>,
+    #[clap(long, default_value = keys::DEFAULT_DERIVATION_PATH)]
+    derivation_path: String,
+    /// Mempool API to read from, the `mempool` section of the config when unset
     #[clap(short, long)]
-    #[clap(default_value = "https://mempool.space/api")]
-    mempool: String,
+    mempool: Option<String>,
     #[clap(subcommand)]
     pub command: AdminCommand,
 }
 
 #[derive(Debug, Clone, Parser)]
 enum AdminCommand {
+    /// Sign a matured parlay with current data, as the watcher would, or with the outcome of
+    /// each leg entered by hand. Events that are not due or on hold are refused like the
+    /// watcher refuses them.
     SignEvent {
         event_id: String,
+        /// Prompt for the outcome of each leg instead of fetching it
+        #[clap(long)]
+        manual: bool,
+        /// Print the scores without signing
+        #[clap(long)]
+        dry_run: bool,
+    },
+    /// Check the database for drift, e.g. missing nonces or signatures that do not verify.
+    Verify {
+        /// Fix the issues that can be derived from other rows
+        #[clap(long)]
+        repair: bool,
+    },
+    /// Keep the watcher from signing an event, e.g. while investigating a data anomaly.
+    Hold {
+        event_id: String,
+        #[clap(long)]
+        reason: Option<String>,
+    },
+    /// Let the watcher sign a held event again.
+    Release { event_id: String },
+    /// Leave a test or mistaken event out of the public listings and search. Its announcement
+    /// and attestation are still served by id.
+    Hide { event_id: String },
+    /// List a hidden event again.
+    Unhide { event_id: String },
+    /// Cancel an unsigned event, deleting it with everything stored for it.
+    DeleteEvent { event_id: String },
+    /// Re-verify every stored attestation signature against its announced nonce and the oracle
+    /// key.
+    VerifySignatures,
+    /// Import a JSON array of events exported from another kormir oracle with the same key.
+    Import { file: PathBuf },
+    /// Check every stored nonce derives from the oracle key and find the indexes of lost ones.
+    /// Stop the oracle before repairing, it would hand out the lost indexes again.
+    RecoverNonces {
+        /// Indexes past the highest stored one to search for lost nonces
+        #[clap(long, default_value_t = recovery::DEFAULT_SCAN_MARGIN)]
+        scan_margin: u32,
+        /// Restore the nonce rows that were found
+        #[clap(long)]
+        repair: bool,
+    },
+    /// Publish the announcement and attestation of events to nostr relays again.
+    Rebroadcast {
+        event_ids: Vec<String>,
+        /// Every announced event, e.g. to backfill a new relay
+        #[clap(long)]
+        all: bool,
+        /// Relay to publish to, the relays of the config file when unset
+        #[clap(long = "relay")]
+        relays: Vec<String>,
     },
+    /// Check the hash chain of the signing audit log.
+    VerifySigningAudit,
+    /// Generate a new BIP39 mnemonic for the oracle key.
+    GenerateMnemonic,
+    /// Apply pending database migrations.
+    Migrate,
+    /// List database migrations and whether they are applied.
+    MigrationStatus,
+    /// Have a running oracle sign a matured event now instead of on its next tick.
+    SignNow { event_id: String },
+    /// Decode the value a signed event attests to.
+    Outcome { event_id: String },
     Events {
         #[clap(long)]
         id: Option<String>,
@@ -44,62 +122,318 @@ enum AdminCommand {
     },
 }
 
+fn print_scores(
+    outcomes: &[AttestationDataOutcome],
+    combined_score: f64,
+    attested_value: u64,
+    clamped: bool,
+) {
+    for outcome in outcomes {
+        println!(
+            "value for {}:\t {:?}",
+            outcome.data_type, outcome.original_value
+        );
+        println!(
+            "normalized value for {}:\t {:?}",
+            outcome.data_type, outcome.normalized_value
+        );
+        println!(
+            "transformed value for {}:\t {:?}",
+            outcome.data_type, outcome.transformed_value
+        );
+    }
+    println!("\n\tcombined score:\t {:?}", combined_score);
+    println!("\tattested value:\t {:?}", attested_value);
+    if clamped {
+        println!("\tscore was outside the announced range and has been clamped");
+    }
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let args = OracleAdminArgs::parse();
+    if let AdminCommand::GenerateMnemonic = args.command {
+        let mnemonic = keys::generate_mnemonic()?;
+        let key_pair = keys::keypair_from_mnemonic(&mnemonic.to_string(), &args.derivation_path)?;
+        println!("{}", mnemonic);
+        println!(
+            "public key: {} ({})",
+            key_pair.x_only_public_key().0,
+            args.derivation_path
+        );
+        return Ok(());
+    }
+
     let pool = PgPool::connect(&args.db).await?;
-    let secp = Secp256k1::new();
-    let secret_key = SecretKey::from_str(&args.key)?;
-    let key_pair = Keypair::from_secret_key(&secp, &secret_key);
+    let key_pair = match &args.mnemonic {
+        Some(mnemonic) => keys::keypair_from_mnemonic(mnemonic, &args.derivation_path)?,
+        None => keys::keypair_from_hex(&args.key)?,
+    };
     let pubkey = key_pair.x_only_public_key();
 
-    let storage = PostgresStorage::new(pool.clone(), pubkey.0, true).await?;
-    let mempool = MempoolClient::new(args.mempool);
-    let oracle = ErnestOracle::new(storage, pool.clone(), key_pair, mempool.clone())?;
+    match args.command {
+        AdminCommand::Migrate => {
+            migrations::run(&pool).await?;
+            println!("Database is up to date");
+            return Ok(());
+        }
+        AdminCommand::MigrationStatus => {
+            for migration in migrations::status(&pool).await? {
+                let state = if migration.applied {
+                    "applied"
+                } else if migration.failed {
+                    "failed"
+                } else {
+                    "pending"
+                };
+                println!(
+                    "{}\t{}\t{}",
+                    migration.version, state, migration.description
+                );
+            }
+            return Ok(());
+        }
+        _ => {}
+    }
+
+    let storage = PostgresStorage::new(pool.clone(), pubkey.0, false).await?;
+    let config = OracleConfig::from_env()?;
+    let mut mempool_config = config.mempool;
+    if args.mempool.is_some() {
+        mempool_config.base_url = args.mempool;
+    }
+    let mempool = mempool_config.client()?;
+    let oracle = ErnestOracle::new(storage, pool.clone(), key_pair, mempool.clone())?
+        .with_network(mempool_config.network)?
+        .with_settlement_delay(config.settlement_delay)?
+        .with_event_id_scheme(config.event_ids)
+        .with_sampling_policy(config.sampling)
+        .with_quorum(config.quorum)?;
 
     match args.command {
-        AdminCommand::SignEvent { event_id } => {
-            let contract = parlay::contract::get_parlay_contract(pool, event_id.clone()).await?;
-            let outcomes = contract
-                .parameters
-                .iter()
-                .map(|parameter| {
-                    println!(
-                        "{}",
-                        serde_json::to_string_pretty(&parameter)
-                            .expect("Could not serialize parameter")
-                    );
-                    let outcome =
-                        inquire::prompt_f64(format!("Enter outcome for {}", parameter.data_type))
-                            .expect("Could not prompt for outcome");
-                    let normalized_value = parameter.normalize_parameter(outcome);
-                    println!(
-                        "normalized value for {:?}:\t {:?}",
-                        parameter.data_type, normalized_value
-                    );
-                    let transformed_value = parameter.apply_transformation(normalized_value);
-                    println!(
-                        "transformed value for {:?}:\t {:?}",
-                        parameter.data_type, transformed_value
-                    );
-                    transformed_value
-                })
-                .collect::<Vec<_>>();
+        AdminCommand::SignEvent {
+            event_id,
+            manual,
+            dry_run,
+        } => {
+            let contract = oracle.get_parlay_contract(event_id.clone()).await?;
+            let evaluation = if manual {
+                let mut values = Vec::with_capacity(contract.parameters.len());
+                for parameter in &contract.parameters {
+                    println!("{}", serde_json::to_string_pretty(&parameter)?);
+                    values.push(inquire::prompt_f64(format!(
+                        "Enter outcome for {}",
+                        parameter.data_type
+                    ))?);
+                }
+                score_parlay_contract(&contract, &values)
+            } else {
+                oracle.evaluate_parlay_contract(&contract).await?
+            };
+            print_scores(
+                &evaluation.outcomes,
+                evaluation.combined_score,
+                evaluation.attestable_value.value,
+                evaluation.attestable_value.clamped,
+            );
+            if dry_run {
+                println!("\n\tDry run, event {:?} was not signed", event_id);
+                return Ok(());
+            }
+            oracle.ensure_due(&event_id).await?;
+            let prompt = match manual {
+                true => "Sign the event with the entered outcomes?",
+                false => "Sign the event with current data?",
+            };
+            if !inquire::prompt_confirmation(prompt)? {
+                return Ok(());
+            }
 
-            let combined_score =
-                parlay::contract::combine_scores(&outcomes, &contract.combination_method);
-            println!("\n\tcombined score:\t {:?}", combined_score);
-            let attestable_value = parlay::contract::convert_to_attestable_value(
-                combined_score,
-                contract.max_normalized_value,
+            let Some(lock) = oracle.lock_unsigned_event(&event_id).await? else {
+                return Err(anyhow::anyhow!(
+                    "Event is already signed or being signed. event_id={}",
+                    event_id
+                ));
+            };
+            let result = match manual {
+                true => {
+                    oracle
+                        .sign_parlay_evaluation(event_id.clone(), evaluation, SigningSource::Admin)
+                        .await
+                }
+                false => {
+                    oracle
+                        .attest_parlay_contract(event_id.clone(), SigningSource::Admin)
+                        .await
+                }
+            };
+            lock.release().await?;
+            result?;
+            // Live data may have moved since the preview, show what was signed
+            let outcome = attestation::get_attestation_outcome(&pool, event_id.clone()).await?;
+            print_scores(
+                &outcome.outcomes,
+                outcome.combined_score,
+                outcome.attested_value as u64,
+                outcome.clamped,
             );
-            println!("\tattested value:\t {:?}", attestable_value);
-            oracle
-                .oracle
-                .sign_numeric_event(event_id.clone(), attestable_value as i64)
-                .await?;
             println!("\n\tSigned event {:?}", event_id);
         }
+        AdminCommand::Migrate | AdminCommand::MigrationStatus | AdminCommand::GenerateMnemonic => {
+            unreachable!()
+        }
+        AdminCommand::SignNow { event_id } => {
+            triggers::request_signing(&pool, &event_id).await?;
+            println!("Requested signing of event {:?}", event_id);
+        }
+        AdminCommand::Outcome { event_id } => {
+            let Some(event) = oracle.oracle.storage.get_event(event_id.clone()).await? else {
+                return Err(anyhow::anyhow!("Event not found. event_id={}", event_id));
+            };
+            let Some(attestation) = event.attestation() else {
+                return Err(anyhow::anyhow!(
+                    "Event is not signed. event_id={}",
+                    event_id
+                ));
+            };
+            let outcome = digits::decode_attestation(&event.announcement, &attestation)?;
+            println!("\tdigits:\t\t {}", attestation.outcomes.concat());
+            println!("\tvalue:\t\t {}", outcome.value);
+            println!("\tscaled value:\t {}", outcome.scaled());
+        }
+        AdminCommand::Import { file } => {
+            let events: Vec<OracleEventData> =
+                serde_json::from_str(&std::fs::read_to_string(file)?)?;
+            let (mut imported, mut existing, mut failed) = (0, 0, 0);
+            for event in &events {
+                match oracle.import_event(event).await {
+                    Ok(ImportResult::Imported) => imported += 1,
+                    Ok(ImportResult::AlreadyExists) => existing += 1,
+                    Err(e) => {
+                        failed += 1;
+                        println!("Could not import event. error={}", e);
+                    }
+                }
+            }
+            println!(
+                "Imported {} event(s), {} already existed, {} failed",
+                imported, existing, failed
+            );
+        }
+        AdminCommand::RecoverNonces {
+            scan_margin,
+            repair,
+        } => {
+            let reports = recovery::audit(&oracle, scan_margin).await?;
+            let issues = reports
+                .into_iter()
+                .filter(|report| report.status != NonceStatus::Consistent)
+                .collect::<Vec<_>>();
+            for report in &issues {
+                println!("{}", serde_json::to_string(report)?);
+            }
+            println!("Found {} event(s) with nonce issues", issues.len());
+            if repair {
+                let repair = recovery::repair(&oracle, &issues).await?;
+                println!("Restored the nonces of {} event(s)", repair.restored);
+                for event_id in &repair.held {
+                    println!(
+                        "Held {}: {}",
+                        event_id,
+                        recovery::UNKNOWN_OUTCOME_HOLD_REASON
+                    );
+                }
+            }
+        }
+        AdminCommand::Rebroadcast {
+            event_ids,
+            all,
+            relays,
+        } => {
+            let relays = if relays.is_empty() {
+                OracleConfig::from_env()?.nostr.relays
+            } else {
+                relays
+            };
+            let event_ids = if all {
+                oracle
+                    .oracle
+                    .storage
+                    .oracle_event_data()
+                    .await?
+                    .into_iter()
+                    .map(|event| event.event_id)
+                    .collect()
+            } else {
+                event_ids
+            };
+            for event_id in event_ids {
+                match nostr::rebroadcast(&oracle, &relays, &event_id).await {
+                    Ok(rebroadcast) => println!("{}", serde_json::to_string(&rebroadcast)?),
+                    Err(e) => println!(
+                        "Could not rebroadcast event. event_id={} error={}",
+                        event_id, e
+                    ),
+                }
+            }
+        }
+        AdminCommand::Hold { event_id, reason } => {
+            oracle.set_hold(&event_id, true, reason.as_deref()).await?;
+            println!("Holding event {:?}", event_id);
+        }
+        AdminCommand::Release { event_id } => {
+            oracle.set_hold(&event_id, false, None).await?;
+            println!("Released event {:?}", event_id);
+        }
+        AdminCommand::Hide { event_id } => {
+            oracle.set_hidden(&event_id, true).await?;
+            println!("Hid event {:?}", event_id);
+        }
+        AdminCommand::Unhide { event_id } => {
+            oracle.set_hidden(&event_id, false).await?;
+            println!("Unhid event {:?}", event_id);
+        }
+        AdminCommand::DeleteEvent { event_id } => {
+            // Holding the signing lock keeps the watcher from signing it in the meantime
+            let Some(lock) = oracle.lock_unsigned_event(&event_id).await? else {
+                return Err(anyhow::anyhow!(
+                    "Event is signed or being signed. event_id={}",
+                    event_id
+                ));
+            };
+            if oracle.oracle.storage.delete_event(&event_id).await? {
+                println!("Deleted event {:?}", event_id);
+            } else {
+                println!("Event not found");
+            }
+            lock.release().await?;
+        }
+        AdminCommand::VerifySignatures => {
+            let report = consistency::verify_signatures(&pool, pubkey.0).await?;
+            for corrupt in &report.corrupted {
+                println!("{}", serde_json::to_string(corrupt)?);
+            }
+            println!(
+                "Verified {} signature(s), {} corrupted",
+                report.checked,
+                report.corrupted.len()
+            );
+        }
+        AdminCommand::VerifySigningAudit => match audit::verify(&pool).await? {
+            Some(id) => println!("Signing audit chain is broken at entry {}", id),
+            None => println!("Signing audit chain is intact"),
+        },
+        AdminCommand::Verify { repair } => {
+            let issues = consistency::check(&pool, pubkey.0).await?;
+            for issue in &issues {
+                println!("{}", serde_json::to_string(issue)?);
+            }
+            println!("Found {} issue(s)", issues.len());
+            if repair {
+                let repaired = consistency::repair(&pool, &issues).await?;
+                println!("Repaired {} issue(s)", repaired);
+            }
+        }
         AdminCommand::Events { id, event_type } => {
             let events = oracle.list_events_with_types(&event_type).await?;
             if let Some(id) = id {
@@ -1,37 +1,79 @@
 use axum::{
     debug_handler,
-    extract::{Query, State},
-    http::StatusCode,
-    response::{Html, IntoResponse},
+    extract::Request,
+    extract::{Path, Query, State},
+    http::{
+        header::{AUTHORIZATION, USER_AGENT},
+        HeaderMap, HeaderValue, StatusCode,
+    },
+    middleware::{self, Next},
+    response::{Html, Response},
     routing::{get, post},
     Json, Router,
 };
-use bitcoin::{
-    key::{Keypair, Secp256k1},
-    secp256k1::SecretKey,
+use ernest_oracle::access_log::{self, AccessLogEntry, REQUEST_ID_HEADER};
+use ernest_oracle::alerts::Alerter;
+use ernest_oracle::attestation::{AttestationProvenance, ErnestOracleOutcome};
+use ernest_oracle::audit::SigningAuditEntry;
+use ernest_oracle::bundle::AnnouncementBundle;
+use ernest_oracle::compat::{
+    self, CompatAnnouncement, CompatAttestation, CompatError, CompatResponse, ExportedEvent,
 };
-use ernest_oracle::attestation::ErnestOracleOutcome;
+use ernest_oracle::config::OracleConfig;
+use ernest_oracle::creation_receipts::CreationReceipt;
+use ernest_oracle::history::MetricHistory;
+use ernest_oracle::limits::API_KEY_HEADER;
+use ernest_oracle::mock_data::{MockData, MockDataConfig};
+use ernest_oracle::nostr::Rebroadcast;
+use ernest_oracle::notifications::Notifier;
+use ernest_oracle::overrides::{CommitOverride, PrepareOverride, PreparedOverride};
+use ernest_oracle::parlay::backtest::{Backtest, BacktestRequest};
+use ernest_oracle::parlay::contract::ParlayContract;
+use ernest_oracle::parlay::estimate::Estimate;
 use ernest_oracle::routes;
+use ernest_oracle::series::{CreateSeries, SeriesManifest};
+use ernest_oracle::stats::OracleStats;
 use ernest_oracle::storage::PostgresStorage;
-use ernest_oracle::{events::EventType, oracle::ErnestOracle};
+use ernest_oracle::usage::{QuotaExceeded, Usage};
+use ernest_oracle::watcher::{MaturitySchedule, WatcherHealth, WatcherHealthReport};
 use ernest_oracle::{
-    mempool::{MempoolClient, BASE_URL},
-    parlay::contract::ParlayContract,
+    events::{EventType, EventTypeMetadata},
+    oracle::{ErnestOracle, PendingEvent},
 };
-use ernest_oracle::{OracleServerError, OracleServerState};
-use kormir::{storage::OracleEventData, OracleAnnouncement, OracleAttestation};
+use ernest_oracle::{ErrorCode, OracleServerError, OracleServerState};
+use kormir::{OracleAnnouncement, OracleAttestation};
 use log::LevelFilter;
 use sqlx::PgPool;
-use std::{str::FromStr, sync::Arc};
+use std::io::Write;
+use std::sync::Arc;
+use std::time::Instant;
 use tokio::{signal, sync::watch};
+use tower_http::compression::CompressionLayer;
 
 pub const PORT: u16 = 3001;
 
+/// Sign generated values, see [`ernest_oracle::mock_data`].
+pub const MOCK_DATA_FLAG: &str = "--mock-data";
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     dotenv::dotenv()?;
     env_logger::Builder::new()
         .filter_level(LevelFilter::Info)
+        .format(|buf, record| {
+            let request_id = access_log::current_request_id()
+                .map(|request_id| format!(" request_id={}", request_id))
+                .unwrap_or_default();
+            writeln!(
+                buf,
+                "[{} {} {}{}] {}",
+                buf.timestamp(),
+                record.level(),
+                record.target(),
+                request_id,
+                record.args()
+            )
+        })
         .init();
     log::info!("Starting Ernest Hashrate Oracle");
 
@@ -39,40 +81,155 @@ async fn main() -> anyhow::Result<()> {
 
     let pg_url = std::env::var("DATABASE_URL")?;
     let pool = PgPool::connect(&pg_url).await?;
-    let secp = Secp256k1::new();
-    let kormir_key = std::env::var("ERNEST_KEY")?;
-    let secret_key = SecretKey::from_str(&kormir_key)?;
-    let key_pair = Keypair::from_secret_key(&secp, &secret_key);
+    let mut config = OracleConfig::from_env()?;
+    if std::env::args().any(|arg| arg == MOCK_DATA_FLAG) {
+        config.mock_data.get_or_insert_with(MockDataConfig::default);
+    }
+    let key_pair = config.load_keypair().await?;
     let pubkey = key_pair.x_only_public_key();
 
-    let storage = PostgresStorage::new(pool.clone(), pubkey.0, true).await?;
-    let mempool = MempoolClient::new(BASE_URL.to_string());
-    let oracle = ErnestOracle::new(storage, pool, key_pair, mempool.clone())?;
+    let mempool = match config.mock_data.clone() {
+        Some(mock_data) => {
+            ernest_oracle::mock_data::ensure_throwaway(&pool, config.mempool.network).await?;
+            log::warn!("Serving mock data, attestations do not reflect the bitcoin network.");
+            config
+                .mempool
+                .client()?
+                .with_mock_data(MockData::new(mock_data))
+        }
+        None => config.mempool.client()?,
+    };
+    ernest_oracle::self_test::run(&pool, pubkey.0, &mempool).await?;
+    if config.verify_signatures_on_startup {
+        let report = ernest_oracle::consistency::verify_signatures(&pool, pubkey.0).await?;
+        for corrupt in &report.corrupted {
+            log::error!(
+                "Stored signature does not verify. event_id={} index={} fault={:?}",
+                corrupt.event_id,
+                corrupt.index,
+                corrupt.fault
+            );
+        }
+        log::info!(
+            "Verified stored signatures. checked={} corrupted={}",
+            report.checked,
+            report.corrupted.len()
+        );
+    }
+    let storage = PostgresStorage::new(pool.clone(), pubkey.0, false).await?;
+    let oracle = ErnestOracle::new(storage, pool, key_pair, mempool.clone())?
+        .with_network(config.mempool.network)?
+        .with_settlement_delay(config.settlement_delay)?
+        .with_event_id_scheme(config.event_ids)
+        .with_sampling_policy(config.sampling)
+        .with_quorum(config.quorum)?;
 
-    let state = Arc::new(OracleServerState { oracle, mempool });
+    let state = Arc::new(OracleServerState {
+        oracle,
+        mempool,
+        watcher: WatcherHealth::default(),
+        schedule: MaturitySchedule::default(),
+        notifier: Notifier::new(config.notifications),
+        alerts: Alerter::new(config.alerts),
+        nostr: config.nostr,
+        limits: config.limits,
+        quotas: config.quotas,
+        admin_token: config.admin_token,
+    });
 
     let state_clone = state.clone();
     let (stop_signal_sender, stop_signal) = watch::channel(false);
     tokio::spawn(async move {
         ernest_oracle::watcher::sign_matured_events_loop(state_clone, stop_signal.clone()).await;
     });
+    let state_clone = state.clone();
+    let outbox_stop_signal = stop_signal_sender.subscribe();
+    tokio::spawn(async move {
+        ernest_oracle::outbox::deliver_loop(state_clone, outbox_stop_signal).await;
+    });
+    let state_clone = state.clone();
+    let history_stop_signal = stop_signal_sender.subscribe();
+    tokio::spawn(async move {
+        ernest_oracle::history::sample_metrics_loop(state_clone, history_stop_signal).await;
+    });
 
-    let app = Router::new()
+    let read_only = config.read_only;
+    let series = if read_only {
+        get(get_series)
+    } else {
+        get(get_series).post(create_series)
+    };
+    let mut api = Router::new()
+        .route("/", get(hello))
+        .route("/info", get(oracle_info))
+        .route("/status", get(get_status))
+        .route("/list-events", get(list_events))
+        .route("/series", series)
+        .route("/announcement", get(get_announcement_event))
+        .route("/announcement/:event_id", get(get_announcement_by_path))
+        .route("/bundle", get(get_bundle))
+        .route("/creation-receipt", get(get_creation_receipt))
+        .route("/event", get(get_event_detail))
+        .route("/attestation", get(get_attestation))
+        .route("/attestation/outcome", get(get_attestation_outcome))
+        .route("/attestation/:event_id", get(get_attestation_by_path))
+        .route("/outcome/preview", get(preview_outcome))
+        .route("/export", get(export_event))
+        .route("/parlay", get(get_parlay_contract))
+        .route("/parlay/legs", get(get_parlay_legs))
+        .route("/parlay/oracle-params", get(get_oracle_params))
+        .route("/parlay/options", get(get_parlay_options))
+        .route("/parlay/backtest", post(backtest_parlay))
+        .route("/parlay/estimate", get(estimate_parlay))
+        .route("/events/available", get(get_available_events))
+        .route("/events/metadata", get(get_events_metadata))
+        .route("/events/pending", get(get_pending_events))
+        .route("/history", get(get_history))
+        .route("/events/search", get(search_events))
+        .route("/stats", get(get_stats))
+        .route("/usage", get(get_usage))
+        .route("/provenance", get(get_provenance));
+    let mut admin = Router::new().route("/signing-audit", get(get_signing_audit));
+    if read_only {
+        log::info!("Serving read-only, creating and signing routes are disabled");
+    } else {
+        api = api
+            .route("/create", post(create_event))
+            .route("/sign-event", post(sign_event))
+            .route("/sign-now", post(request_signing));
+        admin = admin
+            .route("/nostr/rebroadcast", post(rebroadcast_event))
+            .route("/override/prepare", post(prepare_override))
+            .route("/override/commit", post(commit_override))
+            .route("/hold", post(hold_event))
+            .route("/hide", post(hide_event));
+    }
+    let api = api.nest(
+        "/admin",
+        admin.route_layer(middleware::from_fn_with_state(state.clone(), require_admin)),
+    );
+
+    let mut app = Router::new()
+        .nest("/api/v1", api.clone())
+        .nest("/api", api)
         .nest(
-            "/api",
+            "/v1",
             Router::new()
-                .route("/", get(hello))
-                .route("/info", get(oracle_info))
-                .route("/list-events", get(list_events))
-                .route("/create", post(create_event))
-                .route("/announcement", get(get_announcement_event))
-                .route("/attestation", get(get_attestation))
-                .route("/attestation/outcome", get(get_attestation_outcome))
-                .route("/sign-event", post(sign_event))
-                .route("/parlay", get(get_parlay_contract))
-                .route("/events/available", get(get_available_events)),
+                .route("/publickey", get(compat_public_key))
+                .route("/announcements", get(compat_list_announcements))
+                .route("/announcements/:event_id", get(compat_announcement))
+                .route("/attestations/:event_id", get(compat_attestation)),
         )
-        .with_state(state);
+        .with_state(state.clone());
+    // Counting requests writes to the database, which a read-only mirror may not allow
+    if !read_only {
+        app = app.route_layer(middleware::from_fn_with_state(state, count_request));
+    }
+    let app = app
+        .layer(middleware::from_fn(negotiate_api_version))
+        // Event listings with their nonces compress well
+        .layer(CompressionLayer::new())
+        .layer(middleware::from_fn(track_request));
 
     let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", port))
         .await
@@ -124,17 +281,23 @@ async fn hello() -> Html<&'static str> {
 #[axum::debug_handler]
 async fn create_event(
     State(state): State<Arc<OracleServerState>>,
+    headers: HeaderMap,
     Json(event): Json<routes::CreateEvent>,
-) -> Result<Json<OracleAnnouncement>, (StatusCode, Json<OracleServerError>)> {
+) -> Result<Json<routes::CreatedEvent>, (StatusCode, Json<OracleServerError>)> {
     log::info!("Creating event {:?}", event);
-    match routes::create_event_internal(state, event).await {
+    match routes::create_event_internal(state, event, api_key(&headers)).await {
         Ok(event) => Ok(Json(event)),
-        Err(e) => Err((
-            StatusCode::BAD_REQUEST,
-            Json(OracleServerError {
-                reason: e.to_string(),
-            }),
-        )),
+        Err(e) => Err(error_response(e)),
+    }
+}
+
+async fn get_creation_receipt(
+    State(state): State<Arc<OracleServerState>>,
+    query: Query<routes::GetCreationReceipt>,
+) -> Result<Json<CreationReceipt>, (StatusCode, Json<OracleServerError>)> {
+    match routes::get_creation_receipt_internal(state, query.0).await {
+        Ok(receipt) => Ok(Json(receipt)),
+        Err(e) => Err(error_response(e)),
     }
 }
 
@@ -144,12 +307,25 @@ async fn get_announcement_event(
 ) -> Result<Json<OracleAnnouncement>, (StatusCode, Json<OracleServerError>)> {
     match routes::get_announcement_internal(state, event.0).await {
         Ok(event) => Ok(Json(event)),
-        Err(e) => Err((
-            StatusCode::BAD_REQUEST,
-            Json(OracleServerError {
-                reason: e.reason.to_string(),
-            }),
-        )),
+        Err(e) => Err(error_response(e)),
+    }
+}
+
+/// `/announcement/:event_id`, for clients built around path parameters.
+async fn get_announcement_by_path(
+    state: State<Arc<OracleServerState>>,
+    Path(event_id): Path<String>,
+) -> Result<Json<OracleAnnouncement>, (StatusCode, Json<OracleServerError>)> {
+    get_announcement_event(state, Query(routes::GetAnnouncement { event_id })).await
+}
+
+async fn get_bundle(
+    State(state): State<Arc<OracleServerState>>,
+    query: Query<routes::GetBundle>,
+) -> Result<Json<AnnouncementBundle>, (StatusCode, Json<OracleServerError>)> {
+    match routes::get_bundle_internal(state, query.0).await {
+        Ok(bundle) => Ok(Json(bundle)),
+        Err(e) => Err(error_response(e)),
     }
 }
 
@@ -159,45 +335,68 @@ async fn get_attestation(
 ) -> Result<Json<OracleAttestation>, (StatusCode, Json<OracleServerError>)> {
     match routes::get_attestation_internal(state, event.0).await {
         Ok(attestation) => Ok(Json(attestation)),
-        Err(e) => Err((
-            StatusCode::BAD_REQUEST,
-            Json(OracleServerError {
-                reason: e.to_string(),
-            }),
-        )),
+        Err(e) => Err(error_response(e)),
     }
 }
 
+/// `/attestation/:event_id`, for clients built around path parameters.
+async fn get_attestation_by_path(
+    state: State<Arc<OracleServerState>>,
+    Path(event_id): Path<String>,
+) -> Result<Json<OracleAttestation>, (StatusCode, Json<OracleServerError>)> {
+    get_attestation(state, Query(routes::GetAttestation { event_id })).await
+}
+
 async fn sign_event(
     State(state): State<Arc<OracleServerState>>,
+    headers: HeaderMap,
     Json(event): Json<routes::SignEvent>,
 ) -> Result<Json<OracleAttestation>, (StatusCode, Json<OracleServerError>)> {
-    match routes::sign_event_internal(state, event).await {
+    match routes::sign_event_internal(state, event, api_key(&headers)).await {
         Ok(attestation) => Ok(Json(attestation)),
-        Err(e) => Err((
-            StatusCode::BAD_REQUEST,
-            Json(OracleServerError {
-                reason: e.to_string(),
-            }),
-        )),
+        Err(e) => Err(error_response(e)),
     }
 }
 
-async fn oracle_info(State(state): State<Arc<OracleServerState>>) -> impl IntoResponse {
-    Json(routes::oracle_info_internal(state).await).into_response()
+async fn oracle_info(
+    State(state): State<Arc<OracleServerState>>,
+    query: Query<routes::GetInfo>,
+) -> Result<Json<routes::OracleInfo>, (StatusCode, Json<OracleServerError>)> {
+    match routes::oracle_info_internal(state, query.0).await {
+        Ok(info) => Ok(Json(info)),
+        Err(e) => Err(error_response(e)),
+    }
+}
+
+async fn get_status(State(state): State<Arc<OracleServerState>>) -> Json<WatcherHealthReport> {
+    Json(routes::status_internal(state))
 }
 
 async fn list_events(
     State(state): State<Arc<OracleServerState>>,
-) -> Result<Json<Vec<OracleEventData>>, (StatusCode, Json<OracleServerError>)> {
+) -> Result<Json<Vec<routes::EventListing>>, (StatusCode, Json<OracleServerError>)> {
     match routes::list_events_internal(state).await {
         Ok(events) => Ok(Json(events)),
-        Err(e) => Err((
-            StatusCode::BAD_REQUEST,
-            Json(OracleServerError {
-                reason: e.to_string(),
-            }),
-        )),
+        Err(e) => Err(error_response(e)),
+    }
+}
+
+async fn get_pending_events(
+    State(state): State<Arc<OracleServerState>>,
+) -> Result<Json<Vec<PendingEvent>>, (StatusCode, Json<OracleServerError>)> {
+    match routes::pending_events_internal(state).await {
+        Ok(events) => Ok(Json(events)),
+        Err(e) => Err(error_response(e)),
+    }
+}
+
+async fn get_event_detail(
+    State(state): State<Arc<OracleServerState>>,
+    query: Query<routes::GetEventDetail>,
+) -> Result<Json<routes::EventDetail>, (StatusCode, Json<OracleServerError>)> {
+    match routes::get_event_detail_internal(state, query.0).await {
+        Ok(detail) => Ok(Json(detail)),
+        Err(e) => Err(error_response(e)),
     }
 }
 
@@ -207,12 +406,276 @@ async fn get_parlay_contract(
 ) -> Result<Json<ParlayContract>, (StatusCode, Json<OracleServerError>)> {
     match routes::get_parlay_contract_internal(state, event.0).await {
         Ok(event) => Ok(Json(event)),
-        Err(e) => Err((
-            StatusCode::BAD_REQUEST,
-            Json(OracleServerError {
-                reason: e.to_string(),
-            }),
-        )),
+        Err(e) => Err(error_response(e)),
+    }
+}
+
+async fn get_parlay_legs(
+    State(state): State<Arc<OracleServerState>>,
+    query: Query<routes::GetParlayLegs>,
+) -> Result<Json<routes::ParlayLegs>, (StatusCode, Json<OracleServerError>)> {
+    match routes::get_parlay_legs_internal(state, query.0).await {
+        Ok(legs) => Ok(Json(legs)),
+        Err(e) => Err(error_response(e)),
+    }
+}
+
+async fn get_oracle_params(params: Query<routes::GetOracleParams>) -> Json<routes::OracleParams> {
+    Json(routes::oracle_params_internal(params.0))
+}
+
+async fn get_parlay_options() -> Json<routes::ParlayOptions> {
+    Json(routes::parlay_options_internal())
+}
+
+async fn get_events_metadata(
+    State(state): State<Arc<OracleServerState>>,
+    query: Query<routes::GetEventsMetadata>,
+) -> Result<Json<Vec<EventTypeMetadata>>, (StatusCode, Json<OracleServerError>)> {
+    match routes::events_metadata_internal(state, query.0).await {
+        Ok(metadata) => Ok(Json(metadata)),
+        Err(e) => Err(error_response(e)),
+    }
+}
+
+async fn get_history(
+    State(state): State<Arc<OracleServerState>>,
+    query: Query<routes::GetHistory>,
+) -> Result<Json<MetricHistory>, (StatusCode, Json<OracleServerError>)> {
+    match routes::history_internal(state, query.0).await {
+        Ok(history) => Ok(Json(history)),
+        Err(e) => Err(error_response(e)),
+    }
+}
+
+fn api_key(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(API_KEY_HEADER)
+        .and_then(|key| key.to_str().ok())
+        .map(str::to_string)
+}
+
+/// A failed request with the status of its error code.
+fn error_response(e: impl Into<OracleServerError>) -> (StatusCode, Json<OracleServerError>) {
+    let mut e = e.into();
+    e.request_id = access_log::current_request_id();
+    if e.code == ErrorCode::Internal {
+        log::error!("Request failed. error={}", e.reason);
+    }
+    (e.code.status(), Json(e))
+}
+
+async fn count_request(
+    State(state): State<Arc<OracleServerState>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, (StatusCode, Json<OracleServerError>)> {
+    let api_key = api_key(request.headers());
+    if let Err(e) = routes::count_request(&state, api_key.as_deref()).await {
+        if e.downcast_ref::<QuotaExceeded>().is_some() {
+            return Err(error_response(e));
+        }
+        // Usage accounting failing must not take the oracle down with it
+        log::error!("Could not count request. error={}", e);
+    }
+    Ok(next.run(request).await)
+}
+
+async fn get_usage(
+    State(state): State<Arc<OracleServerState>>,
+    headers: HeaderMap,
+) -> Result<Json<Usage>, (StatusCode, Json<OracleServerError>)> {
+    match routes::usage_internal(state, api_key(&headers)).await {
+        Ok(usage) => Ok(Json(usage)),
+        Err(e) => Err(error_response(e)),
+    }
+}
+
+/// Handle the request under its request id and write its access log line.
+async fn track_request(request: Request, next: Next) -> Response {
+    let headers = request.headers();
+    let request_id = access_log::request_id(
+        headers
+            .get(REQUEST_ID_HEADER)
+            .and_then(|header| header.to_str().ok()),
+    );
+    let user_agent = headers
+        .get(USER_AGENT)
+        .and_then(|header| header.to_str().ok())
+        .map(str::to_string);
+    let method = request.method().to_string();
+    let path = request.uri().path().to_string();
+    let started = Instant::now();
+
+    let mut response = access_log::scope(request_id.clone(), next.run(request)).await;
+    AccessLogEntry {
+        request_id: request_id.clone(),
+        method,
+        path,
+        status: response.status().as_u16(),
+        duration_ms: started.elapsed().as_millis() as u64,
+        user_agent,
+    }
+    .log();
+    if let Ok(header) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, header);
+    }
+    response
+}
+
+async fn negotiate_api_version(
+    request: Request,
+    next: Next,
+) -> Result<Response, (StatusCode, Json<OracleServerError>)> {
+    let requested = request
+        .headers()
+        .get(routes::API_VERSION_HEADER)
+        .and_then(|header| header.to_str().ok());
+    let version = routes::negotiate_api_version(requested).map_err(error_response)?;
+    let mut response = next.run(request).await;
+    response
+        .headers_mut()
+        .insert(routes::API_VERSION_HEADER, HeaderValue::from(version));
+    Ok(response)
+}
+
+async fn require_admin(
+    State(state): State<Arc<OracleServerState>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, (StatusCode, Json<OracleServerError>)> {
+    let authorization = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|header| header.to_str().ok());
+    if !routes::authorize_admin(&state, authorization) {
+        return Err(error_response(OracleServerError::new(
+            ErrorCode::Unauthorized,
+            "Admin routes require a valid admin token.",
+        )));
+    }
+    Ok(next.run(request).await)
+}
+
+async fn hold_event(
+    State(state): State<Arc<OracleServerState>>,
+    Json(request): Json<routes::HoldEvent>,
+) -> Result<Json<routes::HoldEvent>, (StatusCode, Json<OracleServerError>)> {
+    match routes::hold_event_internal(state, request).await {
+        Ok(hold) => Ok(Json(hold)),
+        Err(e) => Err(error_response(e)),
+    }
+}
+
+async fn hide_event(
+    State(state): State<Arc<OracleServerState>>,
+    Json(request): Json<routes::HideEvent>,
+) -> Result<Json<routes::HideEvent>, (StatusCode, Json<OracleServerError>)> {
+    match routes::hide_event_internal(state, request).await {
+        Ok(hide) => Ok(Json(hide)),
+        Err(e) => Err(error_response(e)),
+    }
+}
+
+async fn prepare_override(
+    State(state): State<Arc<OracleServerState>>,
+    Json(request): Json<PrepareOverride>,
+) -> Result<Json<PreparedOverride>, (StatusCode, Json<OracleServerError>)> {
+    match routes::prepare_override_internal(state, request).await {
+        Ok(prepared) => Ok(Json(prepared)),
+        Err(e) => Err(error_response(e)),
+    }
+}
+
+async fn commit_override(
+    State(state): State<Arc<OracleServerState>>,
+    Json(request): Json<CommitOverride>,
+) -> Result<Json<OracleAttestation>, (StatusCode, Json<OracleServerError>)> {
+    match routes::commit_override_internal(state, request).await {
+        Ok(attestation) => Ok(Json(attestation)),
+        Err(e) => Err(error_response(e)),
+    }
+}
+
+async fn get_signing_audit(
+    State(state): State<Arc<OracleServerState>>,
+    query: Query<routes::GetSigningAudit>,
+) -> Result<Json<Vec<SigningAuditEntry>>, (StatusCode, Json<OracleServerError>)> {
+    match routes::signing_audit_internal(state, query.0).await {
+        Ok(entries) => Ok(Json(entries)),
+        Err(e) => Err(error_response(e)),
+    }
+}
+
+async fn rebroadcast_event(
+    State(state): State<Arc<OracleServerState>>,
+    Json(request): Json<routes::RebroadcastEvent>,
+) -> Result<Json<Rebroadcast>, (StatusCode, Json<OracleServerError>)> {
+    match routes::rebroadcast_internal(state, request).await {
+        Ok(rebroadcast) => Ok(Json(rebroadcast)),
+        Err(e) => Err(error_response(e)),
+    }
+}
+
+async fn backtest_parlay(
+    State(state): State<Arc<OracleServerState>>,
+    Json(request): Json<BacktestRequest>,
+) -> Result<Json<Backtest>, (StatusCode, Json<OracleServerError>)> {
+    match routes::backtest_internal(state, request).await {
+        Ok(backtest) => Ok(Json(backtest)),
+        Err(e) => Err(error_response(e)),
+    }
+}
+
+async fn estimate_parlay(
+    State(state): State<Arc<OracleServerState>>,
+    query: Query<routes::GetEstimate>,
+) -> Result<Json<Estimate>, (StatusCode, Json<OracleServerError>)> {
+    match routes::estimate_internal(state, query.0).await {
+        Ok(estimate) => Ok(Json(estimate)),
+        Err(e) => Err(error_response(e)),
+    }
+}
+
+async fn preview_outcome(
+    State(state): State<Arc<OracleServerState>>,
+    query: Query<routes::GetOutcomePreview>,
+) -> Result<Json<routes::OutcomePreview>, (StatusCode, Json<OracleServerError>)> {
+    match routes::preview_outcome_internal(state, query.0).await {
+        Ok(preview) => Ok(Json(preview)),
+        Err(e) => Err(error_response(e)),
+    }
+}
+
+async fn create_series(
+    State(state): State<Arc<OracleServerState>>,
+    headers: HeaderMap,
+    Json(request): Json<CreateSeries>,
+) -> Result<Json<SeriesManifest>, (StatusCode, Json<OracleServerError>)> {
+    match routes::create_series_internal(state, request, api_key(&headers)).await {
+        Ok(manifest) => Ok(Json(manifest)),
+        Err(e) => Err(error_response(e)),
+    }
+}
+
+async fn get_series(
+    State(state): State<Arc<OracleServerState>>,
+    query: Query<routes::GetSeries>,
+) -> Result<Json<SeriesManifest>, (StatusCode, Json<OracleServerError>)> {
+    match routes::get_series_internal(state, query.0).await {
+        Ok(manifest) => Ok(Json(manifest)),
+        Err(e) => Err(error_response(e)),
+    }
+}
+
+async fn request_signing(
+    State(state): State<Arc<OracleServerState>>,
+    headers: HeaderMap,
+    Json(event): Json<routes::SignEvent>,
+) -> Result<Json<routes::SignEvent>, (StatusCode, Json<OracleServerError>)> {
+    match routes::request_signing_internal(state, event, api_key(&headers)).await {
+        Ok(event) => Ok(Json(event)),
+        Err(e) => Err(error_response(e)),
     }
 }
 
@@ -227,11 +690,100 @@ async fn get_attestation_outcome(
 ) -> Result<Json<ErnestOracleOutcome>, (StatusCode, Json<OracleServerError>)> {
     match routes::get_attestation_outcome_internal(state, event.0).await {
         Ok(outcome) => Ok(Json(outcome)),
-        Err(e) => Err((
-            StatusCode::BAD_REQUEST,
-            Json(OracleServerError {
-                reason: e.to_string(),
-            }),
-        )),
+        Err(e) => Err(error_response(e)),
+    }
+}
+
+async fn search_events(
+    State(state): State<Arc<OracleServerState>>,
+    search: Query<routes::SearchEvents>,
+) -> Result<Json<Vec<routes::EventSearchResult>>, (StatusCode, Json<OracleServerError>)> {
+    match routes::search_events_internal(state, search.0).await {
+        Ok(events) => Ok(Json(events)),
+        Err(e) => Err(error_response(e)),
+    }
+}
+
+async fn get_stats(
+    State(state): State<Arc<OracleServerState>>,
+) -> Result<Json<OracleStats>, (StatusCode, Json<OracleServerError>)> {
+    match routes::stats_internal(state).await {
+        Ok(stats) => Ok(Json(stats)),
+        Err(e) => Err(error_response(e)),
+    }
+}
+
+async fn get_provenance(
+    State(state): State<Arc<OracleServerState>>,
+    event: Query<routes::GetProvenance>,
+) -> Result<Json<Vec<AttestationProvenance>>, (StatusCode, Json<OracleServerError>)> {
+    match routes::get_provenance_internal(state, event.0).await {
+        Ok(provenance) => Ok(Json(provenance)),
+        Err(e) => Err(error_response(e)),
+    }
+}
+
+async fn export_event(
+    State(state): State<Arc<OracleServerState>>,
+    query: Query<routes::GetExport>,
+) -> Result<Json<ExportedEvent>, (StatusCode, Json<OracleServerError>)> {
+    match compat::export_internal(state, query.0.event_id).await {
+        Ok(export) => Ok(Json(export)),
+        Err(e) => {
+            let code = match e {
+                CompatError::NotFound(_) => ErrorCode::NotFound,
+                CompatError::Internal(_) => ErrorCode::Internal,
+            };
+            Err(error_response(OracleServerError::new(code, e)))
+        }
+    }
+}
+
+fn compat_error<T>(e: CompatError) -> (StatusCode, Json<CompatResponse<T>>) {
+    let status = match e {
+        CompatError::NotFound(_) => StatusCode::NOT_FOUND,
+        CompatError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, Json(CompatResponse::err(e)))
+}
+
+async fn compat_public_key(
+    State(state): State<Arc<OracleServerState>>,
+) -> Json<CompatResponse<String>> {
+    Json(CompatResponse::ok(compat::public_key_internal(state)))
+}
+
+async fn compat_list_announcements(
+    State(state): State<Arc<OracleServerState>>,
+) -> Result<Json<CompatResponse<Vec<String>>>, (StatusCode, Json<CompatResponse<Vec<String>>>)> {
+    match compat::list_announcements_internal(state).await {
+        Ok(events) => Ok(Json(CompatResponse::ok(events))),
+        Err(e) => Err(compat_error(e)),
+    }
+}
+
+async fn compat_announcement(
+    State(state): State<Arc<OracleServerState>>,
+    Path(event_id): Path<String>,
+) -> Result<
+    Json<CompatResponse<CompatAnnouncement>>,
+    (StatusCode, Json<CompatResponse<CompatAnnouncement>>),
+> {
+    match compat::announcement_internal(state, event_id).await {
+        Ok(announcement) => Ok(Json(CompatResponse::ok(announcement))),
+        Err(e) => Err(compat_error(e)),
+    }
+}
+
+async fn compat_attestation(
+    State(state): State<Arc<OracleServerState>>,
+    Path(event_id): Path<String>,
+) -> Result<
+    Json<CompatResponse<CompatAttestation>>,
+    (StatusCode, Json<CompatResponse<CompatAttestation>>),
+> {
+    match compat::attestation_internal(state, event_id).await {
+        Ok(attestation) => Ok(Json(CompatResponse::ok(attestation))),
+        Err(e) => Err(compat_error(e)),
     }
 }
@@ -0,0 +1 @@
+DROP INDEX idx_event_metadata_description_search;
@@ -0,0 +1,2 @@
+CREATE INDEX idx_event_metadata_description_search
+    ON event_metadata USING GIN (to_tsvector('english', COALESCE(description, '')));
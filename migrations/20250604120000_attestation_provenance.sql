@@ -0,0 +1,12 @@
+CREATE TABLE attestation_provenance (
+    id SERIAL PRIMARY KEY,
+    event_id TEXT NOT NULL REFERENCES events(event_id) ON DELETE CASCADE,
+    data_type TEXT NOT NULL,
+    source_url TEXT NOT NULL,
+    raw_response JSONB NOT NULL,
+    fetched_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    value DOUBLE PRECISION NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_attestation_provenance_event_id ON attestation_provenance(event_id);
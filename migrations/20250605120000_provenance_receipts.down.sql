@@ -0,0 +1 @@
+ALTER TABLE attestation_provenance DROP COLUMN signature;
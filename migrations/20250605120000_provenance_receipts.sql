@@ -0,0 +1 @@
+ALTER TABLE attestation_provenance ADD COLUMN signature BYTEA;
@@ -0,0 +1,3 @@
+ALTER TABLE parlay_parameters DROP COLUMN percentile;
+
+DROP TABLE event_options;
@@ -0,0 +1,6 @@
+CREATE TABLE event_options (
+    event_id TEXT PRIMARY KEY REFERENCES events(event_id) ON DELETE CASCADE,
+    fee_percentile SMALLINT NOT NULL DEFAULT 90
+);
+
+ALTER TABLE parlay_parameters ADD COLUMN percentile SMALLINT;
@@ -0,0 +1,3 @@
+ALTER TABLE parlay_parameters DROP COLUMN aggregation;
+
+ALTER TABLE event_options DROP COLUMN aggregation;
@@ -0,0 +1,3 @@
+ALTER TABLE event_options ADD COLUMN aggregation TEXT;
+
+ALTER TABLE parlay_parameters ADD COLUMN aggregation TEXT;
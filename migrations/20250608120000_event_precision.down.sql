@@ -0,0 +1,2 @@
+ALTER TABLE event_options DROP COLUMN precision;
+ALTER TABLE event_options DROP COLUMN nb_digits;
@@ -0,0 +1,2 @@
+ALTER TABLE event_options ADD COLUMN nb_digits INTEGER;
+ALTER TABLE event_options ADD COLUMN precision INTEGER;
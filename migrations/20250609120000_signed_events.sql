@@ -0,0 +1 @@
+ALTER TABLE event_options ADD COLUMN is_signed BOOLEAN;
@@ -0,0 +1 @@
+ALTER TABLE parlay_contracts DROP COLUMN score_mode;
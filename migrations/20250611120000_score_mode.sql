@@ -0,0 +1 @@
+ALTER TABLE parlay_contracts ADD COLUMN score_mode TEXT NOT NULL DEFAULT 'continuous';
@@ -0,0 +1 @@
+ALTER TABLE parlay_parameters DROP COLUMN event_id;
@@ -0,0 +1 @@
+ALTER TABLE parlay_parameters ADD COLUMN event_id TEXT;
@@ -0,0 +1 @@
+ALTER TABLE parlay_contracts ADD COLUMN scoring_version INTEGER NOT NULL DEFAULT 1;
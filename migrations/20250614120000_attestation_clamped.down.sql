@@ -0,0 +1 @@
+ALTER TABLE numeric_attestation_outcome DROP COLUMN clamped;
@@ -0,0 +1 @@
+ALTER TABLE numeric_attestation_outcome ADD COLUMN clamped BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,9 @@
+CREATE TABLE metric_history (
+    id SERIAL PRIMARY KEY,
+    data_type TEXT NOT NULL,
+    value DOUBLE PRECISION NOT NULL,
+    source_url TEXT NOT NULL,
+    sampled_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE INDEX idx_metric_history_data_type_sampled_at ON metric_history(data_type, sampled_at);
@@ -0,0 +1,2 @@
+ALTER TABLE events DROP COLUMN announced;
+ALTER TABLE events DROP COLUMN announce_at;
@@ -0,0 +1,2 @@
+ALTER TABLE events ADD COLUMN announce_at TIMESTAMPTZ;
+ALTER TABLE events ADD COLUMN announced BOOLEAN NOT NULL DEFAULT TRUE;
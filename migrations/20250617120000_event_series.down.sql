@@ -0,0 +1,2 @@
+DROP TABLE event_series_events;
+DROP TABLE event_series;
@@ -0,0 +1,15 @@
+CREATE TABLE event_series (
+    series_id TEXT PRIMARY KEY,
+    signature BYTEA NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE event_series_events (
+    series_id TEXT NOT NULL REFERENCES event_series(series_id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    event_id TEXT NOT NULL REFERENCES events(event_id) ON DELETE CASCADE,
+    maturity BIGINT NOT NULL,
+    PRIMARY KEY (series_id, position)
+);
+
+CREATE INDEX idx_event_series_events_event_id ON event_series_events(event_id);
@@ -0,0 +1,3 @@
+DROP TRIGGER IF EXISTS signing_audit_append_only ON signing_audit;
+DROP FUNCTION IF EXISTS signing_audit_append_only();
+DROP TABLE IF EXISTS signing_audit;
@@ -0,0 +1,23 @@
+-- Append-only log of every signature, each entry hashing the one before it
+CREATE TABLE signing_audit (
+    id BIGSERIAL PRIMARY KEY,
+    event_id TEXT NOT NULL,
+    outcome BIGINT NOT NULL,
+    nonce_indexes INTEGER[] NOT NULL,
+    source TEXT NOT NULL,
+    signed_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    prev_hash BYTEA,
+    hash BYTEA NOT NULL UNIQUE
+);
+
+CREATE INDEX idx_signing_audit_event_id ON signing_audit(event_id);
+
+CREATE FUNCTION signing_audit_append_only() RETURNS TRIGGER AS $$
+BEGIN
+    RAISE EXCEPTION 'signing_audit is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER signing_audit_append_only
+    BEFORE UPDATE OR DELETE ON signing_audit
+    FOR EACH ROW EXECUTE FUNCTION signing_audit_append_only();
@@ -0,0 +1,16 @@
+ALTER TABLE numeric_attestation_data_outcome
+DROP CONSTRAINT fk_event_id,
+ADD CONSTRAINT fk_event_id
+FOREIGN KEY (event_id) REFERENCES numeric_attestation_outcome(event_id);
+
+ALTER TABLE numeric_attestation_outcome DROP CONSTRAINT numeric_attestation_outcome_event_id_fkey;
+
+DROP INDEX IF EXISTS idx_parlay_parameters_event_id;
+ALTER TABLE parlay_parameters DROP CONSTRAINT parlay_parameters_event_id_fkey;
+
+ALTER TABLE parlay_parameters
+DROP CONSTRAINT parlay_parameters_contract_id_fkey,
+ADD CONSTRAINT parlay_parameters_contract_id_fkey
+FOREIGN KEY (contract_id) REFERENCES parlay_contracts(id);
+
+ALTER TABLE parlay_contracts DROP CONSTRAINT parlay_contracts_id_fkey;
@@ -0,0 +1,35 @@
+-- Remove rows left behind by deleted events before linking them to their event
+DELETE FROM numeric_attestation_data_outcome d
+WHERE NOT EXISTS (SELECT 1 FROM events e WHERE e.event_id = d.event_id);
+DELETE FROM numeric_attestation_outcome o
+WHERE NOT EXISTS (SELECT 1 FROM events e WHERE e.event_id = o.event_id);
+DELETE FROM parlay_parameters p
+WHERE NOT EXISTS (SELECT 1 FROM events e WHERE e.event_id = p.contract_id);
+DELETE FROM parlay_contracts c
+WHERE NOT EXISTS (SELECT 1 FROM events e WHERE e.event_id = c.id);
+
+ALTER TABLE parlay_contracts
+ADD CONSTRAINT parlay_contracts_id_fkey
+FOREIGN KEY (id) REFERENCES events(event_id) ON DELETE CASCADE;
+
+ALTER TABLE parlay_parameters
+DROP CONSTRAINT parlay_parameters_contract_id_fkey,
+ADD CONSTRAINT parlay_parameters_contract_id_fkey
+FOREIGN KEY (contract_id) REFERENCES parlay_contracts(id) ON DELETE CASCADE;
+
+-- A leg settles on its referenced event, which must not disappear under it. Existing legs are
+-- not validated so a dangling reference keeps failing loudly instead of being dropped.
+ALTER TABLE parlay_parameters
+ADD CONSTRAINT parlay_parameters_event_id_fkey
+FOREIGN KEY (event_id) REFERENCES events(event_id) ON DELETE RESTRICT NOT VALID;
+
+CREATE INDEX idx_parlay_parameters_event_id ON parlay_parameters(event_id);
+
+ALTER TABLE numeric_attestation_outcome
+ADD CONSTRAINT numeric_attestation_outcome_event_id_fkey
+FOREIGN KEY (event_id) REFERENCES events(event_id) ON DELETE CASCADE;
+
+ALTER TABLE numeric_attestation_data_outcome
+DROP CONSTRAINT fk_event_id,
+ADD CONSTRAINT fk_event_id
+FOREIGN KEY (event_id) REFERENCES numeric_attestation_outcome(event_id) ON DELETE CASCADE;
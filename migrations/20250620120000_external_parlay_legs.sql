@@ -0,0 +1,2 @@
+-- Numeric event of another oracle a leg settles on, with its announcement
+ALTER TABLE parlay_parameters ADD COLUMN external_event JSONB;
@@ -0,0 +1,8 @@
+-- Hashed API key each event was created with, for the daily creation limit
+CREATE TABLE event_creators (
+    event_id TEXT PRIMARY KEY REFERENCES events(event_id) ON DELETE CASCADE,
+    api_key TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX event_creators_api_key_created_at_idx ON event_creators (api_key, created_at);
@@ -0,0 +1,2 @@
+-- Seconds the watcher waits after maturity before signing, the configured default when NULL
+ALTER TABLE events ADD COLUMN settlement_delay INTEGER CHECK (settlement_delay >= 0);
@@ -0,0 +1,13 @@
+-- Manual outcomes prepared by an admin, signed once committed with their token
+CREATE TABLE attestation_overrides (
+    token TEXT PRIMARY KEY,
+    event_id TEXT NOT NULL REFERENCES events(event_id) ON DELETE CASCADE,
+    outcome BIGINT NOT NULL,
+    digits TEXT[] NOT NULL,
+    reason TEXT NOT NULL,
+    prepared_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL,
+    committed_at TIMESTAMPTZ
+);
+
+CREATE INDEX attestation_overrides_event_id_idx ON attestation_overrides (event_id);
@@ -0,0 +1,3 @@
+-- Held events are skipped by the watcher until released
+ALTER TABLE events ADD COLUMN hold BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE events ADD COLUMN hold_reason TEXT;
@@ -0,0 +1,9 @@
+-- Daily usage per hashed API key, counted against the configured quotas
+CREATE TABLE api_key_usage (
+    api_key TEXT NOT NULL,
+    day DATE NOT NULL,
+    events_created BIGINT NOT NULL DEFAULT 0,
+    signings_requested BIGINT NOT NULL DEFAULT 0,
+    requests BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (api_key, day)
+);
@@ -0,0 +1,4 @@
+DROP INDEX idx_metric_history_event_id;
+ALTER TABLE metric_history DROP COLUMN raw_response;
+ALTER TABLE metric_history DROP COLUMN leg;
+ALTER TABLE metric_history DROP COLUMN event_id;
@@ -0,0 +1,5 @@
+-- Values fetched ahead of maturity for a single event or a parlay leg, kept out of the charted history
+ALTER TABLE metric_history ADD COLUMN event_id TEXT REFERENCES events(event_id) ON DELETE CASCADE;
+ALTER TABLE metric_history ADD COLUMN leg INTEGER;
+ALTER TABLE metric_history ADD COLUMN raw_response JSONB;
+CREATE INDEX idx_metric_history_event_id ON metric_history(event_id, leg, sampled_at) WHERE event_id IS NOT NULL;
@@ -0,0 +1,2 @@
+ALTER TABLE metric_history DROP COLUMN samples;
+ALTER TABLE attestation_provenance DROP COLUMN samples;
@@ -0,0 +1,3 @@
+-- Every fetch a signed or prefetched value was chosen from
+ALTER TABLE attestation_provenance ADD COLUMN samples JSONB;
+ALTER TABLE metric_history ADD COLUMN samples JSONB;
@@ -0,0 +1,2 @@
+-- Smoothing a single event's outcome is settled with, see `smoothing::Smoothing`
+ALTER TABLE event_options ADD COLUMN smoothing JSONB;
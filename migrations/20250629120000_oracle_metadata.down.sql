@@ -0,0 +1 @@
+DROP TABLE oracle_metadata;
@@ -0,0 +1,6 @@
+-- The key the oracle announces under, stored on first start and enforced after
+CREATE TABLE oracle_metadata (
+    id BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    oracle_public_key BYTEA NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
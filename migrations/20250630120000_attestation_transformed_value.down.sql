@@ -0,0 +1 @@
+ALTER TABLE numeric_attestation_data_outcome DROP COLUMN transformed_value;
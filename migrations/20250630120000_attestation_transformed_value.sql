@@ -0,0 +1,4 @@
+-- Rows written so far stored the transformed value of parlay legs as normalized_value
+ALTER TABLE numeric_attestation_data_outcome ADD COLUMN transformed_value DOUBLE PRECISION;
+UPDATE numeric_attestation_data_outcome SET transformed_value = normalized_value;
+ALTER TABLE numeric_attestation_data_outcome ALTER COLUMN transformed_value SET NOT NULL;
@@ -0,0 +1,2 @@
+DROP INDEX idx_events_maturity;
+ALTER TABLE events DROP COLUMN maturity;
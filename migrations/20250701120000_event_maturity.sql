@@ -0,0 +1,15 @@
+-- Maturity of the announced event, so due events are found without decoding every unsigned one
+ALTER TABLE events ADD COLUMN maturity BIGINT;
+-- oracle_event starts with the u16 nonce count, the 32 byte nonces and the u32 maturity, big endian
+UPDATE events SET maturity = (
+    'x' || encode(
+        substring(
+            oracle_event
+            FROM 3 + 32 * ((get_byte(oracle_event, 0) << 8) | get_byte(oracle_event, 1))
+            FOR 4
+        ),
+        'hex'
+    )
+)::bit(32)::bigint;
+ALTER TABLE events ALTER COLUMN maturity SET NOT NULL;
+CREATE INDEX idx_events_maturity ON events(maturity);
@@ -0,0 +1,2 @@
+-- Hidden events are left out of listings and search, but still served by id
+ALTER TABLE events ADD COLUMN hidden BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,2 @@
+ALTER TABLE parlay_contracts DROP CONSTRAINT parlay_contracts_scoring_version_check;
+ALTER TABLE parlay_contracts ALTER COLUMN scoring_version SET DEFAULT 1;
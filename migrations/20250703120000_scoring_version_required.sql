@@ -0,0 +1,3 @@
+-- Every contract records the scoring rules it was announced under, none default to the legacy ones
+ALTER TABLE parlay_contracts ALTER COLUMN scoring_version DROP DEFAULT;
+ALTER TABLE parlay_contracts ADD CONSTRAINT parlay_contracts_scoring_version_check CHECK (scoring_version >= 1);
@@ -0,0 +1 @@
+DROP INDEX idx_event_nonces_nonce;
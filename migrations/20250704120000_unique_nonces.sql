@@ -0,0 +1,19 @@
+-- A nonce signed for two events leaks the oracle key, so no nonce is ever stored twice.
+-- Stored duplicates have to be resolved by hand first, name their events instead of failing
+-- on the index.
+DO $$
+DECLARE
+    reused TEXT;
+BEGIN
+    SELECT string_agg(event_ids, '; ') INTO reused FROM (
+        SELECT array_to_string(array_agg(event_id ORDER BY event_id), ',') AS event_ids
+        FROM event_nonces
+        GROUP BY nonce
+        HAVING COUNT(*) > 1
+    ) duplicates;
+    IF reused IS NOT NULL THEN
+        RAISE EXCEPTION 'A nonce is stored for more than one event, signing them would reveal the oracle key. event_ids=%', reused;
+    END IF;
+END $$;
+
+CREATE UNIQUE INDEX idx_event_nonces_nonce ON event_nonces(nonce);
@@ -0,0 +1 @@
+ALTER TABLE event_options DROP COLUMN scaled_outcome;
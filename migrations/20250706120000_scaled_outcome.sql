@@ -0,0 +1,3 @@
+-- Outcomes were signed as ceil(value) whatever the announced precision, events created from now
+-- on sign round(value / 10^precision)
+ALTER TABLE event_options ADD COLUMN scaled_outcome BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1 @@
+DROP TABLE creation_receipts;
@@ -0,0 +1,7 @@
+-- Signed proof an event was created, kept after the event is deleted
+CREATE TABLE creation_receipts (
+    event_id TEXT PRIMARY KEY,
+    api_key_hash TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    signature BYTEA NOT NULL
+);
@@ -0,0 +1 @@
+DROP SEQUENCE event_nonce_index_seq;
@@ -0,0 +1,3 @@
+-- Nonce indexes are handed out by the database so oracle processes sharing it never collide
+CREATE SEQUENCE event_nonce_index_seq MINVALUE 1;
+SELECT setval('event_nonce_index_seq', COALESCE(MAX(index), 0) + 1, false) FROM event_nonces;
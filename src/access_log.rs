@@ -0,0 +1,96 @@
+//! Request ids and the access log.
+//!
+//! Every request is handled under an id, the one in its `X-Request-Id` header when usable or else
+//! a new UUID. The id is answered in the same header and in error bodies and is part of every log
+//! line written while handling the request, so a failure reported by a client can be found in the
+//! server logs.
+
+use std::future::Future;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+pub const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// Log target of the access log lines.
+pub const ACCESS_LOG_TARGET: &str = "access";
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// The id to handle a request under, the `requested` one if it is safe to log and echo back.
+pub fn request_id(requested: Option<&str>) -> String {
+    match requested {
+        Some(request_id)
+            if !request_id.is_empty()
+                && request_id.len() <= MAX_REQUEST_ID_LEN
+                && request_id
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "-_.:".contains(c)) =>
+        {
+            request_id.to_string()
+        }
+        _ => Uuid::new_v4().to_string(),
+    }
+}
+
+/// Run `f` as the handling of request `request_id`.
+pub async fn scope<F: Future>(request_id: String, f: F) -> F::Output {
+    REQUEST_ID.scope(request_id, f).await
+}
+
+/// The id of the request being handled, if any.
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(Clone::clone).ok()
+}
+
+/// One line of the access log.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessLogEntry {
+    pub request_id: String,
+    pub method: String,
+    pub path: String,
+    pub status: u16,
+    pub duration_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+}
+
+impl AccessLogEntry {
+    /// Write the entry as a JSON line, with failed requests as warnings.
+    pub fn log(&self) {
+        let level = match self.status {
+            500.. => log::Level::Warn,
+            _ => log::Level::Info,
+        };
+        match serde_json::to_string(self) {
+            Ok(line) => log::log!(target: ACCESS_LOG_TARGET, level, "{}", line),
+            Err(e) => log::error!("Could not write access log. error={}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn propagates_request_ids() {
+        assert_eq!(request_id(Some("client-42")), "client-42");
+        for requested in [None, Some(""), Some("a b"), Some("id\n")] {
+            assert!(Uuid::parse_str(&request_id(requested)).is_ok());
+        }
+        assert!(Uuid::parse_str(&request_id(Some(&"a".repeat(MAX_REQUEST_ID_LEN + 1)))).is_ok());
+
+        assert_eq!(current_request_id(), None);
+        let handled = scope("client-42".to_string(), async {
+            tokio::task::yield_now().await;
+            current_request_id()
+        })
+        .await;
+        assert_eq!(handled.as_deref(), Some("client-42"));
+    }
+}
@@ -0,0 +1,206 @@
+//! Alerts to the operator about signing outages.
+//!
+//! Counterparties can only settle once the oracle signs, so the operator is alerted when an event
+//! stays unsigned too long after it is due or when fetching outcomes keeps failing. Alerts go to
+//! their own sinks, usually private ones, and are sent once per overdue event and failure streak.
+
+use std::{
+    collections::HashSet,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Mutex,
+    },
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    notifications::{Notification, NotificationConfig, NotificationSink, Notifier},
+    oracle::ErnestOracle,
+};
+
+pub const DEFAULT_UNSIGNED_AFTER_MINS: u64 = 30;
+pub const DEFAULT_SOURCE_FAILURES: u32 = 5;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AlertConfig {
+    pub sinks: Vec<NotificationSink>,
+    /// Minutes an event may stay unsigned after it is due
+    pub unsigned_after_mins: u64,
+    /// Failed outcome fetches in a row before the data source is reported failing
+    pub source_failures: u32,
+}
+
+impl Default for AlertConfig {
+    fn default() -> Self {
+        Self {
+            sinks: vec![],
+            unsigned_after_mins: DEFAULT_UNSIGNED_AFTER_MINS,
+            source_failures: DEFAULT_SOURCE_FAILURES,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Alerter {
+    notifier: Notifier,
+    unsigned_after_secs: i64,
+    source_failures: u32,
+    /// Overdue events already alerted on
+    overdue: Mutex<HashSet<String>>,
+    failures: AtomicU32,
+}
+
+impl Alerter {
+    pub fn new(config: AlertConfig) -> Self {
+        Self {
+            notifier: Notifier::new(NotificationConfig {
+                sinks: config.sinks,
+            }),
+            unsigned_after_secs: config.unsigned_after_mins as i64 * 60,
+            source_failures: config.source_failures.max(1),
+            overdue: Mutex::new(HashSet::new()),
+            failures: AtomicU32::new(0),
+        }
+    }
+
+    /// Alert on the events of `oracle` that are overdue at `now`.
+    pub async fn check_overdue(&self, oracle: &ErnestOracle, now: i64) {
+        match oracle
+            .unsigned_events_due_by(now - self.unsigned_after_secs)
+            .await
+        {
+            Ok(overdue) => {
+                for alert in self.overdue_alerts(overdue) {
+                    self.notifier.notify(alert);
+                }
+            }
+            Err(e) => log::error!("Could not check for overdue events. error={}", e),
+        }
+    }
+
+    /// Alerts for the `overdue` events not alerted on yet. Events that are no longer overdue
+    /// are forgotten.
+    fn overdue_alerts(&self, overdue: Vec<(String, i64)>) -> Vec<Notification> {
+        let mut alerted = self.overdue.lock().unwrap();
+        alerted.retain(|event_id| overdue.iter().any(|(overdue, _)| overdue == event_id));
+        overdue
+            .into_iter()
+            .filter(|(event_id, _)| alerted.insert(event_id.clone()))
+            .map(|(event_id, settles_at)| {
+                log::warn!(
+                    "Event is overdue for signing. event_id={} settles_at={}",
+                    event_id,
+                    settles_at
+                );
+                Notification::SigningOverdue {
+                    event_id,
+                    settles_at,
+                }
+            })
+            .collect()
+    }
+
+    /// Count a failed outcome fetch from `source`.
+    pub fn source_failed(&self, source: &str, error: &str) {
+        if let Some(alert) = self.failure_alert(source, error) {
+            self.notifier.notify(alert);
+        }
+    }
+
+    fn failure_alert(&self, source: &str, error: &str) -> Option<Notification> {
+        let failures = self.failures.fetch_add(1, Ordering::SeqCst) + 1;
+        (failures == self.source_failures).then(|| Notification::DataSourceFailing {
+            source: source.to_string(),
+            failures,
+            error: error.to_string(),
+        })
+    }
+
+    /// Report the event held because its data sources disagree for `reason`.
+    pub fn sources_disagree(&self, event_id: &str, reason: &str) {
+        log::warn!(
+            "Holding event, its data sources disagree. event_id={} reason={}",
+            event_id,
+            reason
+        );
+        self.notifier.notify(Notification::SourcesDisagree {
+            event_id: event_id.to_string(),
+            reason: reason.to_string(),
+        });
+    }
+
+    /// Count a successful outcome fetch from `source`, ending a failure streak.
+    pub fn source_succeeded(&self, source: &str) {
+        if let Some(alert) = self.recovery_alert(source) {
+            self.notifier.notify(alert);
+        }
+    }
+
+    fn recovery_alert(&self, source: &str) -> Option<Notification> {
+        let failures = self.failures.swap(0, Ordering::SeqCst);
+        (failures >= self.source_failures).then(|| Notification::DataSourceRecovered {
+            source: source.to_string(),
+            failures,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::EventType;
+    use crate::mempool::{MempoolClient, BASE_URL};
+    use crate::routes::CreateEvent;
+    use crate::test_util::setup_ernest_oracle;
+
+    #[test]
+    fn alerts_once_per_outage() {
+        let alerter = Alerter::new(AlertConfig {
+            source_failures: 2,
+            ..Default::default()
+        });
+        let overdue = vec![("a".to_string(), 10), ("b".to_string(), 20)];
+        assert_eq!(alerter.overdue_alerts(overdue.clone()).len(), 2);
+        assert!(alerter.overdue_alerts(overdue).is_empty());
+        // `a` got signed and is forgotten
+        assert!(alerter
+            .overdue_alerts(vec![("b".to_string(), 20)])
+            .is_empty());
+
+        assert_eq!(alerter.recovery_alert("mempool"), None);
+        assert_eq!(alerter.failure_alert("mempool", "timeout"), None);
+        assert!(matches!(
+            alerter.failure_alert("mempool", "timeout"),
+            Some(Notification::DataSourceFailing { failures: 2, .. })
+        ));
+        assert_eq!(alerter.failure_alert("mempool", "timeout"), None);
+        assert_eq!(
+            alerter.recovery_alert("mempool"),
+            Some(Notification::DataSourceRecovered {
+                source: "mempool".to_string(),
+                failures: 3,
+            })
+        );
+        assert_eq!(alerter.recovery_alert("mempool"), None);
+    }
+
+    #[tokio::test]
+    async fn finds_overdue_events() {
+        let mempool = MempoolClient::new(BASE_URL.to_string());
+        let oracle = setup_ernest_oracle(mempool).await;
+        let now = chrono::Utc::now().timestamp();
+        let matured = now as u32 - 3600;
+        let announcement = oracle
+            .create_event(CreateEvent::single(EventType::Hashrate, matured))
+            .await
+            .unwrap();
+        let event_id = announcement.oracle_event.event_id;
+
+        let overdue = oracle.unsigned_events_due_by(now - 1800).await.unwrap();
+        assert!(overdue.contains(&(event_id.clone(), matured as i64)));
+        let overdue = oracle.unsigned_events_due_by(now - 7200).await.unwrap();
+        assert!(!overdue.iter().any(|(overdue, _)| *overdue == event_id));
+    }
+}
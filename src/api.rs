@@ -0,0 +1,1209 @@
+//! The HTTP API of the oracle: its errors, the state its handlers share and the client.
+
+use std::time::Duration;
+
+use bitcoin::XOnlyPublicKey;
+use ddk::ddk_manager::Oracle as DlcOracle;
+use ddk::Oracle;
+use dlc_messages::oracle_msgs::{OracleAnnouncement, OracleAttestation};
+use reqwest::{Client, StatusCode};
+use uuid::Uuid;
+
+use crate::attestation::{AttestationProvenance, ErnestOracleOutcome};
+use crate::bundle::AnnouncementBundle;
+use crate::compat::ExportedEvent;
+use crate::creation_receipts::CreationReceipt;
+use crate::events::{EventType, EventTypeMetadata};
+use crate::history::MetricHistory;
+use crate::oracle::PendingEvent;
+use crate::parlay::backtest::{Backtest, BacktestRequest};
+use crate::parlay::builder::ParlayBuilder;
+use crate::parlay::contract::ParlayContract;
+use crate::parlay::estimate::{Estimate, EstimateRequest};
+use crate::routes::{
+    CreateEvent, EventDetail, EventListing, EventSearchResult, OracleInfo, OracleParams,
+    OutcomePreview, ParlayLegs, ParlayOptions, SignEvent,
+};
+use crate::series::{CreateSeries, SeriesManifest};
+use crate::stats::OracleStats;
+use crate::watcher::WatcherHealthReport;
+use crate::{
+    access_log, alerts, limits, mempool, nostr, notifications, oracle, routes, usage, validation,
+    watcher,
+};
+
+/// What kind of failure an [`OracleServerError`] is, for clients to branch on.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    NotFound,
+    /// The event exists but is not due for signing yet
+    NotMatured,
+    /// The event is due but has no attestation yet
+    NotSigned,
+    Validation,
+    /// A data source or another oracle failed
+    Upstream,
+    #[default]
+    Internal,
+    Unauthorized,
+    QuotaExceeded,
+    UnsupportedVersion,
+}
+
+impl ErrorCode {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ErrorCode::NotFound => StatusCode::NOT_FOUND,
+            ErrorCode::NotMatured => StatusCode::TOO_EARLY,
+            ErrorCode::NotSigned => StatusCode::CONFLICT,
+            ErrorCode::Validation => StatusCode::UNPROCESSABLE_ENTITY,
+            ErrorCode::Upstream => StatusCode::BAD_GATEWAY,
+            ErrorCode::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+            ErrorCode::Unauthorized => StatusCode::UNAUTHORIZED,
+            ErrorCode::QuotaExceeded => StatusCode::TOO_MANY_REQUESTS,
+            ErrorCode::UnsupportedVersion => StatusCode::NOT_ACCEPTABLE,
+        }
+    }
+
+    /// The code of an error answered without one, by oracles that predate codes.
+    pub fn from_status(status: StatusCode) -> Self {
+        match status {
+            StatusCode::NOT_FOUND => ErrorCode::NotFound,
+            StatusCode::TOO_EARLY => ErrorCode::NotMatured,
+            StatusCode::CONFLICT => ErrorCode::NotSigned,
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => ErrorCode::Unauthorized,
+            StatusCode::TOO_MANY_REQUESTS => ErrorCode::QuotaExceeded,
+            StatusCode::NOT_ACCEPTABLE => ErrorCode::UnsupportedVersion,
+            StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT => ErrorCode::Upstream,
+            status if status.is_client_error() => ErrorCode::Validation,
+            _ => ErrorCode::Internal,
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OracleServerError {
+    pub reason: String,
+    #[serde(default)]
+    pub code: ErrorCode,
+    /// The invalid fields of a [`ErrorCode::Validation`] error, when known
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub details: Vec<validation::FieldError>,
+    /// The id the oracle handled the failed request under, see [`access_log`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    /// Maturity of the event a [`ErrorCode::NotMatured`] or [`ErrorCode::NotSigned`] error is
+    /// about, for clients to time their polling
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event_maturity_epoch: Option<u32>,
+}
+
+impl OracleServerError {
+    pub fn new(code: ErrorCode, reason: impl ToString) -> Self {
+        Self {
+            reason: reason.to_string(),
+            code,
+            details: vec![],
+            request_id: None,
+            event_maturity_epoch: None,
+        }
+    }
+
+    pub fn with_maturity(mut self, event_maturity_epoch: u32) -> Self {
+        self.event_maturity_epoch = Some(event_maturity_epoch);
+        self
+    }
+}
+
+impl std::fmt::Display for OracleServerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.reason)
+    }
+}
+
+impl std::error::Error for OracleServerError {}
+
+/// Classify an error of the oracle. Errors without a known type are taken to be about the
+/// request.
+impl From<anyhow::Error> for OracleServerError {
+    fn from(e: anyhow::Error) -> Self {
+        if let Some(e) = e.downcast_ref::<OracleServerError>() {
+            return e.clone();
+        }
+        if let Some(errors) = e.downcast_ref::<validation::ValidationErrors>() {
+            return OracleServerError {
+                details: errors.0.clone(),
+                ..OracleServerError::new(ErrorCode::Validation, errors)
+            };
+        }
+        let code = if e.downcast_ref::<limits::LimitExceeded>().is_some() {
+            ErrorCode::Validation
+        } else if e.downcast_ref::<usage::QuotaExceeded>().is_some() {
+            ErrorCode::QuotaExceeded
+        } else if let Some(e) = e.downcast_ref::<sqlx::Error>() {
+            match e {
+                sqlx::Error::RowNotFound => ErrorCode::NotFound,
+                _ => ErrorCode::Internal,
+            }
+        } else if let Some(e) = e.downcast_ref::<kormir::error::Error>() {
+            match e {
+                kormir::error::Error::NotFound => ErrorCode::NotFound,
+                kormir::error::Error::StorageFailure | kormir::error::Error::Internal => {
+                    ErrorCode::Internal
+                }
+                _ => ErrorCode::Validation,
+            }
+        } else if e.downcast_ref::<reqwest::Error>().is_some() {
+            ErrorCode::Upstream
+        } else {
+            ErrorCode::Validation
+        };
+        OracleServerError::new(code, e)
+    }
+}
+
+/// Failing to reach the oracle.
+impl From<reqwest::Error> for OracleServerError {
+    fn from(e: reqwest::Error) -> Self {
+        OracleServerError::new(ErrorCode::Upstream, e)
+    }
+}
+
+/// The error body of an oracle, which may predate error codes.
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ErrorBody {
+    reason: String,
+    code: Option<ErrorCode>,
+    #[serde(default)]
+    details: Vec<validation::FieldError>,
+    request_id: Option<String>,
+    event_maturity_epoch: Option<u32>,
+}
+
+/// Send a request to the oracle and parse its answer, or the error it answered with.
+trait OracleRequest {
+    async fn fetch<T: serde::de::DeserializeOwned>(self) -> Result<T, OracleServerError>;
+}
+
+impl OracleRequest for reqwest::RequestBuilder {
+    async fn fetch<T: serde::de::DeserializeOwned>(self) -> Result<T, OracleServerError> {
+        let response = self.send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            let request_id = response
+                .headers()
+                .get(access_log::REQUEST_ID_HEADER)
+                .and_then(|header| header.to_str().ok())
+                .map(str::to_string);
+            let body = response.text().await?;
+            return Err(match serde_json::from_str::<ErrorBody>(&body) {
+                Ok(error) => OracleServerError {
+                    details: error.details,
+                    request_id: error.request_id.or(request_id),
+                    event_maturity_epoch: error.event_maturity_epoch,
+                    ..OracleServerError::new(
+                        error.code.unwrap_or(ErrorCode::from_status(status)),
+                        error.reason,
+                    )
+                },
+                Err(_) => OracleServerError {
+                    request_id,
+                    ..OracleServerError::new(
+                        ErrorCode::from_status(status),
+                        format!("Oracle request failed. status={} body={}", status, body),
+                    )
+                },
+            });
+        }
+        response.json::<T>().await.map_err(|e| {
+            OracleServerError::new(
+                ErrorCode::Upstream,
+                format!("Could not parse the oracle response. error={}", e),
+            )
+        })
+    }
+}
+
+/// Why [`ErnestOracleClient::wait_for_attestation`] gave up.
+#[derive(Debug)]
+pub enum WaitForAttestationError {
+    /// The event is still unsigned after the timeout, with the error of the last poll
+    Timeout {
+        event_id: String,
+        last_error: OracleServerError,
+    },
+    /// The event is not announced by the oracle
+    Oracle(OracleServerError),
+}
+
+impl std::fmt::Display for WaitForAttestationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WaitForAttestationError::Timeout {
+                event_id,
+                last_error,
+            } => write!(
+                f,
+                "Timed out waiting for attestation. event_id={} error={}",
+                event_id, last_error.reason
+            ),
+            WaitForAttestationError::Oracle(e) => write!(f, "{}", e.reason),
+        }
+    }
+}
+
+impl std::error::Error for WaitForAttestationError {}
+
+pub struct OracleServerState {
+    pub oracle: oracle::ErnestOracle,
+    pub mempool: mempool::MempoolClient,
+    pub watcher: watcher::WatcherHealth,
+    pub schedule: watcher::MaturitySchedule,
+    pub notifier: notifications::Notifier,
+    pub alerts: alerts::Alerter,
+    pub nostr: nostr::NostrConfig,
+    pub limits: limits::CreateLimits,
+    pub quotas: usage::Quotas,
+    /// Bearer token of the admin routes, which are disabled without one
+    pub admin_token: Option<String>,
+}
+
+pub fn oracle_err_to_manager_err(e: OracleServerError) -> ddk::ddk_manager::error::Error {
+    ddk::ddk_manager::error::Error::OracleError(e.reason.to_string())
+}
+
+pub struct ErnestOracleClient {
+    client: Client,
+    base_url: String,
+    pubkey: XOnlyPublicKey,
+}
+
+impl ErnestOracleClient {
+    pub async fn new(base_url: &str) -> Result<ErnestOracleClient, OracleServerError> {
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert(
+            routes::API_VERSION_HEADER,
+            reqwest::header::HeaderValue::from(routes::API_VERSION),
+        );
+        let client = Client::builder()
+            .timeout(Duration::from_secs(5))
+            .default_headers(headers)
+            .build()?;
+
+        // A fresh challenge, so a cached or replayed answer does not pass for the oracle
+        let challenge = Uuid::new_v4().simple().to_string();
+        let info = client
+            .get(format!("{}/api/info?challenge={}", &base_url, challenge))
+            .fetch::<OracleInfo>()
+            .await?;
+        // Oracles from before key proofs send none, only a proof that fails is refused
+        match &info.key_proof {
+            Some(proof)
+                if proof.challenge.as_deref() != Some(challenge.as_str())
+                    || !proof.verify(&info.pubkey) =>
+            {
+                return Err(OracleServerError::new(
+                    ErrorCode::Unauthorized,
+                    format!(
+                        "Oracle did not prove it holds the key it advertises. pubkey={}",
+                        info.pubkey
+                    ),
+                ))
+            }
+            Some(_) => {}
+            None => log::warn!(
+                "Oracle does not prove it holds the key it advertises. pubkey={}",
+                info.pubkey
+            ),
+        }
+
+        Ok(ErnestOracleClient {
+            client,
+            base_url: base_url.to_string(),
+            pubkey: info.pubkey,
+        })
+    }
+    async fn get<T>(&self, path: &str) -> Result<T, OracleServerError>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let url = format!("{}{}", self.base_url, path);
+        let response = self.client.get(url).fetch::<T>().await?;
+        Ok(response)
+    }
+    pub async fn create_event(
+        &self,
+        event: CreateEvent,
+    ) -> Result<OracleAnnouncement, reqwest::Error> {
+        let url = format!("{}/api/create", self.base_url);
+        let response = self
+            .client
+            .post(&url)
+            .json(&event)
+            .send()
+            .await?
+            .json::<OracleAnnouncement>()
+            .await?;
+        Ok(response)
+    }
+
+    /// Create the parlay `builder` describes, failing with the invalid fields before sending
+    /// anything when it does not pass the oracle's checks.
+    pub async fn create_parlay(
+        &self,
+        builder: ParlayBuilder,
+    ) -> Result<OracleAnnouncement, OracleServerError> {
+        let event = builder
+            .build()
+            .map_err(|errors| OracleServerError::from(anyhow::Error::from(errors)))?;
+        let url = format!("{}/api/create", self.base_url);
+        self.client.post(url).json(&event).fetch().await
+    }
+
+    /// The oracle's signed receipt for the creation of `event_id`, served after the event is
+    /// deleted too.
+    pub async fn get_creation_receipt(
+        &self,
+        event_id: &str,
+    ) -> Result<CreationReceipt, OracleServerError> {
+        let path = format!("/api/creation-receipt?eventId={}", event_id);
+        self.get::<CreationReceipt>(&path).await
+    }
+
+    pub async fn get_announcement_event(
+        &self,
+        event_id: &str,
+    ) -> Result<OracleAnnouncement, OracleServerError> {
+        let path = format!("/api/announcement?eventId={}", event_id);
+        let response = self.get::<OracleAnnouncement>(&path).await?;
+        Ok(response)
+    }
+
+    pub async fn get_attestation_event(
+        &self,
+        event_id: &str,
+    ) -> Result<OracleAttestation, OracleServerError> {
+        let path = format!("/api/attestation?eventId={}", event_id);
+        let response = self.get::<OracleAttestation>(&path).await?;
+        Ok(response)
+    }
+
+    /// The full record of an event, see [`EventDetail`].
+    pub async fn get_event_detail(&self, event_id: &str) -> Result<EventDetail, OracleServerError> {
+        let path = format!("/api/event?eventId={}", event_id);
+        let response = self.get::<EventDetail>(&path).await?;
+        Ok(response)
+    }
+
+    pub async fn get_parlay_contract(
+        &self,
+        event_id: &str,
+    ) -> Result<ParlayContract, OracleServerError> {
+        let path = format!("/api/parlay?eventId={}", event_id);
+        let response = self.get::<ParlayContract>(&path).await?;
+        Ok(response)
+    }
+    /// How each leg of an attested parlay scored and what it added to the combined score.
+    pub async fn get_parlay_legs(&self, event_id: &str) -> Result<ParlayLegs, OracleServerError> {
+        let path = format!("/api/parlay/legs?eventId={}", event_id);
+        let response = self.get::<ParlayLegs>(&path).await?;
+        Ok(response)
+    }
+
+    /// The digits a parlay announcement will be created with, so payout curves can be built
+    /// before the event exists.
+    pub async fn get_oracle_params(
+        &self,
+        max_normalized_value: Option<u64>,
+    ) -> Result<OracleParams, OracleServerError> {
+        let path = match max_normalized_value {
+            Some(max_normalized_value) => format!(
+                "/api/parlay/oracle-params?maxNormalizedValue={}",
+                max_normalized_value
+            ),
+            None => "/api/parlay/oracle-params".to_string(),
+        };
+        self.get::<OracleParams>(&path).await
+    }
+
+    async fn sign_event(&self, event: SignEvent) -> Result<OracleAttestation, OracleServerError> {
+        let url = format!("{}/api/sign-event", self.base_url);
+        let response = self
+            .client
+            .post(&url)
+            .json(&event)
+            .fetch::<OracleAttestation>()
+            .await?;
+        Ok(response)
+    }
+
+    pub async fn get_oracle_info(&self) -> Result<OracleInfo, OracleServerError> {
+        let response = self.get::<OracleInfo>("/api/info").await?;
+        Ok(response)
+    }
+
+    pub async fn list_events(&self) -> Result<Vec<EventListing>, OracleServerError> {
+        let events = self.get::<Vec<EventListing>>("/api/list-events").await?;
+        Ok(events)
+    }
+
+    /// Matured events the oracle has not signed yet, held ones included.
+    pub async fn list_pending_events(&self) -> Result<Vec<PendingEvent>, OracleServerError> {
+        self.get::<Vec<PendingEvent>>("/api/events/pending").await
+    }
+
+    pub async fn search_events(
+        &self,
+        query: Option<&str>,
+        tag: Option<&str>,
+    ) -> Result<Vec<EventSearchResult>, OracleServerError> {
+        let url = format!("{}/api/events/search", self.base_url);
+        let mut params = Vec::new();
+        if let Some(query) = query {
+            params.push(("q", query));
+        }
+        if let Some(tag) = tag {
+            params.push(("tag", tag));
+        }
+        let response = self
+            .client
+            .get(url)
+            .query(&params)
+            .fetch::<Vec<EventSearchResult>>()
+            .await?;
+        Ok(response)
+    }
+
+    pub async fn get_provenance(
+        &self,
+        event_id: &str,
+    ) -> Result<Vec<AttestationProvenance>, OracleServerError> {
+        let path = format!("/api/provenance?eventId={}", event_id);
+        self.get::<Vec<AttestationProvenance>>(&path).await
+    }
+
+    /// Fetch the provenance of an attested event and check every receipt against the oracle key.
+    pub async fn get_verified_provenance(
+        &self,
+        event_id: &str,
+    ) -> Result<Vec<AttestationProvenance>, OracleServerError> {
+        let provenance = self.get_provenance(event_id).await?;
+        if let Some(invalid) = provenance.iter().find(|p| !p.verify(&self.pubkey)) {
+            return Err(OracleServerError::new(
+                ErrorCode::Upstream,
+                format!(
+                    "Invalid provenance receipt. event_id={} data_type={}",
+                    invalid.event_id, invalid.data_type
+                ),
+            ));
+        }
+        Ok(provenance)
+    }
+
+    /// The watcher's last run, what it signed and what it left behind.
+    pub async fn get_status(&self) -> Result<WatcherHealthReport, OracleServerError> {
+        self.get::<WatcherHealthReport>("/api/status").await
+    }
+
+    pub async fn get_stats(&self) -> Result<OracleStats, OracleServerError> {
+        self.get::<OracleStats>("/api/stats").await
+    }
+
+    pub async fn get_available_events(&self) -> Result<Vec<EventType>, OracleServerError> {
+        let events = self.get::<Vec<EventType>>("/api/events/available").await?;
+        Ok(events)
+    }
+
+    pub async fn get_parlay_options(&self) -> Result<ParlayOptions, OracleServerError> {
+        self.get::<ParlayOptions>("/api/parlay/options").await
+    }
+
+    /// Units, digits and recent value ranges per event type, `periods` as e.g. `"1m,1y"`.
+    pub async fn get_events_metadata(
+        &self,
+        periods: Option<&str>,
+    ) -> Result<Vec<EventTypeMetadata>, OracleServerError> {
+        let path = match periods {
+            Some(periods) => format!("/api/events/metadata?periods={}", periods),
+            None => "/api/events/metadata".to_string(),
+        };
+        self.get::<Vec<EventTypeMetadata>>(&path).await
+    }
+
+    /// Sampled values of `data_type` between the unix timestamps `from` and `to`.
+    pub async fn get_history(
+        &self,
+        data_type: &EventType,
+        from: Option<i64>,
+        to: Option<i64>,
+    ) -> Result<MetricHistory, OracleServerError> {
+        let mut path = format!("/api/history?dataType={}", data_type);
+        if let Some(from) = from {
+            path.push_str(&format!("&from={}", from));
+        }
+        if let Some(to) = to {
+            path.push_str(&format!("&to={}", to));
+        }
+        self.get::<MetricHistory>(&path).await
+    }
+
+    /// Replay a proposed parlay over the oracle's metric history.
+    pub async fn backtest_parlay(
+        &self,
+        request: &BacktestRequest,
+    ) -> Result<Backtest, OracleServerError> {
+        let url = format!("{}/api/parlay/backtest", self.base_url);
+        self.client
+            .post(&url)
+            .json(request)
+            .fetch::<Backtest>()
+            .await
+    }
+
+    /// Estimate how likely a proposed parlay strikes from the oracle's metric history.
+    pub async fn estimate_parlay(
+        &self,
+        request: &EstimateRequest,
+    ) -> Result<Estimate, OracleServerError> {
+        let url = format!("{}/api/parlay/estimate", self.base_url);
+        let contract = serde_json::to_string(request)
+            .map_err(|e| OracleServerError::new(ErrorCode::Validation, e))?;
+        self.client
+            .get(&url)
+            .query(&[("contract", contract)])
+            .fetch::<Estimate>()
+            .await
+    }
+
+    /// What the attestation of `event_id` would be if it matured now.
+    pub async fn preview_outcome(
+        &self,
+        event_id: &str,
+    ) -> Result<OutcomePreview, OracleServerError> {
+        let path = format!("/api/outcome/preview?eventId={}", event_id);
+        self.get::<OutcomePreview>(&path).await
+    }
+
+    /// Export an event in the DLC spec test vector format.
+    pub async fn export_event(&self, event_id: &str) -> Result<ExportedEvent, OracleServerError> {
+        let path = format!("/api/export?eventId={}", event_id);
+        self.get::<ExportedEvent>(&path).await
+    }
+
+    /// Create a series of events and get the oracle's signed manifest over it.
+    pub async fn create_series(
+        &self,
+        request: &CreateSeries,
+    ) -> Result<SeriesManifest, OracleServerError> {
+        let url = format!("{}/api/series", self.base_url);
+        self.client
+            .post(&url)
+            .json(request)
+            .fetch::<SeriesManifest>()
+            .await
+    }
+
+    pub async fn get_series(&self, series_id: &str) -> Result<SeriesManifest, OracleServerError> {
+        let path = format!("/api/series?seriesId={}", series_id);
+        self.get::<SeriesManifest>(&path).await
+    }
+
+    /// Announcements of `event_ids` with the oracle's signed manifest over the set.
+    pub async fn get_bundle(
+        &self,
+        event_ids: &[&str],
+    ) -> Result<AnnouncementBundle, OracleServerError> {
+        let path = format!("/api/bundle?eventIds={}", event_ids.join(","));
+        self.get::<AnnouncementBundle>(&path).await
+    }
+
+    pub async fn get_attestation_outcome(
+        &self,
+        event_id: &str,
+    ) -> Result<ErnestOracleOutcome, OracleServerError> {
+        let path = format!("/api/attestation/outcome?eventId={}", event_id);
+        let response = self.get::<ErnestOracleOutcome>(&path).await?;
+        Ok(response)
+    }
+
+    /// Poll every `poll_interval` until the event is signed, for at most `timeout`. An event that
+    /// has not matured is not polled again before its maturity.
+    pub async fn wait_for_attestation(
+        &self,
+        event_id: &str,
+        poll_interval: Duration,
+        timeout: Duration,
+    ) -> Result<OracleAttestation, WaitForAttestationError> {
+        self.get_announcement_event(event_id)
+            .await
+            .map_err(WaitForAttestationError::Oracle)?;
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            let last_error = match self.get_attestation_event(event_id).await {
+                Ok(attestation) => return Ok(attestation),
+                Err(e) => e,
+            };
+            let now = tokio::time::Instant::now();
+            if now >= deadline {
+                return Err(WaitForAttestationError::Timeout {
+                    event_id: event_id.to_string(),
+                    last_error,
+                });
+            }
+            let wait = match last_error.event_maturity_epoch {
+                Some(maturity) if last_error.code == ErrorCode::NotMatured => {
+                    let until_maturity = maturity as i64 - chrono::Utc::now().timestamp();
+                    poll_interval.max(Duration::from_secs(until_maturity.max(0) as u64))
+                }
+                _ => poll_interval,
+            };
+            tokio::time::sleep(wait.min(deadline - now)).await;
+        }
+    }
+}
+
+impl Oracle for ErnestOracleClient {
+    fn name(&self) -> String {
+        "Ernest Oracle".to_string()
+    }
+}
+
+#[async_trait::async_trait]
+impl DlcOracle for ErnestOracleClient {
+    /// Returns the public key of the oracle.
+    fn get_public_key(&self) -> XOnlyPublicKey {
+        self.pubkey
+    }
+    /// Returns the announcement for the event with the given id if found.
+    async fn get_announcement(
+        &self,
+        event_id: &str,
+    ) -> Result<OracleAnnouncement, ddk::ddk_manager::error::Error> {
+        self.get_announcement_event(event_id)
+            .await
+            .map_err(oracle_err_to_manager_err)
+    }
+    /// Returns the attestation for the event with the given id if found.
+    async fn get_attestation(
+        &self,
+        event_id: &str,
+    ) -> Result<OracleAttestation, ddk::ddk_manager::error::Error> {
+        self.get_attestation_event(event_id)
+            .await
+            .map_err(oracle_err_to_manager_err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use crate::{
+        events::EventType,
+        parlay::{
+            contract::{CombinationMethod, ScoreMode, SCORING_VERSION},
+            parameter::{ParlayParameter, TransformationFunction},
+        },
+        routes::KeyProof,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn wait_for_attestation_polls_until_signed() {
+        use kormir::storage::MemoryStorage;
+        use wiremock::{matchers::path, Mock, MockServer, ResponseTemplate};
+
+        let secret_key = bitcoin::secp256k1::SecretKey::from_slice(&[3u8; 32]).unwrap();
+        let oracle =
+            kormir::Oracle::from_signing_key(MemoryStorage::default(), secret_key).unwrap();
+        let announcement = oracle
+            .create_enum_event("event".to_string(), vec!["a".to_string()], 0)
+            .await
+            .unwrap();
+        let attestation = oracle
+            .sign_enum_event("event".to_string(), "a".to_string())
+            .await
+            .unwrap();
+
+        let server = MockServer::start().await;
+        Mock::given(path("/api/info"))
+            .respond_with(SignedInfo {
+                secret_key,
+                advertised: oracle.public_key(),
+            })
+            .mount(&server)
+            .await;
+        let client = ErnestOracleClient::new(&server.uri()).await.unwrap();
+        let error = client
+            .wait_for_attestation("event", Duration::from_millis(10), Duration::from_secs(1))
+            .await
+            .unwrap_err();
+        assert!(matches!(error, WaitForAttestationError::Oracle(_)));
+
+        Mock::given(path("/api/announcement"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&announcement))
+            .mount(&server)
+            .await;
+        Mock::given(path("/api/attestation"))
+            .respond_with(
+                ResponseTemplate::new(400)
+                    .set_body_json(
+                        OracleServerError::new(ErrorCode::NotSigned, "Event is not signed")
+                            .with_maturity(1_700_000_000),
+                    )
+                    .insert_header(access_log::REQUEST_ID_HEADER, "request-1"),
+            )
+            .up_to_n_times(3)
+            .with_priority(1)
+            .mount(&server)
+            .await;
+        let error = client
+            .wait_for_attestation("event", Duration::from_millis(10), Duration::ZERO)
+            .await
+            .unwrap_err();
+        match error {
+            WaitForAttestationError::Timeout { last_error, .. } => {
+                assert_eq!(last_error.code, ErrorCode::NotSigned);
+                assert_eq!(last_error.request_id.as_deref(), Some("request-1"));
+                assert_eq!(last_error.event_maturity_epoch, Some(1_700_000_000));
+            }
+            error => panic!("Expected a timeout. error={}", error),
+        }
+
+        Mock::given(path("/api/attestation"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&attestation))
+            .mount(&server)
+            .await;
+        let signed = client
+            .wait_for_attestation("event", Duration::from_millis(10), Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert_eq!(signed, attestation);
+    }
+
+    #[tokio::test]
+    async fn created_event_parses_as_announcement() {
+        use crate::parlay::correlation::{LegWarning, LegWarningKind};
+        use crate::routes::CreatedEvent;
+        use kormir::storage::MemoryStorage;
+
+        let secret_key = bitcoin::secp256k1::SecretKey::from_slice(&[4u8; 32]).unwrap();
+        let oracle =
+            kormir::Oracle::from_signing_key(MemoryStorage::default(), secret_key).unwrap();
+        let announcement = oracle
+            .create_numeric_event("event".to_string(), 20, false, 0, "".to_string(), 0)
+            .await
+            .unwrap();
+        let created = CreatedEvent {
+            announcement: announcement.clone(),
+            warnings: vec![LegWarning {
+                kind: LegWarningKind::Correlated,
+                legs: [0, 1],
+                message: "correlated".to_string(),
+            }],
+            receipt: None,
+        };
+        let json = serde_json::to_value(&created).unwrap();
+        assert_eq!(
+            serde_json::from_value::<OracleAnnouncement>(json.clone()).unwrap(),
+            announcement
+        );
+        let parsed = serde_json::from_value::<CreatedEvent>(json).unwrap();
+        assert_eq!(parsed.announcement, announcement);
+        assert_eq!(parsed.warnings, created.warnings);
+    }
+
+    #[tokio::test]
+    async fn event_detail_roundtrips() {
+        use crate::routes::EventDetail;
+        use kormir::storage::{MemoryStorage, Storage};
+
+        let secret_key = bitcoin::secp256k1::SecretKey::from_slice(&[5u8; 32]).unwrap();
+        let oracle =
+            kormir::Oracle::from_signing_key(MemoryStorage::default(), secret_key).unwrap();
+        oracle
+            .create_numeric_event("event".to_string(), 8, false, 0, "".to_string(), 0)
+            .await
+            .unwrap();
+        let event = oracle
+            .storage
+            .get_event("event".to_string())
+            .await
+            .unwrap()
+            .unwrap();
+        let detail = EventDetail {
+            event,
+            metadata: Default::default(),
+            parlay_contract: None,
+            attestation_outcome: None,
+        };
+        let json = serde_json::to_value(&detail).unwrap();
+        assert!(json.get("parlayContract").is_none());
+        let parsed = serde_json::from_value::<EventDetail>(json).unwrap();
+        assert_eq!(parsed.event.announcement, detail.event.announcement);
+        assert_eq!(parsed.event.indexes, detail.event.indexes);
+    }
+
+    #[test]
+    fn classifies_errors() {
+        let code = |e: anyhow::Error| OracleServerError::from(e).code;
+        assert_eq!(code(sqlx::Error::RowNotFound.into()), ErrorCode::NotFound);
+        assert_eq!(code(sqlx::Error::PoolTimedOut.into()), ErrorCode::Internal);
+        assert_eq!(
+            code(limits::LimitExceeded("too many legs".to_string()).into()),
+            ErrorCode::Validation
+        );
+        assert_eq!(
+            code(OracleServerError::new(ErrorCode::NotSigned, "not signed").into()),
+            ErrorCode::NotSigned
+        );
+        assert_eq!(
+            code(anyhow::anyhow!("Invalid period")),
+            ErrorCode::Validation
+        );
+        let invalid =
+            OracleServerError::from(anyhow::Error::from(validation::ValidationErrors(vec![
+                validation::FieldError {
+                    field: "maturity".to_string(),
+                    message: "must be in the future".to_string(),
+                },
+            ])));
+        assert_eq!(invalid.code, ErrorCode::Validation);
+        assert_eq!(invalid.details[0].field, "maturity");
+
+        let error = serde_json::from_str::<OracleServerError>(r#"{"reason": "old"}"#).unwrap();
+        assert_eq!(error.code, ErrorCode::Internal);
+        assert_eq!(ErrorCode::NotMatured.status(), StatusCode::TOO_EARLY);
+        assert_eq!(
+            ErrorCode::from_status(StatusCode::TOO_EARLY),
+            ErrorCode::NotMatured
+        );
+    }
+
+    #[test]
+    fn negotiates_api_version() {
+        use crate::routes::{negotiate_api_version, API_VERSION};
+
+        assert_eq!(negotiate_api_version(None).unwrap(), API_VERSION);
+        assert_eq!(negotiate_api_version(Some("1")).unwrap(), 1);
+        assert_eq!(negotiate_api_version(Some("v1, 7")).unwrap(), 1);
+        assert!(negotiate_api_version(Some("2")).is_err());
+        assert!(negotiate_api_version(Some("latest")).is_err());
+    }
+
+    /// Answers `/api/info` advertising `advertised` and proving it with `secret_key`.
+    struct SignedInfo {
+        secret_key: bitcoin::secp256k1::SecretKey,
+        advertised: XOnlyPublicKey,
+    }
+
+    impl wiremock::Respond for SignedInfo {
+        fn respond(&self, request: &wiremock::Request) -> wiremock::ResponseTemplate {
+            let challenge = request
+                .url
+                .query_pairs()
+                .find(|(key, _)| key == "challenge")
+                .map(|(_, challenge)| challenge.to_string());
+            let secp = bitcoin::key::Secp256k1::new();
+            let signed_at = Utc::now().timestamp();
+            let signature = secp.sign_schnorr_no_aux_rand(
+                &crate::receipts::key_proof_message(challenge.as_deref(), signed_at),
+                &self.secret_key.keypair(&secp),
+            );
+            wiremock::ResponseTemplate::new(200).set_body_json(OracleInfo {
+                pubkey: self.advertised,
+                name: "mock".to_string(),
+                network: None,
+                data_sources: vec![],
+                event_types: vec![],
+                attestation_schedule: None,
+                version: None,
+                key_proof: Some(KeyProof {
+                    challenge,
+                    signed_at,
+                    signature,
+                }),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn refuses_oracles_that_fail_to_prove_their_key() {
+        use wiremock::{matchers::path, Mock, MockServer};
+
+        let secret_key = bitcoin::secp256k1::SecretKey::from_slice(&[3u8; 32]).unwrap();
+        let other = bitcoin::secp256k1::SecretKey::from_slice(&[4u8; 32]).unwrap();
+        let advertised = secret_key
+            .x_only_public_key(&bitcoin::key::Secp256k1::new())
+            .0;
+
+        let server = MockServer::start().await;
+        Mock::given(path("/api/info"))
+            .respond_with(SignedInfo {
+                secret_key: other,
+                advertised,
+            })
+            .mount(&server)
+            .await;
+        let Err(error) = ErnestOracleClient::new(&server.uri()).await else {
+            panic!("connected to an oracle that did not prove its key");
+        };
+        assert_eq!(error.code, ErrorCode::Unauthorized);
+
+        // A proof over another challenge, as a cached answer would have
+        let server = MockServer::start().await;
+        let secp = bitcoin::key::Secp256k1::new();
+        let signature = secp.sign_schnorr_no_aux_rand(
+            &crate::receipts::key_proof_message(Some("stale"), 0),
+            &secret_key.keypair(&secp),
+        );
+        Mock::given(path("/api/info"))
+            .respond_with(
+                wiremock::ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                    "pubkey": advertised,
+                    "name": "mock",
+                    "keyProof": { "challenge": "stale", "signedAt": 0, "signature": signature },
+                })),
+            )
+            .mount(&server)
+            .await;
+        let Err(error) = ErnestOracleClient::new(&server.uri()).await else {
+            panic!("connected to an oracle that did not prove its key");
+        };
+        assert_eq!(error.code, ErrorCode::Unauthorized);
+
+        let server = MockServer::start().await;
+        Mock::given(path("/api/info"))
+            .respond_with(SignedInfo {
+                secret_key,
+                advertised,
+            })
+            .mount(&server)
+            .await;
+        assert!(ErnestOracleClient::new(&server.uri()).await.is_ok());
+
+        // Older oracles do not send a proof at all
+        let server = MockServer::start().await;
+        Mock::given(path("/api/info"))
+            .respond_with(
+                wiremock::ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                    "pubkey": advertised,
+                    "name": "mock",
+                })),
+            )
+            .mount(&server)
+            .await;
+        let client = ErnestOracleClient::new(&server.uri()).await.unwrap();
+        assert_eq!(client.pubkey, advertised);
+    }
+
+    #[test]
+    fn oracle_info_parses_from_older_oracles() {
+        let info = serde_json::from_value::<OracleInfo>(serde_json::json!({
+            "pubkey": "4d84d5d4e83a64e1a7e0b8d3ce3a1b6a9a2b9e84e2a9a1d4c4b0e4b6f9b1c1d2",
+            "name": "Ernest Parlay Oracle"
+        }))
+        .unwrap();
+        assert!(info.network.is_none());
+        assert!(info.event_types.is_empty());
+        assert!(info.key_proof.is_none());
+    }
+
+    async fn create_event(client: &ErnestOracleClient) -> (OracleAnnouncement, CreateEvent) {
+        let now = Utc::now().timestamp();
+        let event = CreateEvent::Parlay {
+            parameters: vec![
+                ParlayParameter {
+                    data_type: EventType::Hashrate,
+                    threshold: 5000.0,
+                    range: 100000.0,
+                    is_above_threshold: true,
+                    transformation: TransformationFunction::Linear,
+                    weight: 1.0,
+                    percentile: None,
+                    aggregation: None,
+                    period: None,
+                    event_id: None,
+                    external: None,
+                },
+                ParlayParameter {
+                    data_type: EventType::Difficulty,
+                    threshold: 150000.0,
+                    range: 1000000.0,
+                    is_above_threshold: true,
+                    transformation: TransformationFunction::Linear,
+                    weight: 1.0,
+                    percentile: None,
+                    aggregation: None,
+                    period: None,
+                    event_id: None,
+                    external: None,
+                },
+            ],
+            combination_method: CombinationMethod::Multiply,
+            max_normalized_value: Some(10000),
+            event_maturity_epoch: (now + 1000) as u32,
+            score_mode: ScoreMode::default(),
+            description: None,
+            tags: vec![],
+            announce_at: None,
+            settlement_delay: None,
+            event_id: None,
+        };
+        let announcement = client.create_event(event.clone()).await.unwrap();
+        (announcement, event)
+    }
+
+    #[tokio::test]
+    async fn oracle_info() {
+        let oracle_url = std::env::var("ORACLE_URL").expect("ORACLE_URL must be set");
+        let client = ErnestOracleClient::new(&oracle_url).await.unwrap();
+        let info = client.get_oracle_info().await;
+        assert!(info.is_ok())
+    }
+
+    #[tokio::test]
+    async fn test_oracle_client() {
+        let oracle_url = std::env::var("ORACLE_URL").expect("ORACLE_URL must be set");
+        let client = ErnestOracleClient::new(&oracle_url).await.unwrap();
+        let (announcement, event) = create_event(&client).await;
+        let events = client.list_events().await.unwrap();
+        assert!(!events.is_empty());
+
+        let oracle_announcement = client
+            .get_announcement_event(&announcement.oracle_event.event_id)
+            .await
+            .unwrap();
+        assert_eq!(
+            announcement.oracle_event.event_id,
+            oracle_announcement.oracle_event.event_id
+        );
+
+        let params = client.get_oracle_params(Some(10000)).await.unwrap();
+        assert_eq!(
+            params.nb_digits as usize,
+            announcement.oracle_event.oracle_nonces.len()
+        );
+
+        let oracle_parlay_contract = client
+            .get_parlay_contract(&announcement.oracle_event.event_id)
+            .await
+            .unwrap();
+
+        let parlay_contract = if let CreateEvent::Parlay {
+            parameters,
+            combination_method,
+            max_normalized_value,
+            score_mode,
+            ..
+        } = event
+        {
+            ParlayContract {
+                id: announcement.oracle_event.event_id,
+                parameters,
+                combination_method,
+                max_normalized_value: max_normalized_value.unwrap(),
+                score_mode,
+                scoring_version: SCORING_VERSION,
+            }
+        } else {
+            panic!("Event is not a parlay");
+        };
+        assert_eq!(oracle_parlay_contract, parlay_contract);
+    }
+
+    #[tokio::test]
+    async fn test_oracle_client_multiple_events() {
+        let oracle_url = std::env::var("ORACLE_URL").expect("ORACLE_URL must be set");
+        let client = ErnestOracleClient::new(&oracle_url).await.unwrap();
+        let now = Utc::now().timestamp();
+        let event = CreateEvent::Parlay {
+            parameters: vec![
+                ParlayParameter {
+                    data_type: EventType::Hashrate,
+                    threshold: 5000.0,
+                    range: 100000.0,
+                    is_above_threshold: true,
+                    transformation: TransformationFunction::Linear,
+                    weight: 1.0,
+                    percentile: None,
+                    aggregation: None,
+                    period: None,
+                    event_id: None,
+                    external: None,
+                },
+                ParlayParameter {
+                    data_type: EventType::Difficulty,
+                    threshold: 150000.0,
+                    range: 1000000.0,
+                    is_above_threshold: true,
+                    transformation: TransformationFunction::Linear,
+                    weight: 1.0,
+                    percentile: None,
+                    aggregation: None,
+                    period: None,
+                    event_id: None,
+                    external: None,
+                },
+            ],
+            combination_method: CombinationMethod::Multiply,
+            max_normalized_value: Some(10000),
+            event_maturity_epoch: (now + 1000) as u32,
+            score_mode: ScoreMode::default(),
+            description: None,
+            tags: vec![],
+            announce_at: None,
+            settlement_delay: None,
+            event_id: None,
+        };
+
+        let now = Utc::now().timestamp();
+        let event_two = CreateEvent::Parlay {
+            parameters: vec![
+                ParlayParameter {
+                    data_type: EventType::Hashrate,
+                    threshold: 5000.0,
+                    range: 100000.0,
+                    is_above_threshold: true,
+                    transformation: TransformationFunction::Linear,
+                    weight: 1.0,
+                    percentile: None,
+                    aggregation: None,
+                    period: None,
+                    event_id: None,
+                    external: None,
+                },
+                ParlayParameter {
+                    data_type: EventType::Difficulty,
+                    threshold: 150000.0,
+                    range: 1000000.0,
+                    is_above_threshold: true,
+                    transformation: TransformationFunction::Linear,
+                    weight: 1.0,
+                    percentile: None,
+                    aggregation: None,
+                    period: None,
+                    event_id: None,
+                    external: None,
+                },
+            ],
+            combination_method: CombinationMethod::Multiply,
+            max_normalized_value: Some(10000),
+            event_maturity_epoch: (now + 1000) as u32,
+            score_mode: ScoreMode::default(),
+            description: None,
+            tags: vec![],
+            announce_at: None,
+            settlement_delay: None,
+            event_id: None,
+        };
+        client.create_event(event.clone()).await.unwrap();
+        client.create_event(event_two.clone()).await.unwrap();
+    }
+}
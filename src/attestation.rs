@@ -1,6 +1,16 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use sqlx::{prelude::FromRow, PgPool, Postgres};
+use sqlx::{prelude::FromRow, types::Json, PgPool, Postgres, Transaction};
+
+use bitcoin::{
+    secp256k1::{schnorr::Signature, Message},
+    XOnlyPublicKey,
+};
+
+use crate::{
+    mempool::{DataProvenance, DataSample},
+    receipts,
+};
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -8,6 +18,8 @@ pub struct ErnestOracleOutcome {
     pub event_id: String,
     pub combined_score: f64,
     pub attested_value: i32,
+    /// Whether the score had to be clamped to fit the announced digits
+    pub clamped: bool,
     pub outcomes: Vec<AttestationDataOutcome>,
 }
 
@@ -17,16 +29,20 @@ pub struct AttestationOutcome {
     pub event_id: String,
     pub combined_score: f64,
     pub attested_value: i32,
+    pub clamped: bool,
     pub created_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Serialize, Deserialize, FromRow)]
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 #[serde(rename_all = "camelCase")]
 pub struct AttestationDataOutcome {
     pub event_id: String,
     pub data_type: String,
+    /// Position of `original_value` in the leg's range, from 0 to 1
     pub normalized_value: f64,
     pub original_value: f64,
+    /// `normalized_value` after the leg's transformation, what the combined score is made of
+    pub transformed_value: f64,
 }
 
 pub async fn get_attestation_outcome(
@@ -34,91 +50,249 @@ pub async fn get_attestation_outcome(
     event_id: String,
 ) -> anyhow::Result<ErnestOracleOutcome> {
     let outcome = sqlx::query_as::<Postgres, AttestationOutcome>(
-        "SELECT * FROM numeric_attestation_outcome WHERE event_id = $1",
+        "SELECT event_id, combined_score, attested_value, clamped, created_at FROM numeric_attestation_outcome WHERE event_id = $1",
     )
     .bind(&event_id)
-    .fetch_one(&*pool)
+    .fetch_one(pool)
     .await?;
 
-    let data_outcomes = sqlx::query_as::<Postgres, AttestationDataOutcome>(
-        "SELECT * FROM numeric_attestation_data_outcome WHERE event_id = $1",
+    let outcomes = sqlx::query_as::<Postgres, AttestationDataOutcome>(
+        "SELECT event_id, data_type, normalized_value, original_value, transformed_value FROM numeric_attestation_data_outcome WHERE event_id = $1 ORDER BY id",
     )
     .bind(&event_id)
-    .fetch_all(&*pool)
+    .fetch_all(pool)
     .await?;
 
-    let outcomes = data_outcomes
-        .into_iter()
-        .map(|outcome| AttestationDataOutcome {
-            event_id: outcome.event_id,
-            data_type: outcome.data_type,
-            normalized_value: outcome.normalized_value,
-            original_value: outcome.original_value,
-        })
-        .collect();
-
     Ok(ErnestOracleOutcome {
         event_id,
         combined_score: outcome.combined_score,
         attested_value: outcome.attested_value,
+        clamped: outcome.clamped,
         outcomes,
     })
 }
 
+/// The value an event was signed with, `None` while it is unsigned.
+pub async fn get_attested_value(pool: &PgPool, event_id: &str) -> anyhow::Result<Option<i64>> {
+    let value = sqlx::query_scalar::<Postgres, i32>(
+        "SELECT attested_value FROM numeric_attestation_outcome WHERE event_id = $1",
+    )
+    .bind(event_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(value.map(i64::from))
+}
+
 pub async fn save_attestation_data_outcomes(
     pool: &PgPool,
     outcomes: Vec<AttestationDataOutcome>,
 ) -> anyhow::Result<()> {
-    for outcome in outcomes {
-        save_attestation_data_outcome(
-            pool,
-            outcome.event_id,
-            outcome.data_type,
-            outcome.normalized_value,
-            outcome.original_value,
-        )
-        .await?;
+    let mut tx = pool.begin().await?;
+    for outcome in &outcomes {
+        insert_attestation_data_outcome(&mut tx, outcome).await?;
     }
+    tx.commit().await?;
     Ok(())
 }
 
-pub async fn save_attestation_data_outcome(
+pub async fn save_attestation_outcome(
     pool: &PgPool,
     event_id: String,
-    data_type: String,
-    normalized_value: f64,
-    original_value: f64,
+    combined_score: f64,
+    attested_value: i64,
+    clamped: bool,
 ) -> anyhow::Result<()> {
     let mut tx = pool.begin().await?;
+    AttestationRecord {
+        event_id,
+        combined_score,
+        attested_value,
+        clamped,
+        outcomes: vec![],
+        provenance: vec![],
+    }
+    .insert(&mut tx)
+    .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// What is saved along with the signatures of an event, in the transaction that saves them, so
+/// a signed event always has its outcome, its legs and its provenance.
+#[derive(Debug, Clone)]
+pub struct AttestationRecord {
+    pub event_id: String,
+    pub combined_score: f64,
+    pub attested_value: i64,
+    pub clamped: bool,
+    pub outcomes: Vec<AttestationDataOutcome>,
+    /// Data type, provenance and the oracle's receipt over it
+    pub provenance: Vec<(String, DataProvenance, Signature)>,
+}
+
+impl AttestationRecord {
+    /// An outcome signed as given, without leg data or provenance, e.g. a manual override.
+    pub fn value(event_id: String, attested_value: i64) -> Self {
+        Self {
+            event_id,
+            combined_score: attested_value as f64,
+            attested_value,
+            clamped: false,
+            outcomes: vec![],
+            provenance: vec![],
+        }
+    }
+
+    pub async fn insert(&self, tx: &mut Transaction<'_, Postgres>) -> anyhow::Result<()> {
+        sqlx::query(
+            "INSERT INTO numeric_attestation_outcome (event_id, combined_score, attested_value, clamped) VALUES ($1, $2, $3, $4)",
+        )
+        .bind(&self.event_id)
+        .bind(self.combined_score)
+        .bind(self.attested_value)
+        .bind(self.clamped)
+        .execute(&mut **tx)
+        .await?;
+        for outcome in &self.outcomes {
+            insert_attestation_data_outcome(tx, outcome).await?;
+        }
+        for (data_type, provenance, signature) in &self.provenance {
+            insert_attestation_provenance(tx, &self.event_id, data_type, provenance, signature)
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+async fn insert_attestation_data_outcome(
+    tx: &mut Transaction<'_, Postgres>,
+    outcome: &AttestationDataOutcome,
+) -> anyhow::Result<()> {
     sqlx::query(
-      "INSERT INTO numeric_attestation_data_outcome (event_id, data_type, normalized_value, original_value) VALUES ($1, $2, $3, $4)",
+        "INSERT INTO numeric_attestation_data_outcome (event_id, data_type, normalized_value, original_value, transformed_value) VALUES ($1, $2, $3, $4, $5)",
     )
-    .bind(&event_id)
-    .bind(&data_type)
-    .bind(&normalized_value)
-    .bind(&original_value)
-    .execute(&mut *tx)
+    .bind(&outcome.event_id)
+    .bind(&outcome.data_type)
+    .bind(outcome.normalized_value)
+    .bind(outcome.original_value)
+    .bind(outcome.transformed_value)
+    .execute(&mut **tx)
     .await?;
-
-    tx.commit().await?;
     Ok(())
 }
 
-pub async fn save_attestation_outcome(
+/// The upstream data a signed value was derived from, one row per data type fetched.
+///
+/// `signature` is the oracle's receipt over [`receipts::provenance_message`], so anyone holding
+/// the oracle pubkey can pin the oracle to the inputs it claims to have used.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttestationProvenance {
+    pub event_id: String,
+    pub data_type: String,
+    pub source_url: String,
+    pub raw_response: serde_json::Value,
+    pub fetched_at: DateTime<Utc>,
+    pub value: f64,
+    pub signature: Option<Signature>,
+    /// Every fetch the value was chosen from, see [`crate::sampling`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub samples: Vec<DataSample>,
+}
+
+impl AttestationProvenance {
+    pub fn message(&self) -> Message {
+        receipts::provenance_message(
+            &self.event_id,
+            &self.source_url,
+            self.value,
+            self.fetched_at,
+        )
+    }
+
+    /// Whether the receipt is present and signed by `pubkey`.
+    pub fn verify(&self, pubkey: &XOnlyPublicKey) -> bool {
+        self.signature
+            .map(|signature| receipts::verify_receipt(pubkey, &self.message(), &signature))
+            .unwrap_or(false)
+    }
+}
+
+#[derive(Debug, FromRow)]
+struct AttestationProvenanceRow {
+    event_id: String,
+    data_type: String,
+    source_url: String,
+    raw_response: serde_json::Value,
+    fetched_at: DateTime<Utc>,
+    value: f64,
+    signature: Option<Vec<u8>>,
+    samples: Option<Json<Vec<DataSample>>>,
+}
+
+pub async fn save_attestation_provenance(
     pool: &PgPool,
     event_id: String,
-    combined_score: f64,
-    attested_value: u64,
+    data_type: String,
+    provenance: &DataProvenance,
+    signature: Signature,
 ) -> anyhow::Result<()> {
     let mut tx = pool.begin().await?;
+    insert_attestation_provenance(&mut tx, &event_id, &data_type, provenance, &signature).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+async fn insert_attestation_provenance(
+    tx: &mut Transaction<'_, Postgres>,
+    event_id: &str,
+    data_type: &str,
+    provenance: &DataProvenance,
+    signature: &Signature,
+) -> anyhow::Result<()> {
     sqlx::query(
-        "INSERT INTO numeric_attestation_outcome (event_id, combined_score, attested_value) VALUES ($1, $2, $3)",
+        "INSERT INTO attestation_provenance (event_id, data_type, source_url, raw_response, fetched_at, value, signature, samples) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
     )
-    .bind(&event_id)
-    .bind(&combined_score)
-    .bind(attested_value as i64)
-    .execute(&mut *tx)
+    .bind(event_id)
+    .bind(data_type)
+    .bind(&provenance.source_url)
+    .bind(&provenance.raw_response)
+    .bind(provenance.fetched_at)
+    .bind(provenance.value)
+    .bind(signature.serialize().to_vec())
+    .bind((!provenance.samples.is_empty()).then_some(Json(&provenance.samples)))
+    .execute(&mut **tx)
     .await?;
-    tx.commit().await?;
     Ok(())
 }
+
+pub async fn get_attestation_provenance(
+    pool: &PgPool,
+    event_id: String,
+) -> anyhow::Result<Vec<AttestationProvenance>> {
+    let rows = sqlx::query_as::<Postgres, AttestationProvenanceRow>(
+        "SELECT event_id, data_type, source_url, raw_response, fetched_at, value, signature, samples FROM attestation_provenance WHERE event_id = $1 ORDER BY id",
+    )
+    .bind(&event_id)
+    .fetch_all(pool)
+    .await?;
+
+    rows.into_iter()
+        .map(|row| {
+            let signature = row
+                .signature
+                .map(|sig| Signature::from_slice(&sig))
+                .transpose()?;
+            Ok(AttestationProvenance {
+                event_id: row.event_id,
+                data_type: row.data_type,
+                source_url: row.source_url,
+                raw_response: row.raw_response,
+                fetched_at: row.fetched_at,
+                value: row.value,
+                signature,
+                samples: row.samples.map(|samples| samples.0).unwrap_or_default(),
+            })
+        })
+        .collect()
+}
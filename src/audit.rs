@@ -0,0 +1,223 @@
+//! Append-only log of every signature the oracle makes.
+//!
+//! Each entry hashes the one before it, so a row removed or edited behind the database trigger
+//! breaks the chain at that entry. Entries are written in the transaction that saves the
+//! signatures, so an event is never signed without its entry.
+
+use chrono::{DateTime, SubsecRound, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{prelude::FromRow, PgConnection, PgPool, Postgres};
+use strum_macros::{Display, EnumString};
+
+use crate::receipts;
+
+/// Entries returned when an audit request does not set `limit`.
+pub const DEFAULT_AUDIT_LIMIT: i64 = 100;
+pub const MAX_AUDIT_LIMIT: i64 = 1000;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Display, EnumString)]
+#[serde(rename_all = "camelCase")]
+#[strum(serialize_all = "camelCase")]
+pub enum SigningSource {
+    Watcher,
+    Admin,
+    Api,
+    /// An outcome given by an admin, see [`crate::overrides`]
+    Override,
+}
+
+#[derive(Debug, Clone, FromRow)]
+struct SigningAuditRow {
+    id: i64,
+    event_id: String,
+    outcome: i64,
+    nonce_indexes: Vec<i32>,
+    source: String,
+    signed_at: DateTime<Utc>,
+    prev_hash: Option<Vec<u8>>,
+    hash: Vec<u8>,
+}
+
+const COLUMNS: &str = "id, event_id, outcome, nonce_indexes, source, signed_at, prev_hash, hash";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SigningAuditEntry {
+    pub id: i64,
+    pub event_id: String,
+    pub outcome: i64,
+    pub nonce_indexes: Vec<u32>,
+    pub source: String,
+    pub signed_at: DateTime<Utc>,
+    /// Hex hash of the previous entry, `None` for the first one
+    pub prev_hash: Option<String>,
+    pub hash: String,
+}
+
+impl From<SigningAuditRow> for SigningAuditEntry {
+    fn from(row: SigningAuditRow) -> Self {
+        Self {
+            id: row.id,
+            event_id: row.event_id,
+            outcome: row.outcome,
+            nonce_indexes: row.nonce_indexes.into_iter().map(|i| i as u32).collect(),
+            source: row.source,
+            signed_at: row.signed_at,
+            prev_hash: row.prev_hash.map(hex::encode),
+            hash: hex::encode(row.hash),
+        }
+    }
+}
+
+impl SigningAuditEntry {
+    /// Hash of the entry recomputed from its fields and `prev_hash`.
+    pub fn compute_hash(&self) -> anyhow::Result<String> {
+        let prev_hash = self.prev_hash.as_deref().map(hex::decode).transpose()?;
+        Ok(hex::encode(receipts::signing_audit_hash(
+            prev_hash.as_deref(),
+            &self.event_id,
+            self.outcome,
+            &self.nonce_indexes,
+            &self.source,
+            self.signed_at,
+        )))
+    }
+}
+
+/// Append a signature to the audit log.
+pub(crate) async fn record_signing(
+    conn: &mut PgConnection,
+    event_id: &str,
+    outcome: i64,
+    nonce_indexes: &[u32],
+    source: SigningSource,
+) -> anyhow::Result<SigningAuditEntry> {
+    // Postgres keeps microseconds, hash what will be stored
+    let signed_at = Utc::now().trunc_subsecs(6);
+    let source = source.to_string();
+
+    // Concurrent signers would otherwise both chain onto the same entry, held until `conn` commits
+    sqlx::query("LOCK TABLE signing_audit IN EXCLUSIVE MODE")
+        .execute(&mut *conn)
+        .await?;
+    let prev_hash = sqlx::query_scalar::<Postgres, Vec<u8>>(
+        "SELECT hash FROM signing_audit ORDER BY id DESC LIMIT 1",
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+    let hash = receipts::signing_audit_hash(
+        prev_hash.as_deref(),
+        event_id,
+        outcome,
+        nonce_indexes,
+        &source,
+        signed_at,
+    );
+    let row = sqlx::query_as::<Postgres, SigningAuditRow>(&format!(
+        r#"
+        INSERT INTO signing_audit (event_id, outcome, nonce_indexes, source, signed_at, prev_hash, hash)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING {}
+        "#,
+        COLUMNS
+    ))
+    .bind(event_id)
+    .bind(outcome)
+    .bind(nonce_indexes.iter().map(|i| *i as i32).collect::<Vec<_>>())
+    .bind(&source)
+    .bind(signed_at)
+    .bind(prev_hash)
+    .bind(hash.to_vec())
+    .fetch_one(conn)
+    .await?;
+    Ok(row.into())
+}
+
+/// Outcome of the last signature recorded for `event_id`.
+pub async fn signed_outcome(pool: &PgPool, event_id: &str) -> anyhow::Result<Option<i64>> {
+    Ok(sqlx::query_scalar::<Postgres, i64>(
+        "SELECT outcome FROM signing_audit WHERE event_id = $1 ORDER BY id DESC LIMIT 1",
+    )
+    .bind(event_id)
+    .fetch_optional(pool)
+    .await?)
+}
+
+/// Entries after the id `after`, oldest first.
+pub async fn entries(
+    pool: &PgPool,
+    after: Option<i64>,
+    limit: i64,
+) -> anyhow::Result<Vec<SigningAuditEntry>> {
+    let rows = sqlx::query_as::<Postgres, SigningAuditRow>(&format!(
+        "SELECT {} FROM signing_audit WHERE id > $1 ORDER BY id LIMIT $2",
+        COLUMNS
+    ))
+    .bind(after.unwrap_or(0))
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().map(SigningAuditEntry::from).collect())
+}
+
+/// Check `entries` are a chain starting at `prev_hash`. Returns the id of the first entry that
+/// does not match, if any.
+pub fn verify_chain(prev_hash: Option<&str>, entries: &[SigningAuditEntry]) -> Option<i64> {
+    let mut prev_hash = prev_hash.map(str::to_string);
+    for entry in entries {
+        if entry.prev_hash != prev_hash || entry.compute_hash().ok().as_ref() != Some(&entry.hash) {
+            return Some(entry.id);
+        }
+        prev_hash = Some(entry.hash.clone());
+    }
+    None
+}
+
+/// Verify the whole audit log, returning the id of the first broken entry.
+pub async fn verify(pool: &PgPool) -> anyhow::Result<Option<i64>> {
+    let entries = entries(pool, None, i64::MAX).await?;
+    Ok(verify_chain(None, &entries))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mempool::{MempoolClient, BASE_URL};
+    use crate::test_util::setup_ernest_oracle;
+
+    #[tokio::test]
+    async fn records_hash_chain() {
+        let mempool = MempoolClient::new(BASE_URL.to_string());
+        let oracle = setup_ernest_oracle(mempool).await;
+        let pool = &oracle.oracle.storage.pool;
+        let event_id = uuid::Uuid::new_v4().to_string();
+
+        let mut tx = pool.begin().await.unwrap();
+        let first = record_signing(&mut tx, &event_id, 42, &[1, 2], SigningSource::Admin)
+            .await
+            .unwrap();
+        let second = record_signing(&mut tx, &event_id, -7, &[3], SigningSource::Api)
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+        // Other tests may sign in between, check the chain from the first entry on
+        let chain = entries(pool, Some(first.id - 1), i64::MAX).await.unwrap();
+        assert_eq!(chain[0], first);
+        assert!(chain.contains(&second));
+        assert_eq!(verify_chain(first.prev_hash.as_deref(), &chain), None);
+
+        let mut tampered = chain.clone();
+        let position = chain.iter().position(|entry| *entry == second).unwrap();
+        tampered[position].outcome = 7;
+        assert_eq!(
+            verify_chain(first.prev_hash.as_deref(), &tampered),
+            Some(second.id)
+        );
+
+        let update = sqlx::query("UPDATE signing_audit SET outcome = 0 WHERE id = $1")
+            .bind(second.id)
+            .execute(pool)
+            .await;
+        assert!(update.is_err());
+    }
+}
@@ -0,0 +1,142 @@
+//! Signed bundles of announcements.
+//!
+//! An aggregator relaying announcements of this oracle can be asked which events it got and
+//! when. A bundle carries the announcements with one oracle signature over the sorted hashes of
+//! the announcements and the time they were served, so the aggregator can prove the exact set to
+//! third parties without relaying the announcements one by one.
+
+use bitcoin::{
+    hashes::{sha256, Hash},
+    secp256k1::{schnorr::Signature, Message},
+    XOnlyPublicKey,
+};
+use chrono::{DateTime, Utc};
+use kormir::{OracleAnnouncement, Writeable};
+use serde::{Deserialize, Serialize};
+
+use crate::receipts;
+
+/// Most announcements served in one bundle.
+pub const MAX_BUNDLE_EVENTS: usize = 500;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnnouncementBundle {
+    /// The announcements, in the order of their hashes in the manifest
+    pub announcements: Vec<OracleAnnouncement>,
+    pub manifest: BundleManifest,
+}
+
+/// The oracle's signature over the set of announcements it served at `served_at`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleManifest {
+    pub oracle_public_key: XOnlyPublicKey,
+    /// Hashes of the serialized announcements, sorted
+    pub announcement_hashes: Vec<sha256::Hash>,
+    pub served_at: DateTime<Utc>,
+    pub signature: Signature,
+}
+
+impl BundleManifest {
+    pub fn message(announcement_hashes: &[sha256::Hash], served_at: DateTime<Utc>) -> Message {
+        let hashes = announcement_hashes
+            .iter()
+            .map(|hash| hash.to_byte_array())
+            .collect::<Vec<_>>();
+        receipts::bundle_message(&hashes, served_at)
+    }
+
+    /// Whether the manifest is signed by `pubkey`.
+    pub fn verify(&self, pubkey: &XOnlyPublicKey) -> bool {
+        receipts::verify_receipt(
+            pubkey,
+            &Self::message(&self.announcement_hashes, self.served_at),
+            &self.signature,
+        )
+    }
+}
+
+impl AnnouncementBundle {
+    /// Bundle `announcements`, signing the manifest with `sign`.
+    pub fn new(
+        mut announcements: Vec<OracleAnnouncement>,
+        oracle_public_key: XOnlyPublicKey,
+        served_at: DateTime<Utc>,
+        sign: impl FnOnce(&Message) -> Signature,
+    ) -> Self {
+        announcements.sort_by_key(announcement_hash);
+        let announcement_hashes = announcements
+            .iter()
+            .map(announcement_hash)
+            .collect::<Vec<_>>();
+        let signature = sign(&BundleManifest::message(&announcement_hashes, served_at));
+        AnnouncementBundle {
+            announcements,
+            manifest: BundleManifest {
+                oracle_public_key,
+                announcement_hashes,
+                served_at,
+                signature,
+            },
+        }
+    }
+
+    /// Whether the manifest is signed by `pubkey` and commits to exactly these announcements.
+    pub fn verify(&self, pubkey: &XOnlyPublicKey) -> bool {
+        self.manifest.verify(pubkey)
+            && self.announcements.iter().map(announcement_hash).eq(self
+                .manifest
+                .announcement_hashes
+                .iter()
+                .copied())
+    }
+}
+
+/// Hash of the serialized announcement, what a bundle manifest commits to.
+pub fn announcement_hash(announcement: &OracleAnnouncement) -> sha256::Hash {
+    sha256::Hash::hash(&announcement.encode())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::{key::Secp256k1, secp256k1::SecretKey};
+    use kormir::storage::MemoryStorage;
+
+    #[tokio::test]
+    async fn bundles_verify_against_their_announcements() {
+        let secret_key = SecretKey::from_slice(&[5u8; 32]).unwrap();
+        let oracle =
+            kormir::Oracle::from_signing_key(MemoryStorage::default(), secret_key).unwrap();
+        let mut announcements = vec![];
+        for name in ["a", "b", "c"] {
+            announcements.push(
+                oracle
+                    .create_numeric_event(name.to_string(), 8, false, 0, "u".to_string(), 0)
+                    .await
+                    .unwrap(),
+            );
+        }
+        let secp = Secp256k1::new();
+        let keypair = secret_key.keypair(&secp);
+        let sign = |message: &Message| secp.sign_schnorr_no_aux_rand(message, &keypair);
+        let served_at = Utc::now();
+        let bundle =
+            AnnouncementBundle::new(announcements.clone(), oracle.public_key(), served_at, sign);
+        assert!(bundle.verify(&oracle.public_key()));
+
+        // The order announcements were asked in does not change the manifest
+        announcements.reverse();
+        let reversed = AnnouncementBundle::new(announcements, oracle.public_key(), served_at, sign);
+        assert_eq!(reversed.manifest.signature, bundle.manifest.signature);
+
+        let mut dropped = bundle.clone();
+        dropped.announcements.pop();
+        assert!(!dropped.verify(&oracle.public_key()));
+
+        let mut later = bundle.clone();
+        later.manifest.served_at = served_at + chrono::Duration::seconds(1);
+        assert!(!later.verify(&oracle.public_key()));
+    }
+}
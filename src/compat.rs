@@ -0,0 +1,252 @@
+//! Responses matching the REST convention used by other DLC oracle servers (suredbits/lava
+//! style) so wallets built against them can use this oracle unchanged.
+//!
+//! Every response is wrapped as `{"result": ..., "error": ...}` and announcements and
+//! attestations are also provided as hex-encoded DLC spec TLVs.
+
+use std::sync::Arc;
+
+use dlc_messages::ser_impls::write_as_tlv;
+use kormir::{lightning::ln::wire::Type, storage::Storage, OracleAnnouncement, OracleAttestation};
+use serde::{Deserialize, Serialize};
+
+use crate::OracleServerState;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompatResponse<T> {
+    pub result: Option<T>,
+    pub error: Option<String>,
+}
+
+impl<T> CompatResponse<T> {
+    pub fn ok(result: T) -> Self {
+        Self {
+            result: Some(result),
+            error: None,
+        }
+    }
+
+    pub fn err(error: impl ToString) -> Self {
+        Self {
+            result: None,
+            error: Some(error.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompatAnnouncement {
+    pub event_id: String,
+    pub maturation_time: u32,
+    #[serde(rename = "announcementTLV")]
+    pub announcement_tlv: String,
+    pub announcement: OracleAnnouncement,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompatAttestation {
+    pub event_id: String,
+    #[serde(rename = "attestationTLV")]
+    pub attestation_tlv: String,
+    pub outcomes: Vec<String>,
+    pub attestation: OracleAttestation,
+}
+
+/// A DLC message as it appears in the DLC spec test vectors, its JSON fields next to the
+/// hex-encoded TLV.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpecMessage<T> {
+    pub message: T,
+    pub serialized: String,
+}
+
+impl<T: Type + kormir::Writeable> SpecMessage<T> {
+    pub fn new(message: T) -> Result<Self, CompatError> {
+        Ok(Self {
+            serialized: to_tlv_hex(&message)?,
+            message,
+        })
+    }
+}
+
+/// An announced event and its attestation, if signed, for interop testing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportedEvent {
+    pub event_id: String,
+    pub oracle_announcement: SpecMessage<OracleAnnouncement>,
+    pub oracle_attestation: Option<SpecMessage<OracleAttestation>>,
+}
+
+#[derive(Debug)]
+pub enum CompatError {
+    NotFound(String),
+    Internal(String),
+}
+
+impl std::fmt::Display for CompatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CompatError::NotFound(reason) | CompatError::Internal(reason) => {
+                write!(f, "{}", reason)
+            }
+        }
+    }
+}
+
+pub fn to_tlv_hex<T>(message: &T) -> Result<String, CompatError>
+where
+    T: Type + kormir::Writeable,
+{
+    let mut bytes = Vec::new();
+    write_as_tlv(message, &mut bytes).map_err(|e| CompatError::Internal(e.to_string()))?;
+    Ok(hex::encode(bytes))
+}
+
+pub fn public_key_internal(state: Arc<OracleServerState>) -> String {
+    state.oracle.oracle.public_key().to_string()
+}
+
+pub async fn list_announcements_internal(
+    state: Arc<OracleServerState>,
+) -> Result<Vec<String>, CompatError> {
+    let events = state
+        .oracle
+        .oracle
+        .storage
+        .oracle_event_data()
+        .await
+        .map_err(|e| CompatError::Internal(e.to_string()))?;
+    Ok(events.into_iter().map(|event| event.event_id).collect())
+}
+
+pub async fn announcement_internal(
+    state: Arc<OracleServerState>,
+    event_id: String,
+) -> Result<CompatAnnouncement, CompatError> {
+    let storage = &state.oracle.oracle.storage;
+    let not_found =
+        || CompatError::NotFound(format!("Announcement not found. event_id={}", event_id));
+    if !storage
+        .is_announced(&event_id)
+        .await
+        .map_err(|e| CompatError::Internal(e.to_string()))?
+    {
+        return Err(not_found());
+    }
+    let event = storage
+        .get_event(event_id.clone())
+        .await
+        .map_err(|e| CompatError::Internal(e.to_string()))?
+        .ok_or_else(not_found)?;
+
+    Ok(CompatAnnouncement {
+        event_id: event.event_id,
+        maturation_time: event.announcement.oracle_event.event_maturity_epoch,
+        announcement_tlv: to_tlv_hex(&event.announcement)?,
+        announcement: event.announcement,
+    })
+}
+
+pub async fn attestation_internal(
+    state: Arc<OracleServerState>,
+    event_id: String,
+) -> Result<CompatAttestation, CompatError> {
+    let storage = &state.oracle.oracle.storage;
+    let not_found = || CompatError::NotFound(format!("Event not found. event_id={}", event_id));
+    if !storage
+        .is_announced(&event_id)
+        .await
+        .map_err(|e| CompatError::Internal(e.to_string()))?
+    {
+        return Err(not_found());
+    }
+    let event = storage
+        .get_event(event_id.clone())
+        .await
+        .map_err(|e| CompatError::Internal(e.to_string()))?
+        .ok_or_else(not_found)?;
+
+    let attestation = event.attestation().ok_or(CompatError::NotFound(format!(
+        "Event is not signed. event_id={}",
+        event_id
+    )))?;
+
+    Ok(CompatAttestation {
+        event_id: event.event_id,
+        attestation_tlv: to_tlv_hex(&attestation)?,
+        outcomes: attestation.outcomes.clone(),
+        attestation,
+    })
+}
+
+pub async fn export_internal(
+    state: Arc<OracleServerState>,
+    event_id: String,
+) -> Result<ExportedEvent, CompatError> {
+    let CompatAnnouncement {
+        event_id,
+        announcement,
+        ..
+    } = announcement_internal(state.clone(), event_id).await?;
+    let attestation = match attestation_internal(state, event_id.clone()).await {
+        Ok(attestation) => Some(SpecMessage::new(attestation.attestation)?),
+        Err(CompatError::NotFound(_)) => None,
+        Err(e) => return Err(e),
+    };
+
+    Ok(ExportedEvent {
+        event_id,
+        oracle_announcement: SpecMessage::new(announcement)?,
+        oracle_attestation: attestation,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dlc_messages::ser_impls::read_as_tlv;
+    use kormir::{
+        bitcoin::{
+            key::{Keypair, Secp256k1},
+            secp256k1::SecretKey,
+        },
+        lightning::io::Cursor,
+    };
+
+    #[test]
+    fn attestation_tlv_roundtrip() {
+        let secp = Secp256k1::new();
+        let keypair = Keypair::from_secret_key(&secp, &SecretKey::from_slice(&[3u8; 32]).unwrap());
+        let attestation = OracleAttestation {
+            event_id: "event".to_string(),
+            oracle_public_key: keypair.x_only_public_key().0,
+            signatures: vec![],
+            outcomes: vec![],
+        };
+        let tlv = to_tlv_hex(&attestation).unwrap();
+        assert!(tlv.starts_with("fd"));
+
+        let bytes = hex::decode(tlv).unwrap();
+        let decoded: OracleAttestation = read_as_tlv(&mut Cursor::new(&bytes)).unwrap();
+        assert_eq!(decoded, attestation);
+    }
+
+    #[test]
+    fn spec_message_json() {
+        let secp = Secp256k1::new();
+        let keypair = Keypair::from_secret_key(&secp, &SecretKey::from_slice(&[3u8; 32]).unwrap());
+        let attestation = OracleAttestation {
+            event_id: "event".to_string(),
+            oracle_public_key: keypair.x_only_public_key().0,
+            signatures: vec![],
+            outcomes: vec!["1".to_string()],
+        };
+        let json = serde_json::to_value(SpecMessage::new(attestation.clone()).unwrap()).unwrap();
+        assert_eq!(json["message"]["eventId"], "event");
+        assert_eq!(json["serialized"], to_tlv_hex(&attestation).unwrap());
+    }
+}
@@ -0,0 +1,476 @@
+//! Invariants of the oracle database, checked by `oracle-admin verify`, and re-verification
+//! of every stored attestation signature by `oracle-admin verify-signatures`.
+//!
+//! Only drift that can be derived from other rows is repaired. Nonce and signature problems are
+//! reported for an operator to look into, rewriting them could sign conflicting outcomes.
+
+use std::collections::{HashMap, HashSet};
+
+use bitcoin::{
+    hashes::{sha256, Hash},
+    key::Secp256k1,
+    secp256k1::{schnorr::Signature, Message, XOnlyPublicKey},
+};
+use dlc_messages::oracle_msgs::{EventDescriptor, OracleAnnouncement, OracleAttestation};
+use kormir::{lightning::util::ser::Readable, OracleEvent};
+use serde::{Deserialize, Serialize};
+use sqlx::{prelude::FromRow, PgPool, Postgres};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum IssueKind {
+    UnreadableEvent,
+    InvalidAnnouncementSignature,
+    NonceCount {
+        expected: usize,
+        found: usize,
+    },
+    /// A stored nonce differs from the announced one
+    NonceMismatch {
+        index: usize,
+    },
+    PartialSignatures {
+        signed: usize,
+        nonces: usize,
+    },
+    InvalidAttestation,
+    MissingEventType,
+    MissingParlayContract,
+}
+
+impl IssueKind {
+    pub fn repairable(&self) -> bool {
+        matches!(self, IssueKind::MissingEventType)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Issue {
+    pub event_id: String,
+    #[serde(flatten)]
+    pub kind: IssueKind,
+}
+
+/// A stored nonce with the outcome and signature of an attestation, if any.
+#[derive(Debug, Clone, FromRow)]
+pub struct NonceRow {
+    pub event_id: String,
+    pub nonce: Vec<u8>,
+    pub outcome: Option<String>,
+    pub signature: Option<Vec<u8>>,
+}
+
+#[derive(Debug, FromRow)]
+struct EventRow {
+    event_id: String,
+    announcement_signature: Vec<u8>,
+    oracle_event: Vec<u8>,
+}
+
+fn expected_nonces(oracle_event: &OracleEvent) -> usize {
+    match &oracle_event.event_descriptor {
+        EventDescriptor::EnumEvent(_) => 1,
+        EventDescriptor::DigitDecompositionEvent(descriptor) => {
+            descriptor.nb_digits as usize + descriptor.is_signed as usize
+        }
+    }
+}
+
+/// Check the nonces and signatures of one announcement, `nonces` ordered by index.
+pub fn check_signatures(announcement: &OracleAnnouncement, nonces: &[NonceRow]) -> Vec<IssueKind> {
+    let secp = Secp256k1::verification_only();
+    let mut issues = Vec::new();
+    if announcement.validate(&secp).is_err() {
+        issues.push(IssueKind::InvalidAnnouncementSignature);
+    }
+
+    let expected = expected_nonces(&announcement.oracle_event);
+    if nonces.len() != expected {
+        issues.push(IssueKind::NonceCount {
+            expected,
+            found: nonces.len(),
+        });
+    }
+    for (index, (row, announced)) in nonces
+        .iter()
+        .zip(&announcement.oracle_event.oracle_nonces)
+        .enumerate()
+    {
+        if XOnlyPublicKey::from_slice(&row.nonce).ok() != Some(*announced) {
+            issues.push(IssueKind::NonceMismatch { index });
+        }
+    }
+
+    let signed = nonces.iter().filter(|row| row.signature.is_some()).count();
+    if signed == 0 {
+        return issues;
+    }
+    if signed != nonces.len() {
+        issues.push(IssueKind::PartialSignatures {
+            signed,
+            nonces: nonces.len(),
+        });
+        return issues;
+    }
+    let attestation = nonces
+        .iter()
+        .map(|row| {
+            let signature = Signature::from_slice(row.signature.as_deref()?).ok()?;
+            Some((signature, row.outcome.clone()?))
+        })
+        .collect::<Option<Vec<_>>>()
+        .map(|signed| OracleAttestation {
+            event_id: announcement.oracle_event.event_id.clone(),
+            oracle_public_key: announcement.oracle_public_key,
+            signatures: signed.iter().map(|(signature, _)| *signature).collect(),
+            outcomes: signed.into_iter().map(|(_, outcome)| outcome).collect(),
+        });
+    match attestation {
+        Some(attestation) if attestation.validate(&secp, announcement).is_ok() => {}
+        _ => issues.push(IssueKind::InvalidAttestation),
+    }
+    issues
+}
+
+/// Check every event against the invariants.
+pub async fn check(pool: &PgPool, oracle_public_key: XOnlyPublicKey) -> anyhow::Result<Vec<Issue>> {
+    let events = sqlx::query_as::<Postgres, EventRow>(
+        "SELECT event_id, announcement_signature, oracle_event FROM events ORDER BY created_at",
+    )
+    .fetch_all(pool)
+    .await?;
+    let mut nonces: HashMap<String, Vec<NonceRow>> = HashMap::new();
+    for row in sqlx::query_as::<Postgres, NonceRow>(
+        "SELECT event_id, nonce, outcome, signature FROM event_nonces ORDER BY event_id, index",
+    )
+    .fetch_all(pool)
+    .await?
+    {
+        nonces.entry(row.event_id.clone()).or_default().push(row);
+    }
+    let event_types = sqlx::query_as::<Postgres, (String, String)>(
+        "SELECT oracle_event_id, event_type FROM event_types",
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .collect::<HashMap<_, _>>();
+    let parlay_contracts =
+        sqlx::query_scalar::<Postgres, String>("SELECT id FROM parlay_contracts")
+            .fetch_all(pool)
+            .await?
+            .into_iter()
+            .collect::<HashSet<_>>();
+
+    let mut issues = Vec::new();
+    for event in events {
+        let mut push = |kind| {
+            issues.push(Issue {
+                event_id: event.event_id.clone(),
+                kind,
+            })
+        };
+        let mut cursor = kormir::lightning::io::Cursor::new(&event.oracle_event);
+        let (Ok(oracle_event), Ok(announcement_signature)) = (
+            OracleEvent::read(&mut cursor),
+            Signature::from_slice(&event.announcement_signature),
+        ) else {
+            push(IssueKind::UnreadableEvent);
+            continue;
+        };
+        let is_parlay = matches!(
+            &oracle_event.event_descriptor,
+            EventDescriptor::DigitDecompositionEvent(descriptor) if descriptor.unit == "parlay"
+        );
+        let announcement = OracleAnnouncement {
+            announcement_signature,
+            oracle_public_key,
+            oracle_event,
+        };
+
+        let event_nonces = nonces.remove(&event.event_id).unwrap_or_default();
+        for kind in check_signatures(&announcement, &event_nonces) {
+            push(kind);
+        }
+        if !event_types.contains_key(&event.event_id) {
+            push(IssueKind::MissingEventType);
+        }
+        if is_parlay && !parlay_contracts.contains(&event.event_id) {
+            push(IssueKind::MissingParlayContract);
+        }
+    }
+    Ok(issues)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase", tag = "fault")]
+pub enum SignatureFault {
+    UnreadableSignature,
+    /// The signature has no outcome to verify against
+    MissingOutcome,
+    /// The signature was not made with the announced nonce
+    WrongNonce,
+    InvalidSignature,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CorruptSignature {
+    pub event_id: String,
+    /// Nonce index of the corrupted `event_nonces` row
+    pub index: i32,
+    #[serde(flatten)]
+    pub fault: SignatureFault,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SignatureReport {
+    pub checked: usize,
+    pub corrupted: Vec<CorruptSignature>,
+}
+
+/// Verify one stored outcome signature against the announced nonce and the oracle key.
+pub fn verify_signature(
+    oracle_public_key: &XOnlyPublicKey,
+    nonce: &XOnlyPublicKey,
+    outcome: Option<&str>,
+    signature: &[u8],
+) -> Option<SignatureFault> {
+    let Ok(signature) = Signature::from_slice(signature) else {
+        return Some(SignatureFault::UnreadableSignature);
+    };
+    let Some(outcome) = outcome else {
+        return Some(SignatureFault::MissingOutcome);
+    };
+    if signature.serialize()[..32] != nonce.serialize() {
+        return Some(SignatureFault::WrongNonce);
+    }
+    let message = Message::from_digest(sha256::Hash::hash(outcome.as_bytes()).to_byte_array());
+    Secp256k1::verification_only()
+        .verify_schnorr(&signature, &message, oracle_public_key)
+        .err()
+        .map(|_| SignatureFault::InvalidSignature)
+}
+
+#[derive(Debug, FromRow)]
+struct SignedNonceRow {
+    event_id: String,
+    oracle_event: Vec<u8>,
+    index: i32,
+    /// Position of the nonce in the announcement
+    position: i64,
+    outcome: Option<String>,
+    signature: Vec<u8>,
+}
+
+/// Re-verify every stored signature, one row at a time so corrupted rows can be pinpointed.
+pub async fn verify_signatures(
+    pool: &PgPool,
+    oracle_public_key: XOnlyPublicKey,
+) -> anyhow::Result<SignatureReport> {
+    let rows = sqlx::query_as::<Postgres, SignedNonceRow>(
+        r#"
+        SELECT n.event_id, e.oracle_event, n.index, n.position, n.outcome, n.signature
+        FROM (
+            SELECT event_id, index, outcome, signature,
+                ROW_NUMBER() OVER (PARTITION BY event_id ORDER BY index) - 1 AS position
+            FROM event_nonces
+        ) n
+        JOIN events e ON e.event_id = n.event_id
+        WHERE n.signature IS NOT NULL
+        ORDER BY n.event_id, n.index
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut report = SignatureReport {
+        checked: rows.len(),
+        corrupted: Vec::new(),
+    };
+    let mut nonces: HashMap<String, Option<Vec<XOnlyPublicKey>>> = HashMap::new();
+    for row in rows {
+        let announced = nonces.entry(row.event_id.clone()).or_insert_with(|| {
+            let mut cursor = kormir::lightning::io::Cursor::new(&row.oracle_event);
+            OracleEvent::read(&mut cursor)
+                .ok()
+                .map(|event| event.oracle_nonces)
+        });
+        let fault = match announced
+            .as_ref()
+            .and_then(|nonces| nonces.get(row.position as usize))
+        {
+            Some(nonce) => verify_signature(
+                &oracle_public_key,
+                nonce,
+                row.outcome.as_deref(),
+                &row.signature,
+            ),
+            // Without an announced nonce there is nothing the signature could be valid for
+            None => Some(SignatureFault::WrongNonce),
+        };
+        if let Some(fault) = fault {
+            report.corrupted.push(CorruptSignature {
+                event_id: row.event_id,
+                index: row.index,
+                fault,
+            });
+        }
+    }
+    Ok(report)
+}
+
+/// Repair the repairable issues, returning how many were fixed.
+pub async fn repair(pool: &PgPool, issues: &[Issue]) -> anyhow::Result<usize> {
+    let mut repaired = 0;
+    let mut tx = pool.begin().await?;
+    for issue in issues.iter().filter(|issue| issue.kind.repairable()) {
+        if issue.kind == IssueKind::MissingEventType {
+            // Parlay announcements always have a contract, anything else was created as a single
+            sqlx::query(
+                r#"
+                INSERT INTO event_types (oracle_event_id, event_type)
+                SELECT $1, CASE WHEN EXISTS (SELECT 1 FROM parlay_contracts WHERE id = $1)
+                    THEN 'parlay' ELSE 'single' END
+                "#,
+            )
+            .bind(&issue.event_id)
+            .execute(&mut *tx)
+            .await?;
+            repaired += 1;
+        }
+    }
+    tx.commit().await?;
+    Ok(repaired)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::setup_ernest_oracle;
+    use crate::{
+        events::EventType,
+        mempool::{MempoolClient, BASE_URL},
+        routes::CreateEvent,
+    };
+
+    #[tokio::test]
+    async fn detects_and_repairs_drift() {
+        let mempool = MempoolClient::new(BASE_URL.to_string());
+        let oracle = setup_ernest_oracle(mempool).await;
+        let pool = &oracle.oracle.storage.pool;
+        let announcement = oracle
+            .create_event(CreateEvent::single(
+                EventType::Hashrate,
+                chrono::Utc::now().timestamp() as u32 + 1000,
+            ))
+            .await
+            .unwrap();
+        let event_id = announcement.oracle_event.event_id.clone();
+        let pubkey = oracle.oracle.public_key();
+        let issues_of = |issues: Vec<Issue>| {
+            issues
+                .into_iter()
+                .filter(|issue| issue.event_id == event_id)
+                .map(|issue| issue.kind)
+                .collect::<Vec<_>>()
+        };
+        assert!(issues_of(check(pool, pubkey).await.unwrap()).is_empty());
+
+        oracle
+            .oracle
+            .sign_numeric_event(event_id.clone(), 42)
+            .await
+            .unwrap();
+        assert!(issues_of(check(pool, pubkey).await.unwrap()).is_empty());
+
+        sqlx::query("DELETE FROM event_types WHERE oracle_event_id = $1")
+            .bind(&event_id)
+            .execute(pool)
+            .await
+            .unwrap();
+        sqlx::query(
+            r#"
+            UPDATE event_nonces SET outcome = CASE WHEN outcome = '0' THEN '1' ELSE '0' END
+            WHERE event_id = $1
+                AND index = (SELECT MIN(index) + 1 FROM event_nonces WHERE event_id = $1)
+            "#,
+        )
+        .bind(&event_id)
+        .execute(pool)
+        .await
+        .unwrap();
+        let issues = check(pool, pubkey)
+            .await
+            .unwrap()
+            .into_iter()
+            .filter(|issue| issue.event_id == event_id)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            issues.iter().map(|i| i.kind.clone()).collect::<Vec<_>>(),
+            vec![IssueKind::InvalidAttestation, IssueKind::MissingEventType]
+        );
+
+        assert_eq!(repair(pool, &issues).await.unwrap(), 1);
+        assert_eq!(
+            issues_of(check(pool, pubkey).await.unwrap()),
+            vec![IssueKind::InvalidAttestation]
+        );
+    }
+
+    #[tokio::test]
+    async fn flags_corrupted_signatures() {
+        let mempool = MempoolClient::new(BASE_URL.to_string());
+        let oracle = setup_ernest_oracle(mempool).await;
+        let pool = &oracle.oracle.storage.pool;
+        let announcement = oracle
+            .create_event(CreateEvent::single(
+                EventType::Hashrate,
+                chrono::Utc::now().timestamp() as u32 + 1000,
+            ))
+            .await
+            .unwrap();
+        let event_id = announcement.oracle_event.event_id.clone();
+        let pubkey = oracle.oracle.public_key();
+        oracle
+            .oracle
+            .sign_numeric_event(event_id.clone(), 42)
+            .await
+            .unwrap();
+        let corrupted_of = |report: SignatureReport| {
+            report
+                .corrupted
+                .into_iter()
+                .filter(|corrupt| corrupt.event_id == event_id)
+                .collect::<Vec<_>>()
+        };
+        assert!(corrupted_of(verify_signatures(pool, pubkey).await.unwrap()).is_empty());
+
+        let index = sqlx::query_scalar::<Postgres, i32>(
+            "SELECT MAX(index) FROM event_nonces WHERE event_id = $1",
+        )
+        .bind(&event_id)
+        .fetch_one(pool)
+        .await
+        .unwrap();
+        // Flip a bit of the s value, keeping the nonce
+        sqlx::query(
+            "UPDATE event_nonces SET signature = set_byte(signature, 40, get_byte(signature, 40) # 1) WHERE event_id = $1 AND index = $2",
+        )
+        .bind(&event_id)
+        .bind(index)
+        .execute(pool)
+        .await
+        .unwrap();
+        assert_eq!(
+            corrupted_of(verify_signatures(pool, pubkey).await.unwrap()),
+            vec![CorruptSignature {
+                event_id: event_id.clone(),
+                index,
+                fault: SignatureFault::InvalidSignature,
+            }]
+        );
+    }
+}
@@ -0,0 +1,137 @@
+//! Receipts for created events.
+//!
+//! Every created event gets a receipt signed by the oracle over the event id, the hashed API
+//! key of the request (see [`limits::hash_api_key`]) and the creation time, see
+//! [`receipts::creation_message`]. Receipts are kept after their event is deleted, so a client
+//! can prove the oracle accepted its request even when the event is disputed or gone.
+
+use bitcoin::{
+    secp256k1::{schnorr::Signature, Message},
+    XOnlyPublicKey,
+};
+use chrono::{DateTime, DurationRound, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{prelude::FromRow, PgPool, Postgres};
+
+use crate::{limits, oracle::ErnestOracle, receipts};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CreationReceipt {
+    pub event_id: String,
+    /// SHA-256 of the API key the event was created with, `anonymous` without one
+    pub api_key_hash: String,
+    pub created_at: DateTime<Utc>,
+    pub signature: Signature,
+}
+
+impl CreationReceipt {
+    pub fn message(&self) -> Message {
+        receipts::creation_message(&self.event_id, &self.api_key_hash, self.created_at)
+    }
+
+    /// Whether the receipt is signed by `pubkey`.
+    pub fn verify(&self, pubkey: &XOnlyPublicKey) -> bool {
+        receipts::verify_receipt(pubkey, &self.message(), &self.signature)
+    }
+}
+
+#[derive(Debug, FromRow)]
+struct CreationReceiptRow {
+    event_id: String,
+    api_key_hash: String,
+    created_at: DateTime<Utc>,
+    signature: Vec<u8>,
+}
+
+/// Sign and store the receipt for `event_id` created with `api_key`.
+pub async fn issue(
+    oracle: &ErnestOracle,
+    event_id: &str,
+    api_key: Option<&str>,
+) -> anyhow::Result<CreationReceipt> {
+    // The message commits to milliseconds, keep nothing finer so the stored time verifies
+    let created_at = Utc::now().duration_trunc(TimeDelta::milliseconds(1))?;
+    let api_key_hash = limits::hash_api_key(api_key);
+    let signature = oracle.sign_message(&receipts::creation_message(
+        event_id,
+        &api_key_hash,
+        created_at,
+    ));
+    let receipt = CreationReceipt {
+        event_id: event_id.to_string(),
+        api_key_hash,
+        created_at,
+        signature,
+    };
+    sqlx::query(
+        "INSERT INTO creation_receipts (event_id, api_key_hash, created_at, signature) VALUES ($1, $2, $3, $4)",
+    )
+    .bind(&receipt.event_id)
+    .bind(&receipt.api_key_hash)
+    .bind(receipt.created_at)
+    .bind(receipt.signature.serialize().to_vec())
+    .execute(&oracle.oracle.storage.pool)
+    .await?;
+    Ok(receipt)
+}
+
+/// The receipt issued for `event_id`, whether or not the event still exists.
+pub async fn get(pool: &PgPool, event_id: &str) -> anyhow::Result<Option<CreationReceipt>> {
+    let row = sqlx::query_as::<Postgres, CreationReceiptRow>(
+        "SELECT event_id, api_key_hash, created_at, signature FROM creation_receipts WHERE event_id = $1",
+    )
+    .bind(event_id)
+    .fetch_optional(pool)
+    .await?;
+    row.map(|row| {
+        Ok(CreationReceipt {
+            event_id: row.event_id,
+            api_key_hash: row.api_key_hash,
+            created_at: row.created_at,
+            signature: Signature::from_slice(&row.signature)?,
+        })
+    })
+    .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        events::EventType, mempool::MempoolClient, routes::CreateEvent,
+        test_util::setup_ernest_oracle,
+    };
+
+    #[tokio::test]
+    async fn receipts_outlive_their_event() {
+        let oracle =
+            setup_ernest_oracle(MempoolClient::new("http://127.0.0.1:9/api/v1".to_string())).await;
+        let pool = &oracle.oracle.storage.pool;
+        let event_id = oracle
+            .create_event(CreateEvent::single(
+                EventType::Hashrate,
+                Utc::now().timestamp() as u32 + 3600,
+            ))
+            .await
+            .unwrap()
+            .oracle_event
+            .event_id;
+
+        let receipt = issue(&oracle, &event_id, Some("key")).await.unwrap();
+        let pubkey = oracle.oracle.public_key();
+        assert!(receipt.verify(&pubkey));
+        assert_eq!(receipt.api_key_hash, limits::hash_api_key(Some("key")));
+        let forged = CreationReceipt {
+            api_key_hash: limits::hash_api_key(Some("other")),
+            ..receipt.clone()
+        };
+        assert!(!forged.verify(&pubkey));
+
+        oracle.oracle.storage.delete_event(&event_id).await.unwrap();
+        let stored = get(pool, &event_id).await.unwrap().unwrap();
+        assert_eq!(stored, receipt);
+        assert!(stored.verify(&pubkey));
+        assert!(get(pool, "unknown").await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,271 @@
+//! Contract descriptors for DLCs settled by this oracle.
+//!
+//! Builds the ddk [`ContractDescriptor`] for a payout shape over an announcement's digit
+//! decomposition, so integrators do not have to work out the outcome range, the payout pieces
+//! and the rounding intervals themselves. Outcomes are the integers the oracle signs, e.g. the
+//! attestable value of a parlay.
+
+use ddk::ddk_manager::contract::{numerical_descriptor::NumericalDescriptor, ContractDescriptor};
+use ddk::ddk_manager::payout_curve::{
+    PayoutFunction, PayoutFunctionPiece, PayoutPoint, PolynomialPayoutCurvePiece, RoundingInterval,
+    RoundingIntervals,
+};
+use dlc_messages::oracle_msgs::{
+    DigitDecompositionEventDescriptor, EventDescriptor, OracleAnnouncement,
+};
+use dlc_trie::OracleNumericInfo;
+use serde::{Deserialize, Serialize};
+
+/// The payout to the offering party across the announcement's outcomes.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum PayoutShape {
+    /// Pays `min_payout` up to outcome `lower`, rises linearly to `max_payout` at `upper` and
+    /// stays there for every larger outcome.
+    Linear {
+        lower: u64,
+        upper: u64,
+        min_payout: u64,
+        max_payout: u64,
+    },
+    /// Pays `payout_below` for outcomes below `threshold` and `payout_above` from it on.
+    Binary {
+        threshold: u64,
+        payout_below: u64,
+        payout_above: u64,
+    },
+}
+
+/// The largest outcome an unsigned announcement can attest to.
+pub fn max_outcome(announcement: &OracleAnnouncement) -> anyhow::Result<u64> {
+    let descriptor = numeric_descriptor(announcement)?;
+    Ok((1u64 << descriptor.nb_digits) - 1)
+}
+
+/// Build the descriptor paying `shape` out of `total_collateral`, rounding payouts to
+/// multiples of `rounding_mod` sats (use 1 for exact payouts).
+pub fn contract_descriptor(
+    announcement: &OracleAnnouncement,
+    shape: &PayoutShape,
+    total_collateral: u64,
+    rounding_mod: u64,
+) -> anyhow::Result<ContractDescriptor> {
+    let descriptor = numeric_descriptor(announcement)?;
+    if rounding_mod == 0 {
+        return Err(anyhow::anyhow!("Rounding modulus must be at least 1"));
+    }
+    let max_outcome = (1u64 << descriptor.nb_digits) - 1;
+    let points = payout_points(shape, max_outcome)?;
+    if let Some((_, payout)) = points.iter().find(|(_, payout)| *payout > total_collateral) {
+        return Err(anyhow::anyhow!(
+            "Payout exceeds the total collateral. payout={} total_collateral={}",
+            payout,
+            total_collateral
+        ));
+    }
+
+    let pieces = points
+        .windows(2)
+        .map(|segment| {
+            let points = segment
+                .iter()
+                .map(|(event_outcome, outcome_payout)| PayoutPoint {
+                    event_outcome: *event_outcome,
+                    outcome_payout: *outcome_payout,
+                    extra_precision: 0,
+                })
+                .collect();
+            PolynomialPayoutCurvePiece::new(points)
+                .map(PayoutFunctionPiece::PolynomialPayoutCurvePiece)
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| anyhow::anyhow!("Invalid payout curve. error={}", e))?;
+    let payout_function = PayoutFunction::new(pieces)
+        .map_err(|e| anyhow::anyhow!("Invalid payout curve. error={}", e))?;
+    payout_function
+        .validate(max_outcome)
+        .map_err(|e| anyhow::anyhow!("Invalid payout curve. error={}", e))?;
+
+    Ok(ContractDescriptor::Numerical(NumericalDescriptor {
+        payout_function,
+        rounding_intervals: RoundingIntervals {
+            intervals: vec![RoundingInterval {
+                begin_interval: 0,
+                rounding_mod,
+            }],
+        },
+        difference_params: None,
+        oracle_numeric_infos: OracleNumericInfo {
+            base: descriptor.base as usize,
+            nb_digits: vec![descriptor.nb_digits as usize],
+        },
+    }))
+}
+
+fn numeric_descriptor(
+    announcement: &OracleAnnouncement,
+) -> anyhow::Result<&DigitDecompositionEventDescriptor> {
+    let EventDescriptor::DigitDecompositionEvent(descriptor) =
+        &announcement.oracle_event.event_descriptor
+    else {
+        return Err(anyhow::anyhow!("Announcement is not numeric"));
+    };
+    // rust-dlc only builds payout tries over unsigned base 2 outcomes
+    if descriptor.is_signed || descriptor.base != 2 {
+        return Err(anyhow::anyhow!(
+            "Only unsigned base 2 announcements are supported. is_signed={} base={}",
+            descriptor.is_signed,
+            descriptor.base
+        ));
+    }
+    Ok(descriptor)
+}
+
+/// Corner points `(outcome, payout)` of the shape from outcome zero to `max_outcome`, each
+/// consecutive pair being one linear piece.
+fn payout_points(shape: &PayoutShape, max_outcome: u64) -> anyhow::Result<Vec<(u64, u64)>> {
+    let mut points = match *shape {
+        PayoutShape::Linear {
+            lower,
+            upper,
+            min_payout,
+            max_payout,
+        } => {
+            if lower >= upper || upper > max_outcome {
+                return Err(anyhow::anyhow!(
+                    "Linear payout needs lower < upper <= {}. lower={} upper={}",
+                    max_outcome,
+                    lower,
+                    upper
+                ));
+            }
+            vec![
+                (0, min_payout),
+                (lower, min_payout),
+                (upper, max_payout),
+                (max_outcome, max_payout),
+            ]
+        }
+        PayoutShape::Binary {
+            threshold,
+            payout_below,
+            payout_above,
+        } => {
+            if threshold > max_outcome {
+                return Err(anyhow::anyhow!(
+                    "Binary threshold is above the largest outcome. threshold={} max_outcome={}",
+                    threshold,
+                    max_outcome
+                ));
+            }
+            if threshold == 0 {
+                vec![(0, payout_above), (max_outcome, payout_above)]
+            } else {
+                vec![
+                    (0, payout_below),
+                    (threshold - 1, payout_below),
+                    (threshold, payout_above),
+                    (max_outcome, payout_above),
+                ]
+            }
+        }
+    };
+    // Shapes touching either end of the range produce zero-width pieces
+    points.dedup_by_key(|(outcome, _)| *outcome);
+    Ok(points)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dlc_messages::oracle_msgs::OracleEvent;
+    use kormir::bitcoin::{
+        key::{Keypair, Secp256k1},
+        secp256k1::{schnorr::Signature, SecretKey},
+    };
+
+    fn announcement(nb_digits: u16, is_signed: bool) -> OracleAnnouncement {
+        let secp = Secp256k1::new();
+        let keypair = Keypair::from_secret_key(&secp, &SecretKey::from_slice(&[3u8; 32]).unwrap());
+        OracleAnnouncement {
+            announcement_signature: Signature::from_slice(&[1u8; 64]).unwrap(),
+            oracle_public_key: keypair.x_only_public_key().0,
+            oracle_event: OracleEvent {
+                oracle_nonces: vec![keypair.x_only_public_key().0; nb_digits as usize],
+                event_maturity_epoch: 0,
+                event_descriptor: EventDescriptor::DigitDecompositionEvent(
+                    DigitDecompositionEventDescriptor {
+                        base: 2,
+                        is_signed,
+                        unit: "parlay".to_string(),
+                        precision: 0,
+                        nb_digits,
+                    },
+                ),
+                event_id: "parlay".to_string(),
+            },
+        }
+    }
+
+    fn payout_at(descriptor: &ContractDescriptor, total_collateral: u64, outcome: usize) -> u64 {
+        let ContractDescriptor::Numerical(descriptor) = descriptor else {
+            panic!("expected a numerical descriptor");
+        };
+        descriptor
+            .get_range_payouts(total_collateral)
+            .unwrap()
+            .into_iter()
+            .find(|range| range.start <= outcome && outcome < range.start + range.count)
+            .unwrap()
+            .payout
+            .offer
+    }
+
+    #[test]
+    fn linear_payout() {
+        let announcement = announcement(10, false);
+        let shape = PayoutShape::Linear {
+            lower: 100,
+            upper: 500,
+            min_payout: 0,
+            max_payout: 100_000,
+        };
+        let descriptor = contract_descriptor(&announcement, &shape, 100_000, 1).unwrap();
+        assert_eq!(payout_at(&descriptor, 100_000, 0), 0);
+        assert_eq!(payout_at(&descriptor, 100_000, 100), 0);
+        assert_eq!(payout_at(&descriptor, 100_000, 300), 50_000);
+        assert_eq!(payout_at(&descriptor, 100_000, 500), 100_000);
+        assert_eq!(payout_at(&descriptor, 100_000, 1023), 100_000);
+    }
+
+    #[test]
+    fn binary_payout() {
+        let announcement = announcement(10, false);
+        for threshold in [0, 1, 700, 1023] {
+            let shape = PayoutShape::Binary {
+                threshold,
+                payout_below: 0,
+                payout_above: 100_000,
+            };
+            let descriptor = contract_descriptor(&announcement, &shape, 100_000, 1).unwrap();
+            if threshold > 0 {
+                assert_eq!(payout_at(&descriptor, 100_000, threshold as usize - 1), 0);
+            }
+            assert_eq!(payout_at(&descriptor, 100_000, threshold as usize), 100_000);
+            assert_eq!(payout_at(&descriptor, 100_000, 1023), 100_000);
+        }
+    }
+
+    #[test]
+    fn rejects_unusable_inputs() {
+        let shape = PayoutShape::Binary {
+            threshold: 10,
+            payout_below: 0,
+            payout_above: 1000,
+        };
+        assert!(contract_descriptor(&announcement(10, true), &shape, 1000, 1).is_err());
+        assert!(contract_descriptor(&announcement(10, false), &shape, 999, 1).is_err());
+        assert!(contract_descriptor(&announcement(10, false), &shape, 1000, 0).is_err());
+        assert!(contract_descriptor(&announcement(3, false), &shape, 1000, 1).is_err());
+    }
+}
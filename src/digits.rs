@@ -0,0 +1,124 @@
+//! Decoding numeric attestations.
+//!
+//! A numeric event is attested digit by digit, most significant first, with a leading `+` or `-`
+//! outcome for signed events. The value is the number the digits spell in the announced base,
+//! scaled by ten to the announced precision.
+
+use dlc_messages::oracle_msgs::{EventDescriptor, OracleAnnouncement, OracleAttestation};
+
+/// The value a numeric attestation spells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NumericOutcome {
+    /// The number the digits spell
+    pub value: i64,
+    /// Announced power of ten of one unit of `value`
+    pub precision: i32,
+}
+
+impl NumericOutcome {
+    /// The value in the unit of the event.
+    pub fn scaled(&self) -> f64 {
+        self.value as f64 * 10f64.powi(self.precision)
+    }
+}
+
+/// Decode the outcomes of `attestation` with the descriptor of `announcement`. Signatures are not
+/// checked, see [`crate::verify::verify_attestation`] for that.
+pub fn decode_attestation(
+    announcement: &OracleAnnouncement,
+    attestation: &OracleAttestation,
+) -> anyhow::Result<NumericOutcome> {
+    let EventDescriptor::DigitDecompositionEvent(descriptor) =
+        &announcement.oracle_event.event_descriptor
+    else {
+        return Err(anyhow::anyhow!(
+            "Event is not numeric. event_id={}",
+            announcement.oracle_event.event_id
+        ));
+    };
+    let expected = descriptor.nb_digits as usize + descriptor.is_signed as usize;
+    if attestation.outcomes.len() != expected {
+        return Err(anyhow::anyhow!(
+            "Attestation does not have the announced digits. event_id={} expected={} outcomes={}",
+            announcement.oracle_event.event_id,
+            expected,
+            attestation.outcomes.len()
+        ));
+    }
+    Ok(NumericOutcome {
+        value: decode_digits(&attestation.outcomes, descriptor.base, descriptor.is_signed)?,
+        precision: descriptor.precision,
+    })
+}
+
+/// The number the digit outcomes of a numeric attestation spell, most significant first.
+pub fn decode_digits(outcomes: &[String], base: u16, is_signed: bool) -> anyhow::Result<i64> {
+    let (negative, digits) = match (is_signed, outcomes.split_first()) {
+        (true, Some((sign, digits))) if sign == "-" => (true, digits),
+        (true, Some((sign, digits))) if sign == "+" => (false, digits),
+        (true, _) => return Err(anyhow::anyhow!("Attestation has no sign outcome")),
+        (false, _) => (false, outcomes),
+    };
+    let mut value: i64 = 0;
+    for digit in digits {
+        let digit = digit.parse::<u16>()?;
+        if digit >= base {
+            return Err(anyhow::anyhow!("Digit outside the base. digit={}", digit));
+        }
+        value = value
+            .checked_mul(base as i64)
+            .and_then(|value| value.checked_add(digit as i64))
+            .ok_or(anyhow::anyhow!("Attested value does not fit in an i64"))?;
+    }
+    Ok(if negative { -value } else { value })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::secp256k1::SecretKey;
+    use kormir::storage::MemoryStorage;
+
+    #[test]
+    fn decodes_digits() {
+        let digits = |outcomes: &[&str]| outcomes.iter().map(|o| o.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            decode_digits(&digits(&["1", "0", "1"]), 2, false).unwrap(),
+            5
+        );
+        assert_eq!(
+            decode_digits(&digits(&["-", "4", "2"]), 10, true).unwrap(),
+            -42
+        );
+        assert!(decode_digits(&digits(&["2"]), 2, false).is_err());
+        assert!(decode_digits(&digits(&["1"]), 2, true).is_err());
+    }
+
+    #[tokio::test]
+    async fn decodes_attestations_with_precision() {
+        let secret_key = SecretKey::from_slice(&[6u8; 32]).unwrap();
+        let oracle =
+            kormir::Oracle::from_signing_key(MemoryStorage::default(), secret_key).unwrap();
+        let announcement = oracle
+            .create_numeric_event("fees".to_string(), 16, true, -2, "sat/vb".to_string(), 0)
+            .await
+            .unwrap();
+        let attestation = oracle
+            .sign_numeric_event("fees".to_string(), -1250)
+            .await
+            .unwrap();
+        let outcome = decode_attestation(&announcement, &attestation).unwrap();
+        assert_eq!(
+            outcome,
+            NumericOutcome {
+                value: -1250,
+                precision: -2
+            }
+        );
+        assert_eq!(outcome.scaled(), -12.5);
+
+        let mut truncated = attestation;
+        truncated.outcomes.pop();
+        assert!(decode_attestation(&announcement, &truncated).is_err());
+    }
+}
@@ -0,0 +1,122 @@
+//! Event ids supplied by clients or generated with the configured naming scheme.
+//!
+//! Ids end up in wallet UIs, nostr posts and contract descriptors, so besides UUIDs the oracle
+//! can name events after what they attest, e.g. `hashrate-2025-07-01`. Ids are unique, a
+//! generated slug that is taken gets a numeric suffix.
+
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Postgres};
+use uuid::Uuid;
+
+pub const MAX_EVENT_ID_LEN: usize = 64;
+
+/// Suffixes tried before giving up on a generated slug.
+const MAX_SLUG_SUFFIX: u32 = 100;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum EventIdScheme {
+    #[default]
+    Uuid,
+    /// The event type (or `parlay`) and the maturity date
+    Slug,
+}
+
+/// Ids are lowercase letters, digits and inner dashes.
+pub fn validate(event_id: &str) -> anyhow::Result<()> {
+    let valid = !event_id.is_empty()
+        && event_id.len() <= MAX_EVENT_ID_LEN
+        && !event_id.starts_with('-')
+        && !event_id.ends_with('-')
+        && event_id
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+    if !valid {
+        return Err(anyhow::anyhow!(
+            "Event id must be 1 to {} lowercase letters, digits and dashes. event_id={}",
+            MAX_EVENT_ID_LEN,
+            event_id
+        ));
+    }
+    Ok(())
+}
+
+/// `name` in kebab case followed by the UTC date of `maturity`.
+pub fn slug(name: &str, maturity: u32) -> anyhow::Result<String> {
+    let date = chrono::DateTime::from_timestamp(maturity as i64, 0)
+        .ok_or(anyhow::anyhow!("Invalid maturity. maturity={}", maturity))?
+        .format("%Y-%m-%d");
+    let mut kebab = String::with_capacity(name.len() + 11);
+    for c in name.chars() {
+        if c.is_ascii_uppercase() && !kebab.is_empty() {
+            kebab.push('-');
+        }
+        kebab.push(c.to_ascii_lowercase());
+    }
+    Ok(format!("{}-{}", kebab, date))
+}
+
+pub async fn is_taken(pool: &PgPool, event_id: &str) -> anyhow::Result<bool> {
+    Ok(sqlx::query_scalar::<Postgres, bool>(
+        "SELECT EXISTS (SELECT 1 FROM events WHERE event_id = $1)",
+    )
+    .bind(event_id)
+    .fetch_one(pool)
+    .await?)
+}
+
+/// The id of a new event, `requested` if it is free or else one from `scheme`.
+pub async fn assign(
+    pool: &PgPool,
+    requested: Option<&str>,
+    scheme: EventIdScheme,
+    name: &str,
+    maturity: u32,
+) -> anyhow::Result<String> {
+    if let Some(event_id) = requested {
+        validate(event_id)?;
+        if is_taken(pool, event_id).await? {
+            return Err(anyhow::anyhow!(
+                "Event id is already taken. event_id={}",
+                event_id
+            ));
+        }
+        return Ok(event_id.to_string());
+    }
+    match scheme {
+        EventIdScheme::Uuid => Ok(Uuid::new_v4().to_string()),
+        EventIdScheme::Slug => {
+            let base = slug(name, maturity)?;
+            for suffix in 1..=MAX_SLUG_SUFFIX {
+                let event_id = match suffix {
+                    1 => base.clone(),
+                    _ => format!("{}-{}", base, suffix),
+                };
+                if !is_taken(pool, &event_id).await? {
+                    return Ok(event_id);
+                }
+            }
+            Err(anyhow::anyhow!(
+                "No free event id left for this date. slug={}",
+                base
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slugs_name_type_and_date() {
+        assert_eq!(slug("hashrate", 1751371200).unwrap(), "hashrate-2025-07-01");
+        assert_eq!(slug("feeRate", 1751371200).unwrap(), "fee-rate-2025-07-01");
+        assert!(validate("fee-rate-2025-07-01").is_ok());
+        assert!(validate(&Uuid::new_v4().to_string()).is_ok());
+        assert!(validate("Hashrate").is_err());
+        assert!(validate("-hashrate").is_err());
+        assert!(validate("hash rate").is_err());
+        assert!(validate(&"a".repeat(MAX_EVENT_ID_LEN + 1)).is_err());
+    }
+}
@@ -1,11 +1,17 @@
 use std::str::FromStr;
 
-use crate::mempool::{MempoolClient, TimePeriod};
+use crate::mempool::{Aggregation, DataProvenance, FeePercentile, MempoolClient, TimePeriod};
+use crate::oracle::PRECISION as LEGACY_PRECISION;
+use crate::smoothing::Smoothing;
+use crate::units::{announcement_unit, event_type_from_unit, unit_for, Unit};
 use serde::{Deserialize, Serialize};
+use sqlx::{prelude::FromRow, types::Json, PgConnection, PgPool, Postgres};
 use strum::IntoEnumIterator;
 use strum_macros::{Display, EnumIter, EnumString};
 
-#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq, EnumIter, Display, EnumString)]
+#[derive(
+    Debug, Clone, Serialize, Deserialize, Eq, PartialEq, Hash, EnumIter, Display, EnumString,
+)]
 #[serde(rename_all = "camelCase")]
 #[strum(serialize_all = "camelCase")]
 pub enum EventType {
@@ -13,34 +19,130 @@ pub enum EventType {
     FeeRate,
     BlockFees,
     Difficulty,
+    /// Magnitude of the estimated percent change at the next difficulty retarget.
+    NextDifficultyChange,
+    /// Blocks remaining until the next subsidy halving.
+    BlocksUntilHalving,
+    /// Estimated percent change at the next difficulty retarget, announced as a signed event.
+    DifficultyChangePercent,
+    /// Average seconds between blocks over the period, from block timestamps.
+    AvgBlockInterval,
+    /// Average total coinbase output, subsidy plus fees, over the period.
+    CoinbaseValue,
 }
 
 impl EventType {
+    /// The outcome a new event announced with `unit` would sign for the current value, scaled
+    /// by the event type's default precision.
     pub async fn outcome_from_str(
         unit: &str,
         mempool_client: &MempoolClient,
     ) -> anyhow::Result<i64> {
-        let event_type = EventType::from_str(unit)?;
-        let mempool = match event_type {
-            EventType::BlockFees => mempool_client.get_block_fees(TimePeriod::ThreeMonths).await,
-            EventType::Difficulty => mempool_client.get_difficulty(TimePeriod::ThreeMonths).await,
-            EventType::FeeRate => mempool_client.get_fee_rate(TimePeriod::ThreeMonths).await,
-            EventType::Hashrate => mempool_client.get_hashrate(TimePeriod::ThreeMonths).await,
-        }?;
+        let event_type = event_type_from_unit(unit)?;
+        let value = event_type
+            .outcome(mempool_client, &OutcomeOptions::default())
+            .await?;
 
-        Ok(mempool.ceil() as i64)
+        EventParams::from(event_type).outcome(value)
     }
 
     /// OK, we need floating points!!!!
-    pub async fn outcome(&self, mempool_client: &MempoolClient) -> anyhow::Result<f64> {
-        let mempool = match self {
-            EventType::BlockFees => mempool_client.get_block_fees(TimePeriod::ThreeMonths).await,
-            EventType::Difficulty => mempool_client.get_difficulty(TimePeriod::ThreeMonths).await,
-            EventType::FeeRate => mempool_client.get_fee_rate(TimePeriod::ThreeMonths).await,
-            EventType::Hashrate => mempool_client.get_hashrate(TimePeriod::ThreeMonths).await,
-        }?;
+    pub async fn outcome(
+        &self,
+        mempool_client: &MempoolClient,
+        options: &OutcomeOptions,
+    ) -> anyhow::Result<f64> {
+        Ok(self
+            .outcome_with_provenance(mempool_client, options)
+            .await?
+            .value)
+    }
+
+    /// The outcome along with the upstream request and response it was derived from.
+    pub async fn outcome_with_provenance(
+        &self,
+        mempool_client: &MempoolClient,
+        options: &OutcomeOptions,
+    ) -> anyhow::Result<DataProvenance> {
+        if let Some(mock) = mempool_client.mock_data() {
+            return Ok(mock.outcome(self));
+        }
+        let aggregation = options.aggregation.unwrap_or_default();
+        let period = options.period.unwrap_or(TimePeriod::ThreeMonths);
+        match self {
+            EventType::BlockFees => {
+                mempool_client
+                    .block_fees_with_provenance(period, aggregation)
+                    .await
+            }
+            // Without an explicit aggregation the current difficulty is used.
+            EventType::Difficulty => match options.aggregation {
+                Some(aggregation) => {
+                    mempool_client
+                        .difficulty_series_with_provenance(period, aggregation)
+                        .await
+                }
+                None => mempool_client.difficulty_with_provenance(period).await,
+            },
+            EventType::FeeRate => {
+                mempool_client
+                    .fee_rate_with_provenance(period, options.fee_percentile, aggregation)
+                    .await
+            }
+            // Without an explicit aggregation the current hashrate estimate is used.
+            EventType::Hashrate => match options.aggregation {
+                Some(aggregation) => {
+                    mempool_client
+                        .hashrate_series_with_provenance(period, aggregation)
+                        .await
+                }
+                None => mempool_client.hashrate_with_provenance(period).await,
+            },
+            EventType::NextDifficultyChange => {
+                mempool_client
+                    .next_difficulty_change_with_provenance()
+                    .await
+            }
+            EventType::BlocksUntilHalving => {
+                mempool_client.blocks_until_halving_with_provenance().await
+            }
+            EventType::DifficultyChangePercent => {
+                mempool_client
+                    .difficulty_change_percent_with_provenance()
+                    .await
+            }
+            EventType::CoinbaseValue => {
+                mempool_client
+                    .coinbase_value_with_provenance(period, aggregation)
+                    .await
+            }
+            // Without an explicit aggregation the average over the whole period is used.
+            EventType::AvgBlockInterval => match options.aggregation {
+                Some(aggregation) => {
+                    mempool_client
+                        .block_interval_series_with_provenance(period, aggregation)
+                        .await
+                }
+                None => {
+                    mempool_client
+                        .avg_block_interval_with_provenance(period)
+                        .await
+                }
+            },
+        }
+    }
 
-        Ok(mempool)
+    /// Whether the event settles on a series of data points that can be aggregated.
+    pub fn supports_aggregation(&self) -> bool {
+        matches!(
+            self,
+            EventType::Hashrate
+                | EventType::FeeRate
+                | EventType::BlockFees
+                | EventType::Difficulty
+                | EventType::AvgBlockInterval
+                | EventType::CoinbaseValue
+        )
     }
 
     pub fn available_events() -> Vec<EventType> {
@@ -48,42 +150,473 @@ impl EventType {
     }
 }
 
+/// Observed range of an event type's value over a data window.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ValueRange {
+    pub period: TimePeriod,
+    pub min: f64,
+    pub max: f64,
+}
+
+/// What a contract builder needs to pick thresholds and ranges for an event type.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EventTypeMetadata {
+    pub event_type: EventType,
+    /// Announcement unit string, see [`crate::units`]
+    pub unit: String,
+    pub nb_digits: u16,
+    pub precision: i32,
+    pub is_signed: bool,
+    /// `None` when the upstream data could not be fetched
+    pub current_value: Option<f64>,
+    /// Recent min and max per requested window, empty for event types without a data series
+    pub ranges: Vec<ValueRange>,
+}
+
+impl EventTypeMetadata {
+    /// Fetch the current value and, for event types backed by a series, its min and max over
+    /// each of `periods`. Windows that fail to load are left out.
+    pub async fn fetch(
+        event_type: EventType,
+        mempool_client: &MempoolClient,
+        periods: &[TimePeriod],
+    ) -> Self {
+        let current_value = event_type
+            .outcome(mempool_client, &OutcomeOptions::default())
+            .await
+            .inspect_err(|e| {
+                log::warn!(
+                    "Could not fetch current value. event_type={} error={}",
+                    event_type,
+                    e
+                )
+            })
+            .ok();
+
+        let mut ranges = Vec::new();
+        if event_type.supports_aggregation() {
+            for period in periods {
+                let options = |aggregation| OutcomeOptions {
+                    aggregation: Some(aggregation),
+                    period: Some(*period),
+                    ..Default::default()
+                };
+                let min = event_type
+                    .outcome(mempool_client, &options(Aggregation::Min))
+                    .await;
+                let max = event_type
+                    .outcome(mempool_client, &options(Aggregation::Max))
+                    .await;
+                match (min, max) {
+                    (Ok(min), Ok(max)) => ranges.push(ValueRange {
+                        period: *period,
+                        min,
+                        max,
+                    }),
+                    (Err(e), _) | (_, Err(e)) => log::warn!(
+                        "Could not fetch value range. event_type={} period={} error={}",
+                        event_type,
+                        period,
+                        e
+                    ),
+                }
+            }
+        }
+
+        let params = EventParams::from(event_type.clone());
+        Self {
+            event_type,
+            unit: params.unit,
+            nb_digits: params.nb_digits,
+            precision: params.precision,
+            is_signed: params.is_signed,
+            current_value,
+            ranges,
+        }
+    }
+}
+
+/// How the settlement value of an event is derived from the upstream data.
+///
+/// Options that do not apply to an event type are ignored when computing its outcome.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct OutcomeOptions {
+    /// Fee rate percentile, only used by [`EventType::FeeRate`].
+    pub fee_percentile: FeePercentile,
+    /// Reduction over the data window, `None` keeps the event type's default.
+    pub aggregation: Option<Aggregation>,
+    /// Data window, `None` is three months.
+    pub period: Option<TimePeriod>,
+    /// Smoothing applied to the outcome before it is signed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub smoothing: Option<Smoothing>,
+}
+
+impl OutcomeOptions {
+    /// Options requested for a new event, rejecting ones that do not apply to its type.
+    pub fn new(
+        event_type: &EventType,
+        fee_percentile: Option<FeePercentile>,
+        aggregation: Option<Aggregation>,
+    ) -> anyhow::Result<Self> {
+        if fee_percentile.is_some() && *event_type != EventType::FeeRate {
+            return Err(anyhow::anyhow!(
+                "Percentile is only supported for fee rate events. event_type={}",
+                event_type
+            ));
+        }
+        if aggregation.is_some() && !event_type.supports_aggregation() {
+            return Err(anyhow::anyhow!(
+                "Aggregation is not supported for this event type. event_type={}",
+                event_type
+            ));
+        }
+
+        Ok(Self {
+            fee_percentile: fee_percentile.unwrap_or_default(),
+            aggregation,
+            period: None,
+            smoothing: None,
+        })
+    }
+
+    /// Smooth the outcome with `smoothing`. Snapshots are taken with the default options, so
+    /// only those can be smoothed.
+    pub fn with_smoothing(self, smoothing: Option<Smoothing>) -> anyhow::Result<Self> {
+        if smoothing.is_some() && self != Self::default() {
+            return Err(anyhow::anyhow!(
+                "Smoothing is only supported without a percentile or aggregation."
+            ));
+        }
+        Ok(Self { smoothing, ..self })
+    }
+}
+
+#[derive(Debug, FromRow)]
+struct OutcomeOptionsRow {
+    fee_percentile: i16,
+    aggregation: Option<String>,
+    smoothing: Option<Json<Smoothing>>,
+}
+
+#[derive(Debug, FromRow)]
+struct EventParamsRow {
+    nb_digits: Option<i32>,
+    precision: Option<i32>,
+    is_signed: Option<bool>,
+    scaled_outcome: bool,
+}
+
+impl TryFrom<OutcomeOptionsRow> for OutcomeOptions {
+    type Error = anyhow::Error;
+
+    fn try_from(row: OutcomeOptionsRow) -> Result<Self, Self::Error> {
+        Ok(Self {
+            fee_percentile: FeePercentile::try_from(row.fee_percentile as u8)
+                .map_err(|e| anyhow::anyhow!(e))?,
+            aggregation: row
+                .aggregation
+                .map(|a| Aggregation::from_str(&a))
+                .transpose()?,
+            period: None,
+            smoothing: row.smoothing.map(|smoothing| smoothing.0),
+        })
+    }
+}
+
+/// Persist how a single event settles so signing matches what was announced.
+pub(crate) async fn insert_event_options(
+    conn: &mut PgConnection,
+    event_id: &str,
+    options: &OutcomeOptions,
+    params: &EventParams,
+) -> anyhow::Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO event_options
+            (event_id, fee_percentile, aggregation, nb_digits, precision, is_signed, smoothing,
+             scaled_outcome)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        "#,
+    )
+    .bind(event_id)
+    .bind(u8::from(options.fee_percentile) as i16)
+    .bind(options.aggregation.map(|a| a.to_string()))
+    .bind(params.nb_digits as i32)
+    .bind(params.precision)
+    .bind(params.is_signed)
+    .bind(options.smoothing.map(Json))
+    .bind(params.scaled_outcome)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Options an event was created with, events created before options existed use the defaults.
+pub async fn get_outcome_options(pool: &PgPool, event_id: &str) -> anyhow::Result<OutcomeOptions> {
+    let row = sqlx::query_as::<Postgres, OutcomeOptionsRow>(
+        "SELECT fee_percentile, aggregation, smoothing FROM event_options WHERE event_id = $1",
+    )
+    .bind(event_id)
+    .fetch_optional(pool)
+    .await?;
+
+    match row {
+        Some(row) => row.try_into(),
+        None => Ok(OutcomeOptions::default()),
+    }
+}
+
+/// Parameters a single event was announced with. Events created before they were persisted
+/// fall back to the event type's defaults with the legacy precision and unscaled outcomes.
+pub async fn get_event_params(
+    pool: &PgPool,
+    event_id: &str,
+    event_type: &EventType,
+) -> anyhow::Result<EventParams> {
+    let row = sqlx::query_as::<Postgres, EventParamsRow>(
+        "SELECT nb_digits, precision, is_signed, scaled_outcome FROM event_options WHERE event_id = $1",
+    )
+    .bind(event_id)
+    .fetch_optional(pool)
+    .await?;
+
+    let mut params = EventParams::from(event_type.clone());
+    params.precision = LEGACY_PRECISION;
+    params.scaled_outcome = false;
+    if let Some(row) = row {
+        if let Some(nb_digits) = row.nb_digits {
+            params.nb_digits = nb_digits as u16;
+        }
+        if let Some(precision) = row.precision {
+            params.precision = precision;
+        }
+        if let Some(is_signed) = row.is_signed {
+            params.is_signed = is_signed;
+        }
+        params.scaled_outcome = row.scaled_outcome;
+    }
+    Ok(params)
+}
+
+/// Lifecycle of an announced event as seen from the outside.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq, Display, EnumString)]
+#[serde(rename_all = "camelCase")]
+#[strum(serialize_all = "camelCase")]
+pub enum EventStatus {
+    /// Announced and waiting for maturity.
+    Open,
+    /// Past maturity but not yet attested.
+    Matured,
+    /// Attestation signatures exist.
+    Signed,
+}
+
+impl EventStatus {
+    pub fn new(event_maturity_epoch: u32, is_signed: bool, now: u32) -> Self {
+        if is_signed {
+            EventStatus::Signed
+        } else if event_maturity_epoch <= now {
+            EventStatus::Matured
+        } else {
+            EventStatus::Open
+        }
+    }
+}
+
 /// Parameters for an event.
 ///
 /// This is used to store the event type, the number of digits to round to, and the unit of the event.
 /// Specifically when the event is a single contract to be attested to.
 ///
-/// The unit is used to determine the unit of the event.
+/// The signed outcome encodes the value in `scale` as `round(value / 10^precision)`, the DLC
+/// convention [`crate::digits`] decodes with. A precision of `-2` keeps two decimals of a fee
+/// rate, so 12.34 sat/vB is signed as 1234. Events announced before precision was chosen per
+/// event type signed `ceil(value)` whatever their precision, `scaled_outcome` is false for them.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EventParams {
     pub event_type: EventType,
     pub nb_digits: u16,
+    /// Power of ten of one unit of the signed outcome
+    pub precision: i32,
+    /// Whether the outcome is scaled by the precision, false for legacy events
+    #[serde(default)]
+    pub scaled_outcome: bool,
+    /// Signed events carry an extra sign nonce and can attest to negative outcomes.
+    pub is_signed: bool,
+    /// Unit outcomes are expressed in, see [`crate::units`].
+    pub scale: Unit,
+    /// Announcement unit string, the event type followed by the unit symbol.
     pub unit: String,
 }
 
+pub const MIN_NB_DIGITS: u16 = 1;
+/// Every digit is a nonce in the announcement, keep the announcement size reasonable.
+pub const MAX_NB_DIGITS: u16 = 32;
+pub const MIN_PRECISION: i32 = -8;
+pub const MAX_PRECISION: i32 = 8;
+
+impl EventParams {
+    /// Apply the precision and digit count requested at creation, within sane bounds.
+    pub fn with_overrides(
+        mut self,
+        precision: Option<i32>,
+        nb_digits: Option<u16>,
+    ) -> anyhow::Result<Self> {
+        if let Some(nb_digits) = nb_digits {
+            if !(MIN_NB_DIGITS..=MAX_NB_DIGITS).contains(&nb_digits) {
+                return Err(anyhow::anyhow!(
+                    "nb_digits must be between {} and {}. nb_digits={}",
+                    MIN_NB_DIGITS,
+                    MAX_NB_DIGITS,
+                    nb_digits
+                ));
+            }
+            self.nb_digits = nb_digits;
+        }
+        if let Some(precision) = precision {
+            if !(MIN_PRECISION..=MAX_PRECISION).contains(&precision) {
+                return Err(anyhow::anyhow!(
+                    "Precision must be between {} and {}. precision={}",
+                    MIN_PRECISION,
+                    MAX_PRECISION,
+                    precision
+                ));
+            }
+            self.precision = precision;
+        }
+        Ok(self)
+    }
+
+    /// Largest outcome the announced digits can attest to.
+    pub fn max_outcome(&self) -> i64 {
+        (1i64 << self.nb_digits) - 1
+    }
+
+    /// Scale and round a fetched value to the outcome that is signed, failing if the announced
+    /// digits cannot represent it.
+    pub fn outcome(&self, value: f64) -> anyhow::Result<i64> {
+        let outcome = if self.scaled_outcome {
+            (value * 10f64.powi(-self.precision)).round() as i64
+        } else {
+            value.ceil() as i64
+        };
+        let min_outcome = if self.is_signed {
+            -self.max_outcome()
+        } else {
+            0
+        };
+        if outcome < min_outcome || outcome > self.max_outcome() {
+            return Err(anyhow::anyhow!(
+                "Outcome does not fit the announced digits. outcome={} nb_digits={}",
+                outcome,
+                self.nb_digits
+            ));
+        }
+        Ok(outcome)
+    }
+
+    /// The value in `scale` a signed outcome stands for.
+    pub fn value(&self, outcome: i64) -> f64 {
+        if self.scaled_outcome {
+            outcome as f64 * 10f64.powi(self.precision)
+        } else {
+            outcome as f64
+        }
+    }
+}
+
 /// TODO: get the updates params for the data set
 impl From<EventType> for EventParams {
     fn from(value: EventType) -> Self {
         match value {
+            // Hundreds of sats, 20 digits reach about 1 BTC of fees.
             EventType::BlockFees => Self {
-                event_type: value,
                 nb_digits: 20,
-                unit: EventType::BlockFees.to_string(),
+                precision: 2,
+                scaled_outcome: true,
+                is_signed: false,
+                scale: unit_for(&value),
+                unit: announcement_unit(&value),
+                event_type: value,
             },
+            // Hundredths of the unit, 20 digits reach 10485.75.
             EventType::Difficulty => Self {
-                event_type: value,
                 nb_digits: 20,
-                unit: EventType::Difficulty.to_string(),
+                precision: -2,
+                scaled_outcome: true,
+                is_signed: false,
+                scale: unit_for(&value),
+                unit: announcement_unit(&value),
+                event_type: value,
             },
             EventType::FeeRate => Self {
-                event_type: value,
                 nb_digits: 20,
-                unit: EventType::FeeRate.to_string(),
+                precision: -2,
+                scaled_outcome: true,
+                is_signed: false,
+                scale: unit_for(&value),
+                unit: announcement_unit(&value),
+                event_type: value,
             },
             EventType::Hashrate => Self {
+                nb_digits: 20,
+                precision: -2,
+                scaled_outcome: true,
+                is_signed: false,
+                scale: unit_for(&value),
+                unit: announcement_unit(&value),
+                event_type: value,
+            },
+            EventType::NextDifficultyChange => Self {
+                nb_digits: 14,
+                precision: -2,
+                scaled_outcome: true,
+                is_signed: false,
+                scale: unit_for(&value),
+                unit: announcement_unit(&value),
+                event_type: value,
+            },
+            // At most HALVING_INTERVAL blocks, which fits in 18 binary digits.
+            EventType::BlocksUntilHalving => Self {
+                nb_digits: 18,
+                precision: 0,
+                scaled_outcome: true,
+                is_signed: false,
+                scale: unit_for(&value),
+                unit: announcement_unit(&value),
+                event_type: value,
+            },
+            EventType::DifficultyChangePercent => Self {
+                nb_digits: 14,
+                precision: -2,
+                scaled_outcome: true,
+                is_signed: true,
+                scale: unit_for(&value),
+                unit: announcement_unit(&value),
                 event_type: value,
+            },
+            EventType::AvgBlockInterval => Self {
                 nb_digits: 20,
-                unit: EventType::Hashrate.to_string(),
+                precision: -2,
+                scaled_outcome: true,
+                is_signed: false,
+                scale: unit_for(&value),
+                unit: announcement_unit(&value),
+                event_type: value,
+            },
+            // Thousands of sats, 24 digits reach the 50 BTC subsidy of the first blocks.
+            EventType::CoinbaseValue => Self {
+                nb_digits: 24,
+                precision: 3,
+                scaled_outcome: true,
+                is_signed: false,
+                scale: unit_for(&value),
+                unit: announcement_unit(&value),
+                event_type: value,
             },
         }
     }
@@ -92,14 +625,115 @@ impl From<EventType> for EventParams {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_util::{mock_mempool_client, setup_mock_server};
 
     #[test]
     fn test_available_events() {
         let events = EventType::available_events();
-        assert_eq!(events.len(), 4);
+        assert_eq!(events.len(), 9);
         assert_eq!(&events[0].to_string(), "hashrate");
         assert_eq!(&events[1].to_string(), "feeRate");
         assert_eq!(&events[2].to_string(), "blockFees");
         assert_eq!(&events[3].to_string(), "difficulty");
+        assert_eq!(&events[4].to_string(), "nextDifficultyChange");
+        assert_eq!(&events[5].to_string(), "blocksUntilHalving");
+        assert_eq!(&events[6].to_string(), "difficultyChangePercent");
+        assert_eq!(&events[7].to_string(), "avgBlockInterval");
+        assert_eq!(&events[8].to_string(), "coinbaseValue");
+    }
+
+    #[test]
+    fn outcome_options_validation() {
+        assert!(OutcomeOptions::new(&EventType::FeeRate, Some(FeePercentile::P50), None).is_ok());
+        assert!(OutcomeOptions::new(&EventType::Hashrate, Some(FeePercentile::P50), None).is_err());
+        assert!(OutcomeOptions::new(&EventType::Hashrate, None, Some(Aggregation::Max)).is_ok());
+        assert!(
+            OutcomeOptions::new(&EventType::BlocksUntilHalving, None, Some(Aggregation::Max))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn event_params_overrides() {
+        let params = EventParams::from(EventType::FeeRate)
+            .with_overrides(Some(0), Some(12))
+            .unwrap();
+        assert_eq!(params.precision, 0);
+        assert_eq!(params.nb_digits, 12);
+        assert_eq!(params.max_outcome(), 4095);
+        assert_eq!(params.outcome(4094.2).unwrap(), 4094);
+        assert!(params.outcome(4095.5).is_err());
+
+        let params = EventParams::from(EventType::FeeRate);
+        assert!(params.clone().with_overrides(None, Some(0)).is_err());
+        assert!(params.clone().with_overrides(None, Some(64)).is_err());
+        assert!(params.with_overrides(Some(20), None).is_err());
+
+        let params = EventParams::from(EventType::DifficultyChangePercent);
+        assert!(params.is_signed);
+        assert_eq!(params.outcome(-2.47).unwrap(), -247);
+        assert_eq!(params.value(-247), -2.47);
+        assert!(params
+            .outcome(-(params.max_outcome() as f64) - 1.0)
+            .is_err());
+        assert!(EventParams::from(EventType::Difficulty)
+            .outcome(-1.0)
+            .is_err());
+    }
+
+    #[test]
+    fn outcomes_carry_the_announced_precision() {
+        let params = EventParams::from(EventType::FeeRate);
+        assert_eq!(params.precision, -2);
+        assert_eq!(params.outcome(12.345).unwrap(), 1235);
+        assert_eq!(params.value(1235), 12.35);
+
+        let params = EventParams::from(EventType::BlockFees);
+        assert_eq!(params.outcome(3_000_049.0).unwrap(), 30_000);
+        assert_eq!(params.value(30_000), 3_000_000.0);
+
+        // Legacy events sign the value rounded up whatever their precision
+        let legacy = EventParams {
+            scaled_outcome: false,
+            ..EventParams::from(EventType::FeeRate)
+        };
+        assert_eq!(legacy.outcome(12.345).unwrap(), 13);
+        assert_eq!(legacy.value(13), 13.0);
+    }
+
+    #[test]
+    fn event_status() {
+        assert_eq!(EventStatus::new(100, false, 50), EventStatus::Open);
+        assert_eq!(EventStatus::new(100, false, 100), EventStatus::Matured);
+        assert_eq!(EventStatus::new(100, true, 50), EventStatus::Signed);
+        assert_eq!(EventStatus::new(100, true, 150), EventStatus::Signed);
+    }
+
+    #[tokio::test]
+    async fn event_type_metadata() {
+        let mock_server = setup_mock_server().await;
+        let mempool = mock_mempool_client(&mock_server);
+
+        let metadata =
+            EventTypeMetadata::fetch(EventType::Hashrate, &mempool, &[TimePeriod::ThreeMonths])
+                .await;
+        assert_eq!(metadata.unit, announcement_unit(&EventType::Hashrate));
+        assert!(metadata.current_value.is_some());
+        assert_eq!(metadata.ranges.len(), 1);
+        assert!(metadata.ranges[0].min <= metadata.ranges[0].max);
+
+        // No mock for the one year window, the range is left out
+        let metadata =
+            EventTypeMetadata::fetch(EventType::Hashrate, &mempool, &[TimePeriod::OneYear]).await;
+        assert!(metadata.ranges.is_empty());
+
+        let metadata = EventTypeMetadata::fetch(
+            EventType::BlocksUntilHalving,
+            &mempool,
+            &[TimePeriod::ThreeMonths],
+        )
+        .await;
+        assert_eq!(metadata.current_value, Some(209_999.0));
+        assert!(metadata.ranges.is_empty());
     }
 }
@@ -0,0 +1,260 @@
+//! End-to-end test harness.
+//!
+//! Starts the `oracle` binary on a free port against a database of its own, with a fresh key and
+//! mock data (see [`crate::mock_data`]) so no network access is needed, and drives it through
+//! [`ErnestOracleClient`]. Events are created with short maturities and signed by the watcher as
+//! in production. Downstream crates can use it in their CI:
+//!
+//! ```no_run
+//! # async fn e2e() -> anyhow::Result<()> {
+//! use ernest_oracle::{events::EventType, harness::{HarnessConfig, OracleHarness}};
+//!
+//! let oracle = OracleHarness::start(HarnessConfig::from_env()?).await?;
+//! let announcement = oracle.create_single(EventType::Hashrate, 2).await?;
+//! let attestation = oracle.attestation(&announcement.oracle_event.event_id).await?;
+//! oracle.stop().await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    path::{Path, PathBuf},
+    process::Stdio,
+    time::{Duration, Instant},
+};
+
+use bitcoin::{
+    secp256k1::{rand::thread_rng, SecretKey},
+    Network,
+};
+use kormir::{OracleAnnouncement, OracleAttestation};
+use sqlx::{Connection, PgConnection, PgPool};
+use tokio::process::{Child, Command};
+use uuid::Uuid;
+
+use crate::{
+    config::{OracleConfig, CONFIG_ENV},
+    events::EventType,
+    keys::{KEY_ENV, MNEMONIC_ENV},
+    mempool::MempoolConfig,
+    migrations,
+    mock_data::{MockDataConfig, MOCK_BASE_URL},
+    parlay::{contract::CombinationMethod, parameter::ParlayParameter},
+    routes::CreateEvent,
+    ErnestOracleClient,
+};
+
+/// Path of the `oracle` binary for [`HarnessConfig::from_env`].
+pub const ORACLE_BIN_ENV: &str = "ERNEST_ORACLE_BIN";
+
+/// How long the server may take to answer after it was started.
+pub const STARTUP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long [`OracleHarness::attestation`] waits for the watcher to sign.
+pub const SIGNING_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone)]
+pub struct HarnessConfig {
+    /// The `oracle` binary, e.g. `env!("CARGO_BIN_EXE_oracle")` in the tests of this crate
+    pub binary: PathBuf,
+    /// A database the harness may create and drop databases from
+    pub database_url: String,
+    pub mock_data: MockDataConfig,
+}
+
+impl HarnessConfig {
+    /// The binary in `ERNEST_ORACLE_BIN`, else `oracle` on the `PATH`, and the database in
+    /// `DATABASE_URL`.
+    pub fn from_env() -> anyhow::Result<Self> {
+        Ok(Self {
+            binary: std::env::var(ORACLE_BIN_ENV)
+                .unwrap_or("oracle".to_string())
+                .into(),
+            database_url: std::env::var("DATABASE_URL")
+                .map_err(|_| anyhow::anyhow!("Set DATABASE_URL to run the oracle harness"))?,
+            mock_data: MockDataConfig::default(),
+        })
+    }
+}
+
+/// A running oracle server, stopped and its database dropped by [`OracleHarness::stop`].
+pub struct OracleHarness {
+    pub client: ErnestOracleClient,
+    base_url: String,
+    database_url: String,
+    database: String,
+    dir: PathBuf,
+    server: Child,
+}
+
+impl OracleHarness {
+    pub async fn start(config: HarnessConfig) -> anyhow::Result<Self> {
+        let id = Uuid::new_v4().simple().to_string();
+        let database = format!("ernest_e2e_{}", id);
+        let mut database_url = reqwest::Url::parse(&config.database_url)?;
+        let mut admin = PgConnection::connect(database_url.as_str()).await?;
+        sqlx::query(&format!("CREATE DATABASE \"{}\"", database))
+            .execute(&mut admin)
+            .await?;
+        admin.close().await?;
+        database_url.set_path(&database);
+        let pool = PgPool::connect(database_url.as_str()).await?;
+        migrations::run(&pool).await?;
+        pool.close().await;
+
+        // The server reads `.env` and its config from its working directory
+        let dir = std::env::temp_dir().join(format!("ernest-e2e-{}", id));
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(dir.join(".env"), "")?;
+        let oracle_config = OracleConfig {
+            mempool: MempoolConfig {
+                network: Network::Regtest,
+                base_url: Some(MOCK_BASE_URL.to_string()),
+                esplora_url: None,
+                auth_header: None,
+            },
+            mock_data: Some(config.mock_data),
+            ..Default::default()
+        };
+        std::fs::write(
+            dir.join("config.json"),
+            serde_json::to_string(&oracle_config)?,
+        )?;
+
+        let port = std::net::TcpListener::bind("127.0.0.1:0")?
+            .local_addr()?
+            .port();
+        let key = SecretKey::new(&mut thread_rng());
+        let mut server = Command::new(&config.binary)
+            .current_dir(&dir)
+            .env("PORT", port.to_string())
+            .env("DATABASE_URL", database_url.as_str())
+            .env(CONFIG_ENV, dir.join("config.json"))
+            .env(KEY_ENV, key.display_secret().to_string())
+            .env_remove(MNEMONIC_ENV)
+            .stdout(Stdio::from(std::fs::File::create(dir.join("oracle.log"))?))
+            .stderr(Stdio::from(std::fs::File::create(dir.join("oracle.err"))?))
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| {
+                anyhow::anyhow!(
+                    "Could not start the oracle. binary={} error={}",
+                    config.binary.display(),
+                    e
+                )
+            })?;
+        let base_url = format!("http://127.0.0.1:{}", port);
+        Ok(Self {
+            client: match connect(&mut server, &base_url).await {
+                Ok(client) => client,
+                Err(e) => {
+                    let logs = logs(&dir);
+                    server.kill().await?;
+                    let _ = std::fs::remove_dir_all(&dir);
+                    drop_database(&config.database_url, &database).await?;
+                    return Err(e.context(logs));
+                }
+            },
+            base_url,
+            database_url: config.database_url,
+            database,
+            dir,
+            server,
+        })
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// What the server logged so far.
+    pub fn logs(&self) -> String {
+        logs(&self.dir)
+    }
+
+    fn maturity(matures_in_secs: u32) -> u32 {
+        chrono::Utc::now().timestamp() as u32 + matures_in_secs
+    }
+
+    /// Create a single event of `event_type` maturing in `matures_in_secs`.
+    pub async fn create_single(
+        &self,
+        event_type: EventType,
+        matures_in_secs: u32,
+    ) -> anyhow::Result<OracleAnnouncement> {
+        let event = CreateEvent::single(event_type, Self::maturity(matures_in_secs));
+        Ok(self.client.create_event(event).await?)
+    }
+
+    /// Create a parlay of `parameters` maturing in `matures_in_secs`.
+    pub async fn create_parlay(
+        &self,
+        parameters: Vec<ParlayParameter>,
+        combination_method: CombinationMethod,
+        matures_in_secs: u32,
+    ) -> anyhow::Result<OracleAnnouncement> {
+        let event = CreateEvent::Parlay {
+            parameters,
+            combination_method,
+            max_normalized_value: None,
+            event_maturity_epoch: Self::maturity(matures_in_secs),
+            score_mode: Default::default(),
+            description: None,
+            tags: vec![],
+            announce_at: None,
+            settlement_delay: None,
+            event_id: None,
+        };
+        Ok(self.client.create_event(event).await?)
+    }
+
+    /// Wait for the watcher to sign `event_id`, for at most [`SIGNING_TIMEOUT`].
+    pub async fn attestation(&self, event_id: &str) -> anyhow::Result<OracleAttestation> {
+        self.client
+            .wait_for_attestation(event_id, Duration::from_millis(250), SIGNING_TIMEOUT)
+            .await
+            .map_err(|e| anyhow::anyhow!("{}\n{}", e, self.logs()))
+    }
+
+    /// Stop the server and drop its database.
+    pub async fn stop(mut self) -> anyhow::Result<()> {
+        self.server.kill().await?;
+        let _ = std::fs::remove_dir_all(&self.dir);
+        drop_database(&self.database_url, &self.database).await
+    }
+}
+
+/// Wait until the `server` at `base_url` answers.
+async fn connect(server: &mut Child, base_url: &str) -> anyhow::Result<ErnestOracleClient> {
+    let started = Instant::now();
+    loop {
+        if let Some(status) = server.try_wait()? {
+            return Err(anyhow::anyhow!("The oracle exited. status={}", status));
+        }
+        match ErnestOracleClient::new(base_url).await {
+            Ok(client) => return Ok(client),
+            Err(e) if started.elapsed() > STARTUP_TIMEOUT => {
+                return Err(anyhow::anyhow!("The oracle did not start. error={}", e))
+            }
+            Err(_) => tokio::time::sleep(Duration::from_millis(100)).await,
+        }
+    }
+}
+
+fn logs(dir: &Path) -> String {
+    ["oracle.log", "oracle.err"]
+        .iter()
+        .filter_map(|file| std::fs::read_to_string(dir.join(file)).ok())
+        .collect()
+}
+
+async fn drop_database(database_url: &str, database: &str) -> anyhow::Result<()> {
+    let mut admin = PgConnection::connect(database_url).await?;
+    sqlx::query(&format!(
+        "DROP DATABASE IF EXISTS \"{}\" WITH (FORCE)",
+        database
+    ))
+    .execute(&mut admin)
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,287 @@
+//! Periodic snapshots of every event type's value.
+//!
+//! The sampler stores what an event would settle on if it matured at the time of sampling, so
+//! frontends can chart the data a contract settles on without going to mempool.space.
+//!
+//! The watcher also snapshots the values of single events and parlay legs shortly before they
+//! settle (see [`PREFETCH_LEAD_SECS`]), so signing does not wait on mempool.space. Those are kept
+//! out of the charted history.
+
+use std::{str::FromStr, sync::Arc, time::Duration};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{prelude::FromRow, types::Json, PgPool, Postgres};
+use tokio::sync::watch;
+
+use crate::{
+    events::{EventType, OutcomeOptions},
+    mempool::{DataProvenance, DataSample, MempoolClient},
+    OracleServerState,
+};
+
+pub const HISTORY_INTERVAL_SECS: u64 = 60 * 60;
+
+/// How long before an event settles the values it settles on are fetched. Signing uses a value
+/// fetched up to twice this long before settlement, older ones are fetched again.
+pub const PREFETCH_LEAD_SECS: i64 = 5 * 60;
+
+/// Window returned when a history request does not set `from`.
+pub const DEFAULT_HISTORY_DAYS: i64 = 30;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct MetricPoint {
+    pub value: f64,
+    pub sampled_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MetricHistory {
+    pub data_type: EventType,
+    pub points: Vec<MetricPoint>,
+}
+
+pub async fn save_snapshot(
+    pool: &PgPool,
+    data_type: &EventType,
+    provenance: &DataProvenance,
+) -> anyhow::Result<()> {
+    sqlx::query(
+        "INSERT INTO metric_history (data_type, value, source_url, sampled_at) VALUES ($1, $2, $3, $4)",
+    )
+    .bind(data_type.to_string())
+    .bind(provenance.value)
+    .bind(&provenance.source_url)
+    .bind(provenance.fetched_at)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+#[derive(FromRow)]
+struct PrefetchRow {
+    value: f64,
+    source_url: String,
+    sampled_at: DateTime<Utc>,
+    raw_response: Option<serde_json::Value>,
+    samples: Option<Json<Vec<DataSample>>>,
+}
+
+/// Save the value fetched ahead of maturity for `event_id`, with `leg` the parlay leg it is for.
+pub async fn save_prefetch(
+    pool: &PgPool,
+    event_id: &str,
+    leg: Option<usize>,
+    data_type: &EventType,
+    provenance: &DataProvenance,
+) -> anyhow::Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO metric_history (data_type, value, source_url, sampled_at, event_id, leg, raw_response, samples)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        "#,
+    )
+    .bind(data_type.to_string())
+    .bind(provenance.value)
+    .bind(&provenance.source_url)
+    .bind(provenance.fetched_at)
+    .bind(event_id)
+    .bind(leg.map(|leg| leg as i32))
+    .bind(&provenance.raw_response)
+    .bind((!provenance.samples.is_empty()).then_some(Json(&provenance.samples)))
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// The latest value fetched ahead of maturity for `event_id` and `leg` since `since`.
+pub async fn get_prefetch(
+    pool: &PgPool,
+    event_id: &str,
+    leg: Option<usize>,
+    since: DateTime<Utc>,
+) -> anyhow::Result<Option<DataProvenance>> {
+    let row = sqlx::query_as::<Postgres, PrefetchRow>(
+        r#"
+        SELECT value, source_url, sampled_at, raw_response, samples FROM metric_history
+        WHERE event_id = $1 AND leg IS NOT DISTINCT FROM $2 AND sampled_at >= $3
+        ORDER BY sampled_at DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(event_id)
+    .bind(leg.map(|leg| leg as i32))
+    .bind(since)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|row| DataProvenance {
+        source_url: row.source_url,
+        raw_response: row.raw_response.unwrap_or_default(),
+        fetched_at: row.sampled_at,
+        value: row.value,
+        samples: row.samples.map(|samples| samples.0).unwrap_or_default(),
+    }))
+}
+
+/// Snapshots of `data_type` sampled within `[from, to]`, oldest first.
+pub async fn get_history(
+    pool: &PgPool,
+    data_type: &EventType,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> anyhow::Result<MetricHistory> {
+    let points = sqlx::query_as::<Postgres, MetricPoint>(
+        "SELECT value, sampled_at FROM metric_history WHERE data_type = $1 AND event_id IS NULL AND sampled_at BETWEEN $2 AND $3 ORDER BY sampled_at",
+    )
+    .bind(data_type.to_string())
+    .bind(from)
+    .bind(to)
+    .fetch_all(pool)
+    .await?;
+    Ok(MetricHistory {
+        data_type: data_type.clone(),
+        points,
+    })
+}
+
+/// Take one snapshot of every event type. A failing data type does not stop the others.
+pub async fn sample_metrics(pool: &PgPool, mempool: &MempoolClient) {
+    for data_type in EventType::available_events() {
+        let provenance = match data_type
+            .outcome_with_provenance(mempool, &OutcomeOptions::default())
+            .await
+        {
+            Ok(provenance) => provenance,
+            Err(e) => {
+                log::error!(
+                    "Failed to sample metric. data_type={} error={}",
+                    data_type,
+                    e
+                );
+                continue;
+            }
+        };
+        if let Err(e) = save_snapshot(pool, &data_type, &provenance).await {
+            log::error!(
+                "Failed to save metric snapshot. data_type={} error={}",
+                data_type,
+                e
+            );
+        }
+    }
+}
+
+pub async fn sample_metrics_loop(
+    state: Arc<OracleServerState>,
+    mut stop_signal: watch::Receiver<bool>,
+) {
+    let mut timer = tokio::time::interval(Duration::from_secs(HISTORY_INTERVAL_SECS));
+    loop {
+        tokio::select! {
+            _ = stop_signal.changed() => {
+                if *stop_signal.borrow() {
+                    break;
+                }
+            }
+            _ = timer.tick() => {
+                sample_metrics(&state.oracle.oracle.storage.pool, &state.mempool).await;
+            }
+        }
+    }
+}
+
+/// Parse a unix timestamp in seconds.
+pub fn parse_timestamp(timestamp: i64) -> anyhow::Result<DateTime<Utc>> {
+    DateTime::from_timestamp(timestamp, 0).ok_or(anyhow::anyhow!(
+        "Invalid timestamp. timestamp={}",
+        timestamp
+    ))
+}
+
+/// Parse a data type as used in query strings.
+pub fn parse_data_type(data_type: &str) -> anyhow::Result<EventType> {
+    EventType::from_str(data_type)
+        .map_err(|_| anyhow::anyhow!("Unknown data type. data_type={}", data_type))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::routes::CreateEvent;
+    use crate::test_util::{mock_mempool_client, setup_ernest_oracle, setup_mock_server};
+
+    #[tokio::test]
+    async fn samples_are_queryable_by_window() {
+        let pool =
+            PgPool::connect(&std::env::var("DATABASE_URL").expect("$DATABASE_URL is not set"))
+                .await
+                .unwrap();
+        let mock_server = setup_mock_server().await;
+        let mempool = mock_mempool_client(&mock_server);
+
+        let before = Utc::now();
+        sample_metrics(&pool, &mempool).await;
+        let after = Utc::now();
+
+        let history = get_history(&pool, &EventType::BlocksUntilHalving, before, after)
+            .await
+            .unwrap();
+        assert_eq!(history.points.len(), 1);
+        assert_eq!(history.points[0].value, 209_999.0);
+
+        let history = get_history(
+            &pool,
+            &EventType::BlocksUntilHalving,
+            after + chrono::Duration::seconds(1),
+            after + chrono::Duration::seconds(2),
+        )
+        .await
+        .unwrap();
+        assert!(history.points.is_empty());
+    }
+
+    #[tokio::test]
+    async fn signs_with_prefetched_values() {
+        let mock_server = setup_mock_server().await;
+        let mempool = mock_mempool_client(&mock_server);
+        let oracle = setup_ernest_oracle(mempool).await;
+        let announcement = oracle
+            .create_event(CreateEvent::single(
+                EventType::BlocksUntilHalving,
+                Utc::now().timestamp() as u32 + 60,
+            ))
+            .await
+            .unwrap();
+        let event_id = announcement.oracle_event.event_id;
+
+        let before = Utc::now();
+        oracle.prefetch_outcomes(&event_id).await.unwrap();
+        oracle.prefetch_outcomes(&event_id).await.unwrap();
+        let prefetched = get_prefetch(&oracle.oracle.storage.pool, &event_id, None, before)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(prefetched.value, 209_999.0);
+        let history = get_history(
+            &oracle.oracle.storage.pool,
+            &EventType::BlocksUntilHalving,
+            before,
+            Utc::now(),
+        )
+        .await
+        .unwrap();
+        assert!(history.points.is_empty());
+
+        // Mempool is down at maturity
+        let offline =
+            setup_ernest_oracle(MempoolClient::new("http://127.0.0.1:9".to_string())).await;
+        let outcome = offline
+            .single_event_outcome(&event_id, &EventType::BlocksUntilHalving)
+            .await
+            .unwrap();
+        assert_eq!(outcome.outcome, 209_999);
+        assert_eq!(outcome.provenance.fetched_at, prefetched.fetched_at);
+    }
+}
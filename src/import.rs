@@ -0,0 +1,138 @@
+//! Import of events exported from other kormir oracles using the same key, e.g. to move a
+//! SQLite or nostr deployment over to ernest-oracle.
+//!
+//! Events keep their nonce indexes so unsigned ones can still be signed at maturity, and the
+//! nonce index sequence is moved past them.
+
+use bitcoin::{key::Secp256k1, XOnlyPublicKey};
+use dlc_messages::oracle_msgs::EventDescriptor;
+use kormir::{storage::OracleEventData, Writeable};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Postgres};
+
+use crate::storage;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ImportResult {
+    Imported,
+    /// The event was imported before
+    AlreadyExists,
+}
+
+/// Check an exported event is well formed and announced by `oracle_public_key`.
+pub fn validate(data: &OracleEventData, oracle_public_key: &XOnlyPublicKey) -> anyhow::Result<()> {
+    let announcement = &data.announcement;
+    if data.event_id != announcement.oracle_event.event_id {
+        return Err(anyhow::anyhow!(
+            "Event id does not match the announcement. event_id={}",
+            data.event_id
+        ));
+    }
+    if announcement.oracle_public_key != *oracle_public_key {
+        return Err(anyhow::anyhow!(
+            "Event is announced by another oracle. event_id={} oracle_public_key={}",
+            data.event_id,
+            announcement.oracle_public_key
+        ));
+    }
+    let secp = Secp256k1::verification_only();
+    announcement.validate(&secp).map_err(|e| {
+        anyhow::anyhow!(
+            "Invalid announcement. event_id={} error={:?}",
+            data.event_id,
+            e
+        )
+    })?;
+    if data.indexes.len() != announcement.oracle_event.oracle_nonces.len() {
+        return Err(anyhow::anyhow!(
+            "Nonce indexes do not match the announced nonces. event_id={} indexes={} nonces={}",
+            data.event_id,
+            data.indexes.len(),
+            announcement.oracle_event.oracle_nonces.len()
+        ));
+    }
+    if let Some(attestation) = data.attestation() {
+        attestation.validate(&secp, announcement).map_err(|e| {
+            anyhow::anyhow!(
+                "Invalid attestation. event_id={} error={:?}",
+                data.event_id,
+                e
+            )
+        })?;
+    }
+    Ok(())
+}
+
+/// Save a validated event with its nonces and signatures in one transaction.
+pub async fn save_imported_event(
+    pool: &PgPool,
+    data: &OracleEventData,
+) -> anyhow::Result<ImportResult> {
+    let exists = sqlx::query_scalar::<Postgres, bool>(
+        "SELECT EXISTS (SELECT 1 FROM events WHERE event_id = $1)",
+    )
+    .bind(&data.event_id)
+    .fetch_one(pool)
+    .await?;
+    if exists {
+        return Ok(ImportResult::AlreadyExists);
+    }
+
+    let indexes = data.indexes.iter().map(|i| *i as i32).collect::<Vec<_>>();
+    let used = sqlx::query_scalar::<Postgres, i32>(
+        "SELECT id FROM event_nonces WHERE id = ANY($1) ORDER BY id",
+    )
+    .bind(&indexes)
+    .fetch_all(pool)
+    .await?;
+    if !used.is_empty() {
+        // The same index derives the same nonce, reusing it would leak the oracle key
+        return Err(anyhow::anyhow!(
+            "Nonce indexes are used by another event. event_id={} indexes={:?}",
+            data.event_id,
+            used
+        ));
+    }
+
+    let announcement = &data.announcement;
+    let is_enum = matches!(
+        announcement.oracle_event.event_descriptor,
+        EventDescriptor::EnumEvent(_)
+    );
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        r#"
+        INSERT INTO events (
+            event_id, announcement_signature, oracle_event,
+            name, is_enum, maturity
+        )
+        VALUES ($1, $2, $3, $4, $5, $6)
+        "#,
+    )
+    .bind(&data.event_id)
+    .bind(announcement.announcement_signature.encode())
+    .bind(announcement.oracle_event.encode())
+    .bind(&data.event_id)
+    .bind(is_enum)
+    .bind(announcement.oracle_event.event_maturity_epoch as i64)
+    .execute(&mut *tx)
+    .await?;
+
+    storage::insert_nonces(
+        &mut tx,
+        &data.event_id,
+        &data.indexes,
+        &announcement.oracle_event.oracle_nonces,
+        &data.signatures,
+    )
+    .await?;
+
+    // Imported events have no parlay contract, so they are all singles
+    sqlx::query("INSERT INTO event_types (oracle_event_id, event_type) VALUES ($1, 'single')")
+        .bind(&data.event_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(ImportResult::Imported)
+}
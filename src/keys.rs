@@ -0,0 +1,105 @@
+//! The oracle key, from a raw hex secret (`ERNEST_KEY`) or a BIP39 mnemonic
+//! (`ERNEST_MNEMONIC` and optionally `ERNEST_DERIVATION_PATH`).
+//!
+//! A mnemonic derives the signing key at the derivation path, kormir's
+//! [`DEFAULT_DERIVATION_PATH`] by default. Nonces are derived from the signing key as for a hex
+//! key, so an oracle configured with the mnemonic or with the derived key is the same oracle.
+
+use std::str::FromStr;
+
+use bip39::Mnemonic;
+use bitcoin::{
+    bip32::{DerivationPath, Xpriv},
+    key::{Keypair, Secp256k1},
+    secp256k1::{rand::RngCore, SecretKey},
+    Network,
+};
+
+pub const KEY_ENV: &str = "ERNEST_KEY";
+pub const MNEMONIC_ENV: &str = "ERNEST_MNEMONIC";
+pub const DERIVATION_PATH_ENV: &str = "ERNEST_DERIVATION_PATH";
+
+/// Path kormir derives its signing key at.
+pub const DEFAULT_DERIVATION_PATH: &str = "m/86'/0'/0'/0/0";
+
+pub fn keypair_from_hex(key: &str) -> anyhow::Result<Keypair> {
+    let secret_key = SecretKey::from_str(key)?;
+    Ok(Keypair::from_secret_key(&Secp256k1::new(), &secret_key))
+}
+
+pub fn keypair_from_mnemonic(mnemonic: &str, derivation_path: &str) -> anyhow::Result<Keypair> {
+    let secp = Secp256k1::new();
+    let mnemonic = Mnemonic::parse(mnemonic)?;
+    let master = Xpriv::new_master(Network::Bitcoin, &mnemonic.to_seed(""))?;
+    let path = DerivationPath::from_str(derivation_path)?;
+    let signing_key = master.derive_priv(&secp, &path)?.private_key;
+    Ok(Keypair::from_secret_key(&secp, &signing_key))
+}
+
+/// A hex secret key, or otherwise a mnemonic derived at `derivation_path`.
+pub fn keypair_from_secret(secret: &str, derivation_path: &str) -> anyhow::Result<Keypair> {
+    let secret = secret.trim();
+    match keypair_from_hex(secret) {
+        Ok(keypair) => Ok(keypair),
+        Err(_) => keypair_from_mnemonic(secret, derivation_path)
+            .map_err(|_| anyhow::anyhow!("Secret is neither a hex key nor a mnemonic")),
+    }
+}
+
+/// The key configured in the environment, a mnemonic or a hex secret but not both.
+pub fn keypair_from_env() -> anyhow::Result<Keypair> {
+    match (std::env::var(MNEMONIC_ENV), std::env::var(KEY_ENV)) {
+        (Ok(_), Ok(_)) => Err(anyhow::anyhow!(
+            "Only one of {} and {} can be set",
+            MNEMONIC_ENV,
+            KEY_ENV
+        )),
+        (Ok(mnemonic), Err(_)) => {
+            let path =
+                std::env::var(DERIVATION_PATH_ENV).unwrap_or(DEFAULT_DERIVATION_PATH.to_string());
+            keypair_from_mnemonic(&mnemonic, &path)
+        }
+        (Err(_), Ok(key)) => keypair_from_hex(&key),
+        (Err(_), Err(_)) => Err(anyhow::anyhow!(
+            "Set {} or {} to configure the oracle key",
+            MNEMONIC_ENV,
+            KEY_ENV
+        )),
+    }
+}
+
+/// A new 24 word mnemonic.
+pub fn generate_mnemonic() -> anyhow::Result<Mnemonic> {
+    let mut entropy = [0u8; 32];
+    bitcoin::secp256k1::rand::thread_rng().fill_bytes(&mut entropy);
+    Ok(Mnemonic::from_entropy(&entropy)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MNEMONIC: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    #[test]
+    fn derives_bip86_key() {
+        // First receiving key of the BIP86 test vectors
+        let keypair = keypair_from_mnemonic(MNEMONIC, DEFAULT_DERIVATION_PATH).unwrap();
+        assert_eq!(
+            keypair.x_only_public_key().0.to_string(),
+            "cc8a4bc64d897bddc5fbc2f670f7a8ba0b386779106cf1223c6fc5d7cd6fc115"
+        );
+        assert_ne!(
+            keypair_from_mnemonic(MNEMONIC, "m/86'/0'/0'/0/1").unwrap(),
+            keypair
+        );
+        assert!(keypair_from_mnemonic("abandon about", DEFAULT_DERIVATION_PATH).is_err());
+    }
+
+    #[test]
+    fn generated_mnemonic_parses() {
+        let mnemonic = generate_mnemonic().unwrap();
+        assert_eq!(mnemonic.word_count(), 24);
+        assert!(keypair_from_mnemonic(&mnemonic.to_string(), DEFAULT_DERIVATION_PATH).is_ok());
+    }
+}
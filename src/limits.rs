@@ -0,0 +1,206 @@
+//! Configurable limits on what a single create request may ask for.
+//!
+//! Events are counted per API key (the `X-Api-Key` header) and UTC day. Requests without a key
+//! share one allowance. Keys are only stored hashed. Daily quotas on every kind of request are
+//! in [`crate::usage`].
+
+use bitcoin::hashes::{sha256, Hash};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Postgres};
+
+use crate::events::EventParams;
+use crate::oracle::{calculate_oracle_parameters, DEFAULT_MAX_NORMALIZED_VALUE};
+use crate::routes::CreateEvent;
+
+pub const API_KEY_HEADER: &str = "x-api-key";
+pub const DEFAULT_MAX_PARLAY_LEGS: usize = 20;
+
+/// Allowance of requests that do not send an API key.
+const ANONYMOUS_KEY: &str = "anonymous";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CreateLimits {
+    pub max_parlay_legs: Option<usize>,
+    /// Largest digit count of an announced event, parlays included
+    pub max_nb_digits: Option<u16>,
+    pub max_events_per_key_per_day: Option<i64>,
+}
+
+impl Default for CreateLimits {
+    fn default() -> Self {
+        Self {
+            max_parlay_legs: Some(DEFAULT_MAX_PARLAY_LEGS),
+            max_nb_digits: None,
+            max_events_per_key_per_day: None,
+        }
+    }
+}
+
+/// A create request over one of the configured limits.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LimitExceeded(pub String);
+
+impl std::fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for LimitExceeded {}
+
+/// The stored form of an API key.
+pub fn hash_api_key(api_key: Option<&str>) -> String {
+    match api_key {
+        Some(api_key) => sha256::Hash::hash(api_key.as_bytes()).to_string(),
+        None => ANONYMOUS_KEY.to_string(),
+    }
+}
+
+/// Digits the announcement of `event` will have.
+fn announced_nb_digits(event: &CreateEvent) -> u16 {
+    match event {
+        CreateEvent::Single {
+            event_type,
+            nb_digits,
+            ..
+        } => nb_digits.unwrap_or(EventParams::from(event_type.clone()).nb_digits),
+        CreateEvent::Parlay {
+            max_normalized_value,
+            ..
+        } => {
+            calculate_oracle_parameters(
+                max_normalized_value.unwrap_or(DEFAULT_MAX_NORMALIZED_VALUE),
+            )
+            .0
+        }
+    }
+}
+
+impl CreateLimits {
+    /// Check the size of `event` against the limits.
+    pub fn check_event(&self, event: &CreateEvent) -> Result<(), LimitExceeded> {
+        if let (Some(max), CreateEvent::Parlay { parameters, .. }) = (self.max_parlay_legs, event) {
+            if parameters.len() > max {
+                return Err(LimitExceeded(format!(
+                    "Parlay has too many legs. legs={} max={}",
+                    parameters.len(),
+                    max
+                )));
+            }
+        }
+        if let Some(max) = self.max_nb_digits {
+            let nb_digits = announced_nb_digits(event);
+            if nb_digits > max {
+                return Err(LimitExceeded(format!(
+                    "Event has too many digits. nb_digits={} max={}",
+                    nb_digits, max
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check the key can create `events` more events today.
+    pub async fn check_daily_events(
+        &self,
+        pool: &PgPool,
+        api_key: Option<&str>,
+        events: i64,
+    ) -> anyhow::Result<()> {
+        let Some(max) = self.max_events_per_key_per_day else {
+            return Ok(());
+        };
+        let created = events_created_today(pool, api_key).await?;
+        if created + events > max {
+            return Err(LimitExceeded(format!(
+                "Daily event limit reached, it resets at midnight UTC. created={} requested={} max={}",
+                created, events, max
+            ))
+            .into());
+        }
+        Ok(())
+    }
+}
+
+/// Events created with `api_key` since midnight UTC.
+pub async fn events_created_today(pool: &PgPool, api_key: Option<&str>) -> anyhow::Result<i64> {
+    Ok(sqlx::query_scalar::<Postgres, i64>(
+        r#"
+        SELECT COUNT(*) FROM event_creators
+        WHERE api_key = $1 AND created_at >= date_trunc('day', NOW() AT TIME ZONE 'UTC') AT TIME ZONE 'UTC'
+        "#,
+    )
+    .bind(hash_api_key(api_key))
+    .fetch_one(pool)
+    .await?)
+}
+
+/// Remember `event_id` was created with `api_key`.
+pub async fn record_creation(
+    pool: &PgPool,
+    event_id: &str,
+    api_key: Option<&str>,
+) -> anyhow::Result<()> {
+    sqlx::query("INSERT INTO event_creators (event_id, api_key) VALUES ($1, $2)")
+        .bind(event_id)
+        .bind(hash_api_key(api_key))
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::EventType;
+    use crate::mempool::{MempoolClient, BASE_URL};
+    use crate::test_util::{setup_ernest_oracle, SingleEvent};
+
+    fn single(nb_digits: Option<u16>) -> CreateEvent {
+        CreateEvent::from(SingleEvent {
+            nb_digits,
+            ..SingleEvent::new(
+                EventType::Hashrate,
+                chrono::Utc::now().timestamp() as u32 + 1000,
+            )
+        })
+    }
+
+    #[tokio::test]
+    async fn enforces_limits() {
+        let limits = CreateLimits {
+            max_parlay_legs: Some(1),
+            max_nb_digits: Some(24),
+            max_events_per_key_per_day: Some(1),
+        };
+        assert!(limits.check_event(&single(Some(24))).is_ok());
+        assert!(limits.check_event(&single(Some(25))).is_err());
+        assert_eq!(hash_api_key(None), ANONYMOUS_KEY);
+        assert_ne!(hash_api_key(Some("key")), "key");
+
+        let mempool = MempoolClient::new(BASE_URL.to_string());
+        let oracle = setup_ernest_oracle(mempool).await;
+        let pool = &oracle.oracle.storage.pool;
+        let api_key = uuid::Uuid::new_v4().to_string();
+        let announcement = oracle.create_event(single(None)).await.unwrap();
+
+        limits
+            .check_daily_events(pool, Some(&api_key), 1)
+            .await
+            .unwrap();
+        assert!(limits
+            .check_daily_events(pool, Some(&api_key), 2)
+            .await
+            .is_err());
+        record_creation(pool, &announcement.oracle_event.event_id, Some(&api_key))
+            .await
+            .unwrap();
+        let error = limits
+            .check_daily_events(pool, Some(&api_key), 1)
+            .await
+            .unwrap_err();
+        assert!(error.downcast_ref::<LimitExceeded>().is_some());
+        assert_eq!(events_created_today(pool, Some(&api_key)).await.unwrap(), 1);
+    }
+}
@@ -0,0 +1,131 @@
+//! Per-event advisory locks so replicas sharing a database sign each event exactly once.
+//!
+//! Locks are transaction scoped: the lock is held by an open transaction on a dedicated
+//! connection and released when the [`EventLock`] is released or dropped, so a panicking or
+//! failing signer never leaves an event locked.
+
+use std::time::Duration;
+
+use sqlx::{PgPool, Postgres, Transaction};
+
+/// Namespace of the lock keys, keeps them apart from other users of advisory locks.
+pub const SIGNING_LOCK_PREFIX: &str = "ernest-oracle/sign/";
+
+pub struct EventLock {
+    event_id: String,
+    tx: Transaction<'static, Postgres>,
+}
+
+impl EventLock {
+    pub fn event_id(&self) -> &str {
+        &self.event_id
+    }
+
+    pub async fn release(self) -> anyhow::Result<()> {
+        self.tx.commit().await?;
+        Ok(())
+    }
+}
+
+/// Take the signing lock of `event_id`, `None` when another signer holds it.
+pub async fn try_lock_event(pool: &PgPool, event_id: &str) -> anyhow::Result<Option<EventLock>> {
+    let mut tx = pool.begin().await?;
+    let locked = sqlx::query_scalar::<Postgres, bool>(
+        "SELECT pg_try_advisory_xact_lock(hashtextextended($1 || $2, 0))",
+    )
+    .bind(SIGNING_LOCK_PREFIX)
+    .bind(event_id)
+    .fetch_one(&mut *tx)
+    .await?;
+    if !locked {
+        tx.rollback().await?;
+        return Ok(None);
+    }
+    Ok(Some(EventLock {
+        event_id: event_id.to_string(),
+        tx,
+    }))
+}
+
+/// Take the signing lock of `event_id`, waiting up to `timeout` for another signer to release
+/// it. `None` when it is still held then.
+pub async fn lock_event(
+    pool: &PgPool,
+    event_id: &str,
+    timeout: Duration,
+) -> anyhow::Result<Option<EventLock>> {
+    let mut tx = pool.begin().await?;
+    sqlx::query(&format!(
+        "SET LOCAL lock_timeout = '{}ms'",
+        timeout.as_millis()
+    ))
+    .execute(&mut *tx)
+    .await?;
+    let locked = sqlx::query("SELECT pg_advisory_xact_lock(hashtextextended($1 || $2, 0))")
+        .bind(SIGNING_LOCK_PREFIX)
+        .bind(event_id)
+        .execute(&mut *tx)
+        .await;
+    match locked {
+        Ok(_) => Ok(Some(EventLock {
+            event_id: event_id.to_string(),
+            tx,
+        })),
+        // lock_not_available, the transaction is aborted and rolled back on drop
+        Err(sqlx::Error::Database(e)) if e.code().as_deref() == Some("55P03") => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn lock_is_exclusive_until_released() {
+        let pool =
+            PgPool::connect(&std::env::var("DATABASE_URL").expect("$DATABASE_URL is not set"))
+                .await
+                .unwrap();
+        let event_id = uuid::Uuid::new_v4().to_string();
+
+        let lock = try_lock_event(&pool, &event_id).await.unwrap().unwrap();
+        assert!(try_lock_event(&pool, &event_id).await.unwrap().is_none());
+        let other_id = uuid::Uuid::new_v4().to_string();
+        let other = try_lock_event(&pool, &other_id).await.unwrap();
+        assert!(other.is_some());
+
+        lock.release().await.unwrap();
+        let lock = try_lock_event(&pool, &event_id).await.unwrap().unwrap();
+        // Dropping rolls the transaction back in the background, release waits for it
+        lock.release().await.unwrap();
+        assert!(try_lock_event(&pool, &event_id).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn waiting_lock_times_out_or_follows_the_holder() {
+        let pool =
+            PgPool::connect(&std::env::var("DATABASE_URL").expect("$DATABASE_URL is not set"))
+                .await
+                .unwrap();
+        let event_id = uuid::Uuid::new_v4().to_string();
+        let timeout = Duration::from_millis(200);
+
+        let lock = try_lock_event(&pool, &event_id).await.unwrap().unwrap();
+        assert!(lock_event(&pool, &event_id, timeout)
+            .await
+            .unwrap()
+            .is_none());
+
+        let waiting = tokio::spawn({
+            let pool = pool.clone();
+            let event_id = event_id.clone();
+            async move { lock_event(&pool, &event_id, Duration::from_secs(5)).await }
+        });
+        tokio::time::sleep(timeout).await;
+        lock.release().await.unwrap();
+        let lock = waiting.await.unwrap().unwrap();
+        assert!(lock.is_some());
+        assert!(try_lock_event(&pool, &event_id).await.unwrap().is_none());
+    }
+}
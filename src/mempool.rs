@@ -1,8 +1,128 @@
-use reqwest::Client;
-use serde::{Deserialize, Serialize};
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use bitcoin::{
+    secp256k1::rand::{thread_rng, Rng},
+    Network,
+};
+use chrono::{DateTime, Utc};
+use reqwest::{
+    header::{HeaderName, HeaderValue},
+    Client, StatusCode,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use strum_macros::{Display, EnumIter, EnumString};
+
+use crate::{
+    mock_data::{MockData, MOCK_BASE_URL},
+    units::Unit,
+    ErrorCode, OracleServerError,
+};
 
 pub const BASE_URL: &str = "https://mempool.space/api/v1";
 
+/// The mempool.space API for `network`, which serves each network under its own path prefix.
+pub fn mempool_space_url(network: Network) -> anyhow::Result<String> {
+    Ok(format!(
+        "https://mempool.space{}/api/v1",
+        network_prefix(network)?
+    ))
+}
+
+/// The esplora API mempool.space serves for `network` next to its own.
+pub fn mempool_space_esplora_url(network: Network) -> anyhow::Result<String> {
+    Ok(format!(
+        "https://mempool.space{}/api",
+        network_prefix(network)?
+    ))
+}
+
+fn network_prefix(network: Network) -> anyhow::Result<&'static str> {
+    let prefix = match network {
+        Network::Bitcoin => "",
+        Network::Testnet => "/testnet",
+        Network::Testnet4 => "/testnet4",
+        Network::Signet => "/signet",
+        _ => {
+            return Err(anyhow::anyhow!(
+                "mempool.space does not serve this network, set a base url. network={}",
+                network
+            ))
+        }
+    };
+    Ok(prefix)
+}
+
+/// Header sent with every mempool request, e.g. the credentials of a private instance.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AuthHeader {
+    pub name: String,
+    pub value: String,
+}
+
+/// Which mempool instance the oracle reads and which network it attests for.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MempoolConfig {
+    /// `bitcoin`, `testnet`, `testnet4`, `signet` or `regtest`
+    pub network: Network,
+    /// A self-hosted instance including the `/api/v1` prefix, mempool.space when unset
+    pub base_url: Option<String>,
+    /// The esplora API of the instance, which serves the tip height. Taken from a `base_url`
+    /// ending in `/v1` when unset.
+    pub esplora_url: Option<String>,
+    pub auth_header: Option<AuthHeader>,
+}
+
+impl Default for MempoolConfig {
+    fn default() -> Self {
+        Self {
+            network: Network::Bitcoin,
+            base_url: None,
+            esplora_url: None,
+            auth_header: None,
+        }
+    }
+}
+
+impl MempoolConfig {
+    pub fn base_url(&self) -> anyhow::Result<String> {
+        match &self.base_url {
+            Some(base_url) => Ok(base_url.trim_end_matches('/').to_string()),
+            None => mempool_space_url(self.network),
+        }
+    }
+
+    /// The esplora API next to [`Self::base_url`], none for an instance whose `base_url` does
+    /// not end in `/v1` and has no `esplora_url` set.
+    pub fn esplora_url(&self) -> anyhow::Result<Option<String>> {
+        match (&self.esplora_url, &self.base_url) {
+            (Some(esplora_url), _) => Ok(Some(esplora_url.trim_end_matches('/').to_string())),
+            (None, Some(base_url)) => Ok(base_url
+                .trim_end_matches('/')
+                .strip_suffix("/v1")
+                .map(str::to_string)),
+            (None, None) => mempool_space_esplora_url(self.network).map(Some),
+        }
+    }
+
+    pub fn client(&self) -> anyhow::Result<MempoolClient> {
+        let mut client = MempoolClient::new(self.base_url()?);
+        if let Some(esplora_url) = self.esplora_url()? {
+            client = client.with_esplora_url(esplora_url);
+        }
+        match &self.auth_header {
+            Some(header) => client.with_auth_header(&header.name, &header.value),
+            None => Ok(client),
+        }
+    }
+}
+
+/// Blocks between subsidy halvings.
+pub const HALVING_INTERVAL: u64 = 210_000;
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct HashratePeriod {
@@ -26,14 +146,30 @@ pub struct HashrateResponse {
     pub current_difficulty: f64,
 }
 
-#[derive(Debug)]
+#[derive(
+    Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq, EnumIter, Display, EnumString,
+)]
 pub enum TimePeriod {
+    #[serde(rename = "1m")]
+    #[strum(serialize = "1m")]
     OneMonth,
+    #[serde(rename = "3m")]
+    #[strum(serialize = "3m")]
     ThreeMonths,
+    #[serde(rename = "6m")]
+    #[strum(serialize = "6m")]
     SixMonths,
+    #[serde(rename = "1y")]
+    #[strum(serialize = "1y")]
     OneYear,
+    #[serde(rename = "2y")]
+    #[strum(serialize = "2y")]
     TwoYears,
+    #[serde(rename = "3y")]
+    #[strum(serialize = "3y")]
     ThreeYears,
+    #[serde(rename = "all")]
+    #[strum(serialize = "all")]
     All,
 }
 
@@ -75,6 +211,20 @@ impl<'de> Deserialize<'de> for DifficultyAdjustment {
     }
 }
 
+/// Progress of the current difficulty epoch and the estimate for the next retarget.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DifficultyAdjustmentEstimate {
+    pub progress_percent: f64,
+    /// Estimated percent change of the next adjustment.
+    pub difficulty_change: f64,
+    pub estimated_retarget_date: i64,
+    pub remaining_blocks: i64,
+    pub remaining_time: i64,
+    pub previous_retarget: f64,
+    pub next_retarget_height: i64,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct BlockFees {
@@ -83,6 +233,23 @@ pub struct BlockFees {
     pub avg_fees: i64,
 }
 
+/// Average coinbase output, subsidy plus fees, of the blocks around a height.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockRewards {
+    pub avg_height: i64,
+    pub timestamp: i64,
+    pub avg_rewards: i64,
+}
+
+/// Height and time of a data point of a per-block mining series.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockTime {
+    pub avg_height: i64,
+    pub timestamp: i64,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct FeeRate {
@@ -104,74 +271,658 @@ pub struct FeeRate {
     pub avg_fee_100: f64,
 }
 
+/// Which per-block fee rate percentile a fee rate event settles on.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(try_from = "u8", into = "u8")]
+pub enum FeePercentile {
+    P0,
+    P10,
+    P25,
+    P50,
+    P75,
+    #[default]
+    P90,
+    P100,
+}
+
+impl FeePercentile {
+    pub fn value(&self, fee_rate: &FeeRate) -> f64 {
+        match self {
+            FeePercentile::P0 => fee_rate.avg_fee_0,
+            FeePercentile::P10 => fee_rate.avg_fee_10,
+            FeePercentile::P25 => fee_rate.avg_fee_25,
+            FeePercentile::P50 => fee_rate.avg_fee_50,
+            FeePercentile::P75 => fee_rate.avg_fee_75,
+            FeePercentile::P90 => fee_rate.avg_fee_90,
+            FeePercentile::P100 => fee_rate.avg_fee_100,
+        }
+    }
+}
+
+impl TryFrom<u8> for FeePercentile {
+    type Error = String;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(FeePercentile::P0),
+            10 => Ok(FeePercentile::P10),
+            25 => Ok(FeePercentile::P25),
+            50 => Ok(FeePercentile::P50),
+            75 => Ok(FeePercentile::P75),
+            90 => Ok(FeePercentile::P90),
+            100 => Ok(FeePercentile::P100),
+            _ => Err(format!(
+                "Unsupported fee percentile {}, expected one of 0, 10, 25, 50, 75, 90, 100",
+                value
+            )),
+        }
+    }
+}
+
+impl From<FeePercentile> for u8 {
+    fn from(value: FeePercentile) -> Self {
+        match value {
+            FeePercentile::P0 => 0,
+            FeePercentile::P10 => 10,
+            FeePercentile::P25 => 25,
+            FeePercentile::P50 => 50,
+            FeePercentile::P75 => 75,
+            FeePercentile::P90 => 90,
+            FeePercentile::P100 => 100,
+        }
+    }
+}
+
+/// Statistic used to reduce a series of data points to the value an event settles on.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    Serialize,
+    Deserialize,
+    Eq,
+    PartialEq,
+    EnumIter,
+    Display,
+    EnumString,
+)]
+#[serde(rename_all = "camelCase")]
+#[strum(serialize_all = "camelCase")]
+pub enum Aggregation {
+    #[default]
+    Mean,
+    Median,
+    Min,
+    Max,
+    /// The most recent data point.
+    Last,
+}
+
+impl Aggregation {
+    /// Reduce `values`, which are expected in chronological order. An empty series is `NaN`.
+    pub fn apply(&self, values: &[f64]) -> f64 {
+        if values.is_empty() {
+            return f64::NAN;
+        }
+
+        match self {
+            Aggregation::Mean => values.iter().sum::<f64>() / values.len() as f64,
+            Aggregation::Median => {
+                let mut sorted = values.to_vec();
+                sorted.sort_by(|a, b| a.total_cmp(b));
+                let mid = sorted.len() / 2;
+                if sorted.len().is_multiple_of(2) {
+                    (sorted[mid - 1] + sorted[mid]) / 2.0
+                } else {
+                    sorted[mid]
+                }
+            }
+            Aggregation::Min => values.iter().copied().fold(f64::INFINITY, f64::min),
+            Aggregation::Max => values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            Aggregation::Last => values[values.len() - 1],
+        }
+    }
+}
+
+/// A value derived from a single upstream response, kept so an attested value can be audited.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DataProvenance {
+    pub source_url: String,
+    pub raw_response: serde_json::Value,
+    pub fetched_at: DateTime<Utc>,
+    pub value: f64,
+    /// Every fetch the value was chosen from, see [`crate::sampling`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub samples: Vec<DataSample>,
+}
+
+/// One fetch of a sampled value.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DataSample {
+    pub value: f64,
+    pub fetched_at: DateTime<Utc>,
+    /// Too far from the median to be signed
+    pub rejected: bool,
+    /// The source of the sample when it is not the source of the signed value, see
+    /// [`crate::quorum`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_url: Option<String>,
+}
+
+/// How the client bounds its requests to mempool, so a hung or hostile upstream cannot stall
+/// the watcher or the signing handlers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestPolicy {
+    /// Whole request timeout, body included
+    pub timeout: Duration,
+    /// Attempts per fetch, retrying transport errors, 429 and 5xx responses
+    pub max_attempts: u32,
+    /// Backoff before the first retry, doubled for every further one and jittered
+    pub retry_base_delay: Duration,
+    pub max_response_bytes: usize,
+    /// Consecutive failed fetches that open the circuit
+    pub failure_threshold: u32,
+    /// How long an open circuit fails fetches without trying upstream
+    pub cooldown: Duration,
+}
+
+impl Default for RequestPolicy {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(10),
+            max_attempts: 3,
+            retry_base_delay: Duration::from_millis(250),
+            max_response_bytes: 4 * 1024 * 1024,
+            failure_threshold: 5,
+            cooldown: Duration::from_secs(60),
+        }
+    }
+}
+
+impl RequestPolicy {
+    fn backoff(&self, attempt: u32) -> Duration {
+        let base = self.retry_base_delay.as_millis() as u64;
+        let jitter = if base > 0 {
+            thread_rng().gen_range(0..base)
+        } else {
+            0
+        };
+        Duration::from_millis(base.saturating_mul(1 << (attempt - 1).min(16)) + jitter)
+    }
+}
+
+#[derive(Debug, Default)]
+struct CircuitState {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+}
+
+/// Shared by clones of a client, they all talk to the same upstream.
+#[derive(Debug, Default)]
+struct CircuitBreaker(Mutex<CircuitState>);
+
+impl CircuitBreaker {
+    fn check(&self) -> anyhow::Result<()> {
+        let state = self.0.lock().expect("circuit breaker lock poisoned");
+        match state.open_until {
+            Some(open_until) if open_until > Instant::now() => Err(anyhow::anyhow!(
+                "Mempool is failing, requests are paused. consecutive_failures={} retry_in_secs={}",
+                state.consecutive_failures,
+                (open_until - Instant::now()).as_secs()
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    fn record(&self, success: bool, policy: &RequestPolicy) {
+        let mut state = self.0.lock().expect("circuit breaker lock poisoned");
+        if success {
+            *state = CircuitState::default();
+            return;
+        }
+        state.consecutive_failures += 1;
+        if state.consecutive_failures >= policy.failure_threshold {
+            state.open_until = Some(Instant::now() + policy.cooldown);
+        }
+    }
+}
+
+enum FetchError {
+    /// Worth another attempt, e.g. a timeout or a 503
+    Transient(anyhow::Error),
+    Permanent(anyhow::Error),
+}
+
 #[derive(Debug, Clone)]
 pub struct MempoolClient {
     client: Client,
     base_url: String,
+    esplora_url: Option<String>,
+    auth_header: Option<(HeaderName, HeaderValue)>,
+    policy: RequestPolicy,
+    breaker: Arc<CircuitBreaker>,
+    mock: Option<Arc<MockData>>,
+}
+
+fn build_client(policy: &RequestPolicy) -> Client {
+    Client::builder()
+        .timeout(policy.timeout)
+        .build()
+        .expect("reqwest client with a timeout")
 }
 
 /// TODO: do we need to get the latest fee or the average over a time period?
 impl MempoolClient {
     pub fn new(base_url: String) -> Self {
+        let policy = RequestPolicy::default();
         Self {
-            client: Client::new(),
+            client: build_client(&policy),
             base_url,
+            esplora_url: None,
+            auth_header: None,
+            policy,
+            breaker: Arc::default(),
+            mock: None,
         }
     }
 
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Read the tip height from the esplora API at `esplora_url`.
+    pub fn with_esplora_url(mut self, esplora_url: String) -> Self {
+        self.esplora_url = Some(esplora_url);
+        self
+    }
+
+    /// Send `name: value` with every request.
+    pub fn with_auth_header(mut self, name: &str, value: &str) -> anyhow::Result<Self> {
+        let mut value = HeaderValue::from_str(value)?;
+        value.set_sensitive(true);
+        self.auth_header = Some((HeaderName::from_bytes(name.as_bytes())?, value));
+        Ok(self)
+    }
+
+    /// Answer every outcome from `mock` instead of the mempool instance, see [`crate::mock_data`].
+    pub fn with_mock_data(mut self, mock: MockData) -> Self {
+        self.base_url = MOCK_BASE_URL.to_string();
+        self.mock = Some(Arc::new(mock));
+        self
+    }
+
+    pub fn mock_data(&self) -> Option<&MockData> {
+        self.mock.as_deref()
+    }
+
+    pub fn with_policy(mut self, policy: RequestPolicy) -> Self {
+        self.client = build_client(&policy);
+        self.policy = policy;
+        self
+    }
+
     pub async fn get_hashrate(&self, period: TimePeriod) -> anyhow::Result<f64> {
+        Ok(self.hashrate_with_provenance(period).await?.value)
+    }
+
+    pub async fn hashrate_with_provenance(
+        &self,
+        period: TimePeriod,
+    ) -> anyhow::Result<DataProvenance> {
         let url = match period {
             TimePeriod::All => format!("{}/mining/hashrate", self.base_url),
             _ => format!("{}/mining/hashrate/{}", self.base_url, period.as_str()),
         };
 
-        let response = self.client.get(&url).send().await?;
-        let data = response.json::<HashrateResponse>().await?;
-        Ok(data.current_hashrate / 1e18)
+        self.fetch(url, |data: HashrateResponse| {
+            Unit::ExahashPerSecond.from_raw(data.current_hashrate)
+        })
+        .await
+    }
+
+    /// Hashrate reduced over the period's series instead of the current estimate.
+    pub async fn hashrate_series_with_provenance(
+        &self,
+        period: TimePeriod,
+        aggregation: Aggregation,
+    ) -> anyhow::Result<DataProvenance> {
+        let url = match period {
+            TimePeriod::All => format!("{}/mining/hashrate", self.base_url),
+            _ => format!("{}/mining/hashrate/{}", self.base_url, period.as_str()),
+        };
+
+        self.fetch(url, |data: HashrateResponse| {
+            Self::aggregate(data.hashrates, aggregation, |h| {
+                Unit::ExahashPerSecond.from_raw(h.avg_hashrate)
+            })
+        })
+        .await
+    }
+
+    pub async fn get_block_fees(
+        &self,
+        period: TimePeriod,
+        aggregation: Aggregation,
+    ) -> anyhow::Result<f64> {
+        Ok(self
+            .block_fees_with_provenance(period, aggregation)
+            .await?
+            .value)
+    }
+
+    pub async fn block_fees_with_provenance(
+        &self,
+        period: TimePeriod,
+        aggregation: Aggregation,
+    ) -> anyhow::Result<DataProvenance> {
+        let url = format!("{}/mining/blocks/fees/{}", self.base_url, period.as_str());
+        self.fetch(url, |data: Vec<BlockFees>| {
+            Self::aggregate(data, aggregation, |f| f.avg_fees as f64)
+        })
+        .await
+    }
+
+    pub async fn get_coinbase_value(
+        &self,
+        period: TimePeriod,
+        aggregation: Aggregation,
+    ) -> anyhow::Result<f64> {
+        Ok(self
+            .coinbase_value_with_provenance(period, aggregation)
+            .await?
+            .value)
+    }
+
+    pub async fn coinbase_value_with_provenance(
+        &self,
+        period: TimePeriod,
+        aggregation: Aggregation,
+    ) -> anyhow::Result<DataProvenance> {
+        let url = format!(
+            "{}/mining/blocks/rewards/{}",
+            self.base_url,
+            period.as_str()
+        );
+        self.fetch(url, |data: Vec<BlockRewards>| {
+            Self::aggregate(data, aggregation, |r| r.avg_rewards as f64)
+        })
+        .await
+    }
+
+    /// Average seconds between blocks over the period.
+    pub async fn get_avg_block_interval(&self, period: TimePeriod) -> anyhow::Result<f64> {
+        Ok(self.avg_block_interval_with_provenance(period).await?.value)
     }
 
-    pub async fn get_block_fees(&self, period: TimePeriod) -> anyhow::Result<f64> {
+    pub async fn avg_block_interval_with_provenance(
+        &self,
+        period: TimePeriod,
+    ) -> anyhow::Result<DataProvenance> {
         let url = format!("{}/mining/blocks/fees/{}", self.base_url, period.as_str());
-        let response = self.client.get(&url).send().await?;
-        let data = response.json::<Vec<BlockFees>>().await?;
-        let average_fees = Self::calculate_average(data, |f| f.avg_fees as f64);
-        Ok(average_fees)
+        self.fetch(url, |data: Vec<BlockTime>| {
+            match (data.first(), data.last()) {
+                (Some(first), Some(last)) if last.avg_height > first.avg_height => {
+                    block_interval(first, last)
+                }
+                _ => f64::NAN,
+            }
+        })
+        .await
+    }
+
+    /// Seconds between blocks per step of the period's series, reduced with `aggregation`.
+    pub async fn block_interval_series_with_provenance(
+        &self,
+        period: TimePeriod,
+        aggregation: Aggregation,
+    ) -> anyhow::Result<DataProvenance> {
+        let url = format!("{}/mining/blocks/fees/{}", self.base_url, period.as_str());
+        self.fetch(url, |data: Vec<BlockTime>| {
+            let intervals = data
+                .windows(2)
+                .filter(|pair| pair[1].avg_height > pair[0].avg_height)
+                .map(|pair| block_interval(&pair[0], &pair[1]))
+                .collect::<Vec<_>>();
+            aggregation.apply(&intervals)
+        })
+        .await
     }
 
     pub async fn get_difficulty(&self, interval: TimePeriod) -> anyhow::Result<f64> {
+        Ok(self.difficulty_with_provenance(interval).await?.value)
+    }
+
+    pub async fn difficulty_with_provenance(
+        &self,
+        interval: TimePeriod,
+    ) -> anyhow::Result<DataProvenance> {
         let url = format!("{}/mining/hashrate/{}", self.base_url, interval.as_str());
+        self.fetch(url, |data: HashrateResponse| {
+            Unit::Tera.from_raw(data.current_difficulty)
+        })
+        .await
+    }
 
-        let response = self.client.get(&url).send().await?;
-        let data = response.json::<HashrateResponse>().await?;
-        Ok(data.current_difficulty / 1e12)
+    /// Difficulty reduced over the period's series instead of the current difficulty.
+    pub async fn difficulty_series_with_provenance(
+        &self,
+        interval: TimePeriod,
+        aggregation: Aggregation,
+    ) -> anyhow::Result<DataProvenance> {
+        let url = format!("{}/mining/hashrate/{}", self.base_url, interval.as_str());
+        self.fetch(url, |data: HashrateResponse| {
+            Self::aggregate(data.difficulty, aggregation, |d| {
+                Unit::Tera.from_raw(d.difficulty)
+            })
+        })
+        .await
+    }
+
+    pub async fn get_fee_rate(
+        &self,
+        period: TimePeriod,
+        percentile: FeePercentile,
+        aggregation: Aggregation,
+    ) -> anyhow::Result<f64> {
+        Ok(self
+            .fee_rate_with_provenance(period, percentile, aggregation)
+            .await?
+            .value)
     }
 
-    pub async fn get_fee_rate(&self, period: TimePeriod) -> anyhow::Result<f64> {
+    pub async fn fee_rate_with_provenance(
+        &self,
+        period: TimePeriod,
+        percentile: FeePercentile,
+        aggregation: Aggregation,
+    ) -> anyhow::Result<DataProvenance> {
         let url = format!(
             "{}/mining/blocks/fee-rates/{}",
             self.base_url,
             period.as_str()
         );
-        let response = self.client.get(&url).send().await?;
-        let data = response.json::<Vec<FeeRate>>().await?;
-        let average_fee_rate = Self::calculate_average(data, |f| f.avg_fee_90);
-        Ok(average_fee_rate)
+        self.fetch(url, |data: Vec<FeeRate>| {
+            Self::aggregate(data, aggregation, |f| percentile.value(f))
+        })
+        .await
+    }
+
+    /// Magnitude of the estimated percent change at the next difficulty retarget.
+    pub async fn get_next_difficulty_change(&self) -> anyhow::Result<f64> {
+        Ok(self.next_difficulty_change_with_provenance().await?.value)
+    }
+
+    pub async fn next_difficulty_change_with_provenance(&self) -> anyhow::Result<DataProvenance> {
+        let url = format!("{}/difficulty-adjustment", self.base_url);
+        self.fetch(url, |data: DifficultyAdjustmentEstimate| {
+            data.difficulty_change.abs()
+        })
+        .await
+    }
+
+    /// Estimated percent change at the next difficulty retarget, negative for a decrease.
+    pub async fn get_difficulty_change_percent(&self) -> anyhow::Result<f64> {
+        Ok(self
+            .difficulty_change_percent_with_provenance()
+            .await?
+            .value)
+    }
+
+    pub async fn difficulty_change_percent_with_provenance(
+        &self,
+    ) -> anyhow::Result<DataProvenance> {
+        let url = format!("{}/difficulty-adjustment", self.base_url);
+        self.fetch(url, |data: DifficultyAdjustmentEstimate| {
+            data.difficulty_change
+        })
+        .await
+    }
+
+    pub async fn get_tip_height(&self) -> anyhow::Result<u64> {
+        Ok(self.tip_height_with_provenance().await?.value as u64)
+    }
+
+    pub async fn tip_height_with_provenance(&self) -> anyhow::Result<DataProvenance> {
+        let esplora_url = self.esplora_url.as_ref().ok_or_else(|| {
+            OracleServerError::new(
+                ErrorCode::Upstream,
+                format!(
+                    "No esplora url to read the tip height from. base_url={}",
+                    self.base_url
+                ),
+            )
+        })?;
+        let url = format!("{}/blocks/tip/height", esplora_url);
+        self.fetch(url, |height: u64| height as f64).await
+    }
+
+    pub async fn get_blocks_until_halving(&self) -> anyhow::Result<u64> {
+        Ok(self.blocks_until_halving_with_provenance().await?.value as u64)
+    }
+
+    pub async fn blocks_until_halving_with_provenance(&self) -> anyhow::Result<DataProvenance> {
+        let mut provenance = self.tip_height_with_provenance().await?;
+        provenance.value = blocks_until_halving(provenance.value as u64) as f64;
+        Ok(provenance)
     }
 
-    fn calculate_average<T, F>(data: Vec<T>, extractor: F) -> f64
+    /// Fetch `url`, keep the raw JSON body and derive the value from its typed form.
+    async fn fetch<T, F>(&self, url: String, derive: F) -> anyhow::Result<DataProvenance>
+    where
+        T: DeserializeOwned,
+        F: FnOnce(T) -> f64,
+    {
+        let upstream = |e: anyhow::Error| OracleServerError::new(ErrorCode::Upstream, e);
+        self.breaker.check().map_err(upstream)?;
+        let fetched_at = Utc::now();
+        let result = self.get_with_retries(&url).await;
+        self.breaker.record(result.is_ok(), &self.policy);
+        let raw_response = result.map_err(upstream)?;
+        let data =
+            serde_json::from_value::<T>(raw_response.clone()).map_err(|e| upstream(e.into()))?;
+        Ok(DataProvenance {
+            source_url: url,
+            raw_response,
+            fetched_at,
+            value: derive(data),
+            samples: vec![],
+        })
+    }
+
+    async fn get_with_retries(&self, url: &str) -> anyhow::Result<serde_json::Value> {
+        let mut attempt = 1;
+        loop {
+            match self.get_json(url).await {
+                Ok(value) => return Ok(value),
+                Err(FetchError::Transient(e)) if attempt < self.policy.max_attempts => {
+                    log::warn!(
+                        "Retrying mempool request. url={} attempt={} error={}",
+                        url,
+                        attempt,
+                        e
+                    );
+                    tokio::time::sleep(self.policy.backoff(attempt)).await;
+                    attempt += 1;
+                }
+                Err(FetchError::Transient(e)) | Err(FetchError::Permanent(e)) => return Err(e),
+            }
+        }
+    }
+
+    /// GET `url` as JSON, reading at most `max_response_bytes` of the body.
+    async fn get_json(&self, url: &str) -> Result<serde_json::Value, FetchError> {
+        let transient = |e: reqwest::Error| FetchError::Transient(e.into());
+        let mut request = self.client.get(url);
+        if let Some((name, value)) = &self.auth_header {
+            request = request.header(name, value);
+        }
+        let mut response = request.send().await.map_err(transient)?;
+        let status = response.status();
+        if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
+            return Err(FetchError::Transient(anyhow::anyhow!(
+                "Mempool request failed. url={} status={}",
+                url,
+                status
+            )));
+        }
+        if !status.is_success() {
+            return Err(FetchError::Permanent(anyhow::anyhow!(
+                "Mempool request failed. url={} status={}",
+                url,
+                status
+            )));
+        }
+        let too_large = || {
+            FetchError::Permanent(anyhow::anyhow!(
+                "Mempool response is too large. url={} max_bytes={}",
+                url,
+                self.policy.max_response_bytes
+            ))
+        };
+        if response
+            .content_length()
+            .is_some_and(|length| length > self.policy.max_response_bytes as u64)
+        {
+            return Err(too_large());
+        }
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await.map_err(transient)? {
+            if body.len() + chunk.len() > self.policy.max_response_bytes {
+                return Err(too_large());
+            }
+            body.extend_from_slice(&chunk);
+        }
+        serde_json::from_slice(&body).map_err(|e| FetchError::Permanent(e.into()))
+    }
+
+    fn aggregate<T, F>(data: Vec<T>, aggregation: Aggregation, extractor: F) -> f64
     where
         F: Fn(&T) -> f64,
     {
-        let total: f64 = data.iter().map(&extractor).sum();
-        total / data.len() as f64
+        let values = data.iter().map(extractor).collect::<Vec<_>>();
+        aggregation.apply(&values)
     }
 }
 
+/// Average seconds per block between two points of a mining series.
+fn block_interval(from: &BlockTime, to: &BlockTime) -> f64 {
+    (to.timestamp - from.timestamp) as f64 / (to.avg_height - from.avg_height) as f64
+}
+
+/// Blocks remaining until the next halving, a tip on a halving height counts to the next one.
+pub fn blocks_until_halving(tip_height: u64) -> u64 {
+    HALVING_INTERVAL - (tip_height % HALVING_INTERVAL)
+}
+
 #[cfg(test)]
 mod tests {
     use super::MempoolClient;
     use super::*;
-    use crate::test_util::setup_mock_server;
+    use crate::test_util::{mock_mempool_client, setup_mock_server};
+    use std::str::FromStr;
 
     #[tokio::test]
     async fn test_mempool_client() {
@@ -182,7 +933,9 @@ mod tests {
         assert!(hashrate > 0.0);
 
         // Test block fees endpoint
-        let fees = client.get_block_fees(TimePeriod::ThreeMonths).await;
+        let fees = client
+            .get_block_fees(TimePeriod::ThreeMonths, Aggregation::Mean)
+            .await;
         assert!(fees.unwrap() > 0.0);
 
         // Test difficulty adjustments endpoint
@@ -193,7 +946,14 @@ mod tests {
         assert!(difficulty > 0.0);
 
         // Test fee rate endpoint
-        let fee_rate = client.get_fee_rate(TimePeriod::ThreeMonths).await.unwrap();
+        let fee_rate = client
+            .get_fee_rate(
+                TimePeriod::ThreeMonths,
+                FeePercentile::default(),
+                Aggregation::default(),
+            )
+            .await
+            .unwrap();
         assert!(fee_rate > 0.0);
     }
 
@@ -203,16 +963,25 @@ mod tests {
         let mock_server = setup_mock_server().await;
 
         // Create client with mock server URL
-        let client = MempoolClient::new(format!("{}/api/v1", mock_server.uri()));
+        let client = mock_mempool_client(&mock_server);
 
         // Test hashrate endpoint
         let hashrate = client.get_hashrate(TimePeriod::ThreeMonths).await.unwrap();
         assert!(hashrate > 0.0);
 
         // Test block fees endpoint
-        let fees = client.get_block_fees(TimePeriod::ThreeMonths).await;
+        let fees = client
+            .get_block_fees(TimePeriod::ThreeMonths, Aggregation::Mean)
+            .await;
         assert!(fees.unwrap() > 0.0);
 
+        // Test block rewards endpoint
+        let coinbase = client
+            .get_coinbase_value(TimePeriod::ThreeMonths, Aggregation::Mean)
+            .await
+            .unwrap();
+        assert_eq!(coinbase, 649_212_890.0);
+
         // Test difficulty adjustments endpoint
         let difficulty = client
             .get_difficulty(TimePeriod::ThreeMonths)
@@ -221,7 +990,282 @@ mod tests {
         assert!(difficulty > 0.0);
 
         // Test fee rate endpoint
-        let fee_rate = client.get_fee_rate(TimePeriod::ThreeMonths).await.unwrap();
+        let fee_rate = client
+            .get_fee_rate(
+                TimePeriod::ThreeMonths,
+                FeePercentile::default(),
+                Aggregation::default(),
+            )
+            .await
+            .unwrap();
         assert!(fee_rate > 0.0);
+
+        // Test difficulty adjustment estimate endpoint
+        let change = client.get_next_difficulty_change().await.unwrap();
+        assert_eq!(change, 2.47);
+        let change = client.get_difficulty_change_percent().await.unwrap();
+        assert_eq!(change, -2.47);
+
+        // Test tip height endpoint
+        let height = client.get_tip_height().await.unwrap();
+        assert_eq!(height, 840_001);
+        let blocks = client.get_blocks_until_halving().await.unwrap();
+        assert_eq!(blocks, 209_999);
+    }
+
+    #[test]
+    fn halving_countdown() {
+        assert_eq!(blocks_until_halving(0), 210_000);
+        assert_eq!(blocks_until_halving(839_999), 1);
+        assert_eq!(blocks_until_halving(840_000), 210_000);
+        assert_eq!(blocks_until_halving(900_000), 150_000);
+    }
+
+    #[tokio::test]
+    async fn records_provenance_of_fetched_values() {
+        let mock_server = setup_mock_server().await;
+        let client = mock_mempool_client(&mock_server);
+
+        let provenance = client
+            .fee_rate_with_provenance(
+                TimePeriod::ThreeMonths,
+                FeePercentile::P90,
+                Aggregation::Mean,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            provenance.source_url,
+            format!("{}/api/v1/mining/blocks/fee-rates/3m", mock_server.uri())
+        );
+        assert_eq!(provenance.value, 100.0);
+        assert_eq!(provenance.raw_response[0]["avgFee_90"], 100.0);
+    }
+
+    #[tokio::test]
+    async fn fee_rate_percentile() {
+        let mock_server = setup_mock_server().await;
+        let client = mock_mempool_client(&mock_server);
+
+        let median = client
+            .get_fee_rate(
+                TimePeriod::ThreeMonths,
+                FeePercentile::P50,
+                Aggregation::Mean,
+            )
+            .await
+            .unwrap();
+        assert_eq!(median, 20.0);
+        let max = client
+            .get_fee_rate(
+                TimePeriod::ThreeMonths,
+                FeePercentile::P100,
+                Aggregation::Mean,
+            )
+            .await
+            .unwrap();
+        assert_eq!(max, 200.0);
+    }
+
+    #[tokio::test]
+    async fn aggregates_hashrate_series() {
+        let mock_server = setup_mock_server().await;
+        let client = mock_mempool_client(&mock_server);
+
+        let current = client.get_hashrate(TimePeriod::ThreeMonths).await.unwrap();
+        let peak = client
+            .hashrate_series_with_provenance(TimePeriod::ThreeMonths, Aggregation::Max)
+            .await
+            .unwrap();
+        assert_eq!(current, 2520332473552123.0 / 1e18);
+        assert_eq!(peak.value, 2364997621087718.0 / 1e18);
+    }
+
+    #[tokio::test]
+    async fn block_interval_from_block_timestamps() {
+        use wiremock::{
+            matchers::{method, path},
+            Mock, ResponseTemplate,
+        };
+
+        let mock_server = wiremock::MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/mining/blocks/fees/1m"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                { "avgHeight": 800000, "timestamp": 1700000000, "avgFees": 1 },
+                { "avgHeight": 800010, "timestamp": 1700005400, "avgFees": 1 },
+                { "avgHeight": 800020, "timestamp": 1700012400, "avgFees": 1 },
+                { "avgHeight": 800030, "timestamp": 1700018000, "avgFees": 1 }
+            ])))
+            .mount(&mock_server)
+            .await;
+        let client = mock_mempool_client(&mock_server);
+
+        let average = client
+            .get_avg_block_interval(TimePeriod::OneMonth)
+            .await
+            .unwrap();
+        assert_eq!(average, 600.0);
+        let slowest = client
+            .block_interval_series_with_provenance(TimePeriod::OneMonth, Aggregation::Max)
+            .await
+            .unwrap();
+        assert_eq!(slowest.value, 700.0);
+        let fastest = client
+            .block_interval_series_with_provenance(TimePeriod::OneMonth, Aggregation::Min)
+            .await
+            .unwrap();
+        assert_eq!(fastest.value, 540.0);
+    }
+
+    #[test]
+    fn time_period_serde() {
+        let period: TimePeriod = serde_json::from_str("\"1y\"").unwrap();
+        assert_eq!(period, TimePeriod::OneYear);
+        assert_eq!(TimePeriod::OneMonth.to_string(), "1m");
+        assert_eq!(TimePeriod::from_str("all").unwrap(), TimePeriod::All);
+    }
+
+    #[test]
+    fn aggregations() {
+        let values = [3.0, 1.0, 4.0, 1.5];
+        assert_eq!(Aggregation::Mean.apply(&values), 2.375);
+        assert_eq!(Aggregation::Median.apply(&values), 2.25);
+        assert_eq!(Aggregation::Median.apply(&values[..3]), 3.0);
+        assert_eq!(Aggregation::Min.apply(&values), 1.0);
+        assert_eq!(Aggregation::Max.apply(&values), 4.0);
+        assert_eq!(Aggregation::Last.apply(&values), 1.5);
+        assert!(Aggregation::Max.apply(&[]).is_nan());
+    }
+
+    #[test]
+    fn fee_percentile_serde() {
+        let percentile: FeePercentile = serde_json::from_str("25").unwrap();
+        assert_eq!(percentile, FeePercentile::P25);
+        assert_eq!(serde_json::to_string(&FeePercentile::P75).unwrap(), "75");
+        assert!(serde_json::from_str::<FeePercentile>("33").is_err());
+    }
+
+    fn fast_policy() -> RequestPolicy {
+        RequestPolicy {
+            timeout: Duration::from_secs(2),
+            max_attempts: 3,
+            retry_base_delay: Duration::from_millis(1),
+            max_response_bytes: 1024,
+            failure_threshold: 2,
+            cooldown: Duration::from_secs(60),
+        }
+    }
+
+    #[tokio::test]
+    async fn retries_transient_failures() {
+        use wiremock::{matchers::method, Mock, ResponseTemplate};
+
+        let server = wiremock::MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "hashrates": [],
+                "difficulty": [],
+                "currentHashrate": 2520332473552123_i64,
+                "currentDifficulty": 1.0
+            })))
+            .mount(&server)
+            .await;
+        let client = MempoolClient::new(server.uri()).with_policy(fast_policy());
+        let hashrate = client.get_hashrate(TimePeriod::ThreeMonths).await.unwrap();
+        assert!(hashrate > 0.0);
+    }
+
+    #[tokio::test]
+    async fn rejects_oversized_responses_and_opens_circuit() {
+        use wiremock::{matchers::method, Mock, ResponseTemplate};
+
+        let server = wiremock::MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("1".repeat(2048)))
+            .expect(2)
+            .mount(&server)
+            .await;
+        let client = MempoolClient::new(server.uri()).with_policy(fast_policy());
+        let error = client
+            .get_hashrate(TimePeriod::ThreeMonths)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("too large"));
+        assert!(client.get_hashrate(TimePeriod::ThreeMonths).await.is_err());
+
+        // The circuit is open, clones share it and nothing reaches the server
+        let error = client
+            .clone()
+            .get_hashrate(TimePeriod::ThreeMonths)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("paused"));
+    }
+
+    #[tokio::test]
+    async fn resolves_network_and_sends_auth_header() {
+        use wiremock::{
+            matchers::{header, method},
+            Mock, ResponseTemplate,
+        };
+
+        assert_eq!(
+            mempool_space_url(Network::Signet).unwrap(),
+            "https://mempool.space/signet/api/v1"
+        );
+        assert_eq!(
+            mempool_space_esplora_url(Network::Signet).unwrap(),
+            "https://mempool.space/signet/api"
+        );
+        assert!(mempool_space_url(Network::Regtest).is_err());
+        let config: MempoolConfig = serde_json::from_str(r#"{"network": "signet"}"#).unwrap();
+        assert_eq!(config.network, Network::Signet);
+
+        let server = wiremock::MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(header("authorization", "Bearer secret"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(800_000))
+            .mount(&server)
+            .await;
+        let config = MempoolConfig {
+            network: Network::Regtest,
+            base_url: Some(format!("{}/api/v1/", server.uri())),
+            esplora_url: None,
+            auth_header: Some(AuthHeader {
+                name: "Authorization".to_string(),
+                value: "Bearer secret".to_string(),
+            }),
+        };
+        let height = config.client().unwrap().get_tip_height().await.unwrap();
+        assert_eq!(height, 800_000);
+
+        let config = MempoolConfig {
+            base_url: Some(format!("{}/mempool", server.uri())),
+            ..config
+        };
+        assert!(config.client().unwrap().get_tip_height().await.is_err());
+        let config = MempoolConfig {
+            esplora_url: Some(format!("{}/esplora", server.uri())),
+            ..config
+        };
+        let height = config.client().unwrap().get_tip_height().await.unwrap();
+        assert_eq!(height, 800_000);
+    }
+
+    #[tokio::test]
+    async fn failures_are_upstream_errors() {
+        let server = wiremock::MockServer::start().await;
+        let client = MempoolClient::new(server.uri()).with_policy(fast_policy());
+        let error = client
+            .get_hashrate(TimePeriod::ThreeMonths)
+            .await
+            .unwrap_err();
+        assert_eq!(OracleServerError::from(error).code, ErrorCode::Upstream);
     }
 }
@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use sqlx::{prelude::FromRow, PgConnection, PgPool, Postgres};
+
+/// Human-readable information attached to an event at creation.
+///
+/// Nothing here is part of the signed announcement, it only exists so that UIs can show
+/// something more useful than the event id and unit.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct EventMetadata {
+    pub description: Option<String>,
+    pub tags: Vec<String>,
+}
+
+impl EventMetadata {
+    pub fn new(description: Option<String>, tags: Vec<String>) -> Self {
+        let description = description
+            .map(|d| d.trim().to_string())
+            .filter(|d| !d.is_empty());
+
+        let mut normalized: Vec<String> = Vec::with_capacity(tags.len());
+        for tag in tags {
+            let tag = tag.trim().to_lowercase();
+            if !tag.is_empty() && !normalized.contains(&tag) {
+                normalized.push(tag);
+            }
+        }
+
+        Self {
+            description,
+            tags: normalized,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.description.is_none() && self.tags.is_empty()
+    }
+}
+
+#[derive(Debug, FromRow)]
+struct EventMetadataRow {
+    event_id: String,
+    description: Option<String>,
+    tags: Vec<String>,
+}
+
+pub(crate) async fn insert_event_metadata(
+    conn: &mut PgConnection,
+    event_id: &str,
+    metadata: &EventMetadata,
+) -> anyhow::Result<()> {
+    if metadata.is_empty() {
+        return Ok(());
+    }
+
+    sqlx::query("INSERT INTO event_metadata (event_id, description, tags) VALUES ($1, $2, $3)")
+        .bind(event_id)
+        .bind(&metadata.description)
+        .bind(&metadata.tags)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+pub async fn get_event_metadata(pool: &PgPool, event_id: &str) -> anyhow::Result<EventMetadata> {
+    let metadata = sqlx::query_as::<Postgres, EventMetadata>(
+        "SELECT description, tags FROM event_metadata WHERE event_id = $1",
+    )
+    .bind(event_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(metadata.unwrap_or_default())
+}
+
+/// Metadata for every event that has any, keyed by event id.
+pub async fn get_all_event_metadata(
+    pool: &PgPool,
+) -> anyhow::Result<HashMap<String, EventMetadata>> {
+    let rows = sqlx::query_as::<Postgres, EventMetadataRow>(
+        "SELECT event_id, description, tags FROM event_metadata",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            (
+                row.event_id,
+                EventMetadata {
+                    description: row.description,
+                    tags: row.tags,
+                },
+            )
+        })
+        .collect())
+}
+
+#[derive(Debug, FromRow)]
+pub struct EventSearchRow {
+    pub event_id: String,
+    pub oracle_event: Vec<u8>,
+    pub event_type: Option<String>,
+    pub description: Option<String>,
+    pub tags: Vec<String>,
+    pub is_signed: bool,
+}
+
+/// Search events by free text over the description (and event id) and/or by an exact tag.
+///
+/// Both filters are optional, with neither given the most recent events are returned.
+pub async fn search_events(
+    pool: &PgPool,
+    query: Option<&str>,
+    tag: Option<&str>,
+    limit: i64,
+) -> anyhow::Result<Vec<EventSearchRow>> {
+    let query = query.map(str::trim).filter(|q| !q.is_empty());
+    let tag = tag
+        .map(|t| t.trim().to_lowercase())
+        .filter(|t| !t.is_empty());
+
+    let rows = sqlx::query_as::<Postgres, EventSearchRow>(
+        r#"
+        SELECT
+            e.event_id,
+            e.oracle_event,
+            types.event_type,
+            meta.description,
+            COALESCE(meta.tags, '{}') AS tags,
+            EXISTS (
+                SELECT 1 FROM event_nonces en
+                WHERE en.event_id = e.event_id
+                AND en.signature IS NOT NULL
+            ) AS is_signed
+        FROM events e
+        LEFT JOIN event_types types ON e.event_id = types.oracle_event_id
+        LEFT JOIN event_metadata meta ON e.event_id = meta.event_id
+        WHERE e.announced
+            AND NOT e.hidden
+            AND ($1::TEXT IS NULL
+                OR to_tsvector('english', COALESCE(meta.description, ''))
+                    @@ plainto_tsquery('english', $1)
+                OR e.event_id ILIKE $4 ESCAPE '\')
+            AND ($2::TEXT IS NULL OR meta.tags @> ARRAY[$2])
+        ORDER BY e.created_at DESC
+        LIMIT $3
+        "#,
+    )
+    .bind(query)
+    .bind(tag)
+    .bind(limit)
+    .bind(query.map(contains_pattern))
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows)
+}
+
+/// A LIKE pattern matching `text` anywhere, with its wildcards matched literally.
+fn contains_pattern(text: &str) -> String {
+    let escaped = text
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_tags_and_description() {
+        let metadata = EventMetadata::new(
+            Some("  Q3 hashrate hedge ".to_string()),
+            vec![
+                "Hashrate".to_string(),
+                " hedge".to_string(),
+                "hashrate".to_string(),
+                "".to_string(),
+            ],
+        );
+        assert_eq!(metadata.description, Some("Q3 hashrate hedge".to_string()));
+        assert_eq!(metadata.tags, vec!["hashrate", "hedge"]);
+    }
+
+    #[test]
+    fn escapes_wildcards_in_search_patterns() {
+        assert_eq!(contains_pattern("fee-rate"), "%fee-rate%");
+        assert_eq!(contains_pattern("50%_of\\"), "%50\\%\\_of\\\\%");
+    }
+
+    #[test]
+    fn blank_metadata_is_empty() {
+        let metadata = EventMetadata::new(Some("   ".to_string()), vec![" ".to_string()]);
+        assert!(metadata.is_empty());
+    }
+}
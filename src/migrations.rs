@@ -0,0 +1,74 @@
+//! Database migrations embedded in the binaries.
+//!
+//! Migrations are applied explicitly with `oracle-admin migrate`, the server only checks that
+//! none are pending before it starts serving.
+
+use serde::{Deserialize, Serialize};
+use sqlx::{migrate::Migrator, PgPool, Postgres};
+
+pub static MIGRATOR: Migrator = sqlx::migrate!();
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub description: String,
+    pub applied: bool,
+    /// The migration was started but did not complete
+    pub failed: bool,
+}
+
+/// Apply every pending migration.
+pub async fn run(pool: &PgPool) -> anyhow::Result<()> {
+    MIGRATOR.run(pool).await?;
+    Ok(())
+}
+
+/// Status of every up migration, oldest first.
+pub async fn status(pool: &PgPool) -> anyhow::Result<Vec<MigrationStatus>> {
+    let exists =
+        sqlx::query_scalar::<Postgres, bool>("SELECT to_regclass('_sqlx_migrations') IS NOT NULL")
+            .fetch_one(pool)
+            .await?;
+    let applied = if exists {
+        sqlx::query_as::<Postgres, (i64, bool)>("SELECT version, success FROM _sqlx_migrations")
+            .fetch_all(pool)
+            .await?
+    } else {
+        Vec::new()
+    };
+
+    Ok(MIGRATOR
+        .iter()
+        .filter(|migration| !migration.migration_type.is_down_migration())
+        .map(|migration| {
+            let success = applied
+                .iter()
+                .find(|(version, _)| *version == migration.version)
+                .map(|(_, success)| *success);
+            MigrationStatus {
+                version: migration.version,
+                description: migration.description.to_string(),
+                applied: success == Some(true),
+                failed: success == Some(false),
+            }
+        })
+        .collect())
+}
+
+/// Refuse to continue while migrations are pending.
+pub async fn ensure_up_to_date(pool: &PgPool) -> anyhow::Result<()> {
+    let pending = status(pool)
+        .await?
+        .into_iter()
+        .filter(|migration| !migration.applied)
+        .map(|migration| migration.version.to_string())
+        .collect::<Vec<_>>();
+    if !pending.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Database has pending migrations, run `oracle-admin migrate`. pending={}",
+            pending.join(",")
+        ));
+    }
+    Ok(())
+}
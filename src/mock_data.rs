@@ -0,0 +1,229 @@
+//! Generated outcomes for local development.
+//!
+//! With `mockData` in the config, or the `--mock-data` flag, the oracle does not ask mempool.space
+//! but a deterministic generator, so the whole create, mature and sign loop runs without internet
+//! access. Every event type is a constant or a random walk from the time the generator started,
+//! a walk taking one step per [`MockDataConfig::interval_secs`]. The same seed gives the same
+//! values. Outcome options like the percentile or aggregation are ignored.
+//!
+//! Mock outcomes are signed with the oracle key like any other, so [`ensure_throwaway`] refuses
+//! them on mainnet and against a database that already holds events.
+
+use std::collections::HashMap;
+
+use bitcoin::Network;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Postgres};
+
+use crate::{
+    events::{EventParams, EventType},
+    mempool::DataProvenance,
+};
+
+/// Base url of a mempool client serving mock data.
+pub const MOCK_BASE_URL: &str = "mock://data";
+
+pub const DEFAULT_MOCK_INTERVAL_SECS: u64 = 60;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum MockValue {
+    Constant {
+        value: f64,
+    },
+    /// Starts at `start` and moves by up to `step` in either direction every interval
+    RandomWalk {
+        start: f64,
+        step: f64,
+    },
+}
+
+impl MockValue {
+    /// Values in the range of what mempool.space answers for `event_type`.
+    pub fn default_for(event_type: &EventType) -> Self {
+        let walk = |start, step| MockValue::RandomWalk { start, step };
+        match event_type {
+            EventType::Hashrate => walk(800.0, 5.0),
+            EventType::FeeRate => walk(10.0, 0.5),
+            EventType::BlockFees => walk(3_000_000.0, 50_000.0),
+            EventType::Difficulty => walk(110.0, 0.5),
+            EventType::NextDifficultyChange => walk(2.0, 0.2),
+            EventType::BlocksUntilHalving => MockValue::Constant { value: 150_000.0 },
+            EventType::DifficultyChangePercent => walk(1.5, 0.2),
+            EventType::AvgBlockInterval => walk(600.0, 5.0),
+            EventType::CoinbaseValue => walk(315_000_000.0, 50_000.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MockDataConfig {
+    pub seed: u64,
+    /// Seconds between two steps of a random walk
+    pub interval_secs: u64,
+    /// Values per event type, [`MockValue::default_for`] for the ones not set
+    pub values: HashMap<EventType, MockValue>,
+}
+
+impl Default for MockDataConfig {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            interval_secs: DEFAULT_MOCK_INTERVAL_SECS,
+            values: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MockData {
+    config: MockDataConfig,
+    started_at: DateTime<Utc>,
+}
+
+/// SplitMix64, enough randomness for a random walk without a seeded rng dependency.
+fn split_mix(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e3779b97f4a7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^ (x >> 31)
+}
+
+impl MockData {
+    pub fn new(config: MockDataConfig) -> Self {
+        Self {
+            config,
+            started_at: Utc::now(),
+        }
+    }
+
+    /// The value of `event_type` at `at`.
+    pub fn value(&self, event_type: &EventType, at: DateTime<Utc>) -> f64 {
+        let mock = self
+            .config
+            .values
+            .get(event_type)
+            .copied()
+            .unwrap_or_else(|| MockValue::default_for(event_type));
+        let (start, step) = match mock {
+            MockValue::Constant { value } => return value,
+            MockValue::RandomWalk { start, step } => (start, step),
+        };
+        let steps =
+            (at - self.started_at).num_seconds().max(0) as u64 / self.config.interval_secs.max(1);
+        // Every event type walks on its own
+        let walk = event_type
+            .to_string()
+            .bytes()
+            .fold(self.config.seed, |hash, byte| split_mix(hash ^ byte as u64));
+        let is_signed = EventParams::from(event_type.clone()).is_signed;
+        (0..steps).fold(start, |value, i| {
+            // Uniform in [-1, 1]
+            let direction =
+                (split_mix(walk.wrapping_add(i)) >> 11) as f64 / (1u64 << 52) as f64 - 1.0;
+            let value = value + step * direction;
+            match is_signed {
+                true => value,
+                false => value.max(0.0),
+            }
+        })
+    }
+
+    /// The outcome of `event_type` now, as if fetched from a mempool instance.
+    pub fn outcome(&self, event_type: &EventType) -> DataProvenance {
+        let fetched_at = Utc::now();
+        let value = self.value(event_type, fetched_at);
+        DataProvenance {
+            source_url: format!("{}/{}", MOCK_BASE_URL, event_type),
+            raw_response: serde_json::json!({ "value": value }),
+            fetched_at,
+            value,
+            samples: vec![],
+        }
+    }
+}
+
+/// Refuse to serve mock data where its attestations could pass for real ones, on `network`
+/// bitcoin or against a database that already holds events.
+pub async fn ensure_throwaway(pool: &PgPool, network: Network) -> anyhow::Result<()> {
+    if network == Network::Bitcoin {
+        return Err(anyhow::anyhow!(
+            "Refusing to sign mock data on mainnet. network={}",
+            network
+        ));
+    }
+    let events = sqlx::query_scalar::<Postgres, i64>("SELECT COUNT(*) FROM events")
+        .fetch_one(pool)
+        .await?;
+    if events > 0 {
+        return Err(anyhow::anyhow!(
+            "Refusing to sign mock data into a database that holds events, start on an empty one. events={}",
+            events
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        events::OutcomeOptions,
+        mempool::{MempoolClient, BASE_URL},
+        routes::CreateEvent,
+        test_util::setup_ernest_oracle,
+    };
+
+    #[tokio::test]
+    async fn generates_deterministic_values() {
+        let config = MockDataConfig {
+            seed: 7,
+            values: HashMap::from([(EventType::FeeRate, MockValue::Constant { value: 12.0 })]),
+            ..Default::default()
+        };
+        let mock = MockData::new(config.clone());
+        let later = mock.started_at + chrono::Duration::hours(6);
+        assert_eq!(mock.value(&EventType::FeeRate, later), 12.0);
+        assert_eq!(mock.value(&EventType::Hashrate, mock.started_at), 800.0);
+
+        let walked = mock.value(&EventType::Hashrate, later);
+        assert_ne!(walked, 800.0);
+        // Six hours are 360 steps of at most 5 EH/s
+        assert!((walked - 800.0).abs() <= 360.0 * 5.0);
+        let replay = MockData {
+            started_at: mock.started_at,
+            ..MockData::new(config)
+        };
+        assert_eq!(replay.value(&EventType::Hashrate, later), walked);
+        assert_ne!(replay.value(&EventType::Difficulty, later), 110.0);
+
+        // No request leaves the client
+        let mempool = MempoolClient::new("http://127.0.0.1:9/api/v1".to_string())
+            .with_mock_data(MockData::new(MockDataConfig::default()));
+        let outcome = EventType::BlocksUntilHalving
+            .outcome_with_provenance(&mempool, &OutcomeOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(outcome.value, 150_000.0);
+        assert_eq!(mempool.base_url(), MOCK_BASE_URL);
+    }
+
+    #[tokio::test]
+    async fn refuses_mainnet_and_used_databases() {
+        let oracle = setup_ernest_oracle(MempoolClient::new(BASE_URL.to_string())).await;
+        let pool = &oracle.oracle.storage.pool;
+        assert!(ensure_throwaway(pool, Network::Bitcoin).await.is_err());
+
+        oracle
+            .create_event(CreateEvent::single(
+                EventType::Hashrate,
+                Utc::now().timestamp() as u32 + 1000,
+            ))
+            .await
+            .unwrap();
+        let error = ensure_throwaway(pool, Network::Regtest).await.unwrap_err();
+        assert!(error.to_string().contains("holds events"));
+    }
+}
@@ -0,0 +1,217 @@
+//! Publishing announcements and attestations to nostr relays in the kormir format.
+//!
+//! Announcements are kind 88 events and attestations kind 89 events tagging their
+//! announcement, both carrying the base64 encoded message. Events take their timestamps from
+//! the oracle event, so publishing an event again produces the same nostr event ids and relays
+//! that already have them ignore the duplicates.
+
+use std::{collections::BTreeMap, time::Duration};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use kormir::{storage::Storage, OracleAnnouncement, OracleAttestation, Writeable};
+use nostr_sdk::{Client, Event, EventBuilder, EventId, Keys, Kind, Tag, Timestamp};
+use serde::{Deserialize, Serialize};
+use sqlx::Postgres;
+
+use crate::oracle::ErnestOracle;
+
+pub const ANNOUNCEMENT_KIND: u16 = 88;
+pub const ATTESTATION_KIND: u16 = 89;
+
+/// How long to wait for relays to connect before publishing.
+pub const CONNECT_TIMEOUT_SECS: u64 = 10;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NostrConfig {
+    #[serde(default)]
+    pub relays: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Rebroadcast {
+    pub event_id: String,
+    pub announcement_event_id: String,
+    pub attestation_event_id: Option<String>,
+    /// Relays that accepted every published event
+    pub published: Vec<String>,
+    /// Relays that rejected an event, with the error
+    pub failed: BTreeMap<String, String>,
+}
+
+pub fn announcement_event(
+    keys: &Keys,
+    announcement: &OracleAnnouncement,
+    created_at: Timestamp,
+) -> anyhow::Result<Event> {
+    Ok(EventBuilder::new(
+        Kind::Custom(ANNOUNCEMENT_KIND),
+        STANDARD.encode(announcement.encode()),
+    )
+    .custom_created_at(created_at)
+    .sign_with_keys(keys)?)
+}
+
+pub fn attestation_event(
+    keys: &Keys,
+    attestation: &OracleAttestation,
+    announcement_event_id: EventId,
+    created_at: Timestamp,
+) -> anyhow::Result<Event> {
+    Ok(EventBuilder::new(
+        Kind::Custom(ATTESTATION_KIND),
+        STANDARD.encode(attestation.encode()),
+    )
+    .tag(Tag::event(announcement_event_id))
+    .custom_created_at(created_at)
+    .sign_with_keys(keys)?)
+}
+
+/// The announcement event of `event_id`, and its attestation event once signed.
+pub async fn event_messages(
+    oracle: &ErnestOracle,
+    event_id: &str,
+) -> anyhow::Result<(Event, Option<Event>)> {
+    let storage = &oracle.oracle.storage;
+    if !storage.is_announced(event_id).await? {
+        return Err(anyhow::anyhow!(
+            "Event is not announced. event_id={}",
+            event_id
+        ));
+    }
+    let event = storage
+        .get_event(event_id.to_string())
+        .await?
+        .ok_or(anyhow::anyhow!("Event not found. event_id={}", event_id))?;
+    let created_at = sqlx::query_scalar::<Postgres, chrono::DateTime<chrono::Utc>>(
+        "SELECT created_at FROM events WHERE event_id = $1",
+    )
+    .bind(event_id)
+    .fetch_one(&storage.pool)
+    .await?;
+
+    let keys = oracle.nostr_keys()?;
+    let announcement = announcement_event(
+        &keys,
+        &event.announcement,
+        Timestamp::from(created_at.timestamp() as u64),
+    )?;
+    let attestation = match event.attestation() {
+        Some(attestation) => {
+            // Attestations are signed at maturity at the earliest
+            let maturity = event.announcement.oracle_event.event_maturity_epoch as u64;
+            Some(attestation_event(
+                &keys,
+                &attestation,
+                announcement.id,
+                Timestamp::from(maturity.max(created_at.timestamp() as u64)),
+            )?)
+        }
+        None => None,
+    };
+    Ok((announcement, attestation))
+}
+
+/// Publish the announcement and attestation of `event_id` to `relays`.
+pub async fn rebroadcast(
+    oracle: &ErnestOracle,
+    relays: &[String],
+    event_id: &str,
+) -> anyhow::Result<Rebroadcast> {
+    if relays.is_empty() {
+        return Err(anyhow::anyhow!("No nostr relays configured."));
+    }
+    let (announcement, attestation) = event_messages(oracle, event_id).await?;
+
+    let client = Client::new(oracle.nostr_keys()?);
+    for relay in relays {
+        client.add_relay(relay.as_str()).await?;
+    }
+    client.connect().await;
+    client
+        .wait_for_connection(Duration::from_secs(CONNECT_TIMEOUT_SECS))
+        .await;
+
+    let mut failed = BTreeMap::new();
+    for event in std::iter::once(&announcement).chain(attestation.as_ref()) {
+        match client.send_event(event).await {
+            Ok(output) => {
+                for (relay, error) in output.failed {
+                    failed.entry(relay.to_string()).or_insert(error);
+                }
+            }
+            Err(e) => {
+                for relay in relays {
+                    failed.entry(relay.clone()).or_insert(e.to_string());
+                }
+            }
+        }
+    }
+    client.disconnect().await;
+
+    let published = client
+        .relays()
+        .await
+        .into_keys()
+        .map(|relay| relay.to_string())
+        .filter(|relay| !failed.contains_key(relay))
+        .collect();
+    Ok(Rebroadcast {
+        event_id: event_id.to_string(),
+        announcement_event_id: announcement.id.to_hex(),
+        attestation_event_id: attestation.map(|event| event.id.to_hex()),
+        published,
+        failed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        events::EventType,
+        mempool::{MempoolClient, BASE_URL},
+        routes::CreateEvent,
+        test_util::setup_ernest_oracle,
+    };
+
+    #[tokio::test]
+    async fn builds_kormir_events() {
+        let mempool = MempoolClient::new(BASE_URL.to_string());
+        let oracle = setup_ernest_oracle(mempool).await;
+        let announcement = oracle
+            .create_event(CreateEvent::single(
+                EventType::Hashrate,
+                chrono::Utc::now().timestamp() as u32 + 1000,
+            ))
+            .await
+            .unwrap();
+        let event_id = announcement.oracle_event.event_id.clone();
+
+        let (first, attestation) = event_messages(&oracle, &event_id).await.unwrap();
+        assert!(attestation.is_none());
+        assert!(first.verify().is_ok());
+        assert_eq!(first.kind, Kind::Custom(ANNOUNCEMENT_KIND));
+        assert_eq!(
+            STANDARD.decode(&first.content).unwrap(),
+            announcement.encode()
+        );
+
+        let signed = oracle
+            .oracle
+            .sign_numeric_event(event_id.clone(), 42)
+            .await
+            .unwrap();
+        let (again, attestation) = event_messages(&oracle, &event_id).await.unwrap();
+        let attestation = attestation.unwrap();
+        assert_eq!(again.id, first.id);
+        assert!(attestation.verify().is_ok());
+        assert_eq!(attestation.kind, Kind::Custom(ATTESTATION_KIND));
+        assert_eq!(attestation.tags.event_ids().next(), Some(&first.id));
+        assert_eq!(
+            STANDARD.decode(&attestation.content).unwrap(),
+            signed.encode()
+        );
+    }
+}
@@ -1,35 +1,72 @@
 use crate::{
-    attestation::{self, AttestationDataOutcome},
-    events::{EventParams, EventType},
-    mempool::MempoolClient,
+    attestation::{self, AttestationDataOutcome, AttestationRecord},
+    audit::SigningSource,
+    event_ids::{self, EventIdScheme},
+    events::{self, EventParams, EventType, OutcomeOptions},
+    history::{self, PREFETCH_LEAD_SECS},
+    import::{self, ImportResult},
+    lock::{self, EventLock},
+    mempool::{DataProvenance, MempoolClient, MempoolConfig},
     parlay::{
         self,
-        contract::{CombinationMethod, ParlayContract},
+        contract::{AttestableValue, CombinationMethod, ParlayContract, ScoreMode},
         parameter::ParlayParameter,
     },
+    quorum::{self, QuorumConfig},
+    receipts,
     routes::CreateEvent,
-    storage::PostgresStorage,
+    sampling::SamplingPolicy,
+    series::{self, CreateSeries, SeriesEntry, SeriesManifest},
+    smoothing,
+    storage::{to_oracle_event, PostgresStorage},
+    triggers, units, ErrorCode, OracleServerError,
 };
 use bitcoin::{
-    bip32::Xpriv,
+    bip32::{ChildNumber, Xpriv},
     key::{Keypair, Secp256k1},
-    secp256k1::All,
+    secp256k1::{schnorr::Signature, All, Message},
     Network, XOnlyPublicKey,
 };
-use kormir::{Oracle, OracleAnnouncement, OracleAttestation, OracleEvent, Readable};
+use kormir::{
+    storage::{OracleEventData, Storage},
+    EventDescriptor, Oracle, OracleAnnouncement, OracleAttestation, OracleEvent,
+};
 use serde::{Deserialize, Serialize};
-use sqlx::{FromRow, PgPool, Postgres, Row};
+use sqlx::{FromRow, PgPool, Postgres};
 use uuid::Uuid;
 
-pub const IS_SIGNED: bool = false;
+/// Precision events were announced with before it was chosen per event type. Their outcomes
+/// were signed unscaled, see [`crate::events::EventParams`].
 pub const PRECISION: i32 = 2;
 
+/// Scale used for parlay announcements that do not set `maxNormalizedValue`.
+pub const DEFAULT_MAX_NORMALIZED_VALUE: u64 = 10000;
+
+/// Longest an event may wait after maturity before it is signed.
+pub const MAX_SETTLEMENT_DELAY: u32 = 7 * 24 * 60 * 60;
+
+/// Longest a signing request waits for a concurrent signer of the same event.
+pub const SIGNING_LOCK_WAIT: std::time::Duration = std::time::Duration::from_secs(30);
+
 pub struct ErnestOracle {
     pub oracle: Oracle<PostgresStorage>,
+    keypair: Keypair,
     pubkey: XOnlyPublicKey,
     mempool: MempoolClient,
     secp: Secp256k1<All>,
     pool: PgPool,
+    /// Reaches the oracles of external parlay legs
+    http: reqwest::Client,
+    /// Seconds to wait after maturity for events that do not set their own delay
+    settlement_delay: u32,
+    network: Network,
+    /// How ids are generated for events created without one
+    event_id_scheme: EventIdScheme,
+    /// How often values are fetched before they are signed
+    sampling: SamplingPolicy,
+    /// Sources that have to agree with `mempool` before a value is signed
+    sources: Vec<MempoolClient>,
+    quorum_tolerance: f64,
 }
 
 impl ErnestOracle {
@@ -46,46 +83,285 @@ impl ErnestOracle {
             oracle,
             pool,
             secp,
+            keypair,
             pubkey: keypair.x_only_public_key().0,
             mempool,
+            http: reqwest::Client::new(),
+            settlement_delay: 0,
+            network: Network::Bitcoin,
+            event_id_scheme: EventIdScheme::default(),
+            sampling: SamplingPolicy::default(),
+            sources: vec![],
+            quorum_tolerance: quorum::DEFAULT_TOLERANCE,
         })
     }
 
+    pub fn with_event_id_scheme(mut self, event_id_scheme: EventIdScheme) -> Self {
+        self.event_id_scheme = event_id_scheme;
+        self
+    }
+
+    pub fn with_sampling_policy(mut self, sampling: SamplingPolicy) -> Self {
+        self.sampling = sampling;
+        self
+    }
+
+    /// Only sign values the sources of `quorum` agree on with the mempool of the oracle. Call
+    /// after [`Self::with_network`], the sources are on the network of the oracle.
+    pub fn with_quorum(mut self, quorum: QuorumConfig) -> anyhow::Result<Self> {
+        self.sources = quorum
+            .sources
+            .into_iter()
+            .map(|source| {
+                MempoolConfig {
+                    network: self.network,
+                    ..source
+                }
+                .client()
+            })
+            .collect::<anyhow::Result<_>>()?;
+        self.quorum_tolerance = quorum.tolerance;
+        Ok(self)
+    }
+
+    /// Urls of the sources outcomes are read from.
+    pub fn data_sources(&self) -> Vec<String> {
+        [&self.mempool]
+            .into_iter()
+            .chain(&self.sources)
+            .map(|source| source.base_url().to_string())
+            .collect()
+    }
+
+    /// Run the oracle on `network`. Keys and nonces do not depend on the network.
+    pub fn with_network(mut self, network: Network) -> anyhow::Result<Self> {
+        let xprv = Xpriv::new_master(network, &self.keypair.secret_bytes())?;
+        self.oracle = Oracle::new(self.oracle.storage.clone(), self.keypair.secret_key(), xprv);
+        self.network = network;
+        Ok(self)
+    }
+
+    pub fn network(&self) -> Network {
+        self.network
+    }
+
+    /// Wait `settlement_delay` seconds after maturity before signing events that do not set
+    /// their own delay.
+    pub fn with_settlement_delay(mut self, settlement_delay: u32) -> anyhow::Result<Self> {
+        validate_settlement_delay(settlement_delay)?;
+        self.settlement_delay = settlement_delay;
+        Ok(self)
+    }
+
+    pub fn settlement_delay(&self) -> u32 {
+        self.settlement_delay
+    }
+
+    /// When `event_id` is due for signing, its maturity plus settlement delay.
+    pub async fn settles_at(&self, event_id: &str) -> anyhow::Result<i64> {
+        let (maturity, settlement_delay) = sqlx::query_as::<Postgres, (i64, Option<i32>)>(
+            "SELECT maturity, settlement_delay FROM events WHERE event_id = $1",
+        )
+        .bind(event_id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or(anyhow::anyhow!("Event not found. event_id={}", event_id))?;
+        Ok(self.settlement_time(maturity, settlement_delay))
+    }
+
+    /// Check `event_id` is due for signing as the watcher sees it, settled and not held.
+    pub async fn ensure_due(&self, event_id: &str) -> anyhow::Result<()> {
+        let settles_at = self.settles_at(event_id).await?;
+        if settles_at > chrono::Utc::now().timestamp() {
+            return Err(OracleServerError::new(
+                ErrorCode::NotMatured,
+                format!(
+                    "Event is not due for signing yet. settles_at={}",
+                    settles_at
+                ),
+            )
+            .into());
+        }
+        if self.oracle.storage.is_held(event_id).await? {
+            return Err(OracleServerError::new(ErrorCode::NotSigned, "Event is on hold.").into());
+        }
+        Ok(())
+    }
+
+    fn settlement_time(&self, maturity: i64, settlement_delay: Option<i32>) -> i64 {
+        maturity + settlement_delay.map_or(self.settlement_delay as i64, |delay| delay as i64)
+    }
+
+    /// Sign an arbitrary oracle message (see [`crate::receipts`]) with the oracle key.
+    pub fn sign_message(&self, message: &Message) -> Signature {
+        self.secp.sign_schnorr_no_aux_rand(message, &self.keypair)
+    }
+
+    /// The oracle's receipt over where a signed value came from.
+    pub fn provenance_receipt(&self, event_id: &str, provenance: &DataProvenance) -> Signature {
+        let message = receipts::provenance_message(
+            event_id,
+            &provenance.source_url,
+            provenance.value,
+            provenance.fetched_at,
+        );
+        self.sign_message(&message)
+    }
+
+    /// Persist where a signed value came from along with the oracle's receipt over it.
+    pub async fn save_provenance(
+        &self,
+        event_id: String,
+        data_type: String,
+        provenance: &DataProvenance,
+    ) -> anyhow::Result<()> {
+        let signature = self.provenance_receipt(&event_id, provenance);
+        attestation::save_attestation_provenance(
+            &self.pool, event_id, data_type, provenance, signature,
+        )
+        .await
+    }
+
     pub async fn create_event(&self, event: CreateEvent) -> anyhow::Result<OracleAnnouncement> {
+        self.create_event_listed(event, true).await
+    }
+
+    /// Create an event, unlisted until its series lists it when `listed` is false.
+    async fn create_event_listed(
+        &self,
+        event: CreateEvent,
+        listed: bool,
+    ) -> anyhow::Result<OracleAnnouncement> {
+        let metadata = event.metadata();
+        let announce_at = event.announce_at();
+        let settlement_delay = event.settlement_delay();
+        if let Some(settlement_delay) = settlement_delay {
+            validate_settlement_delay(settlement_delay)?;
+        }
+        if let Some(announce_at) = announce_at {
+            if announce_at > event.maturity() {
+                return Err(anyhow::anyhow!(
+                    "Announcement must be published before maturity. announce_at={} maturity={}",
+                    announce_at,
+                    event.maturity()
+                ));
+            }
+        }
+        let name = match &event {
+            CreateEvent::Single { event_type, .. } => event_type.to_string(),
+            CreateEvent::Parlay { .. } => "parlay".to_string(),
+        };
+        let event_id = event_ids::assign(
+            &self.pool,
+            event.event_id(),
+            self.event_id_scheme,
+            &name,
+            event.maturity(),
+        )
+        .await?;
+        // Kormir saves the announcement, staging decides whether it is listed right away
+        let announce_at = announce_at
+            .map(|at| {
+                chrono::DateTime::from_timestamp(at as i64, 0)
+                    .ok_or(anyhow::anyhow!("Invalid announce_at timestamp"))
+            })
+            .transpose()?;
+        if !listed {
+            self.oracle.storage.stage_unlisted(&event_id, None);
+        } else if let Some(announce_at) = announce_at.filter(|at| *at > chrono::Utc::now()) {
+            self.oracle
+                .storage
+                .stage_unlisted(&event_id, Some(announce_at));
+        }
+        self.oracle.storage.stage_metadata(&event_id, metadata);
+        if let Some(settlement_delay) = settlement_delay {
+            self.oracle
+                .storage
+                .stage_settlement_delay(&event_id, settlement_delay);
+        }
+        let announcement = match self.announce(event_id.clone(), event).await {
+            Ok(announcement) => announcement,
+            Err(e) => {
+                self.oracle.storage.unstage_unlisted(&event_id);
+                self.oracle.storage.unstage_metadata(&event_id);
+                self.oracle.storage.unstage_settlement_delay(&event_id);
+                return Err(e);
+            }
+        };
+        if let Err(e) =
+            triggers::notify_event_created(&self.pool, &announcement.oracle_event.event_id).await
+        {
+            log::warn!(
+                "Could not notify the watcher of a new event. event_id={} error={}",
+                announcement.oracle_event.event_id,
+                e
+            );
+        }
+        Ok(announcement)
+    }
+
+    /// Announce `event` under `event_id` with what signing it needs.
+    async fn announce(
+        &self,
+        event_id: String,
+        event: CreateEvent,
+    ) -> anyhow::Result<OracleAnnouncement> {
         let announcement = match event {
             CreateEvent::Single {
                 event_type,
                 maturity,
+                percentile,
+                aggregation,
+                smoothing,
+                precision,
+                nb_digits,
+                ..
             } => {
-                let event_id = Uuid::new_v4().to_string();
-                let event_params: EventParams = event_type.clone().into();
-                let announcement = self
+                let options = OutcomeOptions::new(&event_type, percentile, aggregation)?
+                    .with_smoothing(smoothing)?;
+                let event_params =
+                    EventParams::from(event_type.clone()).with_overrides(precision, nb_digits)?;
+                self.oracle
+                    .storage
+                    .stage_options(&event_id, options, event_params.clone());
+                let announcement = match self
                     .oracle
                     .create_numeric_event(
                         event_id.clone(),
                         event_params.nb_digits,
-                        IS_SIGNED,
-                        PRECISION,
-                        event_params.unit,
+                        event_params.is_signed,
+                        event_params.precision,
+                        event_params.unit.clone(),
                         maturity,
                     )
-                    .await?;
+                    .await
+                {
+                    Ok(announcement) => announcement,
+                    Err(e) => {
+                        self.oracle.storage.unstage_options(&event_id);
+                        return Err(e.into());
+                    }
+                };
                 self.add_event_type_to_oracle_data(event_id, "single")
                     .await?;
-                Ok(announcement)
+                announcement
             }
             CreateEvent::Parlay {
                 parameters,
                 combination_method,
                 max_normalized_value,
                 event_maturity_epoch,
+                score_mode,
+                ..
             } => {
                 let announcement = self
                     .create_parlay_announcement(
+                        event_id,
                         parameters,
                         combination_method,
                         max_normalized_value,
                         event_maturity_epoch,
+                        score_mode,
                     )
                     .await?;
                 self.add_event_type_to_oracle_data(
@@ -93,39 +369,108 @@ impl ErnestOracle {
                     "parlay",
                 )
                 .await?;
-                Ok(announcement)
+                announcement
             }
         };
-        announcement
+        Ok(announcement)
+    }
+
+    /// Create every event of a series and sign the manifest committing to it. Events are
+    /// unlisted until the manifest is saved and removed again if any of them fails.
+    pub async fn create_series(&self, request: CreateSeries) -> anyhow::Result<SeriesManifest> {
+        let maturities = request.maturities()?;
+        let announce_at = request
+            .announce_at
+            .map(|announce_at| {
+                if announce_at > maturities[0] {
+                    return Err(anyhow::anyhow!(
+                        "Series must be published before its first maturity. announce_at={} maturity={}",
+                        announce_at,
+                        maturities[0]
+                    ));
+                }
+                chrono::DateTime::from_timestamp(announce_at as i64, 0)
+                    .ok_or(anyhow::anyhow!("Invalid announce_at timestamp"))
+            })
+            .transpose()?;
+
+        let series_id = Uuid::new_v4().to_string();
+        let mut entries = Vec::with_capacity(maturities.len());
+        let result = async {
+            for maturity in maturities {
+                let announcement = self
+                    .create_event_listed(request.event(maturity), false)
+                    .await?;
+                entries.push(SeriesEntry {
+                    event_id: announcement.oracle_event.event_id,
+                    maturity,
+                });
+            }
+            let signature = self.sign_message(&SeriesManifest::message(&series_id, &entries));
+            let manifest = SeriesManifest {
+                series_id: series_id.clone(),
+                oracle_public_key: self.pubkey,
+                events: entries.clone(),
+                signature,
+            };
+            series::save_series(&self.pool, &manifest, announce_at).await?;
+            Ok(manifest)
+        }
+        .await;
+
+        if result.is_err() {
+            let event_ids = entries
+                .iter()
+                .map(|entry| entry.event_id.clone())
+                .collect::<Vec<_>>();
+            if let Err(e) = series::delete_events(&self.pool, &event_ids).await {
+                log::error!(
+                    "Failed to remove events of a failed series. series_id={} error={}",
+                    series_id,
+                    e
+                );
+            }
+        }
+        result
     }
 
     pub async fn create_parlay_announcement(
         &self,
-        parameters: Vec<ParlayParameter>,
+        id: String,
+        mut parameters: Vec<ParlayParameter>,
         combination_method: CombinationMethod,
         max_normalized_value: Option<u64>,
         event_maturity_epoch: u32,
+        score_mode: ScoreMode,
     ) -> anyhow::Result<OracleAnnouncement> {
-        if parameters.len() == 0 {
+        if parameters.is_empty() {
             return Err(anyhow::anyhow!("Parameters must be non-empty"));
         }
+        for parameter in &mut parameters {
+            if let Some(event_id) = &parameter.event_id {
+                if parameter.external.is_some() {
+                    return Err(anyhow::anyhow!(
+                        "A leg references either an event of this oracle or an external one."
+                    ));
+                }
+                self.validate_event_reference(event_id, &parameter.data_type)
+                    .await?;
+            }
+            if let Some(external) = parameter.external.take() {
+                parameter.external = Some(external.resolve(&self.http).await?);
+            }
+        }
 
-        let max_normalized_value = max_normalized_value.unwrap_or(10000);
+        let max_normalized_value = max_normalized_value.unwrap_or(DEFAULT_MAX_NORMALIZED_VALUE);
         let (nb_digits, _) = calculate_oracle_parameters(max_normalized_value);
 
-        let id = Uuid::new_v4().to_string();
-        ParlayContract::new(
-            self.pool.clone(),
-            id.clone(),
-            parameters,
-            combination_method,
-            max_normalized_value,
-        )
-        .await?;
+        parlay::contract::validate_weights(&parameters)?;
+
+        // The contract references the announced event, so announce first
         let announcement = self
             .oracle
             .create_numeric_event(
-                id,
+                id.clone(),
                 nb_digits,
                 false,
                 2,
@@ -133,65 +478,446 @@ impl ErnestOracle {
                 event_maturity_epoch,
             )
             .await?;
+        if let Err(e) = ParlayContract::new(
+            self.pool.clone(),
+            id.clone(),
+            parameters,
+            combination_method,
+            max_normalized_value,
+            score_mode,
+        )
+        .await
+        {
+            self.oracle.storage.delete_event(&id).await?;
+            return Err(e);
+        }
         Ok(announcement)
     }
 
+    /// A parlay leg may only reference a single event announced by this oracle for the same
+    /// data type.
+    async fn validate_event_reference(
+        &self,
+        event_id: &str,
+        data_type: &EventType,
+    ) -> anyhow::Result<()> {
+        let event = self
+            .oracle
+            .storage
+            .get_event(event_id.to_string())
+            .await?
+            .ok_or(anyhow::anyhow!(
+                "Referenced event does not exist. event_id={}",
+                event_id
+            ))?;
+        let EventDescriptor::DigitDecompositionEvent(descriptor) =
+            event.announcement.oracle_event.event_descriptor
+        else {
+            return Err(anyhow::anyhow!(
+                "Referenced event is not numeric. event_id={}",
+                event_id
+            ));
+        };
+        let event_type = units::event_type_from_unit(&descriptor.unit)?;
+        if &event_type != data_type {
+            return Err(anyhow::anyhow!(
+                "Referenced event has a different data type. event_id={} expected={} actual={}",
+                event_id,
+                data_type,
+                event_type
+            ));
+        }
+        Ok(())
+    }
+
+    /// The value leg `leg` of parlay `contract_id` settles on. Legs referencing a signed single
+    /// event use its attested outcome, the rest use the value fetched ahead of maturity or else
+    /// fetch fresh data, along with its provenance.
+    async fn parameter_value(
+        &self,
+        contract_id: &str,
+        leg: usize,
+        parameter: &ParlayParameter,
+    ) -> anyhow::Result<(f64, Option<DataProvenance>)> {
+        if let Some(external) = &parameter.external {
+            let value = external
+                .attested_value(&self.http)
+                .await?
+                .ok_or(anyhow::anyhow!(
+                    "External event is not attested yet. event_id={} oracle_url={}",
+                    external.event_id,
+                    external.oracle_url
+                ))?;
+            return Ok((value, None));
+        }
+        let Some(event_id) = &parameter.event_id else {
+            if let Some(data) = self.prefetched(contract_id, Some(leg)).await? {
+                return Ok((data.value, Some(data)));
+            }
+            let data = self
+                .sample_outcome(&parameter.data_type, &parameter.outcome_options())
+                .await?;
+            return Ok((data.value, Some(data)));
+        };
+        let params = events::get_event_params(&self.pool, event_id, &parameter.data_type).await?;
+        if let Some(value) = attestation::get_attested_value(&self.pool, event_id).await? {
+            return Ok((params.value(value), None));
+        }
+        let outcome = self
+            .single_event_outcome(event_id, &parameter.data_type)
+            .await?;
+        Ok((params.value(outcome.outcome), Some(outcome.provenance)))
+    }
+
+    /// Fetch the value a single event settles on using the options it was created with, smooth
+    /// it if the event asks to and round it to the outcome its announced digits will sign.
+    pub async fn single_event_outcome(
+        &self,
+        event_id: &str,
+        event_type: &EventType,
+    ) -> anyhow::Result<SingleEventOutcome> {
+        let params = events::get_event_params(&self.pool, event_id, event_type).await?;
+        let options = events::get_outcome_options(&self.pool, event_id).await?;
+        let mut provenance = match self.prefetched(event_id, None).await? {
+            Some(provenance) => provenance,
+            None => self.sample_outcome(event_type, &options).await?,
+        };
+        if let Some(smoothing) = options.smoothing {
+            provenance = smoothing::smooth(&self.pool, event_type, provenance, smoothing).await?;
+        }
+        let outcome = params.outcome(provenance.value)?;
+        Ok(SingleEventOutcome {
+            outcome,
+            provenance,
+        })
+    }
+
+    /// Fetch the value `event_type` settles on from every source as the sampling policy asks.
+    async fn sample_outcome(
+        &self,
+        event_type: &EventType,
+        options: &OutcomeOptions,
+    ) -> anyhow::Result<DataProvenance> {
+        let sources = [&self.mempool]
+            .into_iter()
+            .chain(&self.sources)
+            .collect::<Vec<_>>();
+        quorum::resolve_outcome(
+            event_type,
+            &sources,
+            options,
+            &self.sampling,
+            self.quorum_tolerance,
+        )
+        .await
+    }
+
+    /// The value fetched ahead of maturity for `event_id` and parlay leg `leg`, if it was
+    /// fetched close enough to settlement to sign with.
+    async fn prefetched(
+        &self,
+        event_id: &str,
+        leg: Option<usize>,
+    ) -> anyhow::Result<Option<DataProvenance>> {
+        let settles_at = self.settles_at(event_id).await?;
+        let since = history::parse_timestamp(settles_at - 2 * PREFETCH_LEAD_SECS)?;
+        history::get_prefetch(&self.pool, event_id, leg, since).await
+    }
+
+    /// Fetch and save the values `event_id` settles on ahead of its maturity, skipping values
+    /// already fetched. Parlay legs settling on other events are left to those events.
+    pub async fn prefetch_outcomes(&self, event_id: &str) -> anyhow::Result<()> {
+        let event = self
+            .oracle
+            .storage
+            .get_event(event_id.to_string())
+            .await?
+            .ok_or(anyhow::anyhow!("Event not found. event_id={}", event_id))?;
+        let EventDescriptor::DigitDecompositionEvent(descriptor) =
+            event.announcement.oracle_event.event_descriptor
+        else {
+            return Ok(());
+        };
+        if descriptor.unit == "parlay" {
+            let contract = self.get_parlay_contract(event_id.to_string()).await?;
+            for (leg, parameter) in contract.parameters.iter().enumerate() {
+                if parameter.event_id.is_some()
+                    || parameter.external.is_some()
+                    || self.prefetched(event_id, Some(leg)).await?.is_some()
+                {
+                    continue;
+                }
+                let data = self
+                    .sample_outcome(&parameter.data_type, &parameter.outcome_options())
+                    .await?;
+                history::save_prefetch(
+                    &self.pool,
+                    event_id,
+                    Some(leg),
+                    &parameter.data_type,
+                    &data,
+                )
+                .await?;
+            }
+            return Ok(());
+        }
+        if self.prefetched(event_id, None).await?.is_some() {
+            return Ok(());
+        }
+        let event_type = units::event_type_from_unit(&descriptor.unit)?;
+        let options = events::get_outcome_options(&self.pool, event_id).await?;
+        let data = self.sample_outcome(&event_type, &options).await?;
+        history::save_prefetch(&self.pool, event_id, None, &event_type, &data).await
+    }
+
+    /// Hold an event back from automatic signing, or release it. A released event is handed
+    /// to the watcher right away in case it matured while held.
+    pub async fn set_hold(
+        &self,
+        event_id: &str,
+        hold: bool,
+        reason: Option<&str>,
+    ) -> anyhow::Result<()> {
+        if !self.oracle.storage.set_hold(event_id, hold, reason).await? {
+            return Err(anyhow::anyhow!("Event not found. event_id={}", event_id));
+        }
+        if !hold {
+            triggers::request_signing(&self.pool, event_id).await?;
+        }
+        Ok(())
+    }
+
+    /// Leave an event out of the public listings and search, or list it again. Its
+    /// announcement and attestation are still served by id for contracts built on it.
+    pub async fn set_hidden(&self, event_id: &str, hidden: bool) -> anyhow::Result<()> {
+        if !self.oracle.storage.set_hidden(event_id, hidden).await? {
+            return Err(anyhow::anyhow!("Event not found. event_id={}", event_id));
+        }
+        Ok(())
+    }
+
+    /// Take the signing lock of an event, waiting up to [`SIGNING_LOCK_WAIT`] for a concurrent
+    /// signer. The event may have been signed by it, see [`Self::signed_attestation`].
+    pub async fn lock_event(&self, event_id: &str) -> anyhow::Result<Option<EventLock>> {
+        lock::lock_event(&self.pool, event_id, SIGNING_LOCK_WAIT).await
+    }
+
+    /// The attestation of `event_id`, `None` while it is not signed.
+    pub async fn signed_attestation(
+        &self,
+        event_id: &str,
+    ) -> anyhow::Result<Option<OracleAttestation>> {
+        let Some(event) = self.oracle.storage.get_event(event_id.to_string()).await? else {
+            return Ok(None);
+        };
+        if event.signatures.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(OracleAttestation {
+            event_id: event.event_id,
+            oracle_public_key: event.announcement.oracle_public_key,
+            signatures: event
+                .signatures
+                .iter()
+                .map(|(_, signature)| *signature)
+                .collect(),
+            outcomes: event
+                .signatures
+                .into_iter()
+                .map(|(outcome, _)| outcome)
+                .collect(),
+        }))
+    }
+
+    /// Take the signing lock of an event that is still unsigned. `None` when another signer
+    /// holds the lock or the event was signed in the meantime.
+    pub async fn lock_unsigned_event(&self, event_id: &str) -> anyhow::Result<Option<EventLock>> {
+        let Some(lock) = lock::try_lock_event(&self.pool, event_id).await? else {
+            return Ok(None);
+        };
+        let signed = sqlx::query_scalar::<Postgres, bool>(
+            "SELECT EXISTS (SELECT 1 FROM event_nonces WHERE event_id = $1 AND signature IS NOT NULL)",
+        )
+        .bind(event_id)
+        .fetch_one(&self.pool)
+        .await?;
+        if signed {
+            lock.release().await?;
+            return Ok(None);
+        }
+        Ok(Some(lock))
+    }
+
+    /// Sign a numeric event with kormir and record the signature in the audit log in the
+    /// transaction that saves it.
+    pub async fn sign_numeric_event(
+        &self,
+        event_id: String,
+        outcome: i64,
+        source: SigningSource,
+    ) -> anyhow::Result<OracleAttestation> {
+        self.oracle
+            .storage
+            .stage_signing(&event_id, outcome, source);
+        let attestation = self
+            .oracle
+            .sign_numeric_event(event_id.clone(), outcome)
+            .await;
+        self.oracle.storage.unstage_signing(&event_id);
+        Ok(attestation?)
+    }
+
+    /// Sign `record.attested_value` and save `record` in the transaction that saves the
+    /// signatures, so the event is never signed without its outcome.
+    pub async fn sign_and_record(
+        &self,
+        record: AttestationRecord,
+        source: SigningSource,
+    ) -> anyhow::Result<OracleAttestation> {
+        let event_id = record.event_id.clone();
+        let attested_value = record.attested_value;
+        self.oracle.storage.stage_attestation(record);
+        let attestation = self
+            .sign_numeric_event(event_id.clone(), attested_value, source)
+            .await;
+        self.oracle.storage.unstage_attestation(&event_id);
+        attestation
+    }
+
+    /// Keys publishing the oracle's nostr events, the oracle key itself.
+    pub fn nostr_keys(&self) -> anyhow::Result<nostr_sdk::Keys> {
+        let secret_key = nostr_sdk::SecretKey::from_slice(&self.keypair.secret_bytes())?;
+        Ok(nostr_sdk::Keys::new(secret_key))
+    }
+
+    /// Nonce the oracle derives for `index`, the same derivation kormir signs with.
+    pub fn nonce_public_key(&self, index: u32) -> anyhow::Result<XOnlyPublicKey> {
+        let xprv = Xpriv::new_master(self.network, &self.keypair.secret_bytes())?;
+        let key = xprv
+            .derive_priv(&self.secp, &[ChildNumber::from_hardened_idx(index)?])?
+            .private_key;
+        Ok(key.x_only_public_key(&self.secp).0)
+    }
+
+    /// Import an event exported from another kormir oracle with the same key. Unsigned events
+    /// are only accepted if their nonces derive from this oracle's key, otherwise they could
+    /// never be signed.
+    pub async fn import_event(&self, data: &OracleEventData) -> anyhow::Result<ImportResult> {
+        import::validate(data, &self.pubkey)?;
+        if data.signatures.is_empty() {
+            for (index, nonce) in data
+                .indexes
+                .iter()
+                .zip(&data.announcement.oracle_event.oracle_nonces)
+            {
+                if self.nonce_public_key(*index)? != *nonce {
+                    return Err(anyhow::anyhow!(
+                        "Nonce is not derived from the oracle key. event_id={} index={}",
+                        data.event_id,
+                        index
+                    ));
+                }
+            }
+        }
+        import::save_imported_event(&self.pool, data).await
+    }
+
     pub async fn get_parlay_contract(&self, id: String) -> anyhow::Result<ParlayContract> {
         let contract = parlay::contract::get_parlay_contract(self.pool.clone(), id).await?;
         Ok(contract)
     }
 
-    pub async fn attest_parlay_contract(&self, id: String) -> anyhow::Result<OracleAttestation> {
-        log::info!("Attesting parlay contract. id={}", id);
-        let contract = parlay::contract::get_parlay_contract(self.pool.clone(), id.clone()).await?;
-        let mut scores = Vec::new();
-        let mut outcomes = Vec::new();
-        for parameter in contract.parameters {
-            let outcome = EventType::outcome(&parameter.data_type, &self.mempool)
+    /// Run the parlay scoring pipeline on current data without signing anything.
+    pub async fn evaluate_parlay_contract(
+        &self,
+        contract: &ParlayContract,
+    ) -> anyhow::Result<ParlayEvaluation> {
+        let id = &contract.id;
+        let mut values = Vec::new();
+        let mut provenance = Vec::new();
+        for (leg, parameter) in contract.parameters.iter().enumerate() {
+            let (value, data) = self
+                .parameter_value(id, leg, parameter)
                 .await
                 .map_err(|e| {
-                    anyhow::anyhow!(
+                    // Kept as the source so a disagreement of data sources can be told apart
+                    let context = format!(
                         "Failed to get outcome for parameter. data_type={}, id={}, error={}",
-                        parameter.data_type,
-                        id,
-                        e
-                    )
+                        parameter.data_type, id, e
+                    );
+                    e.context(context)
                 })?;
-            let normalized_value = parameter.normalize_parameter(outcome);
-            let transformed_value = parameter.apply_transformation(normalized_value);
-            let score = transformed_value * parameter.weight;
-            outcomes.push(AttestationDataOutcome {
-                event_id: id.clone(),
-                data_type: parameter.data_type.to_string(),
-                normalized_value: score,
-                original_value: outcome,
-            });
-            scores.push(score);
+            values.push(value);
+            if let Some(data) = data {
+                provenance.push((parameter.data_type.to_string(), data));
+            }
         }
 
-        let combined_score =
-            parlay::contract::combine_scores(&scores, &contract.combination_method);
+        Ok(ParlayEvaluation {
+            provenance,
+            ..score_parlay_contract(contract, &values)
+        })
+    }
+
+    pub async fn attest_parlay_contract(
+        &self,
+        id: String,
+        source: SigningSource,
+    ) -> anyhow::Result<OracleAttestation> {
+        log::info!("Attesting parlay contract. id={}", id);
+        let contract = parlay::contract::get_parlay_contract(self.pool.clone(), id.clone()).await?;
+        let evaluation = self.evaluate_parlay_contract(&contract).await?;
+        self.sign_parlay_evaluation(id, evaluation, source).await
+    }
 
-        let attestable_value = parlay::contract::convert_to_attestable_value(
+    /// Sign the attestable value of `evaluation` for the parlay `id` and record how it was
+    /// scored.
+    pub async fn sign_parlay_evaluation(
+        &self,
+        id: String,
+        evaluation: ParlayEvaluation,
+        source: SigningSource,
+    ) -> anyhow::Result<OracleAttestation> {
+        let ParlayEvaluation {
             combined_score,
-            contract.max_normalized_value,
-        );
+            attestable_value:
+                AttestableValue {
+                    value: attestable_value,
+                    clamped,
+                },
+            outcomes,
+            provenance,
+        } = evaluation;
+        if clamped {
+            log::warn!(
+                "Parlay score outside the announced range, clamping. id={} combined_score={} attested_value={}",
+                id,
+                combined_score,
+                attestable_value
+            );
+        }
 
+        let provenance = provenance
+            .into_iter()
+            .map(|(data_type, data)| {
+                let receipt = self.provenance_receipt(&id, &data);
+                (data_type, data, receipt)
+            })
+            .collect();
         let attestation = self
-            .oracle
-            .sign_numeric_event(id.clone(), attestable_value as i64)
+            .sign_and_record(
+                AttestationRecord {
+                    event_id: id.clone(),
+                    combined_score,
+                    attested_value: attestable_value as i64,
+                    clamped,
+                    outcomes,
+                    provenance,
+                },
+                source,
+            )
             .await?;
 
-        attestation::save_attestation_outcome(
-            &self.pool,
-            id.clone(),
-            combined_score,
-            attestable_value,
-        )
-        .await?;
-
-        attestation::save_attestation_data_outcomes(&self.pool, outcomes).await?;
-
         log::info!(
             "Attested parlay contract. id={} attested_value={}",
             id,
@@ -206,15 +932,18 @@ impl ErnestOracle {
         &self,
         event_type: &str,
     ) -> anyhow::Result<Vec<(String, OracleEvent)>> {
-        // Get current timestamp for maturity check
-        let now = chrono::Utc::now().timestamp() as u32;
+        let now = chrono::Utc::now().timestamp();
 
-        let rows = sqlx::query(
+        // Settlement delays are never negative, so matured events bound the scan
+        let rows = sqlx::query_as::<Postgres, (String, Vec<u8>)>(
             r#"
             SELECT e.event_id, e.oracle_event
             FROM events e
             INNER JOIN event_types et ON e.event_id = et.oracle_event_id
             WHERE et.event_type = $1
+                AND e.maturity <= $2
+                AND e.maturity + COALESCE(e.settlement_delay, $3) <= $2
+                AND NOT e.hold
                 AND NOT EXISTS (
                     SELECT 1 FROM event_nonces en 
                     WHERE en.event_id = e.event_id 
@@ -224,28 +953,114 @@ impl ErnestOracle {
             "#,
         )
         .bind(event_type)
+        .bind(now)
+        .bind(self.settlement_delay as i32)
         .fetch_all(&self.pool)
         .await
         .map_err(|e| anyhow::anyhow!("Failed to get matured unsigned event IDs. error={}", e))?;
 
-        let results = rows
+        // An unreadable event must not keep the others from being signed
+        Ok(rows
             .into_iter()
-            .map(|row| {
-                let event_id: String = row.get("event_id");
-                let oracle_event: Vec<u8> = row.get("oracle_event");
-                let mut cursor = kormir::lightning::io::Cursor::new(&oracle_event);
-                let event = OracleEvent::read(&mut cursor)
-                    .expect("Should be able to read oracle event from db");
-                (event_id, event)
-            })
-            .collect::<Vec<(String, OracleEvent)>>();
+            .filter_map(
+                |(event_id, oracle_event)| match to_oracle_event(&oracle_event) {
+                    Ok(event) => Some((event_id, event)),
+                    Err(_) => {
+                        log::error!("Skipping unreadable event. event_id={}", event_id);
+                        None
+                    }
+                },
+            )
+            .collect())
+    }
+
+    /// Unsigned events that are not held and are due for signing at or before `due_by`, with
+    /// the time they are due.
+    pub async fn unsigned_events_due_by(&self, due_by: i64) -> anyhow::Result<Vec<(String, i64)>> {
+        Ok(sqlx::query_as::<Postgres, (String, i64)>(
+            r#"
+            SELECT e.event_id, e.maturity + COALESCE(e.settlement_delay, $2) AS settles_at
+            FROM events e
+            WHERE e.maturity <= $1
+                AND e.maturity + COALESCE(e.settlement_delay, $2) <= $1
+                AND NOT e.hold
+                AND NOT EXISTS (
+                    SELECT 1 FROM event_nonces en
+                    WHERE en.event_id = e.event_id
+                    AND en.signature IS NOT NULL
+                )
+            "#,
+        )
+        .bind(due_by)
+        .bind(self.settlement_delay as i32)
+        .fetch_all(&self.pool)
+        .await?)
+    }
 
-        Ok(results
+    /// Unsigned events matured at or before `now`, held ones included, oldest maturity first.
+    pub async fn pending_events(&self, now: i64) -> anyhow::Result<Vec<PendingEvent>> {
+        let rows = sqlx::query_as::<Postgres, PendingEventRow>(
+            r#"
+            SELECT e.event_id, e.maturity, e.settlement_delay, et.event_type, e.hold,
+                e.hold_reason
+            FROM events e
+            LEFT JOIN event_types et ON e.event_id = et.oracle_event_id
+            WHERE e.maturity <= $1
+                AND NOT EXISTS (
+                    SELECT 1 FROM event_nonces en
+                    WHERE en.event_id = e.event_id
+                    AND en.signature IS NOT NULL
+                )
+            ORDER BY e.maturity
+            "#,
+        )
+        .bind(now)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
             .into_iter()
-            .filter(|(_, event)| event.event_maturity_epoch <= now)
+            .map(|row| PendingEvent {
+                settles_at: self.settlement_time(row.maturity, row.settlement_delay),
+                event_maturity_epoch: row.maturity as u32,
+                pending_secs: now - row.maturity,
+                event_id: row.event_id,
+                event_type: row.event_type,
+                hold: row.hold,
+                hold_reason: row.hold_reason,
+            })
             .collect())
     }
 
+    /// Maturities of unsigned events and times of scheduled announcements, the times the
+    /// watcher has to act at.
+    pub async fn pending_schedule(&self) -> anyhow::Result<Vec<i64>> {
+        let rows = sqlx::query_as::<Postgres, (i64, Option<i32>)>(
+            r#"
+            SELECT e.maturity, e.settlement_delay
+            FROM events e
+            WHERE NOT EXISTS (
+                SELECT 1 FROM event_nonces en
+                WHERE en.event_id = e.event_id
+                AND en.signature IS NOT NULL
+            )
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        let mut times = rows
+            .iter()
+            .map(|(maturity, settlement_delay)| self.settlement_time(*maturity, *settlement_delay))
+            .collect::<Vec<_>>();
+
+        let announcements = sqlx::query_scalar::<Postgres, chrono::DateTime<chrono::Utc>>(
+            "SELECT announce_at FROM events WHERE NOT announced AND announce_at IS NOT NULL",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        times.extend(announcements.iter().map(|at| at.timestamp()));
+        Ok(times)
+    }
+
     async fn add_event_type_to_oracle_data(
         &self,
         event_id: String,
@@ -266,11 +1081,15 @@ impl ErnestOracle {
             r#"
             SELECT 
                 e.event_id,
-                types.event_type
+                types.event_type,
+                meta.description,
+                COALESCE(meta.tags, '{}') AS tags
             FROM 
                 events e
             JOIN 
                 event_types types ON e.event_id = types.oracle_event_id
+            LEFT JOIN
+                event_metadata meta ON e.event_id = meta.event_id
             WHERE
                 types.event_type = $1
             ORDER BY 
@@ -284,46 +1103,136 @@ impl ErnestOracle {
     }
 }
 
+/// What a parlay settles on with the data available now.
+#[derive(Debug, Clone)]
+pub struct ParlayEvaluation {
+    pub combined_score: f64,
+    pub attestable_value: AttestableValue,
+    pub outcomes: Vec<AttestationDataOutcome>,
+    /// Data fetched for legs that do not settle on a signed single event
+    pub provenance: Vec<(String, DataProvenance)>,
+}
+
+/// Score `contract` on the outcome of each leg, in the unit of its data type.
+pub fn score_parlay_contract(contract: &ParlayContract, values: &[f64]) -> ParlayEvaluation {
+    let score = parlay::contract::score_parameters(
+        &contract.parameters,
+        &contract.combination_method,
+        &contract.score_mode,
+        contract.scoring_version,
+        values,
+    );
+    let outcomes = contract
+        .parameters
+        .iter()
+        .zip(values)
+        .zip(
+            score
+                .normalized_values
+                .iter()
+                .zip(&score.transformed_values),
+        )
+        .map(
+            |((parameter, original_value), (normalized_value, transformed_value))| {
+                AttestationDataOutcome {
+                    event_id: contract.id.clone(),
+                    data_type: parameter.data_type.to_string(),
+                    normalized_value: *normalized_value,
+                    original_value: *original_value,
+                    transformed_value: *transformed_value,
+                }
+            },
+        )
+        .collect::<Vec<_>>();
+
+    ParlayEvaluation {
+        combined_score: score.combined_score,
+        attestable_value: score.attestable_value(contract.max_normalized_value),
+        outcomes,
+        provenance: vec![],
+    }
+}
+
+/// The signed outcome of a single event and the data it was derived from.
+#[derive(Debug, Clone)]
+pub struct SingleEventOutcome {
+    pub outcome: i64,
+    pub provenance: DataProvenance,
+}
+
+/// A matured event the oracle has not signed yet.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingEvent {
+    pub event_id: String,
+    pub event_type: Option<String>,
+    pub event_maturity_epoch: u32,
+    /// When the watcher signs it, maturity plus the settlement delay
+    pub settles_at: i64,
+    /// Seconds since maturity
+    pub pending_secs: i64,
+    /// Held events are not signed until released
+    pub hold: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hold_reason: Option<String>,
+}
+
+#[derive(FromRow)]
+struct PendingEventRow {
+    event_id: String,
+    maturity: i64,
+    settlement_delay: Option<i32>,
+    event_type: Option<String>,
+    hold: bool,
+    hold_reason: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct Events {
     pub event_id: String,
     pub event_type: String,
+    pub description: Option<String>,
+    pub tags: Vec<String>,
 }
 
-/// Calculate oracle parameters from max normalized value
-///
-/// Returns a tuple with:
-/// - nb_digits: Number of binary digits needed for the oracle
-/// - oracle_max_value: Maximum value the oracle can attest to (2^nb_digits - 1)
-/// - max_normalized_value: The input value (for convenience)
-pub fn calculate_oracle_parameters(max_normalized_value: u64) -> (u16, u64) {
-    // Calculate the minimum number of bits needed to represent max_normalized_value
-    let nb_digits = if max_normalized_value == 0 {
-        1 // Handle edge case
-    } else {
-        // Find ceiling of log base 2
-        (max_normalized_value as f64).log2().ceil() as u16
-    };
-
-    // Calculate the maximum value the oracle can represent with nb_digits
-    let oracle_max_value = (1u64 << nb_digits) - 1;
+pub use crate::parlay::math::calculate_oracle_parameters;
 
-    (nb_digits, oracle_max_value)
+fn validate_settlement_delay(settlement_delay: u32) -> anyhow::Result<()> {
+    if settlement_delay > MAX_SETTLEMENT_DELAY {
+        return Err(anyhow::anyhow!(
+            "Settlement delay must be at most {} seconds. settlement_delay={}",
+            MAX_SETTLEMENT_DELAY,
+            settlement_delay
+        ));
+    }
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use crate::{
+        attestation::{self, AttestationDataOutcome, AttestationRecord},
+        audit::SigningSource,
+        digits,
+        event_ids::{self, EventIdScheme},
         events::EventType,
-        mempool::{MempoolClient, BASE_URL},
+        import::ImportResult,
+        mempool::{FeePercentile, MempoolClient, BASE_URL},
+        oracle::MAX_SETTLEMENT_DELAY,
         parlay::{
-            contract::{CombinationMethod, ParlayContract},
+            contract::{CombinationMethod, ScoreMode},
             parameter::{ParlayParameter, TransformationFunction},
         },
         routes::CreateEvent,
-        test_util::{setup_ernest_oracle, setup_mock_server_from_test_vectors, TestVectors},
+        sampling::SamplingPolicy,
+        series::CreateSeries,
+        test_util::{
+            mock_mempool_client, setup_ernest_oracle, setup_mock_server,
+            setup_mock_server_from_test_vectors, SingleEvent, TestVectors,
+        },
     };
-    use sqlx::PgPool;
+    use kormir::{storage::Storage, EventDescriptor};
+    use sqlx::Postgres;
     use std::{fs::read_to_string, str::FromStr, time::Duration};
 
     #[tokio::test]
@@ -332,36 +1241,621 @@ mod tests {
         let test_vectors: TestVectors =
             serde_json::from_str(&test_vectors).expect("Failed to parse test vectors");
 
-        for test_vector in test_vectors.test_vectors {
+        for (test_vector, scoring_version) in
+            test_vectors.test_vectors.into_iter().flat_map(|vector| {
+                vector
+                    .scoring_versions()
+                    .into_iter()
+                    .map(move |version| (vector.clone(), version))
+            })
+        {
+            let name = format!("{} (v{})", test_vector.name, scoring_version);
+            let tolerance = test_vector.tolerance();
             let mock_server = setup_mock_server_from_test_vectors(test_vector.clone()).await;
-            let mempool = MempoolClient::new(format!("{}/api/v1", mock_server.uri()));
-            let pg_url = std::env::var("DATABASE_URL").expect("DATABASE_URL is not set");
-            let pool = PgPool::connect(&pg_url)
+            let mempool = mock_mempool_client(&mock_server);
+            let oracle = setup_ernest_oracle(mempool)
                 .await
-                .expect("Failed to connect to database");
-            let oracle = setup_ernest_oracle(mempool).await;
-            let id = uuid::Uuid::new_v4().to_string();
-            ParlayContract::new(
-                pool.clone(),
-                id.clone(),
-                test_vector
-                    .contract
-                    .parameters
-                    .into_iter()
-                    .map(|p| p.into())
-                    .collect(),
-                CombinationMethod::from_str(&test_vector.contract.combination_method)
+                .with_sampling_policy(SamplingPolicy {
+                    interval_ms: 0,
+                    ..Default::default()
+                });
+            let announcement = oracle
+                .create_event(CreateEvent::Parlay {
+                    parameters: test_vector.contract.parameters,
+                    combination_method: CombinationMethod::from_str(
+                        &test_vector.contract.combination_method,
+                    )
                     .expect("Failed to parse combination method"),
-                test_vector.contract.max_normalized_value as u64,
+                    max_normalized_value: Some(test_vector.contract.max_normalized_value as u64),
+                    event_maturity_epoch: chrono::Utc::now().timestamp() as u32 + 1000,
+                    score_mode: ScoreMode::default(),
+                    description: None,
+                    tags: vec![],
+                    announce_at: None,
+                    settlement_delay: None,
+                    event_id: None,
+                })
+                .await
+                .expect("could not create parlay contract");
+            // Settle the contract as if it had been announced under `scoring_version`
+            sqlx::query("UPDATE parlay_contracts SET scoring_version = $1 WHERE id = $2")
+                .bind(scoring_version as i32)
+                .bind(&announcement.oracle_event.event_id)
+                .execute(&oracle.pool)
+                .await
+                .unwrap();
+            let contract = oracle
+                .get_parlay_contract(announcement.oracle_event.event_id.clone())
+                .await
+                .unwrap();
+            let preview = oracle.evaluate_parlay_contract(&contract).await.unwrap();
+            let event = oracle
+                .oracle
+                .storage
+                .get_event(contract.id.clone())
+                .await
+                .unwrap()
+                .unwrap();
+            assert!(event.signatures.is_empty());
+
+            let attestation = oracle
+                .attest_parlay_contract(
+                    announcement.oracle_event.event_id.clone(),
+                    SigningSource::Admin,
+                )
+                .await
+                .expect("could not attest parlay contract");
+
+            let attested_value = digits::decode_attestation(&announcement, &attestation)
+                .unwrap()
+                .value as u64;
+            assert_eq!(preview.attestable_value.value, attested_value);
+            assert_eq!(
+                attested_value, test_vector.expected.attestation_value,
+                "{}",
+                name
+            );
+            assert!(attestation.validate(&oracle.secp, &announcement).is_ok());
+
+            let outcome = attestation::get_attestation_outcome(&oracle.pool, contract.id)
+                .await
+                .unwrap();
+            assert_eq!(outcome.attested_value as u64, attested_value);
+            assert_eq!(outcome.combined_score, preview.combined_score);
+            assert_eq!(
+                outcome.outcomes.len(),
+                test_vector.expected.normalized_values.len()
+            );
+            for ((leg, normalized), transformed) in outcome
+                .outcomes
+                .iter()
+                .zip(&test_vector.expected.normalized_values)
+                .zip(&test_vector.expected.transformed_values)
+            {
+                assert!(
+                    (leg.normalized_value - normalized).abs() < tolerance,
+                    "{}",
+                    name
+                );
+                assert!(
+                    (leg.transformed_value - transformed).abs() < tolerance,
+                    "{}",
+                    name
+                );
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn signatures_are_saved_with_their_outcome() {
+        let mempool = MempoolClient::new(BASE_URL.to_string());
+        let oracle = setup_ernest_oracle(mempool).await;
+        let announcement = oracle
+            .create_event(CreateEvent::single(
+                EventType::Hashrate,
+                chrono::Utc::now().timestamp() as u32 + 1000,
+            ))
+            .await
+            .unwrap();
+        let event_id = announcement.oracle_event.event_id;
+        let record = AttestationRecord {
+            event_id: event_id.clone(),
+            combined_score: 42.0,
+            attested_value: 42,
+            clamped: false,
+            outcomes: vec![AttestationDataOutcome {
+                event_id: event_id.clone(),
+                data_type: EventType::Hashrate.to_string(),
+                normalized_value: 42.0,
+                original_value: 42.0,
+                transformed_value: 42.0,
+            }],
+            provenance: vec![],
+        };
+
+        // An outcome that cannot be saved takes the signatures down with it
+        attestation::save_attestation_outcome(&oracle.pool, event_id.clone(), 1.0, 1, false)
+            .await
+            .unwrap();
+        assert!(oracle
+            .sign_and_record(record.clone(), SigningSource::Admin)
+            .await
+            .is_err());
+        let storage = &oracle.oracle.storage;
+        let event = storage.get_event(event_id.clone()).await.unwrap().unwrap();
+        assert!(event.signatures.is_empty());
+        assert!(storage.unstage_attestation(&event_id).is_none());
+
+        sqlx::query("DELETE FROM numeric_attestation_outcome WHERE event_id = $1")
+            .bind(&event_id)
+            .execute(&oracle.pool)
+            .await
+            .unwrap();
+        oracle
+            .sign_and_record(record, SigningSource::Admin)
+            .await
+            .unwrap();
+        let event = storage.get_event(event_id.clone()).await.unwrap().unwrap();
+        assert!(!event.signatures.is_empty());
+        let outcome = attestation::get_attestation_outcome(&oracle.pool, event_id)
+            .await
+            .unwrap();
+        assert_eq!(outcome.attested_value, 42);
+        assert_eq!(outcome.outcomes.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn create_event_with_metadata() {
+        let mempool = MempoolClient::new(BASE_URL.to_string());
+        let oracle = setup_ernest_oracle(mempool).await;
+        let announcement = oracle
+            .create_event(CreateEvent::from(SingleEvent {
+                description: Some("Q3 hashrate hedge".to_string()),
+                tags: vec!["Hashrate".to_string(), "q3".to_string()],
+                ..SingleEvent::new(
+                    EventType::Hashrate,
+                    chrono::Utc::now().timestamp() as u32 + 1000,
+                )
+            }))
+            .await
+            .unwrap();
+
+        let metadata = crate::metadata::get_event_metadata(
+            &oracle.oracle.storage.pool,
+            &announcement.oracle_event.event_id,
+        )
+        .await
+        .unwrap();
+        assert_eq!(metadata.description, Some("Q3 hashrate hedge".to_string()));
+        assert_eq!(metadata.tags, vec!["hashrate", "q3"]);
+    }
+
+    #[tokio::test]
+    async fn scheduled_announcement_is_hidden_until_published() {
+        let mempool = MempoolClient::new(BASE_URL.to_string());
+        let oracle = setup_ernest_oracle(mempool).await;
+        let now = chrono::Utc::now().timestamp() as u32;
+        let event = |announce_at| {
+            CreateEvent::from(SingleEvent {
+                announce_at: Some(announce_at),
+                ..SingleEvent::new(EventType::Hashrate, now + 2000)
+            })
+        };
+        assert!(oracle.create_event(event(now + 3000)).await.is_err());
+
+        let announcement = oracle.create_event(event(now + 1000)).await.unwrap();
+        let event_id = announcement.oracle_event.event_id;
+        let storage = &oracle.oracle.storage;
+        assert!(!storage.is_announced(&event_id).await.unwrap());
+        let listed = storage.oracle_event_data().await.unwrap();
+        assert!(!listed.iter().any(|e| e.event_id == event_id));
+
+        sqlx::query("UPDATE events SET announce_at = NOW() WHERE event_id = $1")
+            .bind(&event_id)
+            .execute(&storage.pool)
+            .await
+            .unwrap();
+        let published = storage.publish_due_announcements().await.unwrap();
+        assert!(published.contains(&event_id));
+        assert!(storage.is_announced(&event_id).await.unwrap());
+        let listed = storage.oracle_event_data().await.unwrap();
+        assert!(listed.iter().any(|e| e.event_id == event_id));
+    }
+
+    #[tokio::test]
+    async fn create_series_with_signed_manifest() {
+        let mempool = MempoolClient::new(BASE_URL.to_string());
+        let oracle = setup_ernest_oracle(mempool).await;
+        let first_maturity = chrono::Utc::now().timestamp() as u32 + 1000;
+        let manifest = oracle
+            .create_series(CreateSeries {
+                event_type: EventType::Hashrate,
+                first_maturity,
+                interval_secs: 604_800,
+                count: 3,
+                percentile: None,
+                aggregation: None,
+                precision: None,
+                nb_digits: None,
+                description: None,
+                tags: vec!["weekly".to_string()],
+                announce_at: None,
+                settlement_delay: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(manifest.events.len(), 3);
+        assert_eq!(manifest.events[2].maturity, first_maturity + 2 * 604_800);
+        assert!(manifest.verify(&oracle.oracle.public_key()));
+
+        let storage = &oracle.oracle.storage;
+        for entry in &manifest.events {
+            assert!(storage.is_announced(&entry.event_id).await.unwrap());
+        }
+        let saved = crate::series::get_series(
+            &storage.pool,
+            &manifest.series_id,
+            oracle.oracle.public_key(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(saved.events, manifest.events);
+        assert!(saved.verify(&oracle.oracle.public_key()));
+    }
+
+    #[tokio::test]
+    async fn create_fee_rate_event_with_percentile() {
+        let mempool = MempoolClient::new(BASE_URL.to_string());
+        let oracle = setup_ernest_oracle(mempool).await;
+        let maturity = chrono::Utc::now().timestamp() as u32 + 1000;
+        let announcement = oracle
+            .create_event(CreateEvent::from(SingleEvent {
+                percentile: Some(FeePercentile::P50),
+                ..SingleEvent::new(EventType::FeeRate, maturity)
+            }))
+            .await
+            .unwrap();
+
+        let options = crate::events::get_outcome_options(
+            &oracle.oracle.storage.pool,
+            &announcement.oracle_event.event_id,
+        )
+        .await
+        .unwrap();
+        assert_eq!(options.fee_percentile, FeePercentile::P50);
+
+        let hashrate = oracle
+            .create_event(CreateEvent::from(SingleEvent {
+                percentile: Some(FeePercentile::P50),
+                ..SingleEvent::new(EventType::Hashrate, maturity)
+            }))
+            .await;
+        assert!(hashrate.is_err());
+    }
+
+    #[tokio::test]
+    async fn create_event_with_precision_and_digits() {
+        let mempool = MempoolClient::new(BASE_URL.to_string());
+        let oracle = setup_ernest_oracle(mempool).await;
+        let announcement = oracle
+            .create_event(CreateEvent::from(SingleEvent {
+                precision: Some(0),
+                nb_digits: Some(24),
+                ..SingleEvent::new(
+                    EventType::BlockFees,
+                    chrono::Utc::now().timestamp() as u32 + 1000,
+                )
+            }))
+            .await
+            .unwrap();
+
+        let EventDescriptor::DigitDecompositionEvent(descriptor) =
+            announcement.oracle_event.event_descriptor
+        else {
+            panic!("expected a digit decomposition event");
+        };
+        assert_eq!(descriptor.nb_digits, 24);
+        assert_eq!(descriptor.precision, 0);
+        assert_eq!(announcement.oracle_event.oracle_nonces.len(), 24);
+
+        let params = crate::events::get_event_params(
+            &oracle.oracle.storage.pool,
+            &announcement.oracle_event.event_id,
+            &EventType::BlockFees,
+        )
+        .await
+        .unwrap();
+        assert_eq!(params.nb_digits, 24);
+        assert_eq!(params.precision, 0);
+    }
+
+    #[tokio::test]
+    async fn parlay_leg_references_single_event() {
+        let mock_server = setup_mock_server().await;
+        let mempool = mock_mempool_client(&mock_server);
+        let oracle = setup_ernest_oracle(mempool).await;
+        let maturity = chrono::Utc::now().timestamp() as u32 + 1000;
+        let single = oracle
+            .create_event(CreateEvent::single(EventType::Hashrate, maturity))
+            .await
+            .unwrap();
+        let single_id = single.oracle_event.event_id;
+
+        let parameter = |data_type| ParlayParameter {
+            data_type,
+            threshold: 700.0,
+            range: 100.0,
+            is_above_threshold: true,
+            transformation: TransformationFunction::Linear,
+            weight: 1.0,
+            percentile: None,
+            aggregation: None,
+            period: None,
+            event_id: Some(single_id.clone()),
+            external: None,
+        };
+        let mismatched = oracle
+            .create_parlay_announcement(
+                uuid::Uuid::new_v4().to_string(),
+                vec![parameter(EventType::Difficulty)],
+                CombinationMethod::Multiply,
+                None,
+                maturity,
+                ScoreMode::default(),
+            )
+            .await;
+        assert!(mismatched.is_err());
+
+        let leg = parameter(EventType::Hashrate);
+        let parlay_id = uuid::Uuid::new_v4().to_string();
+        oracle
+            .create_parlay_announcement(
+                parlay_id.clone(),
+                vec![leg.clone()],
+                CombinationMethod::Multiply,
+                None,
+                maturity,
+                ScoreMode::default(),
             )
             .await
-            .expect("could not create parlay contract");
-            let attestation = oracle.attest_parlay_contract(id.clone()).await;
+            .unwrap();
 
-            assert!(attestation.is_ok());
+        let (_, provenance) = oracle.parameter_value(&parlay_id, 0, &leg).await.unwrap();
+        assert!(provenance.is_some());
+
+        crate::attestation::save_attestation_outcome(
+            &oracle.pool,
+            single_id.clone(),
+            0.0,
+            81_200,
+            false,
+        )
+        .await
+        .unwrap();
+        let (value, provenance) = oracle.parameter_value(&parlay_id, 0, &leg).await.unwrap();
+        assert_eq!(value, 812.0);
+        assert!(provenance.is_none());
+    }
+
+    #[tokio::test]
+    async fn assigns_unique_event_ids() {
+        let mempool = MempoolClient::new(BASE_URL.to_string());
+        let oracle = setup_ernest_oracle(mempool)
+            .await
+            .with_event_id_scheme(EventIdScheme::Slug);
+        let maturity = chrono::Utc::now().timestamp() as u32 + 1000;
+        let event = |event_id| {
+            CreateEvent::from(SingleEvent {
+                event_id,
+                ..SingleEvent::new(EventType::FeeRate, maturity)
+            })
+        };
+
+        let slug = event_ids::slug("feeRate", maturity).unwrap();
+        let first = oracle.create_event(event(None)).await.unwrap();
+        let second = oracle.create_event(event(None)).await.unwrap();
+        assert!(first.oracle_event.event_id.starts_with(&slug));
+        assert!(second.oracle_event.event_id.starts_with(&slug));
+        assert_ne!(first.oracle_event.event_id, second.oracle_event.event_id);
+
+        let requested = format!("fee-rate-{}", uuid::Uuid::new_v4().simple());
+        let created = oracle
+            .create_event(event(Some(requested.clone())))
+            .await
+            .unwrap();
+        assert_eq!(created.oracle_event.event_id, requested);
+        assert!(oracle.create_event(event(Some(requested))).await.is_err());
+        assert!(oracle
+            .create_event(event(Some("Not A Slug".to_string())))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn delete_event_removes_its_rows() {
+        let mempool = MempoolClient::new(BASE_URL.to_string());
+        let oracle = setup_ernest_oracle(mempool).await;
+        let pool = &oracle.pool;
+        let maturity = chrono::Utc::now().timestamp() as u32 + 1000;
+        let single = oracle
+            .create_event(CreateEvent::from(SingleEvent {
+                description: Some("deleted".to_string()),
+                ..SingleEvent::new(EventType::Hashrate, maturity)
+            }))
+            .await
+            .unwrap();
+        let single_id = single.oracle_event.event_id;
+        let parlay = oracle
+            .create_parlay_announcement(
+                uuid::Uuid::new_v4().to_string(),
+                vec![ParlayParameter {
+                    data_type: EventType::Hashrate,
+                    threshold: 700.0,
+                    range: 100.0,
+                    is_above_threshold: true,
+                    transformation: TransformationFunction::Linear,
+                    weight: 1.0,
+                    percentile: None,
+                    aggregation: None,
+                    period: None,
+                    event_id: Some(single_id.clone()),
+                    external: None,
+                }],
+                CombinationMethod::Multiply,
+                None,
+                maturity,
+                ScoreMode::default(),
+            )
+            .await
+            .unwrap();
+        let parlay_id = parlay.oracle_event.event_id;
+        crate::attestation::save_attestation_outcome(pool, parlay_id.clone(), 0.5, 500, false)
+            .await
+            .unwrap();
+        crate::attestation::save_attestation_data_outcomes(
+            pool,
+            vec![AttestationDataOutcome {
+                event_id: parlay_id.clone(),
+                data_type: EventType::Hashrate.to_string(),
+                normalized_value: 0.5,
+                original_value: 750.0,
+                transformed_value: 0.5,
+            }],
+        )
+        .await
+        .unwrap();
+
+        let storage = &oracle.oracle.storage;
+        assert!(storage.delete_event(&single_id).await.is_err());
+        assert!(storage.delete_event(&parlay_id).await.unwrap());
+        assert!(!storage.delete_event(&parlay_id).await.unwrap());
+        assert!(storage.delete_event(&single_id).await.unwrap());
+
+        for table in [
+            "SELECT COUNT(*) FROM event_nonces WHERE event_id = ANY($1)",
+            "SELECT COUNT(*) FROM event_types WHERE oracle_event_id = ANY($1)",
+            "SELECT COUNT(*) FROM event_metadata WHERE event_id = ANY($1)",
+            "SELECT COUNT(*) FROM parlay_contracts WHERE id = ANY($1)",
+            "SELECT COUNT(*) FROM parlay_parameters WHERE contract_id = ANY($1)",
+            "SELECT COUNT(*) FROM numeric_attestation_outcome WHERE event_id = ANY($1)",
+            "SELECT COUNT(*) FROM numeric_attestation_data_outcome WHERE event_id = ANY($1)",
+        ] {
+            let count = sqlx::query_scalar::<Postgres, i64>(table)
+                .bind(vec![single_id.clone(), parlay_id.clone()])
+                .fetch_one(pool)
+                .await
+                .unwrap();
+            assert_eq!(count, 0, "{}", table);
         }
     }
 
+    #[tokio::test]
+    async fn sign_negative_difficulty_change() {
+        let mock_server = setup_mock_server().await;
+        let mempool = mock_mempool_client(&mock_server);
+        let oracle = setup_ernest_oracle(mempool).await;
+        let announcement = oracle
+            .create_event(CreateEvent::single(
+                EventType::DifficultyChangePercent,
+                chrono::Utc::now().timestamp() as u32 + 1000,
+            ))
+            .await
+            .unwrap();
+        let event_id = announcement.oracle_event.event_id.clone();
+        assert_eq!(announcement.oracle_event.oracle_nonces.len(), 15);
+
+        let outcome = oracle
+            .single_event_outcome(&event_id, &EventType::DifficultyChangePercent)
+            .await
+            .unwrap();
+        assert_eq!(outcome.outcome, -247);
+
+        let attestation = oracle
+            .oracle
+            .sign_numeric_event(event_id, outcome.outcome)
+            .await
+            .unwrap();
+        assert_eq!(attestation.outcomes[0], "-");
+        assert!(attestation.validate(&oracle.secp, &announcement).is_ok());
+    }
+
+    #[tokio::test]
+    async fn search_events_by_tag_and_text() {
+        let mempool = MempoolClient::new(BASE_URL.to_string());
+        let oracle = setup_ernest_oracle(mempool).await;
+        let tag = uuid::Uuid::new_v4().to_string();
+        let announcement = oracle
+            .create_event(CreateEvent::from(SingleEvent {
+                description: Some("Searchable mempool congestion event".to_string()),
+                tags: vec![tag.clone()],
+                ..SingleEvent::new(
+                    EventType::FeeRate,
+                    chrono::Utc::now().timestamp() as u32 + 1000,
+                )
+            }))
+            .await
+            .unwrap();
+        let event_id = announcement.oracle_event.event_id;
+        let pool = &oracle.oracle.storage.pool;
+
+        let by_tag = crate::metadata::search_events(pool, None, Some(&tag), 10)
+            .await
+            .unwrap();
+        assert_eq!(by_tag.len(), 1);
+        assert_eq!(by_tag[0].event_id, event_id);
+        assert!(!by_tag[0].is_signed);
+
+        let by_text = crate::metadata::search_events(pool, Some("congestion"), Some(&tag), 10)
+            .await
+            .unwrap();
+        assert_eq!(by_text.len(), 1);
+
+        let no_match = crate::metadata::search_events(pool, Some("difficulty"), Some(&tag), 10)
+            .await
+            .unwrap();
+        assert!(no_match.is_empty());
+
+        let wildcard = crate::metadata::search_events(pool, Some("%"), Some(&tag), 10)
+            .await
+            .unwrap();
+        assert!(wildcard.is_empty());
+    }
+
+    #[tokio::test]
+    async fn hidden_events_are_unlisted_but_served_by_id() {
+        let mempool = MempoolClient::new(BASE_URL.to_string());
+        let oracle = setup_ernest_oracle(mempool).await;
+        let tag = uuid::Uuid::new_v4().to_string();
+        let announcement = oracle
+            .create_event(CreateEvent::from(SingleEvent {
+                tags: vec![tag.clone()],
+                ..SingleEvent::new(
+                    EventType::FeeRate,
+                    chrono::Utc::now().timestamp() as u32 + 1000,
+                )
+            }))
+            .await
+            .unwrap();
+        let event_id = announcement.oracle_event.event_id.clone();
+        let storage = &oracle.oracle.storage;
+
+        oracle.set_hidden(&event_id, true).await.unwrap();
+        let listed = storage.oracle_event_data().await.unwrap();
+        assert!(!listed.iter().any(|e| e.event_id == event_id));
+        let found = crate::metadata::search_events(&storage.pool, None, Some(&tag), 10)
+            .await
+            .unwrap();
+        assert!(found.is_empty());
+        assert!(storage.is_announced(&event_id).await.unwrap());
+        let event = storage.get_event(event_id.clone()).await.unwrap().unwrap();
+        assert_eq!(event.announcement, announcement);
+
+        oracle.set_hidden(&event_id, false).await.unwrap();
+        let listed = storage.oracle_event_data().await.unwrap();
+        assert!(listed.iter().any(|e| e.event_id == event_id));
+        assert!(oracle.set_hidden("no-such-event", true).await.is_err());
+    }
+
     #[tokio::test]
     async fn retrieve_matured_unsigned_events() {
         let mempool = MempoolClient::new(BASE_URL.to_string());
@@ -374,6 +1868,11 @@ mod tests {
                 is_above_threshold: true,
                 weight: 1.0,
                 transformation: TransformationFunction::Linear,
+                percentile: None,
+                aggregation: None,
+                period: None,
+                event_id: None,
+                external: None,
             },
             ParlayParameter {
                 data_type: EventType::BlockFees,
@@ -382,6 +1881,11 @@ mod tests {
                 is_above_threshold: true,
                 weight: 1.0,
                 transformation: TransformationFunction::Linear,
+                percentile: None,
+                aggregation: None,
+                period: None,
+                event_id: None,
+                external: None,
             },
         ];
 
@@ -393,6 +1897,12 @@ mod tests {
                 combination_method: CombinationMethod::WeightedAverage,
                 max_normalized_value: None,
                 event_maturity_epoch: expiry,
+                score_mode: ScoreMode::default(),
+                description: Some("Matured unsigned test event".to_string()),
+                tags: vec!["test".to_string()],
+                announce_at: None,
+                settlement_delay: None,
+                event_id: None,
             })
             .await
             .unwrap();
@@ -405,10 +1915,159 @@ mod tests {
             .get_matured_unsigned_event_ids_by_type("parlay")
             .await
             .unwrap();
-        assert!(events.len() > 0);
+        assert!(!events.is_empty());
         let included = events
             .iter()
             .find(|(event_id, _)| event_id == &announcement.oracle_event.event_id);
         assert!(included.is_some());
     }
+
+    #[tokio::test]
+    async fn settlement_delay_defers_signing() {
+        let mempool = MempoolClient::new(BASE_URL.to_string());
+        let oracle = setup_ernest_oracle(mempool)
+            .await
+            .with_settlement_delay(600)
+            .unwrap();
+        let matured = chrono::Utc::now().timestamp() as u32 - 60;
+        let event = |settlement_delay| {
+            CreateEvent::from(SingleEvent {
+                settlement_delay,
+                ..SingleEvent::new(EventType::Hashrate, matured)
+            })
+        };
+        assert!(oracle
+            .create_event(event(Some(MAX_SETTLEMENT_DELAY + 1)))
+            .await
+            .is_err());
+
+        let delayed = oracle.create_event(event(None)).await.unwrap();
+        let immediate = oracle.create_event(event(Some(0))).await.unwrap();
+        let delayed_id = delayed.oracle_event.event_id;
+        let immediate_id = immediate.oracle_event.event_id;
+        assert_eq!(
+            oracle.settles_at(&delayed_id).await.unwrap(),
+            matured as i64 + 600
+        );
+        assert_eq!(
+            oracle.settles_at(&immediate_id).await.unwrap(),
+            matured as i64
+        );
+
+        let events = oracle
+            .get_matured_unsigned_event_ids_by_type("single")
+            .await
+            .unwrap();
+        assert!(events.iter().any(|(event_id, _)| *event_id == immediate_id));
+        assert!(!events.iter().any(|(event_id, _)| *event_id == delayed_id));
+    }
+
+    #[tokio::test]
+    async fn held_event_is_not_signed_automatically() {
+        let mempool = MempoolClient::new(BASE_URL.to_string());
+        let oracle = setup_ernest_oracle(mempool).await;
+        let announcement = oracle
+            .create_event(CreateEvent::single(
+                EventType::Hashrate,
+                chrono::Utc::now().timestamp() as u32 - 60,
+            ))
+            .await
+            .unwrap();
+        let event_id = announcement.oracle_event.event_id;
+        let matured = || async {
+            oracle
+                .get_matured_unsigned_event_ids_by_type("single")
+                .await
+                .unwrap()
+                .into_iter()
+                .any(|(id, _)| id == event_id)
+        };
+
+        oracle
+            .set_hold(&event_id, true, Some("hashrate spike"))
+            .await
+            .unwrap();
+        assert!(oracle.oracle.storage.is_held(&event_id).await.unwrap());
+        assert!(!matured().await);
+        let error = oracle.ensure_due(&event_id).await.unwrap_err();
+        let error = error.downcast_ref::<crate::OracleServerError>().unwrap();
+        assert_eq!(error.code, crate::ErrorCode::NotSigned);
+
+        // Held events still show as pending
+        let now = chrono::Utc::now().timestamp();
+        let pending = oracle.pending_events(now).await.unwrap();
+        let held = pending.iter().find(|e| e.event_id == event_id).unwrap();
+        assert!(held.hold);
+        assert_eq!(held.hold_reason.as_deref(), Some("hashrate spike"));
+        assert!(held.pending_secs >= 60);
+        let earlier = oracle.pending_events(now - 120).await.unwrap();
+        assert!(earlier.iter().all(|e| e.event_id != event_id));
+
+        oracle.set_hold(&event_id, false, None).await.unwrap();
+        assert!(!oracle.oracle.storage.is_held(&event_id).await.unwrap());
+        assert!(matured().await);
+        oracle.ensure_due(&event_id).await.unwrap();
+        assert!(oracle.set_hold("unknown", true, None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn import_kormir_event() {
+        let mempool = MempoolClient::new(BASE_URL.to_string());
+        let oracle = setup_ernest_oracle(mempool).await;
+        let announcement = oracle
+            .create_event(CreateEvent::single(
+                EventType::Hashrate,
+                chrono::Utc::now().timestamp() as u32 + 1000,
+            ))
+            .await
+            .unwrap();
+        let event_id = announcement.oracle_event.event_id.clone();
+        let exported = oracle
+            .oracle
+            .storage
+            .get_event(event_id.clone())
+            .await
+            .unwrap()
+            .unwrap();
+        let exported: kormir::storage::OracleEventData =
+            serde_json::from_str(&serde_json::to_string(&exported).unwrap()).unwrap();
+
+        let mut foreign = exported.clone();
+        foreign.announcement.oracle_public_key = kormir::bitcoin::XOnlyPublicKey::from_slice(
+            &foreign.announcement.oracle_event.oracle_nonces[0].serialize(),
+        )
+        .unwrap();
+        assert!(oracle.import_event(&foreign).await.is_err());
+        assert_eq!(
+            oracle.import_event(&exported).await.unwrap(),
+            ImportResult::AlreadyExists
+        );
+
+        sqlx::query("DELETE FROM events WHERE event_id = $1")
+            .bind(&event_id)
+            .execute(&oracle.oracle.storage.pool)
+            .await
+            .unwrap();
+        assert_eq!(
+            oracle.import_event(&exported).await.unwrap(),
+            ImportResult::Imported
+        );
+        assert!(oracle
+            .signed_attestation(&event_id)
+            .await
+            .unwrap()
+            .is_none());
+        let attestation = oracle
+            .oracle
+            .sign_numeric_event(event_id.clone(), 42)
+            .await
+            .unwrap();
+        assert!(attestation
+            .validate(&kormir::bitcoin::key::Secp256k1::new(), &announcement)
+            .is_ok());
+        assert_eq!(
+            oracle.signed_attestation(&event_id).await.unwrap(),
+            Some(attestation)
+        );
+    }
 }
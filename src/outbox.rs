@@ -0,0 +1,314 @@
+//! Delivering announcements and attestations to notification sinks and nostr relays.
+//!
+//! Messages are written to the `outbox` table in the transaction that saves the announcement or
+//! the signatures they are about, and [`deliver_loop`] delivers them until every sink and relay
+//! took them. A crash between saving and publishing leaves the message in the table, so
+//! consumers see every event at least once. Messages of events that are not announced yet wait
+//! until the event is.
+
+use std::{sync::Arc, time::Duration};
+
+use sqlx::{types::Json, FromRow, PgConnection, PgPool, Postgres};
+use tokio::sync::watch;
+
+use crate::{
+    nostr,
+    notifications::{Notification, Notifier},
+    oracle::ErnestOracle,
+    series, OracleServerState,
+};
+
+/// Seconds between looking for messages to deliver.
+pub const OUTBOX_INTERVAL_SECS: u64 = 5;
+
+/// Messages delivered per pass.
+pub const DELIVERY_BATCH: i64 = 50;
+
+/// Deliveries tried before a message is left for the operator.
+pub const MAX_DELIVERY_ATTEMPTS: i32 = 12;
+
+/// Wait before the first retry, doubled on every failure up to [`MAX_RETRY_DELAY_SECS`].
+pub const RETRY_DELAY_SECS: i64 = 5;
+pub const MAX_RETRY_DELAY_SECS: i64 = 60 * 60;
+
+/// Queue `message` about `event_id`, in the caller's transaction.
+pub async fn enqueue(
+    conn: &mut PgConnection,
+    event_id: &str,
+    message: &Notification,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("INSERT INTO outbox (event_id, message) VALUES ($1, $2)")
+        .bind(event_id)
+        .bind(Json(message))
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+#[derive(Debug, FromRow)]
+pub struct OutboxRow {
+    pub id: i64,
+    pub event_id: String,
+    pub message: Json<Notification>,
+    /// Deliveries tried, this one included
+    pub attempts: i32,
+}
+
+/// Claim the messages due for delivery. Their next attempt is pushed back by the retry delay,
+/// so other replicas skip them and a crash mid-delivery retries them later.
+pub async fn claim(pool: &PgPool, limit: i64) -> anyhow::Result<Vec<OutboxRow>> {
+    let mut rows = sqlx::query_as::<Postgres, OutboxRow>(
+        r#"
+        UPDATE outbox
+        SET attempts = attempts + 1,
+            next_attempt_at = NOW() + LEAST($1 * power(2, attempts), $2) * INTERVAL '1 second'
+        WHERE id IN (
+            SELECT o.id FROM outbox o
+            JOIN events e ON e.event_id = o.event_id
+            WHERE e.announced
+                AND o.next_attempt_at <= NOW()
+                AND o.attempts < $3
+            ORDER BY o.id
+            LIMIT $4
+            FOR UPDATE OF o SKIP LOCKED
+        )
+        RETURNING id, event_id, message, attempts
+        "#,
+    )
+    .bind(RETRY_DELAY_SECS as f64)
+    .bind(MAX_RETRY_DELAY_SECS as f64)
+    .bind(MAX_DELIVERY_ATTEMPTS)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    rows.sort_by_key(|row| row.id);
+    Ok(rows)
+}
+
+/// Publish `message` to `relays`, then send it to the notification sinks. Relays drop events
+/// they have, so a retry after a failing sink publishes nothing twice.
+pub async fn deliver(
+    oracle: &ErnestOracle,
+    notifier: &Notifier,
+    relays: &[String],
+    message: &Notification,
+) -> anyhow::Result<()> {
+    if !relays.is_empty() {
+        let event_ids = match message {
+            Notification::EventAnnounced { event_id, .. }
+            | Notification::EventSigned { event_id, .. } => vec![event_id.clone()],
+            Notification::SeriesAnnounced { series_id, .. } => series::get_series(
+                &oracle.oracle.storage.pool,
+                series_id,
+                oracle.oracle.public_key(),
+            )
+            .await?
+            .events
+            .into_iter()
+            .map(|entry| entry.event_id)
+            .collect(),
+            _ => vec![],
+        };
+        for event_id in event_ids {
+            let rebroadcast = nostr::rebroadcast(oracle, relays, &event_id).await?;
+            if !rebroadcast.failed.is_empty() {
+                return Err(anyhow::anyhow!(
+                    "Relays did not take the event. event_id={} failed={:?}",
+                    event_id,
+                    rebroadcast.failed
+                ));
+            }
+        }
+    }
+    notifier.deliver(message).await
+}
+
+/// Deliver the messages that are due, returning how many were delivered.
+pub async fn drain(
+    oracle: &ErnestOracle,
+    notifier: &Notifier,
+    relays: &[String],
+) -> anyhow::Result<usize> {
+    let pool = &oracle.oracle.storage.pool;
+    let mut delivered = 0;
+    for row in claim(pool, DELIVERY_BATCH).await? {
+        match deliver(oracle, notifier, relays, &row.message).await {
+            Ok(()) => {
+                sqlx::query("DELETE FROM outbox WHERE id = $1")
+                    .bind(row.id)
+                    .execute(pool)
+                    .await?;
+                delivered += 1;
+            }
+            Err(e) => {
+                if row.attempts >= MAX_DELIVERY_ATTEMPTS {
+                    log::error!(
+                        "Giving up on delivering a message. event_id={} id={} attempts={} error={}",
+                        row.event_id,
+                        row.id,
+                        row.attempts,
+                        e
+                    );
+                } else {
+                    log::warn!(
+                        "Could not deliver a message, retrying later. event_id={} id={} attempts={} error={}",
+                        row.event_id,
+                        row.id,
+                        row.attempts,
+                        e
+                    );
+                }
+                sqlx::query("UPDATE outbox SET last_error = $1 WHERE id = $2")
+                    .bind(e.to_string())
+                    .bind(row.id)
+                    .execute(pool)
+                    .await?;
+            }
+        }
+    }
+    Ok(delivered)
+}
+
+pub async fn deliver_loop(state: Arc<OracleServerState>, mut stop_signal: watch::Receiver<bool>) {
+    let mut timer = tokio::time::interval(Duration::from_secs(OUTBOX_INTERVAL_SECS));
+    loop {
+        tokio::select! {
+            _ = stop_signal.changed() => {
+                if *stop_signal.borrow() {
+                    break;
+                }
+            }
+            _ = timer.tick() => {
+                if let Err(e) = drain(&state.oracle, &state.notifier, &state.nostr.relays).await {
+                    log::error!("Could not deliver outbox messages. error={}", e);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        events::EventType,
+        mempool::MempoolClient,
+        notifications::NotificationConfig,
+        routes::CreateEvent,
+        test_util::{setup_ernest_oracle, SingleEvent},
+    };
+    use wiremock::{
+        matchers::{body_json, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    fn event(maturity: u32, announce_at: Option<u32>) -> CreateEvent {
+        CreateEvent::from(SingleEvent {
+            announce_at,
+            ..SingleEvent::new(EventType::Hashrate, maturity)
+        })
+    }
+
+    async fn messages(pool: &PgPool, event_id: &str) -> Vec<(Json<Notification>, i32)> {
+        sqlx::query_as("SELECT message, attempts FROM outbox WHERE event_id = $1 ORDER BY id")
+            .bind(event_id)
+            .fetch_all(pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn delivers_messages_until_the_sinks_take_them() {
+        let oracle =
+            setup_ernest_oracle(MempoolClient::new("http://127.0.0.1:9/api/v1".to_string())).await;
+        let pool = &oracle.oracle.storage.pool;
+        let now = chrono::Utc::now().timestamp() as u32;
+        let event_id = oracle
+            .create_event(event(now + 3600, None))
+            .await
+            .unwrap()
+            .oracle_event
+            .event_id;
+        let scheduled_id = oracle
+            .create_event(event(now + 3600, Some(now + 1800)))
+            .await
+            .unwrap()
+            .oracle_event
+            .event_id;
+        // Messages of other tests are not delivered to this test's sink
+        sqlx::query(
+            "UPDATE outbox SET next_attempt_at = NOW() + INTERVAL '1 hour' WHERE event_id <> ALL($1)",
+        )
+        .bind([&event_id, &scheduled_id])
+        .execute(pool)
+        .await
+        .unwrap();
+
+        let server = MockServer::start().await;
+        let announced = Notification::EventAnnounced {
+            event_id: event_id.clone(),
+            maturity: now + 3600,
+        };
+        Mock::given(method("POST"))
+            .and(path("/webhook"))
+            .and(body_json(&announced))
+            .respond_with(ResponseTemplate::new(500))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/webhook"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        let notifier = Notifier::new(
+            serde_json::from_value::<NotificationConfig>(serde_json::json!({
+                "sinks": [{ "type": "webhook", "url": format!("{}/webhook", server.uri()) }]
+            }))
+            .unwrap(),
+        );
+
+        // The failed delivery is kept for a retry, the scheduled event is not announced yet
+        drain(&oracle, &notifier, &[]).await.unwrap();
+        assert_eq!(messages(pool, &event_id).await[0].1, 1);
+        assert_eq!(messages(pool, &scheduled_id).await[0].1, 0);
+
+        sqlx::query("UPDATE outbox SET next_attempt_at = NOW() WHERE event_id = $1")
+            .bind(&event_id)
+            .execute(pool)
+            .await
+            .unwrap();
+        drain(&oracle, &notifier, &[]).await.unwrap();
+        assert!(messages(pool, &event_id).await.is_empty());
+
+        // Signing queues the attestation in the transaction saving the signatures
+        oracle
+            .oracle
+            .sign_numeric_event(event_id.clone(), 42)
+            .await
+            .unwrap();
+        let signed = messages(pool, &event_id).await;
+        assert_eq!(
+            signed[0].0 .0,
+            Notification::EventSigned {
+                event_id: event_id.clone(),
+                outcome: 42
+            }
+        );
+
+        sqlx::query("UPDATE events SET announce_at = NOW() WHERE event_id = $1")
+            .bind(&scheduled_id)
+            .execute(pool)
+            .await
+            .unwrap();
+        oracle
+            .oracle
+            .storage
+            .publish_due_announcements()
+            .await
+            .unwrap();
+        drain(&oracle, &notifier, &[]).await.unwrap();
+        assert!(messages(pool, &scheduled_id).await.is_empty());
+        assert!(messages(pool, &event_id).await.is_empty());
+    }
+}
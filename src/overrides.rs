@@ -0,0 +1,237 @@
+//! Signing an event with an outcome given by an admin instead of the data source.
+//!
+//! An override is prepared first, which checks the event can be signed with the outcome and
+//! returns the exact digits that will be attested along with a token. Nothing is signed until
+//! the token is committed, so a mistyped outcome can still be caught. Prepared overrides expire
+//! after [`OVERRIDE_EXPIRY_SECS`].
+
+use chrono::{DateTime, Utc};
+use kormir::{storage::Storage, EventDescriptor, OracleAttestation};
+use serde::{Deserialize, Serialize};
+use sqlx::{prelude::FromRow, Postgres};
+use uuid::Uuid;
+
+use crate::{attestation::AttestationRecord, audit::SigningSource, oracle::ErnestOracle};
+
+pub const OVERRIDE_EXPIRY_SECS: i64 = 10 * 60;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrepareOverride {
+    pub event_id: String,
+    /// The number to attest, as the watcher would sign it
+    pub outcome: i64,
+    /// Why the data source is not used, kept with the override
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitOverride {
+    pub token: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct PreparedOverride {
+    pub token: String,
+    pub event_id: String,
+    pub outcome: i64,
+    /// Digit outcomes that will be signed, the sign first for signed events
+    pub digits: Vec<String>,
+    pub reason: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Digit outcomes kormir signs for `outcome` on a base 2 event.
+pub fn encode_digits(outcome: i64, nb_digits: u16, is_signed: bool) -> anyhow::Result<Vec<String>> {
+    let max_outcome = (1i64 << nb_digits) - 1;
+    let min_outcome = if is_signed { -max_outcome } else { 0 };
+    if outcome < min_outcome || outcome > max_outcome {
+        return Err(anyhow::anyhow!(
+            "Outcome does not fit the announced digits. outcome={} nb_digits={} is_signed={}",
+            outcome,
+            nb_digits,
+            is_signed
+        ));
+    }
+    let digits = format!("{:0width$b}", outcome.abs(), width = nb_digits as usize)
+        .chars()
+        .map(|digit| digit.to_string())
+        .collect::<Vec<_>>();
+    let sign = is_signed.then(|| if outcome < 0 { "-" } else { "+" }.to_string());
+    Ok(sign.into_iter().chain(digits).collect())
+}
+
+/// Check `event_id` can be signed with `outcome` and store the override for commit.
+pub async fn prepare(
+    oracle: &ErnestOracle,
+    request: PrepareOverride,
+) -> anyhow::Result<PreparedOverride> {
+    if request.reason.trim().is_empty() {
+        return Err(anyhow::anyhow!("An override needs a reason."));
+    }
+    let storage = &oracle.oracle.storage;
+    let event = storage
+        .get_event(request.event_id.clone())
+        .await?
+        .ok_or(anyhow::anyhow!(
+            "Event not found. event_id={}",
+            request.event_id
+        ))?;
+    if !event.signatures.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Event is already signed. event_id={}",
+            request.event_id
+        ));
+    }
+    let EventDescriptor::DigitDecompositionEvent(descriptor) =
+        &event.announcement.oracle_event.event_descriptor
+    else {
+        return Err(anyhow::anyhow!(
+            "Only numeric events can be overridden. event_id={}",
+            request.event_id
+        ));
+    };
+    if descriptor.base != 2 {
+        return Err(anyhow::anyhow!(
+            "Only base 2 events can be signed. event_id={} base={}",
+            request.event_id,
+            descriptor.base
+        ));
+    }
+    let digits = encode_digits(request.outcome, descriptor.nb_digits, descriptor.is_signed)?;
+
+    Ok(sqlx::query_as::<Postgres, PreparedOverride>(
+        r#"
+        INSERT INTO attestation_overrides (token, event_id, outcome, digits, reason, expires_at)
+        VALUES ($1, $2, $3, $4, $5, NOW() + make_interval(secs => $6))
+        RETURNING token, event_id, outcome, digits, reason, expires_at
+        "#,
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(&request.event_id)
+    .bind(request.outcome)
+    .bind(&digits)
+    .bind(request.reason.trim())
+    .bind(OVERRIDE_EXPIRY_SECS as f64)
+    .fetch_one(&storage.pool)
+    .await?)
+}
+
+/// Sign the event of a prepared override with its outcome.
+pub async fn commit(
+    oracle: &ErnestOracle,
+    token: &str,
+) -> anyhow::Result<(PreparedOverride, OracleAttestation)> {
+    let pool = &oracle.oracle.storage.pool;
+    let prepared = sqlx::query_as::<Postgres, PreparedOverride>(
+        r#"
+        SELECT token, event_id, outcome, digits, reason, expires_at
+        FROM attestation_overrides
+        WHERE token = $1 AND committed_at IS NULL AND expires_at > NOW()
+        "#,
+    )
+    .bind(token)
+    .fetch_optional(pool)
+    .await?
+    .ok_or(anyhow::anyhow!(
+        "No pending override for this token, it may have expired or been committed."
+    ))?;
+
+    let Some(lock) = oracle.lock_unsigned_event(&prepared.event_id).await? else {
+        return Err(anyhow::anyhow!(
+            "Event is already signed or being signed. event_id={}",
+            prepared.event_id
+        ));
+    };
+    let result = async {
+        let attestation = oracle
+            .sign_and_record(
+                AttestationRecord::value(prepared.event_id.clone(), prepared.outcome),
+                SigningSource::Override,
+            )
+            .await?;
+        if attestation.outcomes != prepared.digits {
+            log::error!(
+                "Override signed other digits than prepared. event_id={} prepared={:?} signed={:?}",
+                prepared.event_id,
+                prepared.digits,
+                attestation.outcomes
+            );
+        }
+        sqlx::query("UPDATE attestation_overrides SET committed_at = NOW() WHERE token = $1")
+            .bind(token)
+            .execute(pool)
+            .await?;
+        Ok::<_, anyhow::Error>(attestation)
+    }
+    .await;
+    lock.release().await?;
+    let attestation = result?;
+    log::warn!(
+        "Signed event with a manual override. event_id={} outcome={} reason={}",
+        prepared.event_id,
+        prepared.outcome,
+        prepared.reason
+    );
+    Ok((prepared, attestation))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        events::EventType,
+        mempool::{MempoolClient, BASE_URL},
+        routes::CreateEvent,
+        test_util::{setup_ernest_oracle, SingleEvent},
+    };
+
+    #[test]
+    fn encodes_digits_like_kormir() {
+        assert_eq!(encode_digits(5, 4, false).unwrap(), ["0", "1", "0", "1"]);
+        assert_eq!(encode_digits(-2, 2, true).unwrap(), ["-", "1", "0"]);
+        assert!(encode_digits(16, 4, false).is_err());
+        assert!(encode_digits(-1, 4, false).is_err());
+    }
+
+    #[tokio::test]
+    async fn commits_prepared_override_once() {
+        let mempool = MempoolClient::new(BASE_URL.to_string());
+        let oracle = setup_ernest_oracle(mempool).await;
+        let announcement = oracle
+            .create_event(CreateEvent::from(SingleEvent {
+                nb_digits: Some(8),
+                ..SingleEvent::new(
+                    EventType::Hashrate,
+                    chrono::Utc::now().timestamp() as u32 + 1000,
+                )
+            }))
+            .await
+            .unwrap();
+        let event_id = announcement.oracle_event.event_id;
+        let request = |outcome| PrepareOverride {
+            event_id: event_id.clone(),
+            outcome,
+            reason: "mempool.space is down".to_string(),
+        };
+        assert!(prepare(&oracle, request(256)).await.is_err());
+
+        let prepared = prepare(&oracle, request(200)).await.unwrap();
+        assert_eq!(prepared.digits, ["1", "1", "0", "0", "1", "0", "0", "0"]);
+        let (committed, attestation) = commit(&oracle, &prepared.token).await.unwrap();
+        assert_eq!(committed, prepared);
+        assert_eq!(attestation.outcomes, prepared.digits);
+        // Recorded with the signatures, so recovery never takes the event for unsigned
+        assert_eq!(
+            crate::attestation::get_attested_value(&oracle.oracle.storage.pool, &event_id)
+                .await
+                .unwrap(),
+            Some(200)
+        );
+
+        assert!(commit(&oracle, &prepared.token).await.is_err());
+        assert!(prepare(&oracle, request(100)).await.is_err());
+    }
+}
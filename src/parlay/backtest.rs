@@ -0,0 +1,265 @@
+//! Replay a proposed parlay over the sampled metric history.
+//!
+//! History is sampled with each data type's default options, so leg percentiles, aggregations
+//! and periods are not reflected in a backtest.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+use super::contract::{
+    score_parameters, validate_weights, CombinationMethod, ScoreMode, SCORING_VERSION,
+};
+use super::parameter::ParlayParameter;
+use crate::history::{self, MetricHistory, MetricPoint};
+use crate::oracle::DEFAULT_MAX_NORMALIZED_VALUE;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BacktestRequest {
+    pub parameters: Vec<ParlayParameter>,
+    pub combination_method: CombinationMethod,
+    #[serde(default)]
+    pub score_mode: ScoreMode,
+    pub max_normalized_value: Option<u64>,
+    /// Unix timestamp in seconds, defaults to thirty days before `to`
+    pub from: Option<i64>,
+    /// Unix timestamp in seconds, defaults to now
+    pub to: Option<i64>,
+}
+
+/// The value the parlay would have attested to had it matured at `at`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BacktestPoint {
+    pub at: DateTime<Utc>,
+    pub combined_score: f64,
+    pub attestable_value: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Backtest {
+    pub points: Vec<BacktestPoint>,
+    /// Number of points with a non-zero attestable value
+    pub struck: usize,
+    /// Share of points with a non-zero attestable value, zero without points
+    pub strike_rate: f64,
+    pub min: Option<u64>,
+    pub max: Option<u64>,
+    pub mean: Option<f64>,
+}
+
+impl Backtest {
+    fn new(points: Vec<BacktestPoint>) -> Self {
+        let values = points
+            .iter()
+            .map(|p| p.attestable_value)
+            .collect::<Vec<_>>();
+        let struck = values.iter().filter(|value| **value > 0).count();
+        let strike_rate = if values.is_empty() {
+            0.0
+        } else {
+            struck as f64 / values.len() as f64
+        };
+        let mean =
+            (!values.is_empty()).then(|| values.iter().sum::<u64>() as f64 / values.len() as f64);
+        Self {
+            struck,
+            strike_rate,
+            min: values.iter().copied().min(),
+            max: values.iter().copied().max(),
+            mean,
+            points,
+        }
+    }
+}
+
+/// Score the parlay at every sampling time where each leg has a value, using the most recent
+/// sample of each leg at that time.
+pub fn backtest(
+    parameters: &[ParlayParameter],
+    combination_method: &CombinationMethod,
+    score_mode: &ScoreMode,
+    max_normalized_value: u64,
+    histories: &[MetricHistory],
+) -> Backtest {
+    let series = histories
+        .iter()
+        .map(|history| (history.data_type.to_string(), history.points.as_slice()))
+        .collect::<HashMap<_, _>>();
+    let legs = parameters
+        .iter()
+        .map(|parameter| {
+            series
+                .get(&parameter.data_type.to_string())
+                .copied()
+                .unwrap_or_default()
+        })
+        .collect::<Vec<&[MetricPoint]>>();
+
+    let mut times = legs
+        .iter()
+        .flat_map(|points| points.iter().map(|point| point.sampled_at))
+        .collect::<Vec<_>>();
+    times.sort();
+    times.dedup();
+
+    let points = times
+        .into_iter()
+        .filter_map(|at| {
+            let outcomes = legs
+                .iter()
+                .map(|points| {
+                    points
+                        .iter()
+                        .take_while(|point| point.sampled_at <= at)
+                        .last()
+                        .map(|point| point.value)
+                })
+                .collect::<Option<Vec<_>>>()?;
+            let score = score_parameters(
+                parameters,
+                combination_method,
+                score_mode,
+                SCORING_VERSION,
+                &outcomes,
+            );
+            Some(BacktestPoint {
+                at,
+                combined_score: score.combined_score,
+                attestable_value: score.attestable_value(max_normalized_value).value,
+            })
+        })
+        .collect();
+
+    Backtest::new(points)
+}
+
+pub async fn run_backtest(pool: &PgPool, request: BacktestRequest) -> anyhow::Result<Backtest> {
+    if request.parameters.is_empty() {
+        return Err(anyhow::anyhow!("Parameters must be non-empty"));
+    }
+    validate_weights(&request.parameters)?;
+    if request.parameters.iter().any(|p| p.external.is_some()) {
+        return Err(anyhow::anyhow!(
+            "Legs on external events have no history to backtest on"
+        ));
+    }
+
+    let to = match request.to {
+        Some(to) => history::parse_timestamp(to)?,
+        None => Utc::now(),
+    };
+    let from = match request.from {
+        Some(from) => history::parse_timestamp(from)?,
+        None => to - chrono::Duration::days(history::DEFAULT_HISTORY_DAYS),
+    };
+    if from > to {
+        return Err(anyhow::anyhow!("from must not be after to"));
+    }
+
+    let mut histories = Vec::new();
+    for parameter in &request.parameters {
+        if histories
+            .iter()
+            .any(|h: &MetricHistory| h.data_type == parameter.data_type)
+        {
+            continue;
+        }
+        histories.push(history::get_history(pool, &parameter.data_type, from, to).await?);
+    }
+
+    Ok(backtest(
+        &request.parameters,
+        &request.combination_method,
+        &request.score_mode,
+        request
+            .max_normalized_value
+            .unwrap_or(DEFAULT_MAX_NORMALIZED_VALUE),
+        &histories,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{events::EventType, parlay::parameter::TransformationFunction};
+
+    fn parameter(data_type: EventType, threshold: f64) -> ParlayParameter {
+        ParlayParameter {
+            data_type,
+            threshold,
+            range: 100.0,
+            is_above_threshold: true,
+            transformation: TransformationFunction::Linear,
+            weight: 1.0,
+            percentile: None,
+            aggregation: None,
+            period: None,
+            event_id: None,
+            external: None,
+        }
+    }
+
+    fn history(data_type: EventType, points: &[(i64, f64)]) -> MetricHistory {
+        MetricHistory {
+            data_type,
+            points: points
+                .iter()
+                .map(|(at, value)| MetricPoint {
+                    value: *value,
+                    sampled_at: DateTime::from_timestamp(*at, 0).unwrap(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn replays_latest_sample_of_each_leg() {
+        let parameters = [
+            parameter(EventType::Hashrate, 700.0),
+            parameter(EventType::Difficulty, 100.0),
+        ];
+        let histories = [
+            history(EventType::Hashrate, &[(0, 750.0), (10, 800.0), (20, 650.0)]),
+            history(EventType::Difficulty, &[(5, 200.0)]),
+        ];
+        let backtest = backtest(
+            &parameters,
+            &CombinationMethod::Multiply,
+            &ScoreMode::Continuous,
+            1000,
+            &histories,
+        );
+
+        // Nothing before the first difficulty sample at 5
+        let values = backtest
+            .points
+            .iter()
+            .map(|p| (p.at.timestamp(), p.attestable_value))
+            .collect::<Vec<_>>();
+        assert_eq!(values, vec![(5, 500), (10, 1000), (20, 0)]);
+        assert_eq!(backtest.struck, 2);
+        assert!((backtest.strike_rate - 2.0 / 3.0).abs() < 1e-9);
+        assert_eq!(backtest.min, Some(0));
+        assert_eq!(backtest.max, Some(1000));
+        assert_eq!(backtest.mean, Some(500.0));
+    }
+
+    #[test]
+    fn empty_history() {
+        let backtest = backtest(
+            &[parameter(EventType::Hashrate, 700.0)],
+            &CombinationMethod::Multiply,
+            &ScoreMode::Binary,
+            1000,
+            &[],
+        );
+        assert!(backtest.points.is_empty());
+        assert_eq!(backtest.strike_rate, 0.0);
+        assert_eq!(backtest.mean, None);
+    }
+}
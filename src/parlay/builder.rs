@@ -0,0 +1,252 @@
+//! Building parlay create requests on the client.
+//!
+//! [`ParlayBuilder::build`] checks the request with the rules the oracle applies on creation,
+//! see [`crate::validation`], so an invalid contract fails locally with the same field errors
+//! instead of a round trip:
+//!
+//! ```
+//! use std::time::Duration;
+//!
+//! use ernest_oracle::events::EventType;
+//! use ernest_oracle::parlay::{
+//!     builder::ParlayBuilder, contract::CombinationMethod, parameter::ParlayParameter,
+//! };
+//!
+//! let event = ParlayBuilder::new()
+//!     .leg(ParlayParameter::linear(EventType::Hashrate, 700.0, 100.0, true))
+//!     .leg(ParlayParameter::linear(EventType::FeeRate, 20.0, 10.0, false).with_weight(2.0))
+//!     .combination(CombinationMethod::WeightedAverage)
+//!     .maturity_in(Duration::from_secs(24 * 60 * 60))
+//!     .build()
+//!     .unwrap();
+//!
+//! let errors = ParlayBuilder::new()
+//!     .leg(ParlayParameter::linear(EventType::Hashrate, 700.0, -1.0, true))
+//!     .build()
+//!     .unwrap_err();
+//! assert_eq!(errors.0[0].field, "eventMaturityEpoch");
+//! assert_eq!(errors.0[1].field, "parameters[0].range");
+//! ```
+
+use std::time::Duration;
+
+use super::contract::{CombinationMethod, ScoreMode};
+use super::parameter::ParlayParameter;
+use crate::routes::CreateEvent;
+use crate::validation::{self, ValidationErrors};
+
+#[derive(Debug, Clone, Default)]
+enum Maturity {
+    #[default]
+    Unset,
+    At(u32),
+    In(Duration),
+}
+
+/// A [`CreateEvent::Parlay`] under construction.
+#[derive(Debug, Clone, Default)]
+pub struct ParlayBuilder {
+    parameters: Vec<ParlayParameter>,
+    combination_method: Option<CombinationMethod>,
+    max_normalized_value: Option<u64>,
+    maturity: Maturity,
+    score_mode: ScoreMode,
+    description: Option<String>,
+    tags: Vec<String>,
+    announce_at: Option<u32>,
+    settlement_delay: Option<u32>,
+    event_id: Option<String>,
+}
+
+impl ParlayBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn leg(mut self, parameter: ParlayParameter) -> Self {
+        self.parameters.push(parameter);
+        self
+    }
+
+    /// How the legs are combined, [`CombinationMethod::Multiply`] when not set.
+    pub fn combination(mut self, combination_method: CombinationMethod) -> Self {
+        self.combination_method = Some(combination_method);
+        self
+    }
+
+    pub fn max_normalized_value(mut self, max_normalized_value: u64) -> Self {
+        self.max_normalized_value = Some(max_normalized_value);
+        self
+    }
+
+    /// Mature at unix timestamp `event_maturity_epoch`.
+    pub fn maturity_at(mut self, event_maturity_epoch: u32) -> Self {
+        self.maturity = Maturity::At(event_maturity_epoch);
+        self
+    }
+
+    /// Mature `duration` after the request is built.
+    pub fn maturity_in(mut self, duration: Duration) -> Self {
+        self.maturity = Maturity::In(duration);
+        self
+    }
+
+    pub fn score_mode(mut self, score_mode: ScoreMode) -> Self {
+        self.score_mode = score_mode;
+        self
+    }
+
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    /// Keep the announcement unlisted until `announce_at`, see [`CreateEvent::announce_at`].
+    pub fn announce_at(mut self, announce_at: u32) -> Self {
+        self.announce_at = Some(announce_at);
+        self
+    }
+
+    pub fn settlement_delay(mut self, settlement_delay: u32) -> Self {
+        self.settlement_delay = Some(settlement_delay);
+        self
+    }
+
+    pub fn event_id(mut self, event_id: impl Into<String>) -> Self {
+        self.event_id = Some(event_id.into());
+        self
+    }
+
+    /// The create request, checked as the oracle will check it now.
+    pub fn build(self) -> Result<CreateEvent, ValidationErrors> {
+        self.build_at(chrono::Utc::now().timestamp() as u32)
+    }
+
+    /// The create request, checked as the oracle would check it at unix timestamp `now`.
+    pub fn build_at(self, now: u32) -> Result<CreateEvent, ValidationErrors> {
+        let event_maturity_epoch = match self.maturity {
+            Maturity::Unset => None,
+            Maturity::At(epoch) => Some(epoch),
+            Maturity::In(duration) => Some(
+                u32::try_from(duration.as_secs())
+                    .ok()
+                    .and_then(|secs| now.checked_add(secs))
+                    .unwrap_or(u32::MAX),
+            ),
+        };
+        let event = CreateEvent::Parlay {
+            parameters: self.parameters,
+            combination_method: self
+                .combination_method
+                .unwrap_or(CombinationMethod::Multiply),
+            max_normalized_value: self.max_normalized_value,
+            event_maturity_epoch: event_maturity_epoch.unwrap_or_default(),
+            score_mode: self.score_mode,
+            description: self.description,
+            tags: self.tags,
+            announce_at: self.announce_at,
+            settlement_delay: self.settlement_delay,
+            event_id: self.event_id,
+        };
+        match validation::validate_create_event(&event, now) {
+            Ok(()) => Ok(event),
+            Err(mut errors) => {
+                // An unset maturity fails the check against `now`, name the actual problem
+                if event_maturity_epoch.is_none() {
+                    errors
+                        .0
+                        .iter_mut()
+                        .filter(|error| error.field == "eventMaturityEpoch")
+                        .for_each(|error| error.message = "is required".to_string());
+                }
+                Err(errors)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{events::EventType, validation::MAX_MATURITY_HORIZON_SECS};
+
+    const NOW: u32 = 1_750_000_000;
+
+    fn fields(builder: ParlayBuilder) -> Vec<String> {
+        match builder.build_at(NOW) {
+            Ok(_) => vec![],
+            Err(errors) => errors.0.into_iter().map(|error| error.field).collect(),
+        }
+    }
+
+    #[test]
+    fn builds_what_the_oracle_accepts() {
+        let event = ParlayBuilder::new()
+            .leg(ParlayParameter::linear(
+                EventType::Hashrate,
+                700.0,
+                100.0,
+                true,
+            ))
+            .combination(CombinationMethod::GeometricMean)
+            .maturity_in(Duration::from_secs(3600))
+            .tag("weekly")
+            .build_at(NOW)
+            .unwrap();
+        let CreateEvent::Parlay {
+            parameters,
+            combination_method,
+            event_maturity_epoch,
+            tags,
+            ..
+        } = &event
+        else {
+            panic!("not a parlay");
+        };
+        assert_eq!(parameters.len(), 1);
+        assert_eq!(parameters[0].weight, 1.0);
+        assert_eq!(*combination_method, CombinationMethod::GeometricMean);
+        assert_eq!(*event_maturity_epoch, NOW + 3600);
+        assert_eq!(tags, &["weekly"]);
+        assert!(validation::validate_create_event(&event, NOW).is_ok());
+    }
+
+    #[test]
+    fn reports_the_fields_the_oracle_would_reject() {
+        let hashrate = || ParlayParameter::linear(EventType::Hashrate, 700.0, 100.0, true);
+        let errors = ParlayBuilder::new()
+            .leg(hashrate())
+            .build_at(NOW)
+            .unwrap_err();
+        assert_eq!(errors.0[0].field, "eventMaturityEpoch");
+        assert_eq!(errors.0[0].message, "is required");
+        assert_eq!(
+            fields(ParlayBuilder::new().maturity_in(Duration::from_secs(60))),
+            ["parameters"]
+        );
+        assert_eq!(
+            fields(
+                ParlayBuilder::new()
+                    .leg(hashrate().with_weight(0.0))
+                    .max_normalized_value(0)
+                    .maturity_in(Duration::from_secs(MAX_MATURITY_HORIZON_SECS as u64 + 1))
+                    .event_id("Not an id")
+            ),
+            [
+                "eventId",
+                "eventMaturityEpoch",
+                "maxNormalizedValue",
+                "parameters[0].weight",
+            ]
+        );
+        assert_eq!(
+            fields(ParlayBuilder::new().leg(hashrate()).maturity_at(NOW - 1)),
+            ["eventMaturityEpoch"]
+        );
+    }
+}
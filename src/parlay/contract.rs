@@ -1,24 +1,16 @@
-use super::parameter::ParlayParameter;
+use super::math;
+use super::parameter::{ParlayParameter, ParlayParameterRow};
 use serde::{Deserialize, Serialize};
-use sqlx::postgres::PgRow;
 use sqlx::prelude::FromRow;
+use sqlx::types::Json;
 use sqlx::PgPool;
-use sqlx::Row;
+use sqlx::Postgres;
 use std::str::FromStr;
-use strum_macros::Display;
-use strum_macros::EnumIter;
-use strum_macros::EnumString;
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, EnumIter, Display, EnumString)]
-#[serde(rename_all = "camelCase")]
-#[strum(serialize_all = "camelCase")]
-pub enum CombinationMethod {
-    Multiply,
-    WeightedAverage,
-    GeometricMean,
-    Min,
-    Max,
-}
+pub use super::math::{
+    checked_attestable_value, combine_scores, convert_to_attestable_value, normalize_weights,
+    AttestableValue, CombinationMethod, ParlayScore, ScoreMode, SCORING_VERSION,
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow, PartialEq)]
 #[serde(rename_all = "camelCase")]
@@ -31,6 +23,11 @@ pub struct ParlayContract {
     pub combination_method: CombinationMethod,
     /// The maximum normalized value for the contract
     pub max_normalized_value: u64, // Scale for attestation (e.g., 1000 [.34 -> 340])
+    /// How each leg is scored
+    #[serde(default)]
+    pub score_mode: ScoreMode,
+    /// Version of the scoring rules the contract settles under
+    pub scoring_version: u32,
 }
 
 impl ParlayContract {
@@ -40,18 +37,23 @@ impl ParlayContract {
         parameters: Vec<ParlayParameter>,
         combination_method: CombinationMethod,
         max_normalized_value: u64,
+        score_mode: ScoreMode,
     ) -> anyhow::Result<Self> {
+        validate_weights(&parameters)?;
+
         // Start a transaction
         let mut tx = pool.begin().await?;
 
         // Insert the main contract
         sqlx::query(
-            "INSERT INTO parlay_contracts (id, combination_method, max_normalized_value) 
-         VALUES ($1, $2, $3)",
+            "INSERT INTO parlay_contracts (id, combination_method, max_normalized_value, score_mode, scoring_version) 
+         VALUES ($1, $2, $3, $4, $5)",
         )
         .bind(&id)
         .bind(combination_method.to_string())
         .bind(max_normalized_value as i64)
+        .bind(score_mode.to_string())
+        .bind(SCORING_VERSION as i32)
         .execute(&mut *tx)
         .await?;
 
@@ -59,16 +61,21 @@ impl ParlayContract {
         for param in &parameters {
             sqlx::query(
                 "INSERT INTO parlay_parameters 
-             (contract_id, data_type, threshold, range, is_above_threshold, transformation, weight) 
-             VALUES ($1, $2, $3, $4, $5, $6, $7)",
+             (contract_id, data_type, threshold, range, is_above_threshold, transformation, weight, percentile, aggregation, period, event_id, external_event) 
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)",
             )
             .bind(&id)
             .bind(param.data_type.to_string())
-            .bind(param.threshold as i64)
-            .bind(param.range as i64)
+            .bind(param.threshold)
+            .bind(param.range)
             .bind(param.is_above_threshold)
             .bind(param.transformation.to_string())
-            .bind(param.weight as f64)
+            .bind(param.weight)
+            .bind(param.percentile.map(|p| u8::from(p) as i16))
+            .bind(param.aggregation.map(|a| a.to_string()))
+            .bind(param.period.map(|p| p.to_string()))
+            .bind(&param.event_id)
+            .bind(param.external.as_ref().map(Json))
             .execute(&mut *tx)
             .await?;
         }
@@ -81,90 +88,320 @@ impl ParlayContract {
             parameters,
             combination_method,
             max_normalized_value,
+            score_mode,
+            scoring_version: SCORING_VERSION,
         })
     }
 }
 
-pub async fn get_parlay_contract(pool: PgPool, id: String) -> anyhow::Result<ParlayContract> {
-    let contract = sqlx::query("SELECT * FROM parlay_contracts WHERE id = $1")
-        .bind(&id)
-        .fetch_one(&pool)
-        .await?;
-
-    let parameters = sqlx::query("SELECT * FROM parlay_parameters WHERE contract_id = $1")
-        .bind(&id)
-        .fetch_all(&pool)
-        .await?;
-
-    contract_from_row(contract, parameters)
+#[derive(Debug, FromRow)]
+struct ParlayContractRow {
+    id: String,
+    combination_method: String,
+    max_normalized_value: i64,
+    score_mode: String,
+    scoring_version: i32,
 }
 
-fn contract_from_row(contract: PgRow, parameters: Vec<PgRow>) -> anyhow::Result<ParlayContract> {
-    let id: String = contract.try_get("id").expect("id not found");
-    let combination_method = {
-        let row: String = contract.get("combination_method");
-        CombinationMethod::from_str(&row)?
-    };
-    let max_normalized_value = {
-        let row: i64 = contract.get("max_normalized_value");
-        row as u64
-    };
+pub async fn get_parlay_contract(pool: PgPool, id: String) -> anyhow::Result<ParlayContract> {
+    let contract = sqlx::query_as::<Postgres, ParlayContractRow>(
+        "SELECT id, combination_method, max_normalized_value, score_mode, scoring_version FROM parlay_contracts WHERE id = $1",
+    )
+    .bind(&id)
+    .fetch_one(&pool)
+    .await?;
 
-    let parameters = parameters
-        .iter()
-        .map(|p| super::parameter::parlay_parameter_from_row(p))
-        .collect::<Result<Vec<_>, _>>()?;
+    let parameters = sqlx::query_as::<Postgres, ParlayParameterRow>(&format!(
+        "SELECT {} FROM parlay_parameters WHERE contract_id = $1 ORDER BY parameter_id",
+        ParlayParameterRow::COLUMNS
+    ))
+    .bind(&id)
+    .fetch_all(&pool)
+    .await?;
+
+    let scoring_version = u32::try_from(contract.scoring_version).unwrap_or_default();
+    if !math::is_supported_scoring_version(scoring_version) {
+        return Err(anyhow::anyhow!(
+            "Contract is scored under rules this oracle does not know. id={} scoring_version={} supported={}",
+            id,
+            contract.scoring_version,
+            SCORING_VERSION
+        ));
+    }
 
     Ok(ParlayContract {
-        id,
-        parameters,
-        combination_method,
-        max_normalized_value,
+        id: contract.id,
+        parameters: parameters
+            .into_iter()
+            .map(ParlayParameter::try_from)
+            .collect::<anyhow::Result<Vec<_>>>()?,
+        combination_method: CombinationMethod::from_str(&contract.combination_method)?,
+        max_normalized_value: contract.max_normalized_value as u64,
+        score_mode: ScoreMode::from_str(&contract.score_mode)?,
+        scoring_version,
     })
 }
 
-pub fn combine_scores(events: &[f64], combination_method: &CombinationMethod) -> f64 {
-    match combination_method {
-        CombinationMethod::Multiply => events.iter().product(),
-        CombinationMethod::WeightedAverage => {
-            let sum: f64 = events.iter().sum();
-            sum / events.len() as f64
-        }
-        CombinationMethod::GeometricMean => {
-            let product: f64 = events.iter().product();
-            product.powf(1.0 / events.len() as f64)
+/// Every weight has to be a finite, positive number.
+pub fn validate_weights(parameters: &[ParlayParameter]) -> anyhow::Result<()> {
+    for parameter in parameters {
+        if !parameter.weight.is_finite() || parameter.weight <= 0.0 {
+            return Err(anyhow::anyhow!(
+                "Parameter weights must be positive. data_type={} weight={}",
+                parameter.data_type,
+                parameter.weight
+            ));
         }
-        CombinationMethod::Min => {
-            if events.is_empty() {
-                0.0
-            } else {
-                events.iter().copied().fold(f64::INFINITY, f64::min)
-            }
-        }
-        CombinationMethod::Max => events.iter().copied().fold(0.0, f64::max),
     }
+    Ok(())
 }
 
-pub fn convert_to_attestable_value(combined_score: f64, max_normalized_value: u64) -> u64 {
-    (combined_score * max_normalized_value as f64) as u64
+/// Score `outcomes`, one per parameter and in the same order, see [`math::score_legs`].
+pub fn score_parameters(
+    parameters: &[ParlayParameter],
+    combination_method: &CombinationMethod,
+    score_mode: &ScoreMode,
+    scoring_version: u32,
+    outcomes: &[f64],
+) -> ParlayScore {
+    let legs = parameters
+        .iter()
+        .map(ParlayParameter::leg)
+        .collect::<Vec<_>>();
+    math::score_legs(
+        &legs,
+        combination_method,
+        score_mode,
+        scoring_version,
+        outcomes,
+    )
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{events::EventType, parlay::parameter::TransformationFunction};
+    use crate::{
+        events::EventType,
+        mempool::{Aggregation, MempoolClient, TimePeriod, BASE_URL},
+        parlay::parameter::TransformationFunction,
+        test_util::{setup_ernest_oracle, TestVectors},
+    };
 
     use super::*;
 
+    fn assert_close(actual: f64, expected: f64, name: &str) {
+        assert_within(actual, expected, 1e-6, name);
+    }
+
+    fn assert_within(actual: f64, expected: f64, tolerance: f64, name: &str) {
+        assert!(
+            (actual - expected).abs() < tolerance,
+            "{}: expected {} got {}",
+            name,
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn scores_match_test_vectors() {
+        let test_vectors = std::fs::read_to_string("./vectors.json").unwrap();
+        let test_vectors: TestVectors = serde_json::from_str(&test_vectors).unwrap();
+
+        // The f64 engine and the decimal engine have to agree on every vector
+        for test_vector in &test_vectors.test_vectors {
+            let tolerance = test_vector.tolerance();
+            for scoring_version in test_vector.scoring_versions() {
+                let contract = &test_vector.contract;
+                let name = format!("{} (v{})", test_vector.name, scoring_version);
+                let outcomes = test_vector.outcomes();
+                let method = CombinationMethod::from_str(&contract.combination_method).unwrap();
+                let score = score_parameters(
+                    &contract.parameters,
+                    &method,
+                    &ScoreMode::Continuous,
+                    scoring_version,
+                    &outcomes,
+                );
+
+                let expected = &test_vector.expected;
+                for (actual, expected) in score
+                    .normalized_values
+                    .iter()
+                    .zip(&expected.normalized_values)
+                {
+                    assert_within(*actual, *expected, tolerance, &name);
+                }
+                for (actual, expected) in score
+                    .transformed_values
+                    .iter()
+                    .zip(&expected.transformed_values)
+                {
+                    assert_within(*actual, *expected, tolerance, &name);
+                }
+                assert_within(
+                    score.combined_score,
+                    expected.combined_score,
+                    tolerance,
+                    &name,
+                );
+                assert_eq!(
+                    score
+                        .attestable_value(contract.max_normalized_value as u64)
+                        .value,
+                    expected.attestation_value,
+                    "{}",
+                    name
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn weights_are_relative() {
+        let values = [0.5, 0.25];
+        for method in [
+            CombinationMethod::Multiply,
+            CombinationMethod::WeightedAverage,
+            CombinationMethod::GeometricMean,
+        ] {
+            assert_close(
+                combine_scores(&values, &[1.0, 3.0], &method),
+                combine_scores(&values, &[2.0, 6.0], &method),
+                &method.to_string(),
+            );
+        }
+        assert_eq!(
+            combine_scores(&values, &[1.0, 1.0], &CombinationMethod::Multiply),
+            0.125
+        );
+        assert_eq!(combine_scores(&[], &[], &CombinationMethod::Min), 0.0);
+    }
+
+    #[test]
+    fn attestable_values_are_clamped() {
+        assert_eq!(
+            checked_attestable_value(0.5, 1000),
+            AttestableValue {
+                value: 500,
+                clamped: false
+            }
+        );
+        // 1000 needs 10 digits, so 1023 is the largest value that can be signed
+        assert_eq!(
+            checked_attestable_value(1.0235, 1000),
+            AttestableValue {
+                value: 1023,
+                clamped: false
+            }
+        );
+        assert_eq!(
+            checked_attestable_value(std::f64::consts::E, 1000),
+            AttestableValue {
+                value: 1023,
+                clamped: true
+            }
+        );
+        for score in [-1.0, f64::NAN, f64::NEG_INFINITY] {
+            assert_eq!(
+                checked_attestable_value(score, 1000),
+                AttestableValue {
+                    value: 0,
+                    clamped: true
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_weights() {
+        let parameter = |weight| ParlayParameter {
+            data_type: EventType::Hashrate,
+            threshold: 1000.0,
+            range: 1000.0,
+            is_above_threshold: true,
+            transformation: TransformationFunction::Linear,
+            weight,
+            percentile: None,
+            aggregation: None,
+            period: None,
+            event_id: None,
+            external: None,
+        };
+        assert!(validate_weights(&[parameter(1.0), parameter(0.5)]).is_ok());
+        assert!(validate_weights(&[parameter(0.0)]).is_err());
+        assert!(validate_weights(&[parameter(-1.0)]).is_err());
+        assert!(validate_weights(&[parameter(f64::NAN)]).is_err());
+    }
+
+    #[test]
+    fn binary_mode_scores_threshold_conditions() {
+        let parameter = |threshold, is_above_threshold| ParlayParameter {
+            data_type: EventType::Hashrate,
+            threshold,
+            range: 1.0,
+            is_above_threshold,
+            transformation: TransformationFunction::Quadratic,
+            weight: 1.0,
+            percentile: None,
+            aggregation: None,
+            period: None,
+            event_id: None,
+            external: None,
+        };
+        let parameters = [parameter(700.0, true), parameter(10.0, false)];
+
+        let score = score_parameters(
+            &parameters,
+            &CombinationMethod::Multiply,
+            &ScoreMode::Binary,
+            SCORING_VERSION,
+            &[700.5, 5.0],
+        );
+        assert_eq!(score.transformed_values, vec![1.0, 1.0]);
+        assert_eq!(score.combined_score, 1.0);
+
+        let score = score_parameters(
+            &parameters,
+            &CombinationMethod::Multiply,
+            &ScoreMode::Binary,
+            SCORING_VERSION,
+            &[700.5, 10.0],
+        );
+        assert_eq!(score.transformed_values, vec![1.0, 0.0]);
+        assert_eq!(score.combined_score, 0.0);
+
+        let score = score_parameters(
+            &parameters,
+            &CombinationMethod::WeightedAverage,
+            &ScoreMode::Binary,
+            SCORING_VERSION,
+            &[700.5, 10.0],
+        );
+        assert_eq!(score.combined_score, 0.5);
+    }
+
     #[tokio::test]
     async fn test_parlay_contract() {
-        let pool =
-            PgPool::connect(&std::env::var("DATABASE_URL").expect("$DATABASE_URL is not set"))
-                .await
-                .unwrap();
+        let mempool = MempoolClient::new(BASE_URL.to_string());
+        let oracle = setup_ernest_oracle(mempool).await;
+        let pool = oracle.oracle.storage.pool.clone();
+        // Contracts belong to their announced event
         let id = uuid::Uuid::new_v4().to_string();
-        let _ = ParlayContract::new(
-            pool,
-            id,
+        oracle
+            .oracle
+            .create_numeric_event(
+                id.clone(),
+                14,
+                false,
+                2,
+                "parlay".to_string(),
+                chrono::Utc::now().timestamp() as u32 + 1000,
+            )
+            .await
+            .unwrap();
+        let contract = ParlayContract::new(
+            pool.clone(),
+            id.clone(),
             vec![
                 ParlayParameter {
                     data_type: EventType::Hashrate,
@@ -173,20 +410,43 @@ mod tests {
                     is_above_threshold: true,
                     transformation: TransformationFunction::Linear,
                     weight: 1.0,
+                    percentile: None,
+                    aggregation: None,
+                    period: None,
+                    event_id: None,
+                    external: None,
                 },
                 ParlayParameter {
                     data_type: EventType::Hashrate,
-                    threshold: 1000.0,
-                    range: 1000.0,
+                    threshold: 712.5,
+                    range: 37.25,
                     is_above_threshold: true,
                     transformation: TransformationFunction::Linear,
                     weight: 1.3,
+                    percentile: None,
+                    aggregation: Some(Aggregation::Max),
+                    period: Some(TimePeriod::OneYear),
+                    event_id: None,
+                    external: None,
                 },
             ],
             CombinationMethod::Multiply,
             1000,
+            ScoreMode::Binary,
         )
         .await
         .expect("could not create parlay contract");
+
+        let stored = get_parlay_contract(pool.clone(), id.clone()).await.unwrap();
+        assert_eq!(stored, contract);
+
+        // A contract of a later release is not settled under the rules of this one
+        sqlx::query("UPDATE parlay_contracts SET scoring_version = $1 WHERE id = $2")
+            .bind(SCORING_VERSION as i32 + 1)
+            .bind(&id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert!(get_parlay_contract(pool, id).await.is_err());
     }
 }
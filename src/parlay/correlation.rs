@@ -0,0 +1,164 @@
+//! Warnings for parlay legs that move together.
+//!
+//! A parlay pays out on several conditions at once. Legs on the same data, or on data that
+//! follows it (difficulty follows hashrate, block fees follow fee rates), pointing the same way
+//! are close to a single bet and the combined score overstates how diversified it is.
+
+use serde::{Deserialize, Serialize};
+
+use crate::events::EventType;
+use crate::parlay::parameter::ParlayParameter;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum LegWarningKind {
+    /// The legs settle on the same value in the same direction
+    Duplicate,
+    /// The legs settle on values that move together, in the same direction
+    Correlated,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LegWarning {
+    pub kind: LegWarningKind,
+    /// Positions of the two legs in the parameters
+    pub legs: [usize; 2],
+    pub message: String,
+}
+
+/// Whether two different data types are known to rise and fall together.
+fn correlated(a: &EventType, b: &EventType) -> bool {
+    use EventType::*;
+    matches!(
+        (a, b),
+        (Hashrate, Difficulty)
+            | (Difficulty, Hashrate)
+            | (Hashrate, DifficultyChangePercent)
+            | (DifficultyChangePercent, Hashrate)
+            | (FeeRate, BlockFees)
+            | (BlockFees, FeeRate)
+            | (BlockFees, CoinbaseValue)
+            | (CoinbaseValue, BlockFees)
+    )
+}
+
+/// Whether two legs settle on exactly the same value.
+fn same_value(a: &ParlayParameter, b: &ParlayParameter) -> bool {
+    match (&a.external, &b.external) {
+        (Some(a), Some(b)) => {
+            a.oracle_public_key == b.oracle_public_key && a.event_id == b.event_id
+        }
+        (None, None) => {
+            a.data_type == b.data_type
+                && a.event_id == b.event_id
+                && a.outcome_options() == b.outcome_options()
+        }
+        _ => false,
+    }
+}
+
+fn warning(
+    kind: LegWarningKind,
+    legs: [usize; 2],
+    a: &ParlayParameter,
+    b: &ParlayParameter,
+) -> LegWarning {
+    let message = match kind {
+        LegWarningKind::Duplicate => format!(
+            "Legs {} and {} settle on the same {} value in the same direction, they are effectively one leg",
+            legs[0], legs[1], a.data_type
+        ),
+        LegWarningKind::Correlated => format!(
+            "Legs {} and {} ({} and {}) tend to move together, the parlay may effectively be a single bet",
+            legs[0], legs[1], a.data_type, b.data_type
+        ),
+    };
+    LegWarning {
+        kind,
+        legs,
+        message,
+    }
+}
+
+/// Flag every pair of legs that pulls in the same direction on the same or correlated data.
+pub fn leg_warnings(parameters: &[ParlayParameter]) -> Vec<LegWarning> {
+    let mut warnings = Vec::new();
+    for (i, a) in parameters.iter().enumerate() {
+        for (j, b) in parameters.iter().enumerate().skip(i + 1) {
+            // Opposite directions on the same data bound a range, which is a deliberate bet
+            if a.is_above_threshold != b.is_above_threshold {
+                continue;
+            }
+            let kind = if same_value(a, b) {
+                LegWarningKind::Duplicate
+            } else if a.external.is_some() || b.external.is_some() {
+                continue;
+            } else if a.data_type == b.data_type || correlated(&a.data_type, &b.data_type) {
+                // The same data over another window or percentile still overlaps
+                LegWarningKind::Correlated
+            } else {
+                continue;
+            };
+            warnings.push(warning(kind, [i, j], a, b));
+        }
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mempool::TimePeriod;
+    use crate::parlay::parameter::TransformationFunction;
+
+    fn parameter(data_type: EventType, is_above_threshold: bool) -> ParlayParameter {
+        ParlayParameter {
+            data_type,
+            threshold: 100.0,
+            range: 10.0,
+            is_above_threshold,
+            transformation: TransformationFunction::Linear,
+            weight: 1.0,
+            percentile: None,
+            aggregation: None,
+            period: None,
+            event_id: None,
+            external: None,
+        }
+    }
+
+    #[test]
+    fn flags_duplicate_and_correlated_legs() {
+        let kinds = |parameters: &[ParlayParameter]| {
+            leg_warnings(parameters)
+                .into_iter()
+                .map(|warning| (warning.kind, warning.legs))
+                .collect::<Vec<_>>()
+        };
+        let hashrate = parameter(EventType::Hashrate, true);
+        let yearly_hashrate = ParlayParameter {
+            period: Some(TimePeriod::OneYear),
+            ..hashrate.clone()
+        };
+
+        assert_eq!(
+            kinds(&[
+                hashrate.clone(),
+                parameter(EventType::Difficulty, true),
+                yearly_hashrate,
+                hashrate.clone(),
+            ]),
+            vec![
+                (LegWarningKind::Correlated, [0, 1]),
+                (LegWarningKind::Correlated, [0, 2]),
+                (LegWarningKind::Duplicate, [0, 3]),
+                (LegWarningKind::Correlated, [1, 2]),
+                (LegWarningKind::Correlated, [1, 3]),
+                (LegWarningKind::Correlated, [2, 3]),
+            ]
+        );
+        assert!(kinds(&[hashrate.clone(), parameter(EventType::Hashrate, false)]).is_empty());
+        assert!(kinds(&[hashrate, parameter(EventType::FeeRate, true)]).is_empty());
+    }
+}
@@ -0,0 +1,255 @@
+//! Fixed-point scoring of parlays.
+//!
+//! Contracts announced from scoring version [`DECIMAL_SCORING_VERSION`] on are scored with
+//! [`Decimal`] arithmetic so the attested value is identical on every platform and does not
+//! hinge on float rounding at threshold boundaries. Older contracts keep the f64 pipeline in
+//! [`super::math`].
+
+use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
+use rust_decimal::{Decimal, MathematicalOps};
+
+use super::math::{
+    calculate_oracle_parameters, AttestableValue, CombinationMethod, Leg, ScoreMode,
+    TransformationFunction,
+};
+
+/// First scoring version settled with the decimal engine.
+pub const DECIMAL_SCORING_VERSION: u32 = 3;
+
+/// Intermediate and final values of scoring a parlay with the decimal engine.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecimalScore {
+    pub normalized_values: Vec<Decimal>,
+    pub transformed_values: Vec<Decimal>,
+    pub combined_score: Decimal,
+}
+
+/// Inputs that cannot be represented (NaN, infinities) are treated as zero.
+pub fn to_decimal(value: f64) -> Decimal {
+    Decimal::from_f64(value).unwrap_or(Decimal::ZERO)
+}
+
+pub fn normalize_leg(leg: &Leg, value: Decimal) -> Decimal {
+    let threshold = to_decimal(leg.threshold);
+    let distance = if leg.is_above_threshold {
+        value - threshold
+    } else {
+        threshold - value
+    };
+    if distance <= Decimal::ZERO {
+        return Decimal::ZERO;
+    }
+    // A zero range means any distance past the threshold is a full score
+    distance
+        .checked_div(to_decimal(leg.range))
+        .unwrap_or(Decimal::ONE)
+        .min(Decimal::ONE)
+}
+
+/// Same curves as scoring version 2, every transformation maps `[0, 1]` onto `[0, 1]`.
+pub fn apply_transformation(leg: &Leg, value: Decimal) -> Decimal {
+    match leg.transformation {
+        TransformationFunction::Linear => value,
+        TransformationFunction::Quadratic => value * value,
+        TransformationFunction::Sqrt => value.sqrt().unwrap_or(Decimal::ZERO),
+        TransformationFunction::Exponential => {
+            (value.exp() - Decimal::ONE) / (Decimal::E - Decimal::ONE)
+        }
+        TransformationFunction::Logarithmic => (Decimal::ONE + value).ln() / Decimal::TWO.ln(),
+    }
+}
+
+/// `value^exponent` for a leg value in `[0, 1]`.
+fn pow(value: Decimal, exponent: Decimal) -> Decimal {
+    if value.is_zero() {
+        return Decimal::ZERO;
+    }
+    value.powd(exponent)
+}
+
+/// Decimal counterpart of [`super::math::combine_scores`]. Weight ratios are formed by a
+/// single division so equal weights give exact exponents.
+pub fn combine_scores(
+    values: &[Decimal],
+    weights: &[Decimal],
+    combination_method: &CombinationMethod,
+) -> Decimal {
+    if values.is_empty() {
+        return Decimal::ZERO;
+    }
+
+    let total: Decimal = weights.iter().sum();
+    match combination_method {
+        CombinationMethod::Multiply => {
+            let legs = Decimal::from(values.len());
+            values
+                .iter()
+                .zip(weights)
+                .map(|(value, weight)| pow(*value, weight * legs / total))
+                .product()
+        }
+        CombinationMethod::WeightedAverage => {
+            values
+                .iter()
+                .zip(weights)
+                .map(|(value, weight)| value * weight)
+                .sum::<Decimal>()
+                / total
+        }
+        CombinationMethod::GeometricMean => values
+            .iter()
+            .zip(weights)
+            .map(|(value, weight)| pow(*value, weight / total))
+            .product(),
+        CombinationMethod::Min => values.iter().copied().min().unwrap_or(Decimal::ZERO),
+        CombinationMethod::Max => values.iter().copied().max().unwrap_or(Decimal::ZERO),
+    }
+}
+
+/// Score `outcomes`, one per leg and in the same order.
+pub fn score_legs(
+    legs: &[Leg],
+    combination_method: &CombinationMethod,
+    score_mode: &ScoreMode,
+    outcomes: &[f64],
+) -> DecimalScore {
+    let normalized_values = legs
+        .iter()
+        .zip(outcomes)
+        .map(|(leg, outcome)| {
+            let outcome = to_decimal(*outcome);
+            match score_mode {
+                ScoreMode::Continuous => normalize_leg(leg, outcome),
+                ScoreMode::Binary => {
+                    if normalize_leg(leg, outcome) > Decimal::ZERO {
+                        Decimal::ONE
+                    } else {
+                        Decimal::ZERO
+                    }
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+    let transformed_values = match score_mode {
+        ScoreMode::Continuous => legs
+            .iter()
+            .zip(&normalized_values)
+            .map(|(leg, value)| apply_transformation(leg, *value))
+            .collect::<Vec<_>>(),
+        ScoreMode::Binary => normalized_values.clone(),
+    };
+    let weights = legs
+        .iter()
+        .map(|leg| to_decimal(leg.weight))
+        .collect::<Vec<_>>();
+    let combined_score = combine_scores(&transformed_values, &weights, combination_method);
+
+    DecimalScore {
+        normalized_values,
+        transformed_values,
+        combined_score,
+    }
+}
+
+/// Decimal counterpart of [`super::math::checked_attestable_value`].
+pub fn checked_attestable_value(
+    combined_score: Decimal,
+    max_normalized_value: u64,
+) -> AttestableValue {
+    let (_, oracle_max_value) = calculate_oracle_parameters(max_normalized_value);
+    let scaled = (combined_score * Decimal::from(max_normalized_value)).floor();
+    if scaled < Decimal::ZERO {
+        return AttestableValue {
+            value: 0,
+            clamped: true,
+        };
+    }
+    match scaled.to_u64() {
+        Some(value) if value <= oracle_max_value => AttestableValue {
+            value,
+            clamped: false,
+        },
+        _ => AttestableValue {
+            value: oracle_max_value,
+            clamped: true,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn dec(value: &str) -> Decimal {
+        Decimal::from_str(value).unwrap()
+    }
+
+    #[test]
+    fn normalization_is_exact_at_boundaries() {
+        // 0.1 + 0.2 is not 0.3 in f64, the decimal engine lands exactly on the threshold
+        let below = Leg::linear(0.3, 1.0, true);
+        let at = to_decimal(0.1) + to_decimal(0.2);
+        assert_eq!(normalize_leg(&below, at), Decimal::ZERO);
+
+        let above = Leg::linear(700.0, 100.0, true);
+        assert_eq!(normalize_leg(&above, dec("725")), dec("0.25"));
+        assert_eq!(normalize_leg(&above, dec("900")), Decimal::ONE);
+        let zero_range = Leg::linear(700.0, 0.0, false);
+        assert_eq!(normalize_leg(&zero_range, dec("699")), Decimal::ONE);
+    }
+
+    #[test]
+    fn equal_weights_combine_exactly() {
+        let values = [dec("0.5"), dec("0.5"), dec("0.5")];
+        let weights = [dec("1"), dec("1"), dec("1")];
+        assert_eq!(
+            combine_scores(&values, &weights, &CombinationMethod::Multiply),
+            dec("0.125")
+        );
+        assert_eq!(
+            combine_scores(&values, &weights, &CombinationMethod::WeightedAverage),
+            dec("0.5")
+        );
+        assert_eq!(
+            combine_scores(
+                &[dec("0"), dec("1")],
+                &weights[..2],
+                &CombinationMethod::Multiply
+            ),
+            Decimal::ZERO
+        );
+    }
+
+    #[test]
+    fn attestable_values_are_clamped() {
+        assert_eq!(
+            checked_attestable_value(dec("0.5"), 1000),
+            AttestableValue {
+                value: 500,
+                clamped: false
+            }
+        );
+        assert_eq!(
+            checked_attestable_value(dec("1.023"), 1000),
+            AttestableValue {
+                value: 1023,
+                clamped: false
+            }
+        );
+        assert_eq!(
+            checked_attestable_value(dec("2"), 1000),
+            AttestableValue {
+                value: 1023,
+                clamped: true
+            }
+        );
+        assert_eq!(
+            checked_attestable_value(dec("-0.1"), 1000),
+            AttestableValue {
+                value: 0,
+                clamped: true
+            }
+        );
+    }
+}
@@ -0,0 +1,348 @@
+//! Rough strike-probability estimates for proposed parlays.
+//!
+//! Every historical window as long as the time to maturity is replayed from today's values:
+//! each leg moves by the change it saw over that window, positive series multiplicatively and
+//! signed ones additively. Windows are shared across legs so correlations between data types
+//! carry over. The estimate only reflects the sampled history and is meant for pricing
+//! premiums, not as a forecast.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+use super::contract::{
+    score_parameters, validate_weights, CombinationMethod, ScoreMode, SCORING_VERSION,
+};
+use super::parameter::ParlayParameter;
+use crate::events::EventType;
+use crate::history::{self, MetricHistory, MetricPoint};
+use crate::oracle::DEFAULT_MAX_NORMALIZED_VALUE;
+use crate::volatility;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EstimateRequest {
+    pub parameters: Vec<ParlayParameter>,
+    pub combination_method: CombinationMethod,
+    #[serde(default)]
+    pub score_mode: ScoreMode,
+    pub max_normalized_value: Option<u64>,
+    /// Unix timestamp in seconds the contract would mature at
+    pub event_maturity_epoch: u32,
+    /// Days of history to draw windows from, defaults to thirty
+    pub lookback_days: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LegEstimate {
+    pub data_type: EventType,
+    /// Latest sampled value the scenarios start from
+    pub current_value: Option<f64>,
+    /// Volatility of the returns between samples over the lookback
+    pub volatility: Option<f64>,
+    /// Volatility over the last [`volatility::DEFAULT_VOLATILITY_WINDOW`] returns
+    pub recent_volatility: Option<f64>,
+    /// Share of scenarios in which the leg's threshold condition holds
+    pub strike_probability: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Estimate {
+    pub horizon_secs: i64,
+    pub scenarios: usize,
+    pub legs: Vec<LegEstimate>,
+    /// Share of scenarios with a non-zero attestable value, zero without scenarios
+    pub strike_probability: f64,
+    pub expected_value: Option<f64>,
+}
+
+/// Most recent sample at or before `at`.
+fn value_at(points: &[MetricPoint], at: DateTime<Utc>) -> Option<f64> {
+    points
+        .iter()
+        .take_while(|point| point.sampled_at <= at)
+        .last()
+        .map(|point| point.value)
+}
+
+/// Apply the change from `from` to `to` onto `current`, see [`volatility::change`].
+fn project(current: f64, from: f64, to: f64) -> f64 {
+    if current > 0.0 && from > 0.0 && to > 0.0 {
+        current * (to / from)
+    } else {
+        current + (to - from)
+    }
+}
+
+/// Estimate from `histories` how likely the parlay strikes `horizon` after the last samples.
+pub fn estimate(
+    parameters: &[ParlayParameter],
+    combination_method: &CombinationMethod,
+    score_mode: &ScoreMode,
+    max_normalized_value: u64,
+    histories: &[MetricHistory],
+    horizon: Duration,
+) -> Estimate {
+    let series = histories
+        .iter()
+        .map(|history| (history.data_type.to_string(), history.points.as_slice()))
+        .collect::<HashMap<_, _>>();
+    let legs = parameters
+        .iter()
+        .map(|parameter| {
+            series
+                .get(&parameter.data_type.to_string())
+                .copied()
+                .unwrap_or_default()
+        })
+        .collect::<Vec<&[MetricPoint]>>();
+    let current = legs
+        .iter()
+        .map(|points| points.last().map(|point| point.value))
+        .collect::<Vec<_>>();
+
+    let mut times = legs
+        .iter()
+        .flat_map(|points| points.iter().map(|point| point.sampled_at))
+        .collect::<Vec<_>>();
+    times.sort();
+    times.dedup();
+
+    let scenarios = times
+        .into_iter()
+        .filter_map(|at| {
+            let end = at + horizon;
+            legs.iter()
+                .zip(&current)
+                .map(|(points, current)| {
+                    // Windows must end within the sampled history of every leg
+                    if points.last()?.sampled_at < end {
+                        return None;
+                    }
+                    Some(project(
+                        (*current)?,
+                        value_at(points, at)?,
+                        value_at(points, end)?,
+                    ))
+                })
+                .collect::<Option<Vec<_>>>()
+        })
+        .collect::<Vec<_>>();
+
+    let values = scenarios
+        .iter()
+        .map(|outcomes| {
+            score_parameters(
+                parameters,
+                combination_method,
+                score_mode,
+                SCORING_VERSION,
+                outcomes,
+            )
+            .attestable_value(max_normalized_value)
+            .value
+        })
+        .collect::<Vec<_>>();
+    let share = |count: usize| {
+        if scenarios.is_empty() {
+            0.0
+        } else {
+            count as f64 / scenarios.len() as f64
+        }
+    };
+
+    let legs = parameters
+        .iter()
+        .zip(&legs)
+        .zip(&current)
+        .enumerate()
+        .map(|(i, ((parameter, points), current))| LegEstimate {
+            data_type: parameter.data_type.clone(),
+            current_value: *current,
+            volatility: volatility::volatility(points),
+            recent_volatility: volatility::rolling_volatility(
+                points,
+                volatility::DEFAULT_VOLATILITY_WINDOW,
+            )
+            .last()
+            .map(|point| point.volatility),
+            strike_probability: share(
+                scenarios
+                    .iter()
+                    .filter(|outcomes| parameter.threshold_met(outcomes[i]))
+                    .count(),
+            ),
+        })
+        .collect();
+
+    Estimate {
+        horizon_secs: horizon.num_seconds(),
+        scenarios: scenarios.len(),
+        legs,
+        strike_probability: share(values.iter().filter(|value| **value > 0).count()),
+        expected_value: (!values.is_empty())
+            .then(|| values.iter().sum::<u64>() as f64 / values.len() as f64),
+    }
+}
+
+pub async fn run_estimate(pool: &PgPool, request: EstimateRequest) -> anyhow::Result<Estimate> {
+    if request.parameters.is_empty() {
+        return Err(anyhow::anyhow!("Parameters must be non-empty"));
+    }
+    validate_weights(&request.parameters)?;
+    if request.parameters.iter().any(|p| p.external.is_some()) {
+        return Err(anyhow::anyhow!(
+            "Legs on external events have no history to estimate on"
+        ));
+    }
+
+    let now = Utc::now();
+    let maturity = history::parse_timestamp(request.event_maturity_epoch as i64)?;
+    if maturity <= now {
+        return Err(anyhow::anyhow!(
+            "Maturity must be in the future. event_maturity_epoch={}",
+            request.event_maturity_epoch
+        ));
+    }
+    let lookback_days = request
+        .lookback_days
+        .unwrap_or(history::DEFAULT_HISTORY_DAYS);
+    if lookback_days <= 0 {
+        return Err(anyhow::anyhow!(
+            "Lookback must be at least one day. lookback_days={}",
+            lookback_days
+        ));
+    }
+    let from = now - Duration::days(lookback_days);
+
+    let mut histories = Vec::new();
+    for parameter in &request.parameters {
+        if histories
+            .iter()
+            .any(|h: &MetricHistory| h.data_type == parameter.data_type)
+        {
+            continue;
+        }
+        histories.push(history::get_history(pool, &parameter.data_type, from, now).await?);
+    }
+
+    Ok(estimate(
+        &request.parameters,
+        &request.combination_method,
+        &request.score_mode,
+        request
+            .max_normalized_value
+            .unwrap_or(DEFAULT_MAX_NORMALIZED_VALUE),
+        &histories,
+        maturity - now,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parlay::parameter::TransformationFunction;
+
+    fn parameter(data_type: EventType, threshold: f64) -> ParlayParameter {
+        ParlayParameter {
+            data_type,
+            threshold,
+            range: 100.0,
+            is_above_threshold: true,
+            transformation: TransformationFunction::Linear,
+            weight: 1.0,
+            percentile: None,
+            aggregation: None,
+            period: None,
+            event_id: None,
+            external: None,
+        }
+    }
+
+    fn history(data_type: EventType, points: &[(i64, f64)]) -> MetricHistory {
+        MetricHistory {
+            data_type,
+            points: points
+                .iter()
+                .map(|(at, value)| MetricPoint {
+                    value: *value,
+                    sampled_at: DateTime::from_timestamp(*at, 0).unwrap(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn replays_historical_windows_from_current_values() {
+        let parameters = [parameter(EventType::Hashrate, 200.0)];
+        // Current value 150, the windows of 10s saw 1x, 2x, 1.5x and 0.5x
+        let histories = [history(
+            EventType::Hashrate,
+            &[
+                (0, 100.0),
+                (10, 100.0),
+                (20, 200.0),
+                (30, 300.0),
+                (40, 150.0),
+            ],
+        )];
+        let estimate = estimate(
+            &parameters,
+            &CombinationMethod::Multiply,
+            &ScoreMode::Binary,
+            1000,
+            &histories,
+            Duration::seconds(10),
+        );
+        // 150, 300, 225 and 75
+        assert_eq!(estimate.scenarios, 4);
+        assert_eq!(estimate.strike_probability, 0.5);
+        assert_eq!(estimate.expected_value, Some(500.0));
+        assert_eq!(estimate.legs[0].current_value, Some(150.0));
+        assert_eq!(estimate.legs[0].strike_probability, 0.5);
+    }
+
+    #[test]
+    fn windows_are_shared_across_legs() {
+        let parameters = [
+            parameter(EventType::Hashrate, 150.0),
+            parameter(EventType::Difficulty, 150.0),
+        ];
+        // Both legs double over the first window and halve over the second
+        let histories = [
+            history(EventType::Hashrate, &[(0, 100.0), (10, 200.0), (20, 100.0)]),
+            history(EventType::Difficulty, &[(0, 50.0), (10, 100.0), (20, 50.0)]),
+        ];
+        let estimate = estimate(
+            &parameters,
+            &CombinationMethod::Multiply,
+            &ScoreMode::Binary,
+            1000,
+            &histories,
+            Duration::seconds(10),
+        );
+        assert_eq!(estimate.scenarios, 2);
+        assert_eq!(estimate.legs[0].strike_probability, 0.5);
+        assert_eq!(estimate.legs[1].strike_probability, 0.0);
+        assert_eq!(estimate.strike_probability, 0.0);
+    }
+
+    #[test]
+    fn horizon_longer_than_history() {
+        let estimate = estimate(
+            &[parameter(EventType::Hashrate, 700.0)],
+            &CombinationMethod::Multiply,
+            &ScoreMode::Binary,
+            1000,
+            &[history(EventType::Hashrate, &[(0, 100.0), (10, 200.0)])],
+            Duration::seconds(60),
+        );
+        assert_eq!(estimate.scenarios, 0);
+        assert_eq!(estimate.strike_probability, 0.0);
+        assert_eq!(estimate.expected_value, None);
+    }
+}
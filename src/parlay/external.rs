@@ -0,0 +1,245 @@
+//! Parlay legs settling on numeric events announced by other oracles.
+//!
+//! The other oracle is reached through the REST convention of [`crate::compat`]
+//! (`/announcements/:event_id` and `/attestations/:event_id`). Its announcement is checked
+//! against the expected key and digit encoding when the parlay is created and kept with the
+//! leg, so the attestation is verified against the nonces the parlay was priced on.
+
+use bitcoin::{key::Secp256k1, XOnlyPublicKey};
+use dlc_messages::{oracle_msgs::EventDescriptor, ser_impls::read_as_tlv};
+use kormir::{lightning::io::Cursor, OracleAnnouncement, OracleAttestation};
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+
+use crate::{compat::CompatResponse, digits};
+
+/// How the outcome of a numeric event is split into digits.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct DigitEncoding {
+    pub base: u16,
+    pub nb_digits: u16,
+    pub is_signed: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ExternalEvent {
+    /// Base url of the other oracle's REST API
+    pub oracle_url: String,
+    pub oracle_public_key: XOnlyPublicKey,
+    pub event_id: String,
+    pub encoding: DigitEncoding,
+    /// The announcement fetched when the parlay was created
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub announcement: Option<OracleAnnouncement>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnnouncementResult {
+    #[serde(rename = "announcementTLV")]
+    announcement_tlv: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct AttestationResult {
+    #[serde(rename = "attestationTLV")]
+    attestation_tlv: String,
+}
+
+fn decode_tlv<T: kormir::lightning::ln::wire::Type + kormir::lightning::util::ser::Readable>(
+    tlv: &str,
+) -> anyhow::Result<T> {
+    let bytes = hex::decode(tlv)?;
+    read_as_tlv(&mut Cursor::new(&bytes)).map_err(|e| anyhow::anyhow!("Invalid TLV. error={}", e))
+}
+
+impl ExternalEvent {
+    fn url(&self, resource: &str) -> String {
+        format!(
+            "{}/{}/{}",
+            self.oracle_url.trim_end_matches('/'),
+            resource,
+            self.event_id
+        )
+    }
+
+    /// Check `announcement` is the expected event of the expected oracle.
+    pub fn validate_announcement(&self, announcement: &OracleAnnouncement) -> anyhow::Result<()> {
+        if announcement.oracle_public_key != self.oracle_public_key
+            || announcement.oracle_event.event_id != self.event_id
+        {
+            return Err(anyhow::anyhow!(
+                "Announcement is not the referenced event. event_id={} oracle_public_key={}",
+                self.event_id,
+                self.oracle_public_key
+            ));
+        }
+        announcement
+            .validate(&Secp256k1::verification_only())
+            .map_err(|e| {
+                anyhow::anyhow!(
+                    "Invalid external announcement. event_id={} error={:?}",
+                    self.event_id,
+                    e
+                )
+            })?;
+        let EventDescriptor::DigitDecompositionEvent(descriptor) =
+            &announcement.oracle_event.event_descriptor
+        else {
+            return Err(anyhow::anyhow!(
+                "External event is not numeric. event_id={}",
+                self.event_id
+            ));
+        };
+        let encoding = DigitEncoding {
+            base: descriptor.base,
+            nb_digits: descriptor.nb_digits,
+            is_signed: descriptor.is_signed,
+        };
+        if encoding != self.encoding {
+            return Err(anyhow::anyhow!(
+                "External event has a different encoding. event_id={} expected={:?} actual={:?}",
+                self.event_id,
+                self.encoding,
+                encoding
+            ));
+        }
+        Ok(())
+    }
+
+    /// Fetch and check the announcement, returning the event with it attached.
+    pub async fn resolve(mut self, client: &Client) -> anyhow::Result<Self> {
+        let response = client
+            .get(self.url("announcements"))
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<CompatResponse<AnnouncementResult>>()
+            .await?;
+        let result = response.result.ok_or(anyhow::anyhow!(
+            "External oracle has no announcement. event_id={} error={}",
+            self.event_id,
+            response.error.unwrap_or_default()
+        ))?;
+        let announcement: OracleAnnouncement = decode_tlv(&result.announcement_tlv)?;
+        self.validate_announcement(&announcement)?;
+        self.announcement = Some(announcement);
+        Ok(self)
+    }
+
+    /// The attestation of the other oracle, `None` until it signed.
+    pub async fn fetch_attestation(
+        &self,
+        client: &Client,
+    ) -> anyhow::Result<Option<OracleAttestation>> {
+        let response = client.get(self.url("attestations")).send().await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let response = response
+            .error_for_status()?
+            .json::<CompatResponse<AttestationResult>>()
+            .await?;
+        response
+            .result
+            .map(|result| decode_tlv(&result.attestation_tlv))
+            .transpose()
+    }
+
+    /// Verify `attestation` against the stored announcement and decode the attested value,
+    /// scaled by the announced precision.
+    pub fn decode_attestation(&self, attestation: &OracleAttestation) -> anyhow::Result<f64> {
+        let announcement = self.announcement.as_ref().ok_or(anyhow::anyhow!(
+            "External event has no stored announcement. event_id={}",
+            self.event_id
+        ))?;
+        attestation
+            .validate(&Secp256k1::verification_only(), announcement)
+            .map_err(|e| {
+                anyhow::anyhow!(
+                    "Invalid external attestation. event_id={} error={:?}",
+                    self.event_id,
+                    e
+                )
+            })?;
+        Ok(digits::decode_attestation(announcement, attestation)?.scaled())
+    }
+
+    /// The attested value, `None` while the other oracle has not signed.
+    pub async fn attested_value(&self, client: &Client) -> anyhow::Result<Option<f64>> {
+        match self.fetch_attestation(client).await? {
+            Some(attestation) => Ok(Some(self.decode_attestation(&attestation)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compat::to_tlv_hex;
+    use kormir::storage::MemoryStorage;
+    use wiremock::{matchers::path, Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn settles_on_external_attestation() {
+        let secret_key = bitcoin::secp256k1::SecretKey::from_slice(&[5u8; 32]).unwrap();
+        let oracle =
+            kormir::Oracle::from_signing_key(MemoryStorage::default(), secret_key).unwrap();
+        let announcement = oracle
+            .create_numeric_event("btcusd".to_string(), 20, false, 0, "usd".to_string(), 0)
+            .await
+            .unwrap();
+
+        let server = MockServer::start().await;
+        Mock::given(path("/announcements/btcusd"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "result": { "announcementTLV": to_tlv_hex(&announcement).unwrap() },
+                "error": null,
+            })))
+            .mount(&server)
+            .await;
+        let external = ExternalEvent {
+            oracle_url: server.uri(),
+            oracle_public_key: oracle.public_key(),
+            event_id: "btcusd".to_string(),
+            encoding: DigitEncoding {
+                base: 2,
+                nb_digits: 20,
+                is_signed: false,
+            },
+            announcement: None,
+        };
+        let client = Client::new();
+
+        let mismatched = ExternalEvent {
+            encoding: DigitEncoding {
+                base: 10,
+                ..external.encoding
+            },
+            ..external.clone()
+        };
+        assert!(mismatched.resolve(&client).await.is_err());
+
+        let external = external.resolve(&client).await.unwrap();
+        assert_eq!(external.announcement, Some(announcement));
+        assert_eq!(external.attested_value(&client).await.unwrap(), None);
+
+        let attestation = oracle
+            .sign_numeric_event("btcusd".to_string(), 104_250)
+            .await
+            .unwrap();
+        Mock::given(path("/attestations/btcusd"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "result": { "attestationTLV": to_tlv_hex(&attestation).unwrap() },
+                "error": null,
+            })))
+            .mount(&server)
+            .await;
+        assert_eq!(
+            external.attested_value(&client).await.unwrap(),
+            Some(104_250.0)
+        );
+    }
+}
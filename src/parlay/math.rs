@@ -0,0 +1,579 @@
+//! Scoring parlays, without a database or network access.
+//!
+//! Everything needed to turn leg outcomes into the value a parlay attests: normalization against
+//! the threshold and range, transformation, combination and conversion to the announced digits.
+//! It is the code the oracle settles with and builds without the default `server` feature, so
+//! clients and frontends can compute expected payouts exactly:
+//!
+//! ```
+//! use ernest_oracle::parlay::math::{self, CombinationMethod, ContractSpec, Leg, ScoreMode};
+//!
+//! let spec = ContractSpec {
+//!     legs: vec![Leg::linear(700.0, 100.0, true)],
+//!     combination_method: CombinationMethod::Multiply,
+//!     score_mode: ScoreMode::Continuous,
+//!     max_normalized_value: 1000,
+//!     scoring_version: math::SCORING_VERSION,
+//! };
+//! assert_eq!(math::score(&spec, &[725.0]).value, 250);
+//! ```
+
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumIter, EnumString};
+
+use super::decimal::{self, DECIMAL_SCORING_VERSION};
+
+/// Scoring rules new contracts are announced under. Contracts keep the version they were
+/// created with so changes to the math never alter how an existing announcement settles.
+///
+/// - 1: f64 scoring with the legacy exponential and logarithmic transformations, legs are
+///   scaled by their weight and combined without weights, see [`combine_legacy_scores`]
+/// - 2: f64 scoring with transformations bounded to `[0, 1]`, see
+///   [`TransformationFunction::apply`], and relative weights, see [`CombinationMethod`]
+/// - 3: version 2 semantics computed with the [`super::decimal`] engine
+pub const SCORING_VERSION: u32 = DECIMAL_SCORING_VERSION;
+
+/// Whether this build knows the scoring rules of `scoring_version`. A contract announced under
+/// a later version must not be settled with the rules of an earlier one.
+pub fn is_supported_scoring_version(scoring_version: u32) -> bool {
+    (1..=SCORING_VERSION).contains(&scoring_version)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq, EnumIter, Display, EnumString)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum TransformationFunction {
+    Linear,
+    Quadratic,
+    Sqrt,
+    Exponential,
+    Logarithmic,
+}
+
+impl TransformationFunction {
+    pub fn description(&self) -> &'static str {
+        match self {
+            TransformationFunction::Linear => "The normalized value unchanged",
+            TransformationFunction::Quadratic => "The square of the normalized value",
+            TransformationFunction::Sqrt => "The square root of the normalized value",
+            TransformationFunction::Exponential => "(e^x - 1) / (e - 1) of the normalized value",
+            TransformationFunction::Logarithmic => "ln(1 + x) / ln(2) of the normalized value",
+        }
+    }
+
+    /// Transform a normalized value under the contract's scoring version.
+    ///
+    /// From [`SCORING_VERSION`] 2 on, every transformation maps `[0, 1]` onto `[0, 1]`:
+    /// `Exponential` is `(e^x - 1) / (e - 1)` and `Logarithmic` is `ln(1 + x) / ln 2`.
+    /// Version 1 contracts keep the legacy `e^x` and `ln(x)`, which can leave that range.
+    pub fn apply(&self, normalized_value: f64, scoring_version: u32) -> f64 {
+        let legacy = scoring_version < 2;
+        match self {
+            TransformationFunction::Linear => normalized_value,
+            TransformationFunction::Quadratic => normalized_value * normalized_value,
+            TransformationFunction::Sqrt => normalized_value.sqrt(),
+            TransformationFunction::Exponential if legacy => normalized_value.exp(),
+            TransformationFunction::Exponential => {
+                normalized_value.exp_m1() / (std::f64::consts::E - 1.0)
+            }
+            TransformationFunction::Logarithmic if legacy => normalized_value.ln(),
+            TransformationFunction::Logarithmic => {
+                normalized_value.ln_1p() / std::f64::consts::LN_2
+            }
+        }
+    }
+}
+
+/// How the transformed values of each leg are combined into one score.
+///
+/// From [`SCORING_VERSION`] 2 on, weights are relative: they are normalized to sum to one
+/// before use, so `[2, 2]` behaves exactly like `[1, 1]`. With `n_i = w_i / Σw` and legs `v_i`:
+///
+/// - `WeightedAverage`: `Σ n_i·v_i`
+/// - `GeometricMean`: `Π v_i^n_i`
+/// - `Multiply`: `Π v_i^(n_i·N)` for `N` legs, which is the plain product with equal weights
+/// - `Min` / `Max`: the smallest / largest leg value, weights do not apply
+///
+/// Version 1 contracts settle as announced before weights were relative, see
+/// [`combine_legacy_scores`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, EnumIter, Display, EnumString)]
+#[serde(rename_all = "camelCase")]
+#[strum(serialize_all = "camelCase")]
+pub enum CombinationMethod {
+    Multiply,
+    WeightedAverage,
+    GeometricMean,
+    Min,
+    Max,
+}
+
+impl CombinationMethod {
+    pub fn description(&self) -> &'static str {
+        match self {
+            CombinationMethod::Multiply => "Product of the legs, weights act as exponents",
+            CombinationMethod::WeightedAverage => "Weighted arithmetic mean of the legs",
+            CombinationMethod::GeometricMean => "Weighted geometric mean of the legs",
+            CombinationMethod::Min => "The smallest leg, weights do not apply",
+            CombinationMethod::Max => "The largest leg, weights do not apply",
+        }
+    }
+}
+
+/// How each leg is scored before the legs are combined.
+///
+/// - `Continuous`: legs are normalized against their range and transformed
+/// - `Binary`: a leg is `1.0` when its threshold condition holds and `0.0` otherwise, so the
+///   combination is applied to booleans (range and transformation are ignored)
+#[derive(
+    Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, EnumIter, Display, EnumString,
+)]
+#[serde(rename_all = "camelCase")]
+#[strum(serialize_all = "camelCase")]
+pub enum ScoreMode {
+    #[default]
+    Continuous,
+    Binary,
+}
+
+impl ScoreMode {
+    pub fn description(&self) -> &'static str {
+        match self {
+            ScoreMode::Continuous => "Legs score by their distance past the threshold",
+            ScoreMode::Binary => "Legs score 1 when their threshold holds and 0 otherwise",
+        }
+    }
+}
+
+/// What scoring needs of a parlay leg, see [`super::parameter::ParlayParameter`] for the rest.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Leg {
+    /// The strike, in the unit of the leg's data type
+    pub threshold: f64,
+    /// Distance past the threshold that scores in full
+    pub range: f64,
+    /// Whether the outcome has to exceed the threshold, else stay below it
+    pub is_above_threshold: bool,
+    pub transformation: TransformationFunction,
+    /// Relative weight of the leg
+    pub weight: f64,
+}
+
+impl Leg {
+    /// A leg of weight one scored linearly.
+    pub fn linear(threshold: f64, range: f64, is_above_threshold: bool) -> Self {
+        Self {
+            threshold,
+            range,
+            is_above_threshold,
+            transformation: TransformationFunction::Linear,
+            weight: 1.0,
+        }
+    }
+
+    /// Whether `value` is strictly on the winning side of the threshold.
+    pub fn threshold_met(&self, value: f64) -> bool {
+        if self.is_above_threshold {
+            value > self.threshold
+        } else {
+            value < self.threshold
+        }
+    }
+
+    /// Distance of `value` past the threshold relative to the range, capped at one.
+    pub fn normalize(&self, value: f64) -> f64 {
+        let distance = if self.is_above_threshold {
+            value - self.threshold
+        } else {
+            self.threshold - value
+        };
+        if distance <= 0.0 {
+            return 0.0;
+        }
+        (distance / self.range).min(1.0)
+    }
+}
+
+/// A parlay as announced, enough to score it.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ContractSpec {
+    pub legs: Vec<Leg>,
+    pub combination_method: CombinationMethod,
+    #[serde(default)]
+    pub score_mode: ScoreMode,
+    /// Scale of the attested value, e.g. 1000 attests a score of 0.34 as 340
+    pub max_normalized_value: u64,
+    pub scoring_version: u32,
+}
+
+/// The value a parlay attests for some outcomes, along with how it was scored.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attestable {
+    pub score: ParlayScore,
+    /// The value to sign
+    pub value: u64,
+    /// Whether the score fell outside what the announcement can express and was clamped
+    pub clamped: bool,
+}
+
+/// Score `inputs`, the outcome of every leg of `spec` in order, as the oracle settles it.
+pub fn score(spec: &ContractSpec, inputs: &[f64]) -> Attestable {
+    let score = score_legs(
+        &spec.legs,
+        &spec.combination_method,
+        &spec.score_mode,
+        spec.scoring_version,
+        inputs,
+    );
+    let AttestableValue { value, clamped } = score.attestable_value(spec.max_normalized_value);
+    Attestable {
+        score,
+        value,
+        clamped,
+    }
+}
+
+/// Weights scaled to sum to one.
+pub fn normalize_weights(weights: &[f64]) -> Vec<f64> {
+    let total: f64 = weights.iter().sum();
+    weights.iter().map(|weight| weight / total).collect()
+}
+
+/// Combine leg values with their weights, see [`CombinationMethod`] for the semantics.
+pub fn combine_scores(
+    values: &[f64],
+    weights: &[f64],
+    combination_method: &CombinationMethod,
+) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+
+    let weights = normalize_weights(weights);
+    match combination_method {
+        CombinationMethod::Multiply => {
+            let legs = values.len() as f64;
+            values
+                .iter()
+                .zip(&weights)
+                .map(|(value, weight)| value.powf(weight * legs))
+                .product()
+        }
+        CombinationMethod::WeightedAverage => values
+            .iter()
+            .zip(&weights)
+            .map(|(value, weight)| value * weight)
+            .sum(),
+        CombinationMethod::GeometricMean => values
+            .iter()
+            .zip(&weights)
+            .map(|(value, weight)| value.powf(*weight))
+            .product(),
+        CombinationMethod::Min => values.iter().copied().fold(f64::INFINITY, f64::min),
+        CombinationMethod::Max => values.iter().copied().fold(0.0, f64::max),
+    }
+}
+
+/// Combine leg values the way version 1 contracts settle: each leg is multiplied by its weight
+/// and the products are combined as if every weight were one.
+///
+/// - `Multiply`: `Π w_i·v_i`
+/// - `WeightedAverage`: `Σ w_i·v_i / N`
+/// - `GeometricMean`: `(Π w_i·v_i)^(1/N)`
+/// - `Min` / `Max`: the smallest / largest `w_i·v_i`
+pub fn combine_legacy_scores(
+    values: &[f64],
+    weights: &[f64],
+    combination_method: &CombinationMethod,
+) -> f64 {
+    let scores = values
+        .iter()
+        .zip(weights)
+        .map(|(value, weight)| value * weight)
+        .collect::<Vec<_>>();
+    match combination_method {
+        CombinationMethod::Multiply => scores.iter().product(),
+        CombinationMethod::WeightedAverage => {
+            let sum: f64 = scores.iter().sum();
+            sum / scores.len() as f64
+        }
+        CombinationMethod::GeometricMean => {
+            let product: f64 = scores.iter().product();
+            product.powf(1.0 / scores.len() as f64)
+        }
+        CombinationMethod::Min => {
+            if scores.is_empty() {
+                0.0
+            } else {
+                scores.iter().copied().fold(f64::INFINITY, f64::min)
+            }
+        }
+        CombinationMethod::Max => scores.iter().copied().fold(0.0, f64::max),
+    }
+}
+
+/// What each leg adds to the combined score: the factors it is the product of for `Multiply`
+/// and `GeometricMean`, the terms it is the sum of for `WeightedAverage`. Weights do not apply
+/// to `Min` and `Max`, each leg contributes its value. Under scoring version 1 every leg
+/// contributes its value scaled by its weight, see [`combine_legacy_scores`].
+pub fn leg_contributions(
+    values: &[f64],
+    weights: &[f64],
+    combination_method: &CombinationMethod,
+    scoring_version: u32,
+) -> Vec<f64> {
+    let legs = values.len() as f64;
+    if scoring_version < 2 {
+        return values
+            .iter()
+            .zip(weights)
+            .map(|(value, weight)| {
+                let score = value * weight;
+                match combination_method {
+                    CombinationMethod::WeightedAverage => score / legs,
+                    CombinationMethod::GeometricMean => score.powf(1.0 / legs),
+                    CombinationMethod::Multiply
+                    | CombinationMethod::Min
+                    | CombinationMethod::Max => score,
+                }
+            })
+            .collect();
+    }
+
+    let weights = normalize_weights(weights);
+    values
+        .iter()
+        .zip(&weights)
+        .map(|(value, weight)| match combination_method {
+            CombinationMethod::Multiply => value.powf(weight * legs),
+            CombinationMethod::WeightedAverage => value * weight,
+            CombinationMethod::GeometricMean => value.powf(*weight),
+            CombinationMethod::Min | CombinationMethod::Max => *value,
+        })
+        .collect()
+}
+
+/// Intermediate and final values of scoring a parlay against leg outcomes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParlayScore {
+    pub normalized_values: Vec<f64>,
+    pub transformed_values: Vec<f64>,
+    pub combined_score: f64,
+    /// The combined score as computed by the decimal engine, settlement uses it when present
+    pub exact_score: Option<Decimal>,
+}
+
+impl ParlayScore {
+    pub fn attestable_value(&self, max_normalized_value: u64) -> AttestableValue {
+        match self.exact_score {
+            Some(score) => decimal::checked_attestable_value(score, max_normalized_value),
+            None => checked_attestable_value(self.combined_score, max_normalized_value),
+        }
+    }
+}
+
+impl From<decimal::DecimalScore> for ParlayScore {
+    fn from(score: decimal::DecimalScore) -> Self {
+        let to_f64 = |values: &[Decimal]| {
+            values
+                .iter()
+                .map(|value| value.to_f64().unwrap_or_default())
+                .collect()
+        };
+        Self {
+            normalized_values: to_f64(&score.normalized_values),
+            transformed_values: to_f64(&score.transformed_values),
+            combined_score: score.combined_score.to_f64().unwrap_or_default(),
+            exact_score: Some(score.combined_score),
+        }
+    }
+}
+
+/// Score `outcomes`, one per leg and in the same order, under `scoring_version`.
+pub fn score_legs(
+    legs: &[Leg],
+    combination_method: &CombinationMethod,
+    score_mode: &ScoreMode,
+    scoring_version: u32,
+    outcomes: &[f64],
+) -> ParlayScore {
+    if scoring_version >= DECIMAL_SCORING_VERSION {
+        return decimal::score_legs(legs, combination_method, score_mode, outcomes).into();
+    }
+
+    let normalized_values = legs
+        .iter()
+        .zip(outcomes)
+        .map(|(leg, outcome)| match score_mode {
+            ScoreMode::Continuous => leg.normalize(*outcome),
+            ScoreMode::Binary => {
+                if leg.threshold_met(*outcome) {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+    let transformed_values = match score_mode {
+        ScoreMode::Continuous => legs
+            .iter()
+            .zip(&normalized_values)
+            .map(|(leg, value)| leg.transformation.apply(*value, scoring_version))
+            .collect::<Vec<_>>(),
+        ScoreMode::Binary => normalized_values.clone(),
+    };
+    let weights = legs.iter().map(|leg| leg.weight).collect::<Vec<_>>();
+    let combined_score = if scoring_version < 2 {
+        combine_legacy_scores(&transformed_values, &weights, combination_method)
+    } else {
+        combine_scores(&transformed_values, &weights, combination_method)
+    };
+
+    ParlayScore {
+        normalized_values,
+        transformed_values,
+        combined_score,
+        exact_score: None,
+    }
+}
+
+pub fn convert_to_attestable_value(combined_score: f64, max_normalized_value: u64) -> u64 {
+    (combined_score * max_normalized_value as f64) as u64
+}
+
+/// A value that fits the contract's announced digits.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AttestableValue {
+    pub value: u64,
+    /// Whether the score fell outside what the announcement can express and was clamped
+    pub clamped: bool,
+}
+
+/// Like [`convert_to_attestable_value`] but clamped to `[0, 2^nb_digits - 1]` for the digits
+/// announced with `max_normalized_value`. Scores that are not finite settle on zero.
+pub fn checked_attestable_value(combined_score: f64, max_normalized_value: u64) -> AttestableValue {
+    let (_, oracle_max_value) = calculate_oracle_parameters(max_normalized_value);
+    let scaled = combined_score * max_normalized_value as f64;
+    if !scaled.is_finite() || scaled < 0.0 {
+        return AttestableValue {
+            value: 0,
+            clamped: true,
+        };
+    }
+    let value = scaled as u64;
+    if value > oracle_max_value {
+        return AttestableValue {
+            value: oracle_max_value,
+            clamped: true,
+        };
+    }
+    AttestableValue {
+        value,
+        clamped: false,
+    }
+}
+
+/// Calculate oracle parameters from max normalized value
+///
+/// Returns a tuple with:
+/// - nb_digits: Number of binary digits needed for the oracle
+/// - oracle_max_value: Maximum value the oracle can attest to (2^nb_digits - 1)
+pub fn calculate_oracle_parameters(max_normalized_value: u64) -> (u16, u64) {
+    // Calculate the minimum number of bits needed to represent max_normalized_value
+    let nb_digits = if max_normalized_value == 0 {
+        1 // Handle edge case
+    } else {
+        // Find ceiling of log base 2
+        (max_normalized_value as f64).log2().ceil() as u16
+    };
+
+    // Calculate the maximum value the oracle can represent with nb_digits
+    let oracle_max_value = (1u64 << nb_digits) - 1;
+
+    (nb_digits, oracle_max_value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn leg_contributions_make_up_the_combined_score() {
+        let values = [0.5, 0.8, 0.25];
+        let weights = [1.0, 2.0, 1.0];
+        for method in [
+            CombinationMethod::Multiply,
+            CombinationMethod::WeightedAverage,
+            CombinationMethod::GeometricMean,
+        ] {
+            for scoring_version in 1..=SCORING_VERSION {
+                let contributions = leg_contributions(&values, &weights, &method, scoring_version);
+                let recomposed = match method {
+                    CombinationMethod::WeightedAverage => contributions.iter().sum::<f64>(),
+                    _ => contributions.iter().product::<f64>(),
+                };
+                let combined = if scoring_version < 2 {
+                    combine_legacy_scores(&values, &weights, &method)
+                } else {
+                    combine_scores(&values, &weights, &method)
+                };
+                assert!(
+                    (recomposed - combined).abs() < 1e-12,
+                    "{} (v{})",
+                    method,
+                    scoring_version
+                );
+            }
+        }
+        assert_eq!(
+            leg_contributions(&values, &weights, &CombinationMethod::Min, SCORING_VERSION),
+            values
+        );
+        assert_eq!(
+            leg_contributions(&values, &weights, &CombinationMethod::Min, 1),
+            [0.5, 1.6, 0.25]
+        );
+    }
+
+    #[test]
+    fn scores_like_the_oracle() {
+        let mut spec = ContractSpec {
+            legs: vec![
+                Leg::linear(700.0, 100.0, true),
+                Leg {
+                    transformation: TransformationFunction::Quadratic,
+                    ..Leg::linear(10.0, 5.0, false)
+                },
+            ],
+            combination_method: CombinationMethod::Multiply,
+            score_mode: ScoreMode::Continuous,
+            max_normalized_value: 1000,
+            scoring_version: SCORING_VERSION,
+        };
+        // 0.5 and 0.6^2
+        let attestable = score(&spec, &[750.0, 7.0]);
+        assert_eq!(attestable.value, 180);
+        assert!(!attestable.clamped);
+        assert_eq!(attestable.score.normalized_values, [0.5, 0.6]);
+        assert!(attestable.score.exact_score.is_some());
+
+        spec.scoring_version = 2;
+        assert_eq!(score(&spec, &[750.0, 7.0]).value, 180);
+
+        // Version 1 scales each leg by its weight instead of treating weights as exponents
+        spec.legs[0].weight = 2.0;
+        assert_eq!(score(&spec, &[750.0, 7.0]).value, 200);
+        spec.scoring_version = 1;
+        assert_eq!(score(&spec, &[750.0, 7.0]).value, 360);
+        spec.scoring_version = 2;
+        spec.legs[0].weight = 1.0;
+
+        spec.score_mode = ScoreMode::Binary;
+        assert_eq!(score(&spec, &[750.0, 11.0]).value, 0);
+        assert_eq!(score(&spec, &[750.0, 9.0]).value, 1000);
+    }
+}
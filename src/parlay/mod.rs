@@ -1,2 +1,16 @@
+#[cfg(feature = "server")]
+pub mod backtest;
+#[cfg(feature = "server")]
+pub mod builder;
+#[cfg(feature = "server")]
 pub mod contract;
+#[cfg(feature = "server")]
+pub mod correlation;
+pub mod decimal;
+#[cfg(feature = "server")]
+pub mod estimate;
+#[cfg(feature = "server")]
+pub mod external;
+pub mod math;
+#[cfg(feature = "server")]
 pub mod parameter;
@@ -1,19 +1,21 @@
-use crate::events::EventType;
+use crate::events::{EventType, OutcomeOptions};
+use crate::mempool::{Aggregation, FeePercentile, TimePeriod};
+use crate::parlay::external::ExternalEvent;
+use crate::parlay::math::Leg;
 use serde::{Deserialize, Serialize};
-use sqlx::postgres::PgRow;
 use sqlx::prelude::FromRow;
-use sqlx::Row;
+use sqlx::types::Json;
 use std::str::FromStr;
-use strum_macros::Display;
-use strum_macros::EnumIter;
-use strum_macros::EnumString;
+
+pub use crate::parlay::math::TransformationFunction;
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct ParlayParameter {
     /// The type of event to be monitored from Bitcoin core
     pub data_type: EventType,
-    /// The threshold value for the event for contract strike
+    /// The threshold value for the event for contract strike, in the data type's unit
+    /// (see [`crate::units`])
     pub threshold: f64,
     /// The range of the data type
     pub range: f64,
@@ -23,75 +25,136 @@ pub struct ParlayParameter {
     pub transformation: TransformationFunction,
     /// The weight of the event
     pub weight: f64,
+    /// Fee rate percentile to settle on, only used for fee rate parameters
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub percentile: Option<FeePercentile>,
+    /// Statistic applied over the data window, defaults to the data type's own
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aggregation: Option<Aggregation>,
+    /// Data window used at attestation, defaults to three months
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub period: Option<TimePeriod>,
+    /// An announced single event this leg settles on instead of fetching `data_type` itself.
+    /// Its signed outcome is used when available, otherwise the value is fetched with the
+    /// referenced event's options.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event_id: Option<String>,
+    /// A numeric event of another oracle this leg settles on, `threshold` and `range` are in
+    /// its unit. `data_type` only labels the leg.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub external: Option<ExternalEvent>,
 }
 
 impl ParlayParameter {
-    pub fn normalize_parameter(&self, value: f64) -> f64 {
-        if self.is_above_threshold {
-            // Parameter must EXCEED threshold (e.g., hash rate > X)
-            if value <= self.threshold {
-                // Below threshold - return 0
-                return 0.0;
-            } else {
-                // Above threshold - normalize based on distance
-                let distance = value - self.threshold;
-                let normalized = distance as f64 / self.range;
-                // Cap at 1.0 for values beyond threshold + range
-                return normalized.min(1.0);
-            }
-        } else {
-            // Parameter must STAY BELOW threshold (e.g., price < Y)
-            if value >= self.threshold {
-                // Above threshold - return 0
-                return 0.0;
-            } else {
-                // Below threshold - normalize based on distance
-                let distance = self.threshold - value;
-                let normalized = distance / self.range;
-                // Cap at 1.0 for values beyond threshold - range
-                return normalized.min(1.0);
-            }
+    /// A leg on `data_type` fetched by the oracle, with a linear transformation and a weight
+    /// of one.
+    pub fn linear(
+        data_type: EventType,
+        threshold: f64,
+        range: f64,
+        is_above_threshold: bool,
+    ) -> Self {
+        Self {
+            data_type,
+            threshold,
+            range,
+            is_above_threshold,
+            transformation: TransformationFunction::Linear,
+            weight: 1.0,
+            percentile: None,
+            aggregation: None,
+            period: None,
+            event_id: None,
+            external: None,
+        }
+    }
+
+    pub fn with_weight(mut self, weight: f64) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    pub fn outcome_options(&self) -> OutcomeOptions {
+        OutcomeOptions {
+            fee_percentile: self.percentile.unwrap_or_default(),
+            aggregation: self.aggregation,
+            period: self.period,
+            smoothing: None,
         }
     }
 
-    pub fn apply_transformation(&self, normalized_value: f64) -> f64 {
-        match self.transformation {
-            TransformationFunction::Linear => normalized_value,
-            TransformationFunction::Quadratic => normalized_value * normalized_value,
-            TransformationFunction::Sqrt => normalized_value.sqrt(),
-            TransformationFunction::Exponential => normalized_value.exp(),
-            TransformationFunction::Logarithmic => normalized_value.ln(),
+    /// The part of the parameter scoring needs.
+    pub fn leg(&self) -> Leg {
+        Leg {
+            threshold: self.threshold,
+            range: self.range,
+            is_above_threshold: self.is_above_threshold,
+            transformation: self.transformation.clone(),
+            weight: self.weight,
         }
     }
+
+    /// Whether `value` is strictly on the winning side of the threshold.
+    pub fn threshold_met(&self, value: f64) -> bool {
+        self.leg().threshold_met(value)
+    }
+
+    pub fn normalize_parameter(&self, value: f64) -> f64 {
+        self.leg().normalize(value)
+    }
+
+    /// Transform a normalized value under the contract's scoring version, see
+    /// [`TransformationFunction::apply`].
+    pub fn apply_transformation(&self, normalized_value: f64, scoring_version: u32) -> f64 {
+        self.transformation.apply(normalized_value, scoring_version)
+    }
+}
+
+/// A stored parameter, see [`ParlayParameterRow::COLUMNS`].
+#[derive(Debug, FromRow)]
+pub struct ParlayParameterRow {
+    pub data_type: String,
+    pub threshold: f64,
+    pub range: f64,
+    pub is_above_threshold: bool,
+    pub transformation: String,
+    pub weight: f64,
+    pub percentile: Option<i16>,
+    pub aggregation: Option<String>,
+    pub period: Option<String>,
+    pub event_id: Option<String>,
+    pub external_event: Option<Json<ExternalEvent>>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq, EnumIter, Display, EnumString)]
-#[serde(rename_all = "lowercase")]
-#[strum(serialize_all = "lowercase")]
-pub enum TransformationFunction {
-    Linear,
-    Quadratic,
-    Sqrt,
-    Exponential,
-    Logarithmic,
+impl ParlayParameterRow {
+    pub const COLUMNS: &'static str = "data_type, threshold, range, is_above_threshold, transformation, weight, percentile, aggregation, period, event_id, external_event";
 }
 
-pub fn parlay_parameter_from_row(row: &PgRow) -> anyhow::Result<ParlayParameter> {
-    let data_type: String = row.get("data_type");
-    let threshold: f64 = row.get("threshold");
-    let range: f64 = row.get("range");
-    let is_above_threshold: bool = row.get("is_above_threshold");
-    let transformation: String = row.get("transformation");
-    let weight: f64 = row.get("weight");
-
-    Ok(ParlayParameter {
-        data_type: EventType::from_str(&data_type)?,
-        threshold,
-        range,
-        is_above_threshold,
-        transformation: TransformationFunction::from_str(&transformation)?,
-        weight,
-    })
+impl TryFrom<ParlayParameterRow> for ParlayParameter {
+    type Error = anyhow::Error;
+
+    fn try_from(row: ParlayParameterRow) -> anyhow::Result<Self> {
+        Ok(ParlayParameter {
+            data_type: EventType::from_str(&row.data_type)?,
+            threshold: row.threshold,
+            range: row.range,
+            is_above_threshold: row.is_above_threshold,
+            transformation: TransformationFunction::from_str(&row.transformation)?,
+            weight: row.weight,
+            percentile: row
+                .percentile
+                .map(|p| FeePercentile::try_from(p as u8))
+                .transpose()
+                .map_err(|e| anyhow::anyhow!(e))?,
+            aggregation: row
+                .aggregation
+                .map(|a| Aggregation::from_str(&a))
+                .transpose()?,
+            period: row.period.map(|p| TimePeriod::from_str(&p)).transpose()?,
+            event_id: row.event_id,
+            external: row.external_event.map(|external| external.0),
+        })
+    }
 }
 
 #[cfg(test)]
@@ -115,6 +178,45 @@ mod tests {
         assert_eq!(trans[4], "logarithmic");
     }
 
+    #[test]
+    fn transformations_stay_in_unit_range() {
+        let parameter = |transformation| ParlayParameter {
+            data_type: EventType::Hashrate,
+            threshold: 0.0,
+            range: 1.0,
+            is_above_threshold: true,
+            transformation,
+            weight: 1.0,
+            percentile: None,
+            aggregation: None,
+            period: None,
+            event_id: None,
+            external: None,
+        };
+        for transformation in TransformationFunction::iter() {
+            let parameter = parameter(transformation);
+            assert_eq!(parameter.apply_transformation(0.0, 2), 0.0);
+            assert!((parameter.apply_transformation(1.0, 2) - 1.0).abs() < 1e-12);
+            let mid = parameter.apply_transformation(0.5, 2);
+            assert!(mid > 0.0 && mid < 1.0);
+        }
+
+        let legacy = parameter(TransformationFunction::Logarithmic);
+        assert_eq!(legacy.apply_transformation(0.0, 1), f64::NEG_INFINITY);
+        let legacy = parameter(TransformationFunction::Exponential);
+        assert_eq!(legacy.apply_transformation(0.0, 1), 1.0);
+    }
+
+    #[test]
+    fn parlay_options_cover_every_variant() {
+        let options = crate::routes::parlay_options_internal();
+        assert_eq!(options.transformations.len(), 5);
+        assert_eq!(options.transformations[0].name, "linear");
+        assert_eq!(options.combination_methods.len(), 5);
+        assert_eq!(options.combination_methods[1].name, "weightedAverage");
+        assert_eq!(options.score_modes.len(), 2);
+    }
+
     #[test]
     fn combination_method_conversion() {
         let comb = CombinationMethod::iter()
@@ -0,0 +1,195 @@
+//! Agreement of several data sources on an outcome.
+//!
+//! Besides the mempool instance in [`crate::config`], further mempool API compatible sources can
+//! be configured, e.g. mempool.space next to a self-hosted mempool. Every source is sampled (see
+//! [`crate::sampling`]) and a value is only signed when a majority of sources answered and all
+//! answers are within [`QuorumConfig::tolerance`] of their median. Sources disagreeing beyond it
+//! fail with [`SourcesDisagree`], on which the watcher holds the event and alerts the operator
+//! instead of signing whichever source happened to be asked.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    events::{EventType, OutcomeOptions},
+    mempool::{DataProvenance, DataSample, MempoolClient, MempoolConfig},
+    sampling::{self, SamplingPolicy},
+};
+
+pub const DEFAULT_TOLERANCE: f64 = 0.005;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct QuorumConfig {
+    /// Sources next to the configured mempool instance, on the network of the oracle
+    pub sources: Vec<MempoolConfig>,
+    /// Largest accepted deviation of a source from the median, relative to the median
+    pub tolerance: f64,
+}
+
+impl Default for QuorumConfig {
+    fn default() -> Self {
+        Self {
+            sources: vec![],
+            tolerance: DEFAULT_TOLERANCE,
+        }
+    }
+}
+
+/// Sources answering with values too far apart to sign any of them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourcesDisagree {
+    pub data_type: EventType,
+    /// The value of every answering source by its url
+    pub values: Vec<(String, f64)>,
+}
+
+impl std::fmt::Display for SourcesDisagree {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let values = self
+            .values
+            .iter()
+            .map(|(source_url, value)| format!("{}={}", source_url, value))
+            .collect::<Vec<_>>();
+        write!(
+            f,
+            "Data sources disagree beyond the tolerance. data_type={} values={}",
+            self.data_type,
+            values.join(",")
+        )
+    }
+}
+
+impl std::error::Error for SourcesDisagree {}
+
+/// Settle on the median of the `answers` of `sources` sources, each answer settled by sampling.
+pub fn resolve(
+    data_type: &EventType,
+    mut answers: Vec<DataProvenance>,
+    sources: usize,
+    tolerance: f64,
+) -> anyhow::Result<DataProvenance> {
+    if answers.len() < sources / 2 + 1 {
+        return Err(anyhow::anyhow!(
+            "Too few data sources answered. answered={} sources={}",
+            answers.len(),
+            sources
+        ));
+    }
+    answers.sort_by(|a, b| a.value.total_cmp(&b.value));
+    let middle = answers.remove((answers.len() - 1) / 2);
+    if answers
+        .iter()
+        .any(|answer| (answer.value - middle.value).abs() > tolerance * middle.value.abs())
+    {
+        let mut values = answers
+            .iter()
+            .chain([&middle])
+            .map(|answer| (answer.source_url.clone(), answer.value))
+            .collect::<Vec<_>>();
+        values.sort_by(|a, b| a.0.cmp(&b.0));
+        return Err(SourcesDisagree {
+            data_type: data_type.clone(),
+            values,
+        }
+        .into());
+    }
+    let mut settled = middle;
+    settled
+        .samples
+        .extend(answers.into_iter().map(|answer| DataSample {
+            value: answer.value,
+            fetched_at: answer.fetched_at,
+            rejected: false,
+            source_url: Some(answer.source_url),
+        }));
+    Ok(settled)
+}
+
+/// Sample the outcome of `event_type` from every source and settle on a value they agree on.
+pub async fn resolve_outcome(
+    event_type: &EventType,
+    sources: &[&MempoolClient],
+    options: &OutcomeOptions,
+    policy: &SamplingPolicy,
+    tolerance: f64,
+) -> anyhow::Result<DataProvenance> {
+    if let [source] = sources {
+        return sampling::sample_outcome(event_type, source, options, policy).await;
+    }
+    let mut answers = Vec::with_capacity(sources.len());
+    let mut last_error = None;
+    for source in sources {
+        match sampling::sample_outcome(event_type, source, options, policy).await {
+            Ok(answer) => answers.push(answer),
+            Err(e) => {
+                log::warn!(
+                    "Data source failed. source={} data_type={} error={}",
+                    source.base_url(),
+                    event_type,
+                    e
+                );
+                last_error = Some(e);
+            }
+        }
+    }
+    match last_error {
+        // The error is kept so an upstream outage is still reported as one
+        Some(e) if answers.len() < sources.len() / 2 + 1 => Err(e),
+        _ => resolve(event_type, answers, sources.len(), tolerance),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{mock_mempool_client, setup_mock_server};
+    use wiremock::{matchers::path, Mock, ResponseTemplate};
+
+    async fn resolve(sources: Vec<&MempoolClient>) -> anyhow::Result<DataProvenance> {
+        let policy = SamplingPolicy {
+            samples: 1,
+            ..Default::default()
+        };
+        resolve_outcome(
+            &EventType::BlocksUntilHalving,
+            &sources,
+            &OutcomeOptions::default(),
+            &policy,
+            DEFAULT_TOLERANCE,
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn signs_only_when_sources_agree() {
+        let first = setup_mock_server().await;
+        let second = setup_mock_server().await;
+        let (first_client, second_client) =
+            (mock_mempool_client(&first), mock_mempool_client(&second));
+        let settled = resolve(vec![&first_client, &second_client]).await.unwrap();
+        assert_eq!(settled.value, 209_999.0);
+        assert_eq!(settled.samples.len(), 1);
+        assert!(settled.samples[0].source_url.is_some());
+
+        // The second source is on another chain tip
+        let forked = wiremock::MockServer::start().await;
+        Mock::given(path("/api/blocks/tip/height"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(100_000))
+            .mount(&forked)
+            .await;
+        let forked_client = mock_mempool_client(&forked);
+        let error = resolve(vec![&first_client, &forked_client])
+            .await
+            .unwrap_err();
+        let disagree = error.downcast_ref::<SourcesDisagree>().unwrap();
+        assert_eq!(disagree.values.len(), 2);
+
+        // A source that is down is outvoted
+        let down = MempoolClient::new("http://127.0.0.1:9/api/v1".to_string())
+            .with_esplora_url("http://127.0.0.1:9/api".to_string());
+        let settled = resolve(vec![&first_client, &second_client, &down])
+            .await
+            .unwrap();
+        assert_eq!(settled.value, 209_999.0);
+    }
+}
@@ -0,0 +1,172 @@
+//! Messages the oracle signs outside of announcements and attestations.
+//!
+//! Every message is hashed with a domain tag and length-prefixed fields so a signature over one
+//! kind of receipt can never be replayed as another.
+
+use bitcoin::{
+    hashes::{sha256, Hash, HashEngine},
+    key::Secp256k1,
+    secp256k1::{schnorr::Signature, Message},
+    XOnlyPublicKey,
+};
+use chrono::{DateTime, Utc};
+
+pub const BUNDLE_TAG: &str = "ernest-oracle/bundle/v1";
+pub const CREATION_TAG: &str = "ernest-oracle/creation/v1";
+pub const KEY_PROOF_TAG: &str = "ernest-oracle/key-proof/v1";
+pub const PROVENANCE_TAG: &str = "ernest-oracle/provenance/v1";
+pub const SERIES_TAG: &str = "ernest-oracle/series/v1";
+pub const SIGNING_AUDIT_TAG: &str = "ernest-oracle/signing-audit/v1";
+
+struct ReceiptEngine(sha256::HashEngine);
+
+impl ReceiptEngine {
+    fn new(tag: &str) -> Self {
+        let mut engine = ReceiptEngine(sha256::Hash::engine());
+        engine.bytes(tag.as_bytes());
+        engine
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.0.input(&(bytes.len() as u32).to_be_bytes());
+        self.0.input(bytes);
+    }
+
+    fn digest(self) -> [u8; 32] {
+        sha256::Hash::from_engine(self.0).to_byte_array()
+    }
+
+    fn message(self) -> Message {
+        Message::from_digest(self.digest())
+    }
+}
+
+/// Canonical message binding an event to the upstream value it was settled on.
+pub fn provenance_message(
+    event_id: &str,
+    source_url: &str,
+    value: f64,
+    fetched_at: DateTime<Utc>,
+) -> Message {
+    let mut engine = ReceiptEngine::new(PROVENANCE_TAG);
+    engine.bytes(event_id.as_bytes());
+    engine.bytes(source_url.as_bytes());
+    engine.bytes(&value.to_be_bytes());
+    engine.bytes(&fetched_at.timestamp_millis().to_be_bytes());
+    engine.message()
+}
+
+/// Canonical message binding a created event to the hashed API key that created it.
+pub fn creation_message(event_id: &str, api_key_hash: &str, created_at: DateTime<Utc>) -> Message {
+    let mut engine = ReceiptEngine::new(CREATION_TAG);
+    engine.bytes(event_id.as_bytes());
+    engine.bytes(api_key_hash.as_bytes());
+    engine.bytes(&created_at.timestamp_millis().to_be_bytes());
+    engine.message()
+}
+
+/// Canonical message committing to a series of events in order.
+pub fn series_message(series_id: &str, events: &[(&str, u32)]) -> Message {
+    let mut engine = ReceiptEngine::new(SERIES_TAG);
+    engine.bytes(series_id.as_bytes());
+    engine.bytes(&(events.len() as u32).to_be_bytes());
+    for (event_id, maturity) in events {
+        engine.bytes(event_id.as_bytes());
+        engine.bytes(&maturity.to_be_bytes());
+    }
+    engine.message()
+}
+
+/// Canonical message committing to the set of announcements served at `served_at`, by their
+/// sorted hashes.
+pub fn bundle_message(announcement_hashes: &[[u8; 32]], served_at: DateTime<Utc>) -> Message {
+    let mut engine = ReceiptEngine::new(BUNDLE_TAG);
+    engine.bytes(&(announcement_hashes.len() as u32).to_be_bytes());
+    for hash in announcement_hashes {
+        engine.bytes(hash);
+    }
+    engine.bytes(&served_at.timestamp_millis().to_be_bytes());
+    engine.message()
+}
+
+/// Canonical message proving the oracle holds its key, over a client's challenge or, without
+/// one, over the time alone.
+pub fn key_proof_message(challenge: Option<&str>, signed_at: i64) -> Message {
+    let mut engine = ReceiptEngine::new(KEY_PROOF_TAG);
+    engine.bytes(&[challenge.is_some() as u8]);
+    engine.bytes(challenge.unwrap_or_default().as_bytes());
+    engine.bytes(&signed_at.to_be_bytes());
+    engine.message()
+}
+
+/// Hash of a signing audit entry, chained to the hash of the entry before it.
+pub fn signing_audit_hash(
+    prev_hash: Option<&[u8]>,
+    event_id: &str,
+    outcome: i64,
+    nonce_indexes: &[u32],
+    source: &str,
+    signed_at: DateTime<Utc>,
+) -> [u8; 32] {
+    let mut engine = ReceiptEngine::new(SIGNING_AUDIT_TAG);
+    engine.bytes(prev_hash.unwrap_or_default());
+    engine.bytes(event_id.as_bytes());
+    engine.bytes(&outcome.to_be_bytes());
+    engine.bytes(&(nonce_indexes.len() as u32).to_be_bytes());
+    for index in nonce_indexes {
+        engine.bytes(&index.to_be_bytes());
+    }
+    engine.bytes(source.as_bytes());
+    engine.bytes(&signed_at.timestamp_micros().to_be_bytes());
+    engine.digest()
+}
+
+pub fn verify_receipt(pubkey: &XOnlyPublicKey, message: &Message, signature: &Signature) -> bool {
+    Secp256k1::verification_only()
+        .verify_schnorr(signature, message, pubkey)
+        .is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::key::Keypair;
+    use bitcoin::secp256k1::SecretKey;
+    use chrono::TimeZone;
+
+    #[test]
+    fn provenance_receipt_roundtrip() {
+        let secp = Secp256k1::new();
+        let keypair = Keypair::from_secret_key(&secp, &SecretKey::from_slice(&[7u8; 32]).unwrap());
+        let fetched_at = Utc.timestamp_millis_opt(1_700_000_000_123).unwrap();
+        let message = provenance_message("event", "https://mempool.space", 12.5, fetched_at);
+        let signature = secp.sign_schnorr_no_aux_rand(&message, &keypair);
+
+        let pubkey = keypair.x_only_public_key().0;
+        assert!(verify_receipt(&pubkey, &message, &signature));
+
+        let tampered = provenance_message("event", "https://mempool.space", 12.6, fetched_at);
+        assert!(!verify_receipt(&pubkey, &tampered, &signature));
+    }
+
+    #[test]
+    fn series_message_commits_to_order() {
+        assert_ne!(
+            series_message("series", &[("a", 1), ("b", 2)]),
+            series_message("series", &[("b", 2), ("a", 1)])
+        );
+        assert_ne!(
+            series_message("series", &[("a", 1)]),
+            series_message("series", &[("a", 2)])
+        );
+    }
+
+    #[test]
+    fn provenance_fields_are_length_prefixed() {
+        let fetched_at = Utc.timestamp_millis_opt(0).unwrap();
+        assert_ne!(
+            provenance_message("ab", "c", 1.0, fetched_at),
+            provenance_message("a", "bc", 1.0, fetched_at)
+        );
+    }
+}
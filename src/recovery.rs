@@ -0,0 +1,365 @@
+//! Audit and recovery of event nonces.
+//!
+//! Nonces are derived from the oracle key and their index, so they can be proven and, when
+//! `event_nonces` rows are lost, found again by deriving a range of indexes. Losing rows is
+//! dangerous: a database restored from an older backup also rewinds the nonce index sequence,
+//! so new events would reuse nonces of announced ones. Restore the rows before starting the
+//! oracle again, repairing moves the sequence past them.
+
+use std::{collections::HashMap, ops::Range};
+
+use bitcoin::XOnlyPublicKey;
+use kormir::storage::Storage;
+use serde::{Deserialize, Serialize};
+use sqlx::Postgres;
+
+use crate::{
+    attestation,
+    audit::{self, SigningSource},
+    oracle::ErnestOracle,
+    storage,
+};
+
+/// Indexes past the highest stored one that are searched for missing nonces.
+pub const DEFAULT_SCAN_MARGIN: u32 = 10_000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "status")]
+pub enum NonceStatus {
+    /// Every nonce is stored and derives from its index
+    Consistent,
+    /// A stored index does not derive the announced nonce
+    Mismatch { index: u32 },
+    /// Nonce rows are missing, `recovered` has the index of every announced nonce if all were
+    /// found
+    Missing { recovered: Option<Vec<u32>> },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NonceReport {
+    pub event_id: String,
+    #[serde(flatten)]
+    pub status: NonceStatus,
+}
+
+/// The nonces of `indexes` mapped to their index.
+pub fn derive_nonces(
+    oracle: &ErnestOracle,
+    indexes: Range<u32>,
+) -> anyhow::Result<HashMap<XOnlyPublicKey, u32>> {
+    indexes
+        .map(|index| Ok((oracle.nonce_public_key(index)?, index)))
+        .collect()
+}
+
+/// Check the stored nonces of one event, looking missing ones up in `derived`.
+pub async fn audit_event(
+    oracle: &ErnestOracle,
+    event_id: &str,
+    derived: &HashMap<XOnlyPublicKey, u32>,
+) -> anyhow::Result<NonceReport> {
+    let event = oracle
+        .oracle
+        .storage
+        .get_event(event_id.to_string())
+        .await?
+        .ok_or(anyhow::anyhow!("Event not found. event_id={}", event_id))?;
+    let nonces = &event.announcement.oracle_event.oracle_nonces;
+
+    let status = if event.indexes.len() == nonces.len() {
+        let mut status = NonceStatus::Consistent;
+        for (index, nonce) in event.indexes.iter().zip(nonces) {
+            if oracle.nonce_public_key(*index)? != *nonce {
+                status = NonceStatus::Mismatch { index: *index };
+                break;
+            }
+        }
+        status
+    } else {
+        NonceStatus::Missing {
+            recovered: nonces
+                .iter()
+                .map(|nonce| derived.get(nonce).copied())
+                .collect(),
+        }
+    };
+    Ok(NonceReport {
+        event_id: event_id.to_string(),
+        status,
+    })
+}
+
+/// Audit every event, searching up to `scan_margin` indexes past the highest stored one.
+pub async fn audit(oracle: &ErnestOracle, scan_margin: u32) -> anyhow::Result<Vec<NonceReport>> {
+    let pool = &oracle.oracle.storage.pool;
+    let event_ids =
+        sqlx::query_scalar::<Postgres, String>("SELECT event_id FROM events ORDER BY created_at")
+            .fetch_all(pool)
+            .await?;
+    let max_index =
+        sqlx::query_scalar::<Postgres, i32>("SELECT COALESCE(MAX(index), 0) FROM event_nonces")
+            .fetch_one(pool)
+            .await?;
+    let derived = derive_nonces(oracle, 0..(max_index as u32).saturating_add(scan_margin))?;
+
+    let mut reports = Vec::with_capacity(event_ids.len());
+    for event_id in event_ids {
+        reports.push(audit_event(oracle, &event_id, &derived).await?);
+    }
+    Ok(reports)
+}
+
+/// Reason restored events are held with when their outcome is not known.
+pub const UNKNOWN_OUTCOME_HOLD_REASON: &str =
+    "Nonces were restored without a recorded outcome, the event may already be signed";
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Repair {
+    /// Number of events whose nonce rows were restored
+    pub restored: usize,
+    /// Restored events held back from signing, see [`UNKNOWN_OUTCOME_HOLD_REASON`]
+    pub held: Vec<String>,
+}
+
+/// Restore the nonce rows of the recoverable events.
+///
+/// Signatures are lost with the rows. Events with a recorded attestation or signing audit
+/// entry are signed again with the recorded value, which reproduces the original signatures.
+/// Matured events without either may have been signed with a value that was not recorded:
+/// signing them with another would reveal the key, so they are held until an operator checks
+/// them.
+pub async fn repair(oracle: &ErnestOracle, reports: &[NonceReport]) -> anyhow::Result<Repair> {
+    let pool = &oracle.oracle.storage.pool;
+    let mut repair = Repair::default();
+    for report in reports {
+        let NonceStatus::Missing {
+            recovered: Some(indexes),
+        } = &report.status
+        else {
+            continue;
+        };
+        let event = oracle
+            .oracle
+            .storage
+            .get_event(report.event_id.clone())
+            .await?
+            .ok_or(anyhow::anyhow!(
+                "Event not found. event_id={}",
+                report.event_id
+            ))?;
+
+        let value = match attestation::get_attested_value(pool, &report.event_id).await? {
+            Some(value) => Some(value),
+            None => audit::signed_outcome(pool, &report.event_id).await?,
+        };
+        let matured = sqlx::query_scalar::<Postgres, bool>(
+            "SELECT maturity <= $1 FROM events WHERE event_id = $2",
+        )
+        .bind(chrono::Utc::now().timestamp())
+        .bind(&report.event_id)
+        .fetch_one(pool)
+        .await?;
+        let hold = value.is_none() && matured;
+
+        let mut tx = pool.begin().await?;
+        sqlx::query("DELETE FROM event_nonces WHERE event_id = $1")
+            .bind(&report.event_id)
+            .execute(&mut *tx)
+            .await?;
+        storage::insert_nonces(
+            &mut tx,
+            &report.event_id,
+            indexes,
+            &event.announcement.oracle_event.oracle_nonces,
+            &[],
+        )
+        .await?;
+        if hold {
+            sqlx::query("UPDATE events SET hold = TRUE, hold_reason = $1 WHERE event_id = $2")
+                .bind(UNKNOWN_OUTCOME_HOLD_REASON)
+                .bind(&report.event_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+
+        if let Some(value) = value {
+            oracle
+                .sign_numeric_event(report.event_id.clone(), value, SigningSource::Admin)
+                .await?;
+        }
+        if hold {
+            log::warn!(
+                "Holding restored event, its outcome is not recorded. event_id={}",
+                report.event_id
+            );
+            repair.held.push(report.event_id.clone());
+        }
+        repair.restored += 1;
+    }
+    Ok(repair)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::setup_ernest_oracle;
+    use crate::{
+        events::EventType,
+        mempool::{MempoolClient, BASE_URL},
+        routes::CreateEvent,
+    };
+
+    #[tokio::test]
+    async fn recovers_lost_nonce_rows() {
+        let mempool = MempoolClient::new(BASE_URL.to_string());
+        let oracle = setup_ernest_oracle(mempool).await;
+        let pool = &oracle.oracle.storage.pool;
+        let announcement = oracle
+            .create_event(CreateEvent::single(
+                EventType::Hashrate,
+                chrono::Utc::now().timestamp() as u32 + 1000,
+            ))
+            .await
+            .unwrap();
+        let event_id = announcement.oracle_event.event_id.clone();
+        let signed = oracle
+            .oracle
+            .sign_numeric_event(event_id.clone(), 42)
+            .await
+            .unwrap();
+        attestation::save_attestation_outcome(pool, event_id.clone(), 42.0, 42, false)
+            .await
+            .unwrap();
+        let stored = oracle
+            .oracle
+            .storage
+            .get_event(event_id.clone())
+            .await
+            .unwrap()
+            .unwrap();
+        let first = *stored.indexes.iter().min().unwrap();
+        let last = *stored.indexes.iter().max().unwrap();
+        let derived = derive_nonces(&oracle, first..last + 2).unwrap();
+        assert_eq!(
+            audit_event(&oracle, &event_id, &derived)
+                .await
+                .unwrap()
+                .status,
+            NonceStatus::Consistent
+        );
+
+        sqlx::query("DELETE FROM event_nonces WHERE event_id = $1")
+            .bind(&event_id)
+            .execute(pool)
+            .await
+            .unwrap();
+        let report = audit_event(&oracle, &event_id, &derived).await.unwrap();
+        assert_eq!(
+            report.status,
+            NonceStatus::Missing {
+                recovered: Some(stored.indexes.clone())
+            }
+        );
+
+        assert_eq!(
+            repair(&oracle, &[report]).await.unwrap(),
+            Repair {
+                restored: 1,
+                held: vec![]
+            }
+        );
+        let restored = oracle
+            .oracle
+            .storage
+            .get_event(event_id.clone())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(restored.indexes, stored.indexes);
+        assert_eq!(restored.attestation(), Some(signed));
+    }
+
+    #[tokio::test]
+    async fn holds_matured_events_without_a_recorded_outcome() {
+        let mempool = MempoolClient::new(BASE_URL.to_string());
+        let oracle = setup_ernest_oracle(mempool).await;
+        let pool = &oracle.oracle.storage.pool;
+        let mut event_ids = vec![];
+        for _ in 0..2 {
+            let announcement = oracle
+                .create_event(CreateEvent::single(
+                    EventType::Hashrate,
+                    chrono::Utc::now().timestamp() as u32 + 1000,
+                ))
+                .await
+                .unwrap();
+            event_ids.push(announcement.oracle_event.event_id);
+        }
+        let (audited, unrecorded) = (event_ids[0].clone(), event_ids[1].clone());
+        // Signed through the audit log, but without an attestation outcome
+        let signed = oracle
+            .sign_numeric_event(audited.clone(), 42, SigningSource::Admin)
+            .await
+            .unwrap();
+        // Signed without leaving any record of the outcome
+        oracle
+            .oracle
+            .sign_numeric_event(unrecorded.clone(), 42)
+            .await
+            .unwrap();
+        sqlx::query("UPDATE events SET maturity = $1 WHERE event_id = ANY($2)")
+            .bind(chrono::Utc::now().timestamp() - 60)
+            .bind(&event_ids)
+            .execute(pool)
+            .await
+            .unwrap();
+
+        let mut indexes = vec![];
+        for event_id in &event_ids {
+            let stored = oracle
+                .oracle
+                .storage
+                .get_event(event_id.clone())
+                .await
+                .unwrap()
+                .unwrap();
+            indexes.extend(stored.indexes);
+        }
+        let derived = derive_nonces(
+            &oracle,
+            *indexes.iter().min().unwrap()..*indexes.iter().max().unwrap() + 1,
+        )
+        .unwrap();
+        sqlx::query("DELETE FROM event_nonces WHERE event_id = ANY($1)")
+            .bind(&event_ids)
+            .execute(pool)
+            .await
+            .unwrap();
+        let mut reports = vec![];
+        for event_id in &event_ids {
+            reports.push(audit_event(&oracle, event_id, &derived).await.unwrap());
+        }
+
+        assert_eq!(
+            repair(&oracle, &reports).await.unwrap(),
+            Repair {
+                restored: 2,
+                held: vec![unrecorded.clone()]
+            }
+        );
+        assert_eq!(
+            oracle.signed_attestation(&audited).await.unwrap(),
+            Some(signed)
+        );
+        assert!(!oracle.oracle.storage.is_held(&audited).await.unwrap());
+        assert!(oracle.oracle.storage.is_held(&unrecorded).await.unwrap());
+        assert!(oracle
+            .signed_attestation(&unrecorded)
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
@@ -1,20 +1,50 @@
-use crate::attestation::ErnestOracleOutcome;
-use crate::events::EventType;
+use crate::attestation::{AttestationDataOutcome, AttestationProvenance, ErnestOracleOutcome};
+use crate::audit::{self, SigningAuditEntry, SigningSource};
+use crate::bundle::{AnnouncementBundle, MAX_BUNDLE_EVENTS};
+use crate::creation_receipts::{self, CreationReceipt};
+use crate::events::{EventStatus, EventType, EventTypeMetadata};
+use crate::history::{self, MetricHistory};
+use crate::limits;
+use crate::mempool::{Aggregation, DataProvenance, FeePercentile, TimePeriod};
+use crate::metadata::{self, EventMetadata};
+use crate::nostr::{self, Rebroadcast};
+use crate::oracle::{
+    calculate_oracle_parameters, PendingEvent, SingleEventOutcome, DEFAULT_MAX_NORMALIZED_VALUE,
+};
+use crate::overrides::{self, CommitOverride, PrepareOverride, PreparedOverride};
 use crate::parlay::{
-    contract::{CombinationMethod, ParlayContract},
-    parameter::ParlayParameter,
+    backtest::{self, Backtest, BacktestRequest},
+    contract::{CombinationMethod, ParlayContract, ScoreMode},
+    correlation::{self, LegWarning},
+    estimate::{self, Estimate, EstimateRequest},
+    math,
+    parameter::{ParlayParameter, TransformationFunction},
 };
+use crate::receipts;
+use crate::series::{self, CreateSeries, SeriesManifest};
+use crate::smoothing::Smoothing;
+use crate::stats::{self, OracleStats};
+use crate::storage::to_oracle_event;
+use crate::triggers;
+use crate::units;
+use crate::usage::{self, Usage, UsageKind};
+use crate::validation;
+use crate::watcher::{self, WatcherHealthReport, WATCHER_INTERVAL_SECS};
 use crate::OracleServerState;
-use crate::{attestation, OracleServerError};
+use crate::{attestation, ErrorCode, OracleServerError};
 use anyhow::anyhow;
-use bitcoin::XOnlyPublicKey;
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::secp256k1::{schnorr::Signature, Message};
+use bitcoin::{Network, XOnlyPublicKey};
 use kormir::{
     storage::{OracleEventData, Storage},
     EventDescriptor, OracleAnnouncement, OracleAttestation,
 };
 
 use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
 
+use std::str::FromStr;
 use std::sync::Arc;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,6 +54,34 @@ pub enum CreateEvent {
         #[serde(rename = "eventType")]
         event_type: EventType,
         maturity: u32,
+        /// Fee rate percentile to settle on, only valid for fee rate events.
+        #[serde(default)]
+        percentile: Option<FeePercentile>,
+        /// Statistic applied over the data window, only valid for series backed events.
+        #[serde(default)]
+        aggregation: Option<Aggregation>,
+        /// Smoothing applied to the outcome before signing, see [`crate::smoothing`].
+        #[serde(default)]
+        smoothing: Option<Smoothing>,
+        /// Overrides the event type's default precision.
+        #[serde(default)]
+        precision: Option<i32>,
+        /// Overrides the event type's default number of binary digits.
+        #[serde(default, rename = "nbDigits")]
+        nb_digits: Option<u16>,
+        #[serde(default)]
+        description: Option<String>,
+        #[serde(default)]
+        tags: Vec<String>,
+        /// Keep the announcement unlisted until this unix timestamp, see [`CreateEvent::announce_at`].
+        #[serde(default, rename = "announceAt")]
+        announce_at: Option<u32>,
+        /// Seconds to wait after maturity before signing, see [`CreateEvent::settlement_delay`].
+        #[serde(default, rename = "settlementDelay")]
+        settlement_delay: Option<u32>,
+        /// Human readable id, see [`crate::event_ids`]. Generated when unset.
+        #[serde(default, rename = "eventId")]
+        event_id: Option<String>,
     },
     Parlay {
         parameters: Vec<ParlayParameter>,
@@ -33,41 +91,356 @@ pub enum CreateEvent {
         max_normalized_value: Option<u64>,
         #[serde(rename = "eventMaturityEpoch")]
         event_maturity_epoch: u32,
+        /// Score legs continuously (the default) or as all-or-nothing thresholds.
+        #[serde(default, rename = "scoreMode")]
+        score_mode: ScoreMode,
+        #[serde(default)]
+        description: Option<String>,
+        #[serde(default)]
+        tags: Vec<String>,
+        /// Keep the announcement unlisted until this unix timestamp, see [`CreateEvent::announce_at`].
+        #[serde(default, rename = "announceAt")]
+        announce_at: Option<u32>,
+        /// Seconds to wait after maturity before signing, see [`CreateEvent::settlement_delay`].
+        #[serde(default, rename = "settlementDelay")]
+        settlement_delay: Option<u32>,
+        /// Human readable id, see [`crate::event_ids`]. Generated when unset.
+        #[serde(default, rename = "eventId")]
+        event_id: Option<String>,
     },
 }
 
+impl CreateEvent {
+    /// A single event on `event_type` maturing at `maturity`, with every option left to its
+    /// default.
+    pub fn single(event_type: EventType, maturity: u32) -> Self {
+        CreateEvent::Single {
+            event_type,
+            maturity,
+            percentile: None,
+            aggregation: None,
+            smoothing: None,
+            precision: None,
+            nb_digits: None,
+            description: None,
+            tags: vec![],
+            announce_at: None,
+            settlement_delay: None,
+            event_id: None,
+        }
+    }
+
+    pub fn metadata(&self) -> EventMetadata {
+        match self {
+            CreateEvent::Single {
+                description, tags, ..
+            }
+            | CreateEvent::Parlay {
+                description, tags, ..
+            } => EventMetadata::new(description.clone(), tags.clone()),
+        }
+    }
+
+    /// When the announcement is published. Scheduled announcements are not listed, searchable
+    /// or retrievable until the watcher publishes them.
+    pub fn announce_at(&self) -> Option<u32> {
+        match self {
+            CreateEvent::Single { announce_at, .. } | CreateEvent::Parlay { announce_at, .. } => {
+                *announce_at
+            }
+        }
+    }
+
+    /// How long the watcher waits after maturity before fetching data and signing, so
+    /// upstream sources lagging behind the chain can catch up. The oracle's configured default
+    /// applies when unset.
+    pub fn settlement_delay(&self) -> Option<u32> {
+        match self {
+            CreateEvent::Single {
+                settlement_delay, ..
+            }
+            | CreateEvent::Parlay {
+                settlement_delay, ..
+            } => *settlement_delay,
+        }
+    }
+
+    pub fn event_id(&self) -> Option<&str> {
+        match self {
+            CreateEvent::Single { event_id, .. } | CreateEvent::Parlay { event_id, .. } => {
+                event_id.as_deref()
+            }
+        }
+    }
+
+    pub fn maturity(&self) -> u32 {
+        match self {
+            CreateEvent::Single { maturity, .. } => *maturity,
+            CreateEvent::Parlay {
+                event_maturity_epoch,
+                ..
+            } => *event_maturity_epoch,
+        }
+    }
+}
+
+pub async fn create_series_internal(
+    state: Arc<OracleServerState>,
+    request: CreateSeries,
+    api_key: Option<String>,
+) -> anyhow::Result<SeriesManifest> {
+    // Every event of a series counts against the limits like one created on its own
+    let maturities = request.maturities()?;
+    let events = maturities.len() as i64;
+    let now = chrono::Utc::now().timestamp() as u32;
+    for maturity in &maturities {
+        validation::validate_create_event(&request.event(*maturity), now)?;
+    }
+    state.limits.check_event(&request.event(maturities[0]))?;
+    let pool = &state.oracle.oracle.storage.pool;
+    state
+        .limits
+        .check_daily_events(pool, api_key.as_deref(), events)
+        .await?;
+    usage::consume_many(
+        pool,
+        &state.quotas,
+        api_key.as_deref(),
+        UsageKind::EventsCreated,
+        events,
+    )
+    .await?;
+    let announce_at = request.announce_at;
+    let settlement_delay = request
+        .settlement_delay
+        .unwrap_or(state.oracle.settlement_delay());
+    let manifest = match state.oracle.create_series(request).await {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            usage::refund_many(pool, api_key.as_deref(), UsageKind::EventsCreated, events).await?;
+            return Err(e);
+        }
+    };
+    for entry in &manifest.events {
+        limits::record_creation(pool, &entry.event_id, api_key.as_deref()).await?;
+        issue_creation_receipt(&state, &entry.event_id, api_key.as_deref()).await;
+    }
+    state.schedule.extend(
+        manifest
+            .events
+            .iter()
+            .map(|entry| entry.maturity as i64 + settlement_delay as i64)
+            .chain(announce_at.map(|at| at as i64)),
+    );
+    Ok(manifest)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetSeries {
+    pub series_id: String,
+}
+
+pub async fn get_series_internal(
+    state: Arc<OracleServerState>,
+    query: GetSeries,
+) -> anyhow::Result<SeriesManifest> {
+    series::get_series(
+        &state.oracle.oracle.storage.pool,
+        &query.series_id,
+        state.oracle.oracle.public_key(),
+    )
+    .await
+}
+
+/// A created announcement, with warnings about parlay legs that move together.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreatedEvent {
+    #[serde(flatten)]
+    pub announcement: OracleAnnouncement,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<LegWarning>,
+    /// The oracle's proof it accepted the request, see [`crate::creation_receipts`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub receipt: Option<CreationReceipt>,
+}
+
 pub async fn create_event_internal(
     state: Arc<OracleServerState>,
     event: CreateEvent,
-) -> anyhow::Result<OracleAnnouncement> {
-    state.oracle.create_event(event).await
+    api_key: Option<String>,
+) -> anyhow::Result<CreatedEvent> {
+    validation::validate_create_event(&event, chrono::Utc::now().timestamp() as u32)?;
+    state.limits.check_event(&event)?;
+    let pool = &state.oracle.oracle.storage.pool;
+    state
+        .limits
+        .check_daily_events(pool, api_key.as_deref(), 1)
+        .await?;
+    usage::consume(
+        pool,
+        &state.quotas,
+        api_key.as_deref(),
+        UsageKind::EventsCreated,
+    )
+    .await?;
+    let warnings = match &event {
+        CreateEvent::Parlay { parameters, .. } => correlation::leg_warnings(parameters),
+        _ => vec![],
+    };
+    let announce_at = event.announce_at();
+    let settlement_delay = event
+        .settlement_delay()
+        .unwrap_or(state.oracle.settlement_delay());
+    let announcement = match state.oracle.create_event(event).await {
+        Ok(announcement) => announcement,
+        Err(e) => {
+            usage::refund(pool, api_key.as_deref(), UsageKind::EventsCreated).await?;
+            return Err(e);
+        }
+    };
+    limits::record_creation(
+        pool,
+        &announcement.oracle_event.event_id,
+        api_key.as_deref(),
+    )
+    .await?;
+    let receipt = issue_creation_receipt(
+        &state,
+        &announcement.oracle_event.event_id,
+        api_key.as_deref(),
+    )
+    .await;
+    state.schedule.extend(
+        std::iter::once(
+            announcement.oracle_event.event_maturity_epoch as i64 + settlement_delay as i64,
+        )
+        .chain(announce_at.map(|at| at as i64)),
+    );
+    Ok(CreatedEvent {
+        announcement,
+        warnings,
+        receipt,
+    })
+}
+
+/// Issue the creation receipt of an announced event. The event exists whether or not this
+/// succeeds, so a failure is logged and the event goes without a receipt.
+async fn issue_creation_receipt(
+    state: &OracleServerState,
+    event_id: &str,
+    api_key: Option<&str>,
+) -> Option<CreationReceipt> {
+    match creation_receipts::issue(&state.oracle, event_id, api_key).await {
+        Ok(receipt) => Some(receipt),
+        Err(e) => {
+            log::error!(
+                "Could not issue creation receipt. error={} event_id={}",
+                e,
+                event_id
+            );
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetCreationReceipt {
+    pub event_id: String,
+}
+
+pub async fn get_creation_receipt_internal(
+    state: Arc<OracleServerState>,
+    query: GetCreationReceipt,
+) -> anyhow::Result<CreationReceipt> {
+    creation_receipts::get(&state.oracle.oracle.storage.pool, &query.event_id)
+        .await?
+        .ok_or(OracleServerError::new(ErrorCode::NotFound, "No receipt for this event.").into())
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct GetAnnouncement {
-    event_id: String,
+    pub event_id: String,
 }
 
 pub async fn get_announcement_internal(
     state: Arc<OracleServerState>,
     event: GetAnnouncement,
 ) -> Result<OracleAnnouncement, OracleServerError> {
-    Ok(state
-        .oracle
-        .oracle
-        .storage
+    let storage = &state.oracle.oracle.storage;
+    let not_found = || OracleServerError::new(ErrorCode::NotFound, "Announcement not found");
+    if !storage.is_announced(&event.event_id).await? {
+        return Err(not_found());
+    }
+    Ok(storage
         .get_event(event.event_id)
         .await
-        .map_err(|e| OracleServerError {
-            reason: e.to_string(),
-        })?
-        .ok_or(OracleServerError {
-            reason: "Announcement not found".to_string(),
-        })?
+        .map_err(anyhow::Error::from)?
+        .ok_or_else(not_found)?
         .announcement)
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetBundle {
+    /// Comma separated ids of announced events
+    pub event_ids: String,
+}
+
+/// The announcements of `event_ids` with the oracle's signed manifest over them. Fails when any
+/// of them is not announced, so the manifest covers exactly the requested set.
+pub async fn get_bundle_internal(
+    state: Arc<OracleServerState>,
+    query: GetBundle,
+) -> Result<AnnouncementBundle, OracleServerError> {
+    let mut event_ids = query
+        .event_ids
+        .split(',')
+        .map(str::trim)
+        .filter(|event_id| !event_id.is_empty())
+        .collect::<Vec<_>>();
+    event_ids.sort();
+    event_ids.dedup();
+    if event_ids.is_empty() || event_ids.len() > MAX_BUNDLE_EVENTS {
+        return Err(OracleServerError::new(
+            ErrorCode::Validation,
+            format!(
+                "A bundle has between 1 and {} events. requested={}",
+                MAX_BUNDLE_EVENTS,
+                event_ids.len()
+            ),
+        ));
+    }
+    let mut announcements = Vec::with_capacity(event_ids.len());
+    for event_id in event_ids {
+        let announcement = get_announcement_internal(
+            state.clone(),
+            GetAnnouncement {
+                event_id: event_id.to_string(),
+            },
+        )
+        .await
+        .map_err(|e| match e.code {
+            ErrorCode::NotFound => OracleServerError::new(
+                ErrorCode::NotFound,
+                format!("Announcement not found. event_id={}", event_id),
+            ),
+            _ => e,
+        })?;
+        announcements.push(announcement);
+    }
+    Ok(AnnouncementBundle::new(
+        announcements,
+        state.oracle.oracle.public_key(),
+        // The manifest signs milliseconds
+        chrono::SubsecRound::trunc_subsecs(chrono::Utc::now(), 3),
+        |message| state.oracle.sign_message(message),
+    ))
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SignEvent {
@@ -77,7 +450,15 @@ pub struct SignEvent {
 pub async fn sign_event_internal(
     state: Arc<OracleServerState>,
     event: SignEvent,
+    api_key: Option<String>,
 ) -> anyhow::Result<OracleAttestation> {
+    usage::consume(
+        &state.oracle.oracle.storage.pool,
+        &state.quotas,
+        api_key.as_deref(),
+        UsageKind::SigningsRequested,
+    )
+    .await?;
     let event = state
         .oracle
         .oracle
@@ -86,76 +467,401 @@ pub async fn sign_event_internal(
         .await?;
 
     let Some(event) = event else {
-        return Err(anyhow!("Event does not exist.".to_string()));
+        return Err(OracleServerError::new(ErrorCode::NotFound, "Event does not exist.").into());
     };
 
-    let unit = match event.announcement.oracle_event.event_descriptor {
-        EventDescriptor::DigitDecompositionEvent(descriptor) => descriptor.unit,
+    state.oracle.ensure_due(&event.event_id).await?;
+    watcher::sign_event(&state, &event.event_id, SigningSource::Api).await
+}
+
+/// Ask the watcher to sign a matured event now instead of on its next tick.
+pub async fn request_signing_internal(
+    state: Arc<OracleServerState>,
+    event: SignEvent,
+    api_key: Option<String>,
+) -> anyhow::Result<SignEvent> {
+    usage::consume(
+        &state.oracle.oracle.storage.pool,
+        &state.quotas,
+        api_key.as_deref(),
+        UsageKind::SigningsRequested,
+    )
+    .await?;
+    if state
+        .oracle
+        .oracle
+        .storage
+        .get_event(event.event_id.clone())
+        .await?
+        .is_none()
+    {
+        return Err(OracleServerError::new(ErrorCode::NotFound, "Event does not exist.").into());
+    }
+    triggers::request_signing(&state.oracle.oracle.storage.pool, &event.event_id).await?;
+    Ok(event)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetOutcomePreview {
+    #[serde(alias = "event_id")]
+    pub event_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetExport {
+    #[serde(alias = "event_id")]
+    pub event_id: String,
+}
+
+/// Data a previewed outcome was derived from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreviewSource {
+    pub data_type: String,
+    #[serde(flatten)]
+    pub provenance: DataProvenance,
+}
+
+/// What the attestation of an event would be if it matured now.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutcomePreview {
+    pub event_id: String,
+    /// The data type of a single event, `parlay` for parlays
+    pub event_type: String,
+    /// The outcome that would be signed
+    pub outcome: i64,
+    /// Whether the event already has an attestation
+    pub signed: bool,
+    pub combined_score: Option<f64>,
+    /// Whether a parlay score falls outside the announced range and would be clamped
+    pub clamped: bool,
+    /// Per-leg values of a parlay
+    pub outcomes: Vec<AttestationDataOutcome>,
+    pub sources: Vec<PreviewSource>,
+}
+
+/// Run the signing pipeline of an event on current data without producing signatures.
+pub async fn preview_outcome_internal(
+    state: Arc<OracleServerState>,
+    query: GetOutcomePreview,
+) -> anyhow::Result<OutcomePreview> {
+    let storage = &state.oracle.oracle.storage;
+    let not_found = || OracleServerError::new(ErrorCode::NotFound, "Event does not exist.");
+    if !storage.is_announced(&query.event_id).await? {
+        return Err(not_found().into());
+    }
+    let event = storage
+        .get_event(query.event_id)
+        .await?
+        .ok_or_else(not_found)?;
+
+    let unit = match &event.announcement.oracle_event.event_descriptor {
+        EventDescriptor::DigitDecompositionEvent(descriptor) => descriptor.unit.clone(),
         EventDescriptor::EnumEvent(_) => {
-            return Err(anyhow!("Cannot sign enum descriptor.".to_string()))
+            return Err(anyhow!("Cannot preview enum descriptor."));
         }
     };
+    let signed = !event.signatures.is_empty();
 
-    let outcome = EventType::outcome_from_str(&unit, &state.mempool).await?;
+    if unit == "parlay" {
+        let contract = state
+            .oracle
+            .get_parlay_contract(event.event_id.clone())
+            .await?;
+        let evaluation = state.oracle.evaluate_parlay_contract(&contract).await?;
+        return Ok(OutcomePreview {
+            event_id: event.event_id,
+            event_type: unit,
+            outcome: evaluation.attestable_value.value as i64,
+            signed,
+            combined_score: Some(evaluation.combined_score),
+            clamped: evaluation.attestable_value.clamped,
+            outcomes: evaluation.outcomes,
+            sources: evaluation
+                .provenance
+                .into_iter()
+                .map(|(data_type, provenance)| PreviewSource {
+                    data_type,
+                    provenance,
+                })
+                .collect(),
+        });
+    }
 
-    Ok(state
-        .oracle
+    let event_type = units::event_type_from_unit(&unit)?;
+    let SingleEventOutcome {
+        outcome,
+        provenance,
+    } = state
         .oracle
-        .sign_numeric_event(event.event_id, outcome)
-        .await?)
+        .single_event_outcome(&event.event_id, &event_type)
+        .await?;
+    Ok(OutcomePreview {
+        event_id: event.event_id,
+        event_type: event_type.to_string(),
+        outcome,
+        signed,
+        combined_score: None,
+        clamped: false,
+        outcomes: Vec::new(),
+        sources: vec![PreviewSource {
+            data_type: event_type.to_string(),
+            provenance,
+        }],
+    })
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct GetAttestation {
-    event_id: String,
+    pub event_id: String,
 }
 
 pub async fn get_attestation_internal(
     state: Arc<OracleServerState>,
     event: GetAttestation,
 ) -> anyhow::Result<OracleAttestation> {
-    let event = match state
-        .oracle
-        .oracle
-        .storage
+    let storage = &state.oracle.oracle.storage;
+    let not_found = || OracleServerError::new(ErrorCode::NotFound, "Could not find event.");
+    if !storage.is_announced(&event.event_id).await? {
+        return Err(not_found().into());
+    }
+    let event = storage
         .get_event(event.event_id)
         .await?
-    {
-        Some(e) => e,
-        None => return Err(anyhow!("Could not find event.")),
-    };
+        .ok_or_else(not_found)?;
 
     if event.signatures.is_empty() {
-        return Err(anyhow!("Event is not signed."));
+        let maturity = event.announcement.oracle_event.event_maturity_epoch;
+        let error = if maturity as i64 > chrono::Utc::now().timestamp() {
+            OracleServerError::new(ErrorCode::NotMatured, "Event has not matured yet.")
+        } else {
+            OracleServerError::new(ErrorCode::NotSigned, "Event is not signed yet.")
+        };
+        Err(error.with_maturity(maturity).into())
     } else {
         Ok(OracleAttestation {
             event_id: event.event_id,
             oracle_public_key: event.announcement.oracle_public_key,
-            signatures: event.signatures.iter().cloned().map(|sig| sig.1).collect(),
-            outcomes: event.signatures.iter().cloned().map(|o| o.0).collect(),
+            signatures: event.signatures.iter().map(|sig| sig.1).collect(),
+            outcomes: event.signatures.iter().map(|o| o.0.clone()).collect(),
         })
     }
 }
 
+/// When the oracle signs matured events.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AttestationSchedule {
+    /// Seconds between watcher ticks, an event is signed at most this long after it is due
+    pub watcher_interval_secs: u64,
+    /// Seconds after maturity events are due, unless they set their own delay
+    pub settlement_delay: u32,
+}
+
+/// Everything but the key and name is optional for oracles that predate it.
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct OracleInfo {
     pub pubkey: XOnlyPublicKey,
     pub name: String,
+    #[serde(default)]
+    pub network: Option<Network>,
+    /// APIs the outcomes are read from
+    #[serde(default)]
+    pub data_sources: Vec<String>,
+    /// Event types the oracle can create, as in [`EventType`]
+    #[serde(default)]
+    pub event_types: Vec<String>,
+    #[serde(default)]
+    pub attestation_schedule: Option<AttestationSchedule>,
+    #[serde(default)]
+    pub version: Option<String>,
+    /// The oracle key's signature over the challenge of the request
+    #[serde(default)]
+    pub key_proof: Option<KeyProof>,
+}
+
+/// Longest challenge the oracle signs in [`OracleInfo::key_proof`].
+pub const MAX_CHALLENGE_LEN: usize = 128;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetInfo {
+    /// Signed back in [`OracleInfo::key_proof`], so a client knows it is answered by the holder
+    /// of the key and not from a cache or a spoofed server
+    pub challenge: Option<String>,
 }
 
-pub async fn oracle_info_internal(state: Arc<OracleServerState>) -> OracleInfo {
-    OracleInfo {
+/// Proof that the oracle holds the key it advertises.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyProof {
+    /// The challenge of the request, the proof only covers `signed_at` without one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub challenge: Option<String>,
+    /// Unix timestamp in seconds
+    pub signed_at: i64,
+    pub signature: Signature,
+}
+
+impl KeyProof {
+    pub fn message(&self) -> Message {
+        receipts::key_proof_message(self.challenge.as_deref(), self.signed_at)
+    }
+
+    /// Whether the proof is signed by `pubkey`.
+    pub fn verify(&self, pubkey: &XOnlyPublicKey) -> bool {
+        receipts::verify_receipt(pubkey, &self.message(), &self.signature)
+    }
+}
+
+pub async fn oracle_info_internal(
+    state: Arc<OracleServerState>,
+    query: GetInfo,
+) -> Result<OracleInfo, OracleServerError> {
+    if query
+        .challenge
+        .as_ref()
+        .is_some_and(|challenge| challenge.len() > MAX_CHALLENGE_LEN)
+    {
+        return Err(OracleServerError::new(
+            ErrorCode::Validation,
+            format!("Challenge is too long. max_length={}", MAX_CHALLENGE_LEN),
+        ));
+    }
+    let signed_at = chrono::Utc::now().timestamp();
+    let signature = state.oracle.sign_message(&receipts::key_proof_message(
+        query.challenge.as_deref(),
+        signed_at,
+    ));
+    Ok(OracleInfo {
         pubkey: state.oracle.oracle.public_key(),
         name: "Ernest Parlay Oracle".to_string(),
-    }
+        network: Some(state.oracle.network()),
+        data_sources: state.oracle.data_sources(),
+        event_types: EventType::iter().map(|t| t.to_string()).collect(),
+        attestation_schedule: Some(AttestationSchedule {
+            watcher_interval_secs: WATCHER_INTERVAL_SECS,
+            settlement_delay: state.oracle.settlement_delay(),
+        }),
+        version: Some(env!("CARGO_PKG_VERSION").to_string()),
+        key_proof: Some(KeyProof {
+            challenge: query.challenge,
+            signed_at,
+            signature,
+        }),
+    })
+}
+
+/// An event as returned by the listing endpoints, the kormir event data with the metadata
+/// flattened alongside it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventListing {
+    #[serde(flatten)]
+    pub event: OracleEventData,
+    #[serde(flatten)]
+    pub metadata: EventMetadata,
 }
 
 pub async fn list_events_internal(
     state: Arc<OracleServerState>,
-) -> anyhow::Result<Vec<OracleEventData>> {
+) -> anyhow::Result<Vec<EventListing>> {
     let events = state.oracle.oracle.storage.oracle_event_data().await?;
-    Ok(events)
+    let mut metadata = metadata::get_all_event_metadata(&state.oracle.oracle.storage.pool).await?;
+    Ok(events
+        .into_iter()
+        .map(|event| EventListing {
+            metadata: metadata.remove(&event.event_id).unwrap_or_default(),
+            event,
+        })
+        .collect())
+}
+
+/// The status of the watcher, kept in memory so monitoring needs no database access.
+pub fn status_internal(state: Arc<OracleServerState>) -> WatcherHealthReport {
+    state.watcher.report()
+}
+
+/// Matured events without an attestation, for monitoring whether signing falls behind.
+pub async fn pending_events_internal(
+    state: Arc<OracleServerState>,
+) -> anyhow::Result<Vec<PendingEvent>> {
+    state
+        .oracle
+        .pending_events(chrono::Utc::now().timestamp())
+        .await
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetEventDetail {
+    #[serde(alias = "event_id")]
+    pub event_id: String,
+}
+
+/// The complete record of an event: nonces, announcement and signatures, along with its
+/// metadata, parlay contract and attested outcome when it has them.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventDetail {
+    #[serde(flatten)]
+    pub event: OracleEventData,
+    #[serde(flatten)]
+    pub metadata: EventMetadata,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parlay_contract: Option<ParlayContract>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attestation_outcome: Option<ErnestOracleOutcome>,
+}
+
+pub async fn get_event_detail_internal(
+    state: Arc<OracleServerState>,
+    query: GetEventDetail,
+) -> anyhow::Result<EventDetail> {
+    let storage = &state.oracle.oracle.storage;
+    let not_found = || {
+        OracleServerError::new(
+            ErrorCode::NotFound,
+            format!("Event not found. event_id={}", query.event_id),
+        )
+    };
+    if !storage.is_announced(&query.event_id).await? {
+        return Err(not_found().into());
+    }
+    let event = storage
+        .get_event(query.event_id.clone())
+        .await?
+        .ok_or_else(not_found)?;
+    let is_parlay = matches!(
+        &event.announcement.oracle_event.event_descriptor,
+        EventDescriptor::DigitDecompositionEvent(descriptor) if descriptor.unit == "parlay"
+    );
+    let parlay_contract = match is_parlay {
+        true => Some(
+            state
+                .oracle
+                .get_parlay_contract(query.event_id.clone())
+                .await?,
+        ),
+        false => None,
+    };
+    let attestation_outcome =
+        match attestation::get_attested_value(&storage.pool, &query.event_id).await? {
+            Some(_) => Some(
+                attestation::get_attestation_outcome(&storage.pool, query.event_id.clone()).await?,
+            ),
+            None => None,
+        };
+    Ok(EventDetail {
+        metadata: metadata::get_event_metadata(&storage.pool, &query.event_id).await?,
+        event,
+        parlay_contract,
+        attestation_outcome,
+    })
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -168,7 +874,268 @@ pub async fn get_parlay_contract_internal(
     state: Arc<OracleServerState>,
     event: GetParlayContract,
 ) -> anyhow::Result<ParlayContract> {
-    Ok(state.oracle.get_parlay_contract(event.event_id).await?)
+    state.oracle.get_parlay_contract(event.event_id).await
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetParlayLegs {
+    pub event_id: String,
+}
+
+/// How each leg of an attested parlay scored, enough for a counterparty to recompute the
+/// combined score.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ParlayLegs {
+    pub event_id: String,
+    pub combination_method: CombinationMethod,
+    pub score_mode: ScoreMode,
+    pub scoring_version: u32,
+    pub combined_score: f64,
+    pub attested_value: i32,
+    pub clamped: bool,
+    pub legs: Vec<ParlayLegOutcome>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ParlayLegOutcome {
+    pub data_type: String,
+    /// Value the leg settled on, in the data type's unit
+    pub original_value: f64,
+    pub normalized_value: f64,
+    pub transformed_value: f64,
+    /// The leg's weight, over the sum of the weights from scoring version 2 on
+    pub weight: f64,
+    /// What the leg adds to the combined score, see [`math::leg_contributions`]
+    pub contribution: f64,
+}
+
+pub async fn get_parlay_legs_internal(
+    state: Arc<OracleServerState>,
+    query: GetParlayLegs,
+) -> anyhow::Result<ParlayLegs> {
+    let pool = &state.oracle.oracle.storage.pool;
+    let exists = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS (SELECT 1 FROM parlay_contracts WHERE id = $1)",
+    )
+    .bind(&query.event_id)
+    .fetch_one(pool)
+    .await?;
+    if !exists {
+        return Err(OracleServerError::new(ErrorCode::NotFound, "Parlay does not exist.").into());
+    }
+    if attestation::get_attested_value(pool, &query.event_id)
+        .await?
+        .is_none()
+    {
+        return Err(
+            OracleServerError::new(ErrorCode::NotSigned, "Parlay is not attested yet.").into(),
+        );
+    }
+
+    let contract = state
+        .oracle
+        .get_parlay_contract(query.event_id.clone())
+        .await?;
+    let outcome = attestation::get_attestation_outcome(pool, query.event_id).await?;
+    if outcome.outcomes.len() != contract.parameters.len() {
+        return Err(anyhow!(
+            "Attestation does not have an outcome per leg. event_id={} legs={} outcomes={}",
+            contract.id,
+            contract.parameters.len(),
+            outcome.outcomes.len()
+        ));
+    }
+
+    let weights = contract
+        .parameters
+        .iter()
+        .map(|parameter| parameter.weight)
+        .collect::<Vec<_>>();
+    let transformed_values = outcome
+        .outcomes
+        .iter()
+        .map(|leg| leg.transformed_value)
+        .collect::<Vec<_>>();
+    let contributions = math::leg_contributions(
+        &transformed_values,
+        &weights,
+        &contract.combination_method,
+        contract.scoring_version,
+    );
+    let weights = if contract.scoring_version < 2 {
+        weights
+    } else {
+        math::normalize_weights(&weights)
+    };
+    let legs = outcome
+        .outcomes
+        .into_iter()
+        .zip(weights)
+        .zip(contributions)
+        .map(|((leg, weight), contribution)| ParlayLegOutcome {
+            data_type: leg.data_type,
+            original_value: leg.original_value,
+            normalized_value: leg.normalized_value,
+            transformed_value: leg.transformed_value,
+            weight,
+            contribution,
+        })
+        .collect();
+
+    Ok(ParlayLegs {
+        event_id: contract.id,
+        combination_method: contract.combination_method,
+        score_mode: contract.score_mode,
+        scoring_version: contract.scoring_version,
+        combined_score: outcome.combined_score,
+        attested_value: outcome.attested_value,
+        clamped: outcome.clamped,
+        legs,
+    })
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetOracleParams {
+    pub max_normalized_value: Option<u64>,
+}
+
+/// The digit decomposition a parlay announcement with `max_normalized_value` will carry.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct OracleParams {
+    pub max_normalized_value: u64,
+    pub nb_digits: u16,
+    pub oracle_max_value: u64,
+}
+
+pub fn oracle_params_internal(params: GetOracleParams) -> OracleParams {
+    let max_normalized_value = params
+        .max_normalized_value
+        .unwrap_or(DEFAULT_MAX_NORMALIZED_VALUE);
+    let (nb_digits, oracle_max_value) = calculate_oracle_parameters(max_normalized_value);
+    OracleParams {
+        max_normalized_value,
+        nb_digits,
+        oracle_max_value,
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ParlayOption {
+    pub name: String,
+    pub description: String,
+}
+
+/// Everything a parlay can be configured with, generated from the enums themselves.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ParlayOptions {
+    pub transformations: Vec<ParlayOption>,
+    pub combination_methods: Vec<ParlayOption>,
+    pub score_modes: Vec<ParlayOption>,
+}
+
+pub fn parlay_options_internal() -> ParlayOptions {
+    fn options<T: IntoEnumIterator + ToString>(
+        description: fn(&T) -> &'static str,
+    ) -> Vec<ParlayOption> {
+        T::iter()
+            .map(|option| ParlayOption {
+                name: option.to_string(),
+                description: description(&option).to_string(),
+            })
+            .collect()
+    }
+    ParlayOptions {
+        transformations: options(TransformationFunction::description),
+        combination_methods: options(CombinationMethod::description),
+        score_modes: options(ScoreMode::description),
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetEventsMetadata {
+    /// Comma separated windows for the value ranges, e.g. `1m,1y`. Defaults to three months.
+    pub periods: Option<String>,
+}
+
+pub async fn events_metadata_internal(
+    state: Arc<OracleServerState>,
+    query: GetEventsMetadata,
+) -> anyhow::Result<Vec<EventTypeMetadata>> {
+    let periods = match query.periods.as_deref() {
+        Some(periods) => periods
+            .split(',')
+            .map(|period| {
+                TimePeriod::from_str(period.trim())
+                    .map_err(|_| anyhow!("Invalid period. period={}", period))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?,
+        None => vec![TimePeriod::ThreeMonths],
+    };
+
+    let mut metadata = Vec::new();
+    for event_type in EventType::available_events() {
+        metadata.push(EventTypeMetadata::fetch(event_type, &state.mempool, &periods).await);
+    }
+    Ok(metadata)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetHistory {
+    pub data_type: String,
+    /// Unix timestamp in seconds, defaults to thirty days before `to`
+    pub from: Option<i64>,
+    /// Unix timestamp in seconds, defaults to now
+    pub to: Option<i64>,
+}
+
+pub async fn history_internal(
+    state: Arc<OracleServerState>,
+    query: GetHistory,
+) -> anyhow::Result<MetricHistory> {
+    let data_type = history::parse_data_type(&query.data_type)?;
+    let to = match query.to {
+        Some(to) => history::parse_timestamp(to)?,
+        None => chrono::Utc::now(),
+    };
+    let from = match query.from {
+        Some(from) => history::parse_timestamp(from)?,
+        None => to - chrono::Duration::days(history::DEFAULT_HISTORY_DAYS),
+    };
+    if from > to {
+        return Err(anyhow!("from must not be after to"));
+    }
+    history::get_history(&state.oracle.oracle.storage.pool, &data_type, from, to).await
+}
+
+pub async fn backtest_internal(
+    state: Arc<OracleServerState>,
+    request: BacktestRequest,
+) -> anyhow::Result<Backtest> {
+    backtest::run_backtest(&state.oracle.oracle.storage.pool, request).await
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetEstimate {
+    /// JSON encoded [`EstimateRequest`]
+    pub contract: String,
+}
+
+pub async fn estimate_internal(
+    state: Arc<OracleServerState>,
+    query: GetEstimate,
+) -> anyhow::Result<Estimate> {
+    let request = serde_json::from_str::<EstimateRequest>(&query.contract)
+        .map_err(|e| anyhow!("Invalid contract. error={}", e))?;
+    estimate::run_estimate(&state.oracle.oracle.storage.pool, request).await
 }
 
 pub fn get_available_events_internal() -> Vec<EventType> {
@@ -185,8 +1152,245 @@ pub async fn get_attestation_outcome_internal(
     state: Arc<OracleServerState>,
     event: GetAttestationOutcome,
 ) -> anyhow::Result<ErnestOracleOutcome> {
-    Ok(
-        attestation::get_attestation_outcome(&state.oracle.oracle.storage.pool, event.event_id)
-            .await?,
+    let storage = &state.oracle.oracle.storage;
+    if !storage.is_announced(&event.event_id).await? {
+        return Err(OracleServerError::new(ErrorCode::NotFound, "Could not find event.").into());
+    }
+    attestation::get_attestation_outcome(&storage.pool, event.event_id).await
+}
+
+pub const DEFAULT_SEARCH_LIMIT: i64 = 100;
+pub const MAX_SEARCH_LIMIT: i64 = 500;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchEvents {
+    pub q: Option<String>,
+    pub tag: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventSearchResult {
+    pub event_id: String,
+    pub event_type: Option<String>,
+    pub event_maturity_epoch: u32,
+    pub status: EventStatus,
+    #[serde(flatten)]
+    pub metadata: EventMetadata,
+}
+
+pub async fn search_events_internal(
+    state: Arc<OracleServerState>,
+    search: SearchEvents,
+) -> anyhow::Result<Vec<EventSearchResult>> {
+    let limit = search
+        .limit
+        .unwrap_or(DEFAULT_SEARCH_LIMIT)
+        .clamp(1, MAX_SEARCH_LIMIT);
+    let rows = metadata::search_events(
+        &state.oracle.oracle.storage.pool,
+        search.q.as_deref(),
+        search.tag.as_deref(),
+        limit,
     )
+    .await?;
+
+    let now = chrono::Utc::now().timestamp() as u32;
+    rows.into_iter()
+        .map(|row| {
+            let oracle_event = to_oracle_event(&row.oracle_event)?;
+            Ok(EventSearchResult {
+                event_id: row.event_id,
+                event_type: row.event_type,
+                event_maturity_epoch: oracle_event.event_maturity_epoch,
+                status: EventStatus::new(oracle_event.event_maturity_epoch, row.is_signed, now),
+                metadata: EventMetadata {
+                    description: row.description,
+                    tags: row.tags,
+                },
+            })
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetSigningAudit {
+    /// Only return entries with a greater id
+    pub after: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+pub async fn signing_audit_internal(
+    state: Arc<OracleServerState>,
+    query: GetSigningAudit,
+) -> anyhow::Result<Vec<SigningAuditEntry>> {
+    let limit = query
+        .limit
+        .unwrap_or(audit::DEFAULT_AUDIT_LIMIT)
+        .clamp(1, audit::MAX_AUDIT_LIMIT);
+    audit::entries(&state.oracle.oracle.storage.pool, query.after, limit).await
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RebroadcastEvent {
+    pub event_id: String,
+    /// Relays to publish to instead of the configured ones, e.g. to backfill a new relay
+    #[serde(default)]
+    pub relays: Vec<String>,
+}
+
+pub async fn rebroadcast_internal(
+    state: Arc<OracleServerState>,
+    request: RebroadcastEvent,
+) -> anyhow::Result<Rebroadcast> {
+    let relays = if request.relays.is_empty() {
+        &state.nostr.relays
+    } else {
+        &request.relays
+    };
+    nostr::rebroadcast(&state.oracle, relays, &request.event_id).await
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HoldEvent {
+    pub event_id: String,
+    /// `false` releases the event
+    pub hold: bool,
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+pub async fn hold_event_internal(
+    state: Arc<OracleServerState>,
+    request: HoldEvent,
+) -> anyhow::Result<HoldEvent> {
+    state
+        .oracle
+        .set_hold(&request.event_id, request.hold, request.reason.as_deref())
+        .await?;
+    Ok(request)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HideEvent {
+    pub event_id: String,
+    /// `false` lists the event again
+    pub hidden: bool,
+}
+
+pub async fn hide_event_internal(
+    state: Arc<OracleServerState>,
+    request: HideEvent,
+) -> anyhow::Result<HideEvent> {
+    state
+        .oracle
+        .set_hidden(&request.event_id, request.hidden)
+        .await?;
+    Ok(request)
+}
+
+/// Current version of the HTTP API, served under `/api/v1` and the unversioned `/api` alias.
+pub const API_VERSION: u32 = 1;
+pub const SUPPORTED_API_VERSIONS: [u32; 1] = [1];
+
+/// Sent by clients with the API versions they accept, comma separated, and answered with the
+/// version served.
+pub const API_VERSION_HEADER: &str = "x-api-version";
+
+/// The highest supported version a client accepts, [`API_VERSION`] when it did not say.
+pub fn negotiate_api_version(requested: Option<&str>) -> Result<u32, OracleServerError> {
+    let Some(requested) = requested else {
+        return Ok(API_VERSION);
+    };
+    requested
+        .split(',')
+        .filter_map(|version| version.trim().trim_start_matches('v').parse::<u32>().ok())
+        .filter(|version| SUPPORTED_API_VERSIONS.contains(version))
+        .max()
+        .ok_or(OracleServerError::new(
+            ErrorCode::UnsupportedVersion,
+            format!(
+                "Unsupported API version. requested={} supported={:?}",
+                requested, SUPPORTED_API_VERSIONS
+            ),
+        ))
+}
+
+/// Check the bearer token of an admin request against the configured admin token. Admin
+/// routes are refused when no token is configured.
+pub fn authorize_admin(state: &OracleServerState, authorization: Option<&str>) -> bool {
+    let (Some(expected), Some(token)) = (
+        state.admin_token.as_deref(),
+        authorization.and_then(|header| header.strip_prefix("Bearer ")),
+    ) else {
+        return false;
+    };
+    // Compare digests so the comparison time does not depend on the token
+    sha256::Hash::hash(expected.as_bytes()) == sha256::Hash::hash(token.as_bytes())
+}
+
+pub async fn prepare_override_internal(
+    state: Arc<OracleServerState>,
+    request: PrepareOverride,
+) -> anyhow::Result<PreparedOverride> {
+    overrides::prepare(&state.oracle, request).await
+}
+
+pub async fn commit_override_internal(
+    state: Arc<OracleServerState>,
+    request: CommitOverride,
+) -> anyhow::Result<OracleAttestation> {
+    let (_, attestation) = overrides::commit(&state.oracle, &request.token).await?;
+    Ok(attestation)
+}
+
+pub async fn usage_internal(
+    state: Arc<OracleServerState>,
+    api_key: Option<String>,
+) -> anyhow::Result<Usage> {
+    usage::usage(
+        &state.oracle.oracle.storage.pool,
+        &state.quotas,
+        api_key.as_deref(),
+    )
+    .await
+}
+
+/// Count a request against the quota of its key.
+pub async fn count_request(state: &OracleServerState, api_key: Option<&str>) -> anyhow::Result<()> {
+    usage::consume(
+        &state.oracle.oracle.storage.pool,
+        &state.quotas,
+        api_key,
+        UsageKind::Requests,
+    )
+    .await
+}
+
+pub async fn stats_internal(state: Arc<OracleServerState>) -> anyhow::Result<OracleStats> {
+    stats::get_oracle_stats(&state.oracle.oracle.storage.pool, state.watcher.report()).await
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetProvenance {
+    #[serde(alias = "event_id")]
+    pub event_id: String,
+}
+
+pub async fn get_provenance_internal(
+    state: Arc<OracleServerState>,
+    event: GetProvenance,
+) -> anyhow::Result<Vec<AttestationProvenance>> {
+    let storage = &state.oracle.oracle.storage;
+    if !storage.is_announced(&event.event_id).await? {
+        return Err(OracleServerError::new(ErrorCode::NotFound, "Could not find event.").into());
+    }
+    attestation::get_attestation_provenance(&storage.pool, event.event_id).await
 }
@@ -0,0 +1,199 @@
+//! Fetching an outcome several times before signing it.
+//!
+//! A single bad response from mempool.space must not decide a contract, so the value is fetched
+//! [`SamplingPolicy::samples`] times, samples further than [`SamplingPolicy::max_deviation`] from
+//! the median are rejected and the median of the rest is signed. Every sample is kept in the
+//! provenance of the signed value.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    events::{EventType, OutcomeOptions},
+    mempool::{DataProvenance, DataSample, MempoolClient},
+};
+
+pub const DEFAULT_SAMPLES: usize = 3;
+pub const DEFAULT_SAMPLE_INTERVAL_MS: u64 = 500;
+pub const DEFAULT_MAX_DEVIATION: f64 = 0.01;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SamplingPolicy {
+    /// Fetches per signed value, one disables sampling
+    pub samples: usize,
+    /// Wait between two fetches
+    pub interval_ms: u64,
+    /// Largest accepted deviation from the median, relative to the median
+    pub max_deviation: f64,
+}
+
+impl Default for SamplingPolicy {
+    fn default() -> Self {
+        Self {
+            samples: DEFAULT_SAMPLES,
+            interval_ms: DEFAULT_SAMPLE_INTERVAL_MS,
+            max_deviation: DEFAULT_MAX_DEVIATION,
+        }
+    }
+}
+
+impl SamplingPolicy {
+    /// Samples that have to agree for a value to be signed, a majority of the fetches.
+    pub fn quorum(&self) -> usize {
+        self.samples / 2 + 1
+    }
+}
+
+/// The middle of `samples` sorted by value, the lower one of the two for an even count so the
+/// signed value is one mempool answered with.
+fn median(samples: &mut [DataProvenance]) -> Option<&DataProvenance> {
+    samples.sort_by(|a, b| a.value.total_cmp(&b.value));
+    samples.get(samples.len().saturating_sub(1) / 2)
+}
+
+/// Reject the outliers of `samples` and settle on the median of the rest.
+pub fn settle(
+    mut samples: Vec<DataProvenance>,
+    policy: &SamplingPolicy,
+) -> anyhow::Result<DataProvenance> {
+    let middle = median(&mut samples)
+        .ok_or(anyhow::anyhow!("No samples to settle on."))?
+        .value;
+    let tolerance = policy.max_deviation * middle.abs();
+    let (mut accepted, rejected): (Vec<_>, Vec<_>) = samples
+        .into_iter()
+        .partition(|sample| (sample.value - middle).abs() <= tolerance);
+    if accepted.len() < policy.quorum() {
+        return Err(anyhow::anyhow!(
+            "Samples disagree, not enough are within the accepted deviation. accepted={} rejected={} quorum={}",
+            accepted.len(),
+            rejected.len(),
+            policy.quorum()
+        ));
+    }
+    let mut recorded = accepted
+        .iter()
+        .map(|sample| (sample, false))
+        .chain(rejected.iter().map(|sample| (sample, true)))
+        .map(|(sample, rejected)| DataSample {
+            value: sample.value,
+            fetched_at: sample.fetched_at,
+            rejected,
+            source_url: None,
+        })
+        .collect::<Vec<_>>();
+    recorded.sort_by_key(|sample| sample.fetched_at);
+    for sample in &rejected {
+        log::warn!(
+            "Rejected outlying sample. source_url={} value={} median={}",
+            sample.source_url,
+            sample.value,
+            middle
+        );
+    }
+    let mut settled = median(&mut accepted)
+        .expect("the quorum is at least one sample")
+        .clone();
+    settled.samples = recorded;
+    Ok(settled)
+}
+
+/// Fetch the outcome of `event_type` as `policy` asks and settle on a value.
+pub async fn sample_outcome(
+    event_type: &EventType,
+    mempool: &MempoolClient,
+    options: &OutcomeOptions,
+    policy: &SamplingPolicy,
+) -> anyhow::Result<DataProvenance> {
+    if policy.samples <= 1 {
+        return event_type.outcome_with_provenance(mempool, options).await;
+    }
+    let mut samples = Vec::with_capacity(policy.samples);
+    let mut last_error = None;
+    for sample in 0..policy.samples {
+        if sample > 0 {
+            tokio::time::sleep(Duration::from_millis(policy.interval_ms)).await;
+        }
+        match event_type.outcome_with_provenance(mempool, options).await {
+            Ok(provenance) => samples.push(provenance),
+            Err(e) => {
+                log::warn!(
+                    "Failed to fetch sample. data_type={} sample={} error={}",
+                    event_type,
+                    sample,
+                    e
+                );
+                last_error = Some(e);
+            }
+        }
+    }
+    if samples.len() < policy.quorum() {
+        // The error is kept so an upstream outage is still reported as one
+        return Err(last_error.unwrap_or(anyhow::anyhow!("Too few samples were fetched.")));
+    }
+    settle(samples, policy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, Utc};
+
+    fn sample(value: f64, seconds: i64) -> DataProvenance {
+        DataProvenance {
+            source_url: "https://mempool.space/api/v1/mining/hashrate/3m".to_string(),
+            raw_response: serde_json::json!({ "value": value }),
+            fetched_at: Utc::now() + Duration::seconds(seconds),
+            value,
+            samples: vec![],
+        }
+    }
+
+    #[test]
+    fn signs_the_median_without_outliers() {
+        let policy = SamplingPolicy::default();
+        let settled = settle(
+            vec![sample(101.0, 0), sample(5.0, 1), sample(100.0, 2)],
+            &policy,
+        )
+        .unwrap();
+        assert_eq!(settled.value, 100.0);
+        assert_eq!(settled.raw_response["value"], 100.0);
+        assert_eq!(
+            settled
+                .samples
+                .iter()
+                .map(|sample| (sample.value, sample.rejected))
+                .collect::<Vec<_>>(),
+            [(101.0, false), (5.0, true), (100.0, false)]
+        );
+
+        let disagreeing = settle(
+            vec![sample(100.0, 0), sample(5.0, 1), sample(200.0, 2)],
+            &policy,
+        );
+        assert!(disagreeing.is_err());
+    }
+
+    #[tokio::test]
+    async fn fetches_every_sample() {
+        let mock_server = crate::test_util::setup_mock_server().await;
+        let mempool = crate::test_util::mock_mempool_client(&mock_server);
+        let policy = SamplingPolicy {
+            interval_ms: 0,
+            ..Default::default()
+        };
+        let settled = sample_outcome(
+            &EventType::BlocksUntilHalving,
+            &mempool,
+            &OutcomeOptions::default(),
+            &policy,
+        )
+        .await
+        .unwrap();
+        assert_eq!(settled.value, 209_999.0);
+        assert_eq!(settled.samples.len(), DEFAULT_SAMPLES);
+    }
+}
@@ -0,0 +1,364 @@
+//! Loading the oracle key from a secrets manager instead of the environment.
+//!
+//! The provider is selected in the [`crate::config`] file under `"key"`. The secret holds a hex
+//! key or a BIP39 mnemonic, see [`keys::keypair_from_secret`].
+//!
+//! ```json
+//! { "key": { "provider": "vault", "address": "https://vault:8200", "path": "ernest-oracle" } }
+//! { "key": { "provider": "awsSecretsManager", "region": "us-east-1", "secretId": "ernest-oracle" } }
+//! ```
+//!
+//! Vault uses `VAULT_TOKEN` unless a token is configured. AWS credentials are read from
+//! `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and `AWS_SESSION_TOKEN`.
+
+use bitcoin::{
+    hashes::{hmac, sha256, Hash, HashEngine},
+    key::Keypair,
+};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+use crate::keys;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "provider")]
+pub enum SecretsProvider {
+    /// A field of a HashiCorp Vault KV v2 secret
+    #[serde(rename_all = "camelCase")]
+    Vault {
+        address: String,
+        #[serde(default = "default_vault_mount")]
+        mount: String,
+        path: String,
+        #[serde(default = "default_field")]
+        field: String,
+        #[serde(default)]
+        token: Option<String>,
+        #[serde(default)]
+        derivation_path: Option<String>,
+    },
+    /// An AWS Secrets Manager secret, or a field of it if the secret is JSON
+    #[serde(rename_all = "camelCase")]
+    AwsSecretsManager {
+        region: String,
+        secret_id: String,
+        #[serde(default)]
+        field: Option<String>,
+        /// Overrides `https://secretsmanager.<region>.amazonaws.com`
+        #[serde(default)]
+        endpoint: Option<String>,
+        #[serde(default)]
+        derivation_path: Option<String>,
+    },
+}
+
+fn default_vault_mount() -> String {
+    "secret".to_string()
+}
+
+fn default_field() -> String {
+    "key".to_string()
+}
+
+impl SecretsProvider {
+    pub async fn load_keypair(&self) -> anyhow::Result<Keypair> {
+        let secret = self.fetch_secret().await?;
+        let derivation_path = match self {
+            SecretsProvider::Vault {
+                derivation_path, ..
+            }
+            | SecretsProvider::AwsSecretsManager {
+                derivation_path, ..
+            } => derivation_path.as_deref(),
+        };
+        keys::keypair_from_secret(
+            &secret,
+            derivation_path.unwrap_or(keys::DEFAULT_DERIVATION_PATH),
+        )
+    }
+
+    pub async fn fetch_secret(&self) -> anyhow::Result<String> {
+        let client = Client::new();
+        match self {
+            SecretsProvider::Vault {
+                address,
+                mount,
+                path,
+                field,
+                token,
+                ..
+            } => {
+                let token = match token {
+                    Some(token) => token.clone(),
+                    None => std::env::var("VAULT_TOKEN")
+                        .map_err(|_| anyhow::anyhow!("VAULT_TOKEN is not set"))?,
+                };
+                let response = client
+                    .get(format!(
+                        "{}/v1/{}/data/{}",
+                        address.trim_end_matches('/'),
+                        mount,
+                        path
+                    ))
+                    .header("X-Vault-Token", token)
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json::<serde_json::Value>()
+                    .await?;
+                response["data"]["data"][field]
+                    .as_str()
+                    .map(str::to_string)
+                    .ok_or(anyhow::anyhow!(
+                        "Vault secret has no field. path={} field={}",
+                        path,
+                        field
+                    ))
+            }
+            SecretsProvider::AwsSecretsManager {
+                region,
+                secret_id,
+                field,
+                endpoint,
+                ..
+            } => {
+                let credentials = AwsCredentials::from_env()?;
+                let endpoint = endpoint
+                    .clone()
+                    .unwrap_or(format!("https://secretsmanager.{}.amazonaws.com", region));
+                let body = serde_json::json!({ "SecretId": secret_id }).to_string();
+                let request = credentials.sign(
+                    client.post(&endpoint),
+                    &endpoint,
+                    region,
+                    "secretsmanager",
+                    "secretsmanager.GetSecretValue",
+                    &body,
+                    chrono::Utc::now(),
+                )?;
+                let response = request
+                    .body(body)
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json::<serde_json::Value>()
+                    .await?;
+                let secret = response["SecretString"].as_str().ok_or(anyhow::anyhow!(
+                    "Secret has no string value. secret_id={}",
+                    secret_id
+                ))?;
+                match field {
+                    Some(field) => serde_json::from_str::<serde_json::Value>(secret)?[field]
+                        .as_str()
+                        .map(str::to_string)
+                        .ok_or(anyhow::anyhow!(
+                            "Secret has no field. secret_id={} field={}",
+                            secret_id,
+                            field
+                        )),
+                    None => Ok(secret.to_string()),
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct AwsCredentials {
+    access_key_id: String,
+    secret_access_key: String,
+    session_token: Option<String>,
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; 32] {
+    let mut engine = hmac::HmacEngine::<sha256::Hash>::new(key);
+    engine.input(data);
+    hmac::Hmac::<sha256::Hash>::from_engine(engine).to_byte_array()
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    hex::encode(sha256::Hash::hash(data).to_byte_array())
+}
+
+/// The SigV4 key for a day, region and service.
+fn signing_key(secret_access_key: &str, date: &str, region: &str, service: &str) -> [u8; 32] {
+    let key = hmac_sha256(
+        format!("AWS4{}", secret_access_key).as_bytes(),
+        date.as_bytes(),
+    );
+    let key = hmac_sha256(&key, region.as_bytes());
+    let key = hmac_sha256(&key, service.as_bytes());
+    hmac_sha256(&key, b"aws4_request")
+}
+
+impl AwsCredentials {
+    fn from_env() -> anyhow::Result<Self> {
+        let var =
+            |name: &str| std::env::var(name).map_err(|_| anyhow::anyhow!("{} is not set", name));
+        Ok(Self {
+            access_key_id: var("AWS_ACCESS_KEY_ID")?,
+            secret_access_key: var("AWS_SECRET_ACCESS_KEY")?,
+            session_token: std::env::var("AWS_SESSION_TOKEN").ok(),
+        })
+    }
+
+    /// Add the SigV4 headers of a JSON 1.1 `target` call with `body` to `request`.
+    #[allow(clippy::too_many_arguments)]
+    fn sign(
+        &self,
+        request: reqwest::RequestBuilder,
+        endpoint: &str,
+        region: &str,
+        service: &str,
+        target: &str,
+        body: &str,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> anyhow::Result<reqwest::RequestBuilder> {
+        let url = reqwest::Url::parse(endpoint)?;
+        let host = match url.port() {
+            Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+            None => url.host_str().unwrap_or_default().to_string(),
+        };
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+
+        let mut headers = vec![
+            ("content-type", "application/x-amz-json-1.1".to_string()),
+            ("host", host),
+            ("x-amz-date", amz_date.clone()),
+        ];
+        if let Some(token) = &self.session_token {
+            headers.push(("x-amz-security-token", token.clone()));
+        }
+        headers.push(("x-amz-target", target.to_string()));
+        let signed_headers = headers
+            .iter()
+            .map(|(name, _)| *name)
+            .collect::<Vec<_>>()
+            .join(";");
+        let canonical_headers = headers
+            .iter()
+            .map(|(name, value)| format!("{}:{}\n", name, value))
+            .collect::<String>();
+        let canonical_request = format!(
+            "POST\n{}\n\n{}\n{}\n{}",
+            url.path(),
+            canonical_headers,
+            signed_headers,
+            sha256_hex(body.as_bytes())
+        );
+        let scope = format!("{}/{}/{}/aws4_request", date, region, service);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            sha256_hex(canonical_request.as_bytes())
+        );
+        let signature = hex::encode(hmac_sha256(
+            &signing_key(&self.secret_access_key, &date, region, service),
+            string_to_sign.as_bytes(),
+        ));
+
+        let mut request = request.header(
+            "authorization",
+            format!(
+                "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+                self.access_key_id, scope, signed_headers, signature
+            ),
+        );
+        for (name, value) in headers.into_iter().filter(|(name, _)| *name != "host") {
+            request = request.header(name, value);
+        }
+        Ok(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::{
+        matchers::{header, header_exists, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    const KEY: &str = "34d95a073eee38ecb968a0da8273926cda601802541a715c011fb340dd6d1706";
+
+    #[test]
+    fn sigv4_signing_key() {
+        // Example of the AWS SigV4 documentation
+        let key = signing_key(
+            "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+            "20120215",
+            "us-east-1",
+            "iam",
+        );
+        assert_eq!(
+            hex::encode(key),
+            "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d"
+        );
+    }
+
+    #[tokio::test]
+    async fn loads_key_from_vault() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/secret/data/ernest-oracle"))
+            .and(header("X-Vault-Token", "token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "data": { "data": { "key": KEY }, "metadata": {} }
+            })))
+            .mount(&server)
+            .await;
+        let provider: SecretsProvider = serde_json::from_value(serde_json::json!({
+            "provider": "vault",
+            "address": server.uri(),
+            "path": "ernest-oracle",
+            "token": "token",
+        }))
+        .unwrap();
+        assert_eq!(
+            provider.load_keypair().await.unwrap(),
+            keys::keypair_from_hex(KEY).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn signs_secrets_manager_request() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(header("x-amz-target", "secretsmanager.GetSecretValue"))
+            .and(header_exists("authorization"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "SecretString": serde_json::json!({ "key": KEY }).to_string()
+            })))
+            .mount(&server)
+            .await;
+        let credentials = AwsCredentials {
+            access_key_id: "AKIDEXAMPLE".to_string(),
+            secret_access_key: "secret".to_string(),
+            session_token: None,
+        };
+        let client = Client::new();
+        let request = credentials
+            .sign(
+                client.post(server.uri()),
+                &server.uri(),
+                "us-east-1",
+                "secretsmanager",
+                "secretsmanager.GetSecretValue",
+                "{}",
+                chrono::DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+            )
+            .unwrap()
+            .build()
+            .unwrap();
+        let authorization = request.headers()["authorization"].to_str().unwrap();
+        assert!(authorization.starts_with(
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20231114/us-east-1/secretsmanager/aws4_request, SignedHeaders=content-type;host;x-amz-date;x-amz-target, Signature="
+        ));
+        assert_eq!(
+            client.execute(request).await.unwrap().status(),
+            reqwest::StatusCode::OK
+        );
+    }
+}
@@ -0,0 +1,169 @@
+//! Checks the server runs before it starts serving.
+//!
+//! Announcing under another key than past events leaves every contract on them without a
+//! settling attestation, so the oracle refuses to start when the configured key did not announce
+//! the stored events. It also refuses with pending migrations or a data source that does not
+//! answer, instead of failing on the first request or signing.
+
+use bitcoin::{
+    hashes::{sha256, Hash},
+    key::Secp256k1,
+    secp256k1::{schnorr::Signature, Message},
+    XOnlyPublicKey,
+};
+use sqlx::{PgPool, Postgres};
+
+use crate::{
+    events::{EventType, OutcomeOptions},
+    mempool::MempoolClient,
+    migrations,
+};
+
+/// Latest announcements checked against the configured key.
+pub const KEY_CHECK_EVENTS: i64 = 10;
+
+/// Run every check, failing on the first that does not pass.
+pub async fn run(
+    pool: &PgPool,
+    oracle_public_key: XOnlyPublicKey,
+    mempool: &MempoolClient,
+) -> anyhow::Result<()> {
+    migrations::ensure_up_to_date(pool).await?;
+    check_key(pool, oracle_public_key).await?;
+    check_data_source(mempool).await?;
+    log::info!(
+        "Startup self-test passed. pubkey={} data_source={}",
+        oracle_public_key,
+        mempool.base_url()
+    );
+    Ok(())
+}
+
+/// Check `oracle_public_key` announced the latest stored events. A single announcement that does
+/// not verify is drift for `oracle-admin verify`, none verifying is another key.
+pub async fn check_key(pool: &PgPool, oracle_public_key: XOnlyPublicKey) -> anyhow::Result<()> {
+    let events = sqlx::query_as::<Postgres, (String, Vec<u8>, Vec<u8>)>(
+        r#"
+        SELECT event_id, announcement_signature, oracle_event FROM events
+        ORDER BY created_at DESC
+        LIMIT $1
+        "#,
+    )
+    .bind(KEY_CHECK_EVENTS)
+    .fetch_all(pool)
+    .await?;
+    if events.is_empty() {
+        return Ok(());
+    }
+    let secp = Secp256k1::verification_only();
+    let announced = events.iter().any(|(_, signature, oracle_event)| {
+        let Ok(signature) = Signature::from_slice(signature) else {
+            return false;
+        };
+        let message = Message::from_digest(sha256::Hash::hash(oracle_event).to_byte_array());
+        secp.verify_schnorr(&signature, &message, &oracle_public_key)
+            .is_ok()
+    });
+    if !announced {
+        return Err(anyhow::anyhow!(
+            "The configured key did not announce the stored events, refusing to serve under another key. pubkey={} event_id={}",
+            oracle_public_key,
+            events[0].0
+        ));
+    }
+    Ok(())
+}
+
+/// Check the data source answers with a value.
+pub async fn check_data_source(mempool: &MempoolClient) -> anyhow::Result<()> {
+    EventType::BlocksUntilHalving
+        .outcome_with_provenance(mempool, &OutcomeOptions::default())
+        .await
+        .map_err(|e| {
+            anyhow::anyhow!(
+                "Data source does not respond. url={} error={}",
+                mempool.base_url(),
+                e
+            )
+        })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        routes::CreateEvent,
+        storage::PostgresStorage,
+        test_util::{mock_mempool_client, setup_ernest_oracle, setup_mock_server},
+    };
+    use bitcoin::secp256k1::{rand::thread_rng, SecretKey};
+
+    #[tokio::test]
+    async fn refuses_another_key_and_a_silent_source() {
+        let mock_server = setup_mock_server().await;
+        let mempool = mock_mempool_client(&mock_server);
+        let oracle = setup_ernest_oracle(mempool.clone()).await;
+        oracle
+            .create_event(CreateEvent::single(
+                EventType::Hashrate,
+                chrono::Utc::now().timestamp() as u32 + 3600,
+            ))
+            .await
+            .unwrap();
+        let pool = &oracle.oracle.storage.pool;
+        check_key(pool, oracle.oracle.public_key()).await.unwrap();
+        check_data_source(&mempool).await.unwrap();
+
+        let other = SecretKey::new(&mut thread_rng())
+            .x_only_public_key(&Secp256k1::new())
+            .0;
+        assert!(check_key(pool, other).await.is_err());
+        // The key stored on first use turns the other key away
+        assert!(PostgresStorage::new(pool.clone(), other, false)
+            .await
+            .is_err());
+
+        let silent = MempoolClient::new("http://127.0.0.1:9/api/v1".to_string());
+        assert!(check_data_source(&silent).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn nonces_are_never_stored_twice() {
+        let mempool = MempoolClient::new("http://127.0.0.1:9/api/v1".to_string());
+        let oracle = setup_ernest_oracle(mempool).await;
+        let announcement = oracle
+            .oracle
+            .create_numeric_event(
+                uuid::Uuid::new_v4().to_string(),
+                4,
+                false,
+                0,
+                "u".to_string(),
+                chrono::Utc::now().timestamp() as u32 + 3600,
+            )
+            .await
+            .unwrap();
+        let pool = &oracle.oracle.storage.pool;
+        let nonces = &announcement.oracle_event.oracle_nonces;
+        let mut conn = pool.acquire().await.unwrap();
+        let reused = crate::storage::insert_nonces(
+            &mut conn,
+            &uuid::Uuid::new_v4().to_string(),
+            &[u32::MAX - 1],
+            &nonces[..1],
+            &[],
+        )
+        .await;
+        assert!(reused.is_err());
+        let repeated = crate::storage::insert_nonces(
+            &mut conn,
+            &announcement.oracle_event.event_id,
+            &[u32::MAX - 1, u32::MAX],
+            &[nonces[0], nonces[0]],
+            &[],
+        )
+        .await;
+        assert!(repeated.is_err());
+    }
+}
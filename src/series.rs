@@ -0,0 +1,280 @@
+//! Series of single events created and committed to in one go.
+//!
+//! A series is a schedule of events of the same type, e.g. 52 weekly hashrate events. The
+//! oracle signs a manifest over the ordered event ids and maturities (see
+//! [`receipts::series_message`]) so counterparties can check the whole schedule was fixed up
+//! front. Events of a series stay unlisted until every one of them is created.
+
+use bitcoin::{secp256k1::schnorr::Signature, secp256k1::Message, XOnlyPublicKey};
+use serde::{Deserialize, Serialize};
+use sqlx::{prelude::FromRow, PgPool, Postgres};
+
+use crate::{
+    events::EventType,
+    mempool::{Aggregation, FeePercentile},
+    notifications::Notification,
+    outbox, receipts,
+    routes::CreateEvent,
+};
+
+/// Ten years of weekly events.
+pub const MAX_SERIES_EVENTS: u32 = 520;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateSeries {
+    pub event_type: EventType,
+    /// Maturity of the first event as a unix timestamp
+    pub first_maturity: u32,
+    /// Seconds between consecutive maturities
+    pub interval_secs: u32,
+    pub count: u32,
+    #[serde(default)]
+    pub percentile: Option<FeePercentile>,
+    #[serde(default)]
+    pub aggregation: Option<Aggregation>,
+    #[serde(default)]
+    pub precision: Option<i32>,
+    #[serde(default)]
+    pub nb_digits: Option<u16>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Keep the whole series unlisted until this unix timestamp
+    #[serde(default)]
+    pub announce_at: Option<u32>,
+    /// Seconds to wait after each maturity before signing, see [`CreateEvent::settlement_delay`]
+    #[serde(default)]
+    pub settlement_delay: Option<u32>,
+}
+
+impl CreateSeries {
+    /// Maturities of the series in order.
+    pub fn maturities(&self) -> anyhow::Result<Vec<u32>> {
+        if self.count == 0 || self.count > MAX_SERIES_EVENTS {
+            return Err(anyhow::anyhow!(
+                "Series must have between 1 and {} events. count={}",
+                MAX_SERIES_EVENTS,
+                self.count
+            ));
+        }
+        if self.interval_secs == 0 {
+            return Err(anyhow::anyhow!("Series interval must be positive"));
+        }
+        (0..self.count)
+            .map(|i| {
+                i.checked_mul(self.interval_secs)
+                    .and_then(|offset| self.first_maturity.checked_add(offset))
+                    .ok_or(anyhow::anyhow!("Series maturity overflows. position={}", i))
+            })
+            .collect()
+    }
+
+    /// The event at `maturity`. Its announcement is published with the rest of the series.
+    pub fn event(&self, maturity: u32) -> CreateEvent {
+        CreateEvent::Single {
+            event_type: self.event_type.clone(),
+            maturity,
+            percentile: self.percentile,
+            aggregation: self.aggregation,
+            smoothing: None,
+            precision: self.precision,
+            nb_digits: self.nb_digits,
+            description: self.description.clone(),
+            tags: self.tags.clone(),
+            announce_at: None,
+            settlement_delay: self.settlement_delay,
+            event_id: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SeriesEntry {
+    pub event_id: String,
+    pub maturity: u32,
+}
+
+/// The oracle's commitment to a series.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SeriesManifest {
+    pub series_id: String,
+    pub oracle_public_key: XOnlyPublicKey,
+    pub events: Vec<SeriesEntry>,
+    pub signature: Signature,
+}
+
+impl SeriesManifest {
+    pub fn message(series_id: &str, events: &[SeriesEntry]) -> Message {
+        let events = events
+            .iter()
+            .map(|entry| (entry.event_id.as_str(), entry.maturity))
+            .collect::<Vec<_>>();
+        receipts::series_message(series_id, &events)
+    }
+
+    /// Whether the manifest is signed by `pubkey`.
+    pub fn verify(&self, pubkey: &XOnlyPublicKey) -> bool {
+        receipts::verify_receipt(
+            pubkey,
+            &Self::message(&self.series_id, &self.events),
+            &self.signature,
+        )
+    }
+}
+
+/// Remove the events of a series that failed to be created.
+pub async fn delete_events(pool: &PgPool, event_ids: &[String]) -> anyhow::Result<()> {
+    sqlx::query("DELETE FROM events WHERE event_id = ANY($1)")
+        .bind(event_ids)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Save the manifest and publish its events, or schedule them for `announce_at`, in one
+/// transaction.
+pub async fn save_series(
+    pool: &PgPool,
+    manifest: &SeriesManifest,
+    announce_at: Option<chrono::DateTime<chrono::Utc>>,
+) -> anyhow::Result<()> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("INSERT INTO event_series (series_id, signature) VALUES ($1, $2)")
+        .bind(&manifest.series_id)
+        .bind(manifest.signature.serialize().to_vec())
+        .execute(&mut *tx)
+        .await?;
+    for (position, entry) in manifest.events.iter().enumerate() {
+        sqlx::query(
+            "INSERT INTO event_series_events (series_id, position, event_id, maturity) VALUES ($1, $2, $3, $4)",
+        )
+        .bind(&manifest.series_id)
+        .bind(position as i32)
+        .bind(&entry.event_id)
+        .bind(entry.maturity as i64)
+        .execute(&mut *tx)
+        .await?;
+    }
+    let event_ids = manifest
+        .events
+        .iter()
+        .map(|entry| entry.event_id.clone())
+        .collect::<Vec<_>>();
+    sqlx::query(
+        "UPDATE events SET announce_at = $2, announced = ($2 IS NULL OR $2 <= NOW()) WHERE event_id = ANY($1)",
+    )
+    .bind(&event_ids)
+    .bind(announce_at)
+    .execute(&mut *tx)
+    .await?;
+    // One message announces the series instead of one per event
+    sqlx::query("DELETE FROM outbox WHERE event_id = ANY($1)")
+        .bind(&event_ids)
+        .execute(&mut *tx)
+        .await?;
+    if let Some(first) = manifest.events.first() {
+        outbox::enqueue(
+            &mut tx,
+            &first.event_id,
+            &Notification::SeriesAnnounced {
+                series_id: manifest.series_id.clone(),
+                events: manifest.events.len(),
+                first_maturity: first.maturity,
+            },
+        )
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+#[derive(Debug, FromRow)]
+struct SeriesEntryRow {
+    event_id: String,
+    maturity: i64,
+}
+
+/// The manifest of a published series.
+pub async fn get_series(
+    pool: &PgPool,
+    series_id: &str,
+    oracle_public_key: XOnlyPublicKey,
+) -> anyhow::Result<SeriesManifest> {
+    let signature = sqlx::query_scalar::<Postgres, Vec<u8>>(
+        r#"
+        SELECT s.signature FROM event_series s
+        WHERE s.series_id = $1
+            AND NOT EXISTS (
+                SELECT 1 FROM event_series_events se
+                JOIN events e ON e.event_id = se.event_id
+                WHERE se.series_id = s.series_id AND NOT e.announced
+            )
+        "#,
+    )
+    .bind(series_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or(anyhow::anyhow!("Series not found. series_id={}", series_id))?;
+
+    let events = sqlx::query_as::<Postgres, SeriesEntryRow>(
+        "SELECT event_id, maturity FROM event_series_events WHERE series_id = $1 ORDER BY position",
+    )
+    .bind(series_id)
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|row| {
+        Ok(SeriesEntry {
+            event_id: row.event_id,
+            maturity: u32::try_from(row.maturity)?,
+        })
+    })
+    .collect::<anyhow::Result<Vec<_>>>()?;
+
+    Ok(SeriesManifest {
+        series_id: series_id.to_string(),
+        oracle_public_key,
+        events,
+        signature: Signature::from_slice(&signature)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn series(first_maturity: u32, interval_secs: u32, count: u32) -> CreateSeries {
+        CreateSeries {
+            event_type: EventType::Hashrate,
+            first_maturity,
+            interval_secs,
+            count,
+            percentile: None,
+            aggregation: None,
+            precision: None,
+            nb_digits: None,
+            description: None,
+            tags: vec![],
+            announce_at: None,
+            settlement_delay: None,
+        }
+    }
+
+    #[test]
+    fn maturities_are_evenly_spaced() {
+        assert_eq!(
+            series(1000, 604_800, 3).maturities().unwrap(),
+            vec![1000, 605_800, 1_210_600]
+        );
+        assert!(series(1000, 604_800, 0).maturities().is_err());
+        assert!(series(1000, 0, 3).maturities().is_err());
+        assert!(series(1000, 604_800, MAX_SERIES_EVENTS + 1)
+            .maturities()
+            .is_err());
+        assert!(series(u32::MAX - 10, 604_800, 2).maturities().is_err());
+    }
+}
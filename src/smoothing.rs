@@ -0,0 +1,167 @@
+//! Smoothing the value a single event settles on.
+//!
+//! An event created with a [`Smoothing`] does not settle on the value fetched at maturity alone
+//! but on its moving average with the latest snapshots of the data type (see [`crate::history`]),
+//! so one anomalous reading cannot decide a contract. The averaged snapshots are kept as samples
+//! in the provenance of the signed value.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{prelude::FromRow, PgPool, Postgres};
+
+use crate::{
+    events::EventType,
+    history::HISTORY_INTERVAL_SECS,
+    mempool::{DataProvenance, DataSample},
+};
+
+/// Largest moving average window, a week of hourly snapshots.
+pub const MAX_WINDOW: u16 = 7 * 24;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(tag = "method", rename_all = "camelCase")]
+pub enum Smoothing {
+    /// Mean of the value at maturity and the `window - 1` snapshots before it
+    #[serde(rename_all = "camelCase")]
+    MovingAverage { window: u16 },
+}
+
+impl Smoothing {
+    /// Values averaged, the value at maturity included.
+    pub fn window(&self) -> u16 {
+        match self {
+            Smoothing::MovingAverage { window } => *window,
+        }
+    }
+}
+
+#[derive(FromRow)]
+struct SnapshotRow {
+    value: f64,
+    source_url: String,
+    sampled_at: DateTime<Utc>,
+}
+
+/// Smooth `provenance`, the value of `data_type` fetched at maturity. Snapshots older than the
+/// window at the sampling interval do not count, too few recent ones fail the signing rather than
+/// settle on a shorter average than announced.
+pub async fn smooth(
+    pool: &PgPool,
+    data_type: &EventType,
+    mut provenance: DataProvenance,
+    smoothing: Smoothing,
+) -> anyhow::Result<DataProvenance> {
+    let Smoothing::MovingAverage { window } = smoothing;
+    let needed = window.saturating_sub(1) as usize;
+    if needed == 0 {
+        return Ok(provenance);
+    }
+    let since = provenance.fetched_at
+        - chrono::Duration::seconds(window as i64 * HISTORY_INTERVAL_SECS as i64);
+    let snapshots = sqlx::query_as::<Postgres, SnapshotRow>(
+        r#"
+        SELECT value, source_url, sampled_at FROM metric_history
+        WHERE data_type = $1 AND event_id IS NULL AND sampled_at >= $2 AND sampled_at < $3
+        ORDER BY sampled_at DESC
+        LIMIT $4
+        "#,
+    )
+    .bind(data_type.to_string())
+    .bind(since)
+    .bind(provenance.fetched_at)
+    .bind(needed as i64)
+    .fetch_all(pool)
+    .await?;
+    if snapshots.len() < needed {
+        return Err(anyhow::anyhow!(
+            "Too few snapshots to smooth the outcome. data_type={} window={} snapshots={}",
+            data_type,
+            window,
+            snapshots.len()
+        ));
+    }
+    let total = snapshots.iter().map(|s| s.value).sum::<f64>() + provenance.value;
+    provenance.value = total / window as f64;
+    provenance
+        .samples
+        .extend(snapshots.into_iter().rev().map(|snapshot| DataSample {
+            value: snapshot.value,
+            fetched_at: snapshot.sampled_at,
+            rejected: false,
+            source_url: Some(snapshot.source_url),
+        }));
+    Ok(provenance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        history,
+        mempool::{MempoolClient, BASE_URL},
+        test_util::setup_ernest_oracle,
+    };
+
+    fn provenance(value: f64, fetched_at: DateTime<Utc>) -> DataProvenance {
+        DataProvenance {
+            source_url: "https://mempool.space/api/v1/mining/hashrate/3m".to_string(),
+            raw_response: serde_json::json!({ "value": value }),
+            fetched_at,
+            value,
+            samples: vec![],
+        }
+    }
+
+    #[tokio::test]
+    async fn averages_the_latest_snapshots() {
+        let oracle = setup_ernest_oracle(MempoolClient::new(BASE_URL.to_string())).await;
+        let pool = &oracle.oracle.storage.pool;
+        // Snapshots of a type no other test writes, in the future to stay clear of the sampler
+        let data_type = EventType::DifficultyChangePercent;
+        sqlx::query("DELETE FROM metric_history WHERE data_type = $1 AND sampled_at > now()")
+            .bind(data_type.to_string())
+            .execute(pool)
+            .await
+            .unwrap();
+        let maturity = Utc::now() + chrono::Duration::days(3650);
+        let hour = chrono::Duration::seconds(HISTORY_INTERVAL_SECS as i64);
+        for (hours, value) in [(4, 1000.0), (2, 10.0), (1, 20.0)] {
+            history::save_snapshot(
+                pool,
+                &data_type,
+                &provenance(value, maturity - hour * hours),
+            )
+            .await
+            .unwrap();
+        }
+
+        let spike = provenance(300.0, maturity);
+        let smoothed = smooth(
+            pool,
+            &data_type,
+            spike.clone(),
+            Smoothing::MovingAverage { window: 3 },
+        )
+        .await
+        .unwrap();
+        assert_eq!(smoothed.value, 110.0);
+        assert_eq!(
+            smoothed
+                .samples
+                .iter()
+                .map(|sample| sample.value)
+                .collect::<Vec<_>>(),
+            [10.0, 20.0]
+        );
+
+        // Only three snapshots are within a window of five hours
+        let error = smooth(
+            pool,
+            &data_type,
+            spike,
+            Smoothing::MovingAverage { window: 5 },
+        )
+        .await;
+        assert!(error.is_err());
+    }
+}
@@ -0,0 +1,185 @@
+use std::collections::BTreeMap;
+
+use chrono::Utc;
+use kormir::EventDescriptor;
+use serde::{Deserialize, Serialize};
+use sqlx::{prelude::FromRow, PgPool, Postgres};
+
+use crate::{storage::to_oracle_event, units, watcher::WatcherHealthReport};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OracleStats {
+    pub total_events: i64,
+    pub signed_events: i64,
+    /// Matured events still waiting for an attestation.
+    pub pending_events: i64,
+    /// Events that have not reached maturity yet.
+    pub open_events: i64,
+    pub by_event_type: Vec<EventTypeStats>,
+    /// Number of events settling on each data type. A parlay counts once for every
+    /// distinct data type among its legs.
+    pub by_data_type: BTreeMap<String, i64>,
+    /// Mean seconds between maturity and the recorded attestation outcome.
+    pub average_attestation_delay_secs: Option<f64>,
+    pub watcher: WatcherHealthReport,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct EventTypeStats {
+    pub event_type: String,
+    pub total: i64,
+    pub signed: i64,
+}
+
+#[derive(Debug, FromRow)]
+struct DataTypeCount {
+    data_type: String,
+    events: i64,
+}
+
+#[derive(Debug, FromRow)]
+struct EventTimings {
+    pending_events: i64,
+    open_events: i64,
+    average_attestation_delay_secs: Option<f64>,
+}
+
+pub async fn get_oracle_stats(
+    pool: &PgPool,
+    watcher: WatcherHealthReport,
+) -> anyhow::Result<OracleStats> {
+    let by_event_type = sqlx::query_as::<Postgres, EventTypeStats>(
+        r#"
+        SELECT
+            COALESCE(types.event_type, 'unknown') AS event_type,
+            COUNT(*) AS total,
+            COUNT(*) FILTER (WHERE EXISTS (
+                SELECT 1 FROM event_nonces en
+                WHERE en.event_id = e.event_id
+                AND en.signature IS NOT NULL
+            )) AS signed
+        FROM events e
+        LEFT JOIN event_types types ON e.event_id = types.oracle_event_id
+        GROUP BY 1
+        ORDER BY 1
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let parlay_data_types = sqlx::query_as::<Postgres, DataTypeCount>(
+        r#"
+        SELECT data_type, COUNT(DISTINCT contract_id) AS events
+        FROM parlay_parameters
+        GROUP BY data_type
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let timings = sqlx::query_as::<Postgres, EventTimings>(
+        r#"
+        WITH event_status AS (
+            SELECT
+                e.maturity,
+                EXISTS (
+                    SELECT 1 FROM event_nonces en
+                    WHERE en.event_id = e.event_id
+                    AND en.signature IS NOT NULL
+                ) AS is_signed,
+                outcome.created_at AS attested_at
+            FROM events e
+            LEFT JOIN numeric_attestation_outcome outcome ON e.event_id = outcome.event_id
+        )
+        SELECT
+            COUNT(*) FILTER (WHERE NOT is_signed AND maturity <= $1) AS pending_events,
+            COUNT(*) FILTER (WHERE NOT is_signed AND maturity > $1) AS open_events,
+            (AVG(GREATEST(EXTRACT(EPOCH FROM attested_at)::BIGINT - maturity, 0))
+                FILTER (WHERE is_signed AND attested_at IS NOT NULL))::FLOAT8
+                AS average_attestation_delay_secs
+        FROM event_status
+        "#,
+    )
+    .bind(Utc::now().timestamp())
+    .fetch_one(pool)
+    .await?;
+
+    // The unit of a single event only lives inside its encoded oracle event.
+    let single_events = sqlx::query_scalar::<Postgres, Vec<u8>>(
+        r#"
+        SELECT e.oracle_event
+        FROM events e
+        JOIN event_types types ON e.event_id = types.oracle_event_id
+        WHERE types.event_type = 'single'
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut by_data_type = parlay_data_types
+        .into_iter()
+        .map(|row| (row.data_type, row.events))
+        .collect::<BTreeMap<_, _>>();
+
+    for oracle_event in single_events {
+        let oracle_event = to_oracle_event(&oracle_event)?;
+        if let EventDescriptor::DigitDecompositionEvent(descriptor) = oracle_event.event_descriptor
+        {
+            let data_type = units::event_type_from_unit(&descriptor.unit)
+                .map(|event_type| event_type.to_string())
+                .unwrap_or(descriptor.unit);
+            *by_data_type.entry(data_type).or_default() += 1;
+        }
+    }
+
+    let total_events = by_event_type.iter().map(|s| s.total).sum();
+    let signed_events = by_event_type.iter().map(|s| s.signed).sum();
+    Ok(OracleStats {
+        total_events,
+        signed_events,
+        pending_events: timings.pending_events,
+        open_events: timings.open_events,
+        by_event_type,
+        by_data_type,
+        average_attestation_delay_secs: timings.average_attestation_delay_secs,
+        watcher,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        events::EventType,
+        mempool::{MempoolClient, BASE_URL},
+        routes::CreateEvent,
+        test_util::setup_ernest_oracle,
+        watcher::WatcherHealth,
+    };
+
+    #[tokio::test]
+    async fn stats_count_created_events() {
+        let oracle = setup_ernest_oracle(MempoolClient::new(BASE_URL.to_string())).await;
+        let pool = oracle.oracle.storage.pool.clone();
+        let before = get_oracle_stats(&pool, WatcherHealth::default().report())
+            .await
+            .unwrap();
+
+        oracle
+            .create_event(CreateEvent::single(
+                EventType::Difficulty,
+                Utc::now().timestamp() as u32 + 1000,
+            ))
+            .await
+            .unwrap();
+
+        let after = get_oracle_stats(&pool, WatcherHealth::default().report())
+            .await
+            .unwrap();
+        assert!(after.total_events > before.total_events);
+        assert!(after.by_data_type.get("difficulty").copied().unwrap_or(0) > 0);
+        assert!(!after.watcher.healthy);
+    }
+}
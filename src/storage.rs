@@ -1,5 +1,12 @@
+use crate::attestation::AttestationRecord;
+use crate::audit::{self, SigningSource};
+use crate::events::{self, EventParams, OutcomeOptions};
+use crate::metadata::{self, EventMetadata};
+use crate::notifications::Notification;
+use crate::{digits, migrations, outbox};
 use bitcoin::secp256k1::schnorr::Signature;
 use bitcoin::secp256k1::XOnlyPublicKey;
+use chrono::{DateTime, Utc};
 use dlc_messages::oracle_msgs::{EventDescriptor, OracleAnnouncement};
 use kormir::error::Error;
 use kormir::lightning::util::ser::Readable;
@@ -7,16 +14,76 @@ use kormir::storage::OracleEventData;
 use kormir::storage::Storage;
 use kormir::OracleEvent;
 use kormir::Writeable;
-use sqlx::Row;
-use sqlx::{PgPool, Pool, Postgres};
-use std::sync::atomic::{AtomicU32, Ordering};
-use std::sync::Arc;
+use sqlx::{FromRow, PgConnection, PgPool, Pool, Postgres};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, FromRow)]
+struct EventRow {
+    event_id: String,
+    announcement_signature: Vec<u8>,
+    oracle_event: Vec<u8>,
+}
+
+impl EventRow {
+    fn announcement(&self, oracle_public_key: XOnlyPublicKey) -> Result<OracleAnnouncement, Error> {
+        Ok(OracleAnnouncement {
+            announcement_signature: Signature::from_slice(&self.announcement_signature).map_err(
+                |e| {
+                    log::error!(
+                        "Invalid announcement signature. event_id={} error={}",
+                        self.event_id,
+                        e
+                    );
+                    Error::StorageFailure
+                },
+            )?,
+            oracle_public_key,
+            oracle_event: to_oracle_event(&self.oracle_event)?,
+        })
+    }
+}
+
+#[derive(Debug, FromRow)]
+struct NonceRow {
+    index: i32,
+    outcome: Option<String>,
+    signature: Option<Vec<u8>>,
+}
+
+impl NonceRow {
+    /// Indexes and attestation signatures of an event's nonces, ordered by index.
+    fn split(nonces: Vec<NonceRow>) -> (Vec<u32>, Vec<(String, Signature)>) {
+        let indexes = nonces.iter().map(|nonce| nonce.index as u32).collect();
+        let signatures = nonces
+            .into_iter()
+            .filter_map(|nonce| {
+                Some((
+                    nonce.outcome?,
+                    Signature::from_slice(&nonce.signature?).ok()?,
+                ))
+            })
+            .collect();
+        (indexes, signatures)
+    }
+}
 
 #[derive(Clone)]
 pub struct PostgresStorage {
     pub pool: Pool<Postgres>,
     oracle_public_key: XOnlyPublicKey,
-    current_index: Arc<AtomicU32>,
+    /// Records saved with the signatures of their event, see [`Self::stage_attestation`]
+    staged_attestations: Arc<Mutex<HashMap<String, AttestationRecord>>>,
+    /// Audit entries saved with the signatures of their event, see [`Self::stage_signing`]
+    staged_signings: Arc<Mutex<HashMap<String, (i64, SigningSource)>>>,
+    /// Announcements saved unlisted, see [`Self::stage_unlisted`]
+    staged_unlisted: Arc<Mutex<HashMap<String, Option<DateTime<Utc>>>>>,
+    /// Metadata saved with the announcement of its event, see [`Self::stage_metadata`]
+    staged_metadata: Arc<Mutex<HashMap<String, EventMetadata>>>,
+    /// Outcome options saved with the announcement of their event, see [`Self::stage_options`]
+    staged_options: Arc<Mutex<HashMap<String, (OutcomeOptions, EventParams)>>>,
+    /// Delays saved with the announcement of their event, see [`Self::stage_settlement_delay`]
+    staged_settlement_delays: Arc<Mutex<HashMap<String, u32>>>,
 }
 
 impl PostgresStorage {
@@ -26,96 +93,80 @@ impl PostgresStorage {
         migrate: bool,
     ) -> anyhow::Result<Self> {
         if migrate {
-            sqlx::migrate!();
+            migrations::run(&pool).await?;
         }
-
-        let row = sqlx::query("SELECT COALESCE(MAX(index), 0) as max_index FROM event_nonces")
-            .fetch_one(&pool)
-            .await?;
-        let current_index: i32 = row.get("max_index");
+        ensure_public_key(&pool, &oracle_public_key).await?;
 
         Ok(Self {
             pool,
             oracle_public_key,
-            current_index: Arc::new(AtomicU32::new(current_index as u32 + 1)),
+            staged_attestations: Arc::default(),
+            staged_signings: Arc::default(),
+            staged_unlisted: Arc::default(),
+            staged_metadata: Arc::default(),
+            staged_options: Arc::default(),
+            staged_settlement_delays: Arc::default(),
         })
     }
 
+    /// Save `record` in the transaction that saves the signatures of its event, kormir signing
+    /// through [`Storage::save_signatures`] without a way to pass it along. Unstage it when the
+    /// signing fails.
+    pub fn stage_attestation(&self, record: AttestationRecord) {
+        self.staged_attestations
+            .lock()
+            .unwrap()
+            .insert(record.event_id.clone(), record);
+    }
+
+    pub fn unstage_attestation(&self, event_id: &str) -> Option<AttestationRecord> {
+        self.staged_attestations.lock().unwrap().remove(event_id)
+    }
+
+    /// Record the signing of `event_id` in the audit log in the transaction that saves its
+    /// signatures, see [`audit::record_signing`].
+    pub fn stage_signing(&self, event_id: &str, outcome: i64, source: SigningSource) {
+        self.staged_signings
+            .lock()
+            .unwrap()
+            .insert(event_id.to_string(), (outcome, source));
+    }
+
+    pub fn unstage_signing(&self, event_id: &str) -> Option<(i64, SigningSource)> {
+        self.staged_signings.lock().unwrap().remove(event_id)
+    }
+
     pub async fn oracle_event_data(&self) -> Result<Vec<OracleEventData>, Error> {
         let mut tx = self.pool.begin().await.map_err(|_| Error::StorageFailure)?;
-        let row = sqlx::query("SELECT event_id, announcement_signature, oracle_event FROM events")
-            .fetch_all(&mut *tx)
-            .await
-            .map_err(|_| Error::StorageFailure)?;
-        let events = row
-            .iter()
-            .map(|row| {
-                let event_id: String = row.get("event_id");
-                let announcement_signature: Vec<u8> = row.get("announcement_signature");
-                let oracle_event: Vec<u8> = row.get("oracle_event");
-
-                (event_id, announcement_signature, oracle_event)
-            })
-            .collect::<Vec<_>>();
+        let events = sqlx::query_as::<Postgres, EventRow>(
+            "SELECT event_id, announcement_signature, oracle_event FROM events WHERE announced AND NOT hidden",
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|_| Error::StorageFailure)?;
 
         let mut oracle_events = Vec::with_capacity(events.len());
-        for (event_id, announcement_signature, oracle_event) in events {
-            let event_row = sqlx::query(
+        for event in events {
+            let nonces = sqlx::query_as::<Postgres, NonceRow>(
                 r#"
-                SELECT index, outcome, signature, nonce
+                SELECT index, outcome, signature
                 FROM event_nonces
                 WHERE event_id = $1
                 ORDER BY index
                 "#,
             )
-            .bind(event_id.clone())
+            .bind(&event.event_id)
             .fetch_all(&mut *tx)
             .await
             .map_err(|_| Error::StorageFailure)?;
+            let (indexes, signatures) = NonceRow::split(nonces);
 
-            let nonces = event_row
-                .iter()
-                .map(|row| {
-                    let index: i32 = row.get("index");
-                    let outcome: Option<String> = row.get("outcome");
-                    let signature: Option<Vec<u8>> = row.get("signature");
-                    let nonce: Option<Vec<u8>> = row.get("nonce");
-                    (index, outcome, signature, nonce)
-                })
-                .collect::<Vec<_>>();
-
-            let indexes = nonces
-                .iter()
-                .map(|(index, _, _, _)| *index as u32)
-                .collect();
-
-            let signatures = nonces
-                .into_iter()
-                .filter_map(|(_, outcome, sig, _)| {
-                    if let (Some(outcome), Some(sig)) = (outcome, sig) {
-                        Some((outcome, Signature::from_slice(&sig).ok()?))
-                    } else {
-                        None
-                    }
-                })
-                .collect();
-
-            let oracle_event = to_oracle_event(&oracle_event);
-
-            let announcement = OracleAnnouncement {
-                announcement_signature: Signature::from_slice(&announcement_signature)
-                    .map_err(|_| Error::StorageFailure)?,
-                oracle_public_key: self.oracle_public_key,
-                oracle_event,
-            };
-
-            let data = OracleEventData {
-                event_id,
-                announcement,
+            oracle_events.push(OracleEventData {
+                announcement: event.announcement(self.oracle_public_key)?,
+                event_id: event.event_id,
                 indexes,
                 signatures,
-            };
-            oracle_events.push(data);
+            });
         }
 
         tx.commit().await.map_err(|_| Error::StorageFailure)?;
@@ -125,28 +176,187 @@ impl PostgresStorage {
     pub async fn get_event_maturity(&self, event_id: String) -> Result<u32, Error> {
         let mut tx = self.pool.begin().await.map_err(|_| Error::StorageFailure)?;
 
-        let row = sqlx::query("SELECT oracle_event FROM events WHERE event_id = $1")
+        let oracle_event = sqlx::query_scalar::<Postgres, Vec<u8>>(
+            "SELECT oracle_event FROM events WHERE event_id = $1",
+        )
+        .bind(event_id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|_| Error::StorageFailure)?;
+
+        Ok(to_oracle_event(&oracle_event)?.event_maturity_epoch)
+    }
+
+    /// Save the announcement of `event_id` unlisted, until `announce_at` or, without one, until
+    /// it is listed explicitly. Staged before kormir saves it, so neither the event nor its
+    /// outbox message is visible early. Unstage it when the creation fails.
+    pub fn stage_unlisted(&self, event_id: &str, announce_at: Option<DateTime<Utc>>) {
+        self.staged_unlisted
+            .lock()
+            .unwrap()
+            .insert(event_id.to_string(), announce_at);
+    }
+
+    pub fn unstage_unlisted(&self, event_id: &str) -> Option<Option<DateTime<Utc>>> {
+        self.staged_unlisted.lock().unwrap().remove(event_id)
+    }
+
+    /// Save `metadata` in the transaction that saves the announcement of `event_id`, so an
+    /// event is never listed without it. Unstage it when the creation fails.
+    pub fn stage_metadata(&self, event_id: &str, metadata: EventMetadata) {
+        self.staged_metadata
+            .lock()
+            .unwrap()
+            .insert(event_id.to_string(), metadata);
+    }
+
+    pub fn unstage_metadata(&self, event_id: &str) -> Option<EventMetadata> {
+        self.staged_metadata.lock().unwrap().remove(event_id)
+    }
+
+    /// Save the outcome options and `params` of a single event in the transaction that saves
+    /// its announcement, an announced event without them would be signed with the legacy
+    /// precision. Unstage them when the creation fails.
+    pub fn stage_options(&self, event_id: &str, options: OutcomeOptions, params: EventParams) {
+        self.staged_options
+            .lock()
+            .unwrap()
+            .insert(event_id.to_string(), (options, params));
+    }
+
+    pub fn unstage_options(&self, event_id: &str) -> Option<(OutcomeOptions, EventParams)> {
+        self.staged_options.lock().unwrap().remove(event_id)
+    }
+
+    /// Hold an event back from automatic signing, or release it. Returns whether the event
+    /// exists.
+    pub async fn set_hold(
+        &self,
+        event_id: &str,
+        hold: bool,
+        reason: Option<&str>,
+    ) -> anyhow::Result<bool> {
+        let updated = sqlx::query(
+            "UPDATE events SET hold = $1, hold_reason = CASE WHEN $1 THEN $2 END WHERE event_id = $3",
+        )
+        .bind(hold)
+        .bind(reason)
+        .bind(event_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(updated.rows_affected() > 0)
+    }
+
+    /// Leave an event out of listings and search, or list it again. Returns whether the event
+    /// exists.
+    pub async fn set_hidden(&self, event_id: &str, hidden: bool) -> anyhow::Result<bool> {
+        let updated = sqlx::query("UPDATE events SET hidden = $1 WHERE event_id = $2")
+            .bind(hidden)
             .bind(event_id)
-            .fetch_one(&mut *tx)
-            .await
-            .map_err(|_| Error::StorageFailure)?;
+            .execute(&self.pool)
+            .await?;
+        Ok(updated.rows_affected() > 0)
+    }
 
-        let oracle_event: Vec<u8> = row.get("oracle_event");
-        let oracle_event = to_oracle_event(&oracle_event);
-        let event_maturity_epoch = oracle_event.event_maturity_epoch;
-        Ok(event_maturity_epoch)
+    pub async fn is_held(&self, event_id: &str) -> anyhow::Result<bool> {
+        Ok(sqlx::query_scalar::<Postgres, bool>(
+            "SELECT EXISTS (SELECT 1 FROM events WHERE event_id = $1 AND hold)",
+        )
+        .bind(event_id)
+        .fetch_one(&self.pool)
+        .await?)
+    }
+
+    /// Whether `event_id` is a `single` or a `parlay` event, `None` for events created before
+    /// event types were recorded.
+    pub async fn event_type(&self, event_id: &str) -> anyhow::Result<Option<String>> {
+        Ok(sqlx::query_scalar::<Postgres, String>(
+            "SELECT event_type FROM event_types WHERE oracle_event_id = $1",
+        )
+        .bind(event_id)
+        .fetch_optional(&self.pool)
+        .await?)
+    }
+
+    /// Save the announcement of `event_id` waiting `settlement_delay` seconds after maturity
+    /// before it is signed, so the watcher never sees it with the default delay. Unstage it
+    /// when the creation fails.
+    pub fn stage_settlement_delay(&self, event_id: &str, settlement_delay: u32) {
+        self.staged_settlement_delays
+            .lock()
+            .unwrap()
+            .insert(event_id.to_string(), settlement_delay);
+    }
+
+    pub fn unstage_settlement_delay(&self, event_id: &str) -> Option<u32> {
+        self.staged_settlement_delays
+            .lock()
+            .unwrap()
+            .remove(event_id)
+    }
+
+    /// Delete an event with its nonces, type, metadata, parlay contract and attestation
+    /// records, returning whether it existed. Events a parlay leg settles on are kept.
+    pub async fn delete_event(&self, event_id: &str) -> anyhow::Result<bool> {
+        let mut tx = self.pool.begin().await?;
+        let referenced_by = sqlx::query_scalar::<Postgres, String>(
+            "SELECT DISTINCT contract_id FROM parlay_parameters WHERE event_id = $1 AND contract_id <> $1",
+        )
+        .bind(event_id)
+        .fetch_all(&mut *tx)
+        .await?;
+        if !referenced_by.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Event is referenced by parlay contracts. event_id={} contracts={:?}",
+                event_id,
+                referenced_by
+            ));
+        }
+        // Everything else references the event and is removed by its foreign keys
+        let deleted = sqlx::query("DELETE FROM events WHERE event_id = $1")
+            .bind(event_id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        tx.commit().await?;
+        Ok(deleted > 0)
+    }
+
+    /// Whether the announcement is public, `false` for unknown events.
+    pub async fn is_announced(&self, event_id: &str) -> anyhow::Result<bool> {
+        let announced = sqlx::query_scalar::<Postgres, bool>(
+            "SELECT announced FROM events WHERE event_id = $1",
+        )
+        .bind(event_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(announced.unwrap_or(false))
+    }
+
+    /// Publish every scheduled announcement that is due, returning their event ids.
+    pub async fn publish_due_announcements(&self) -> anyhow::Result<Vec<String>> {
+        let event_ids = sqlx::query_scalar::<Postgres, String>(
+            "UPDATE events SET announced = TRUE WHERE NOT announced AND announce_at <= NOW() RETURNING event_id",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(event_ids)
     }
 }
 
 impl Storage for PostgresStorage {
     async fn get_next_nonce_indexes(&self, num: usize) -> Result<Vec<u32>, Error> {
-        let mut current_index = self.current_index.fetch_add(num as u32, Ordering::SeqCst);
-        let mut indexes = Vec::with_capacity(num);
-        for _ in 0..num {
-            indexes.push(current_index);
-            current_index += 1;
-        }
-        Ok(indexes)
+        let indexes = sqlx::query_scalar::<Postgres, i64>(
+            "SELECT nextval('event_nonce_index_seq') FROM generate_series(1, $1)",
+        )
+        .bind(num as i32)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| {
+            log::error!("Could not allocate nonce indexes. error={}", e);
+            Error::StorageFailure
+        })?;
+        Ok(indexes.into_iter().map(|index| index as u32).collect())
     }
 
     async fn save_announcement(
@@ -155,7 +365,7 @@ impl Storage for PostgresStorage {
         indexes: Vec<u32>,
     ) -> Result<String, Error> {
         let mut tx = self.pool.begin().await.map_err(|e| {
-            eprintln!("Could not begin transaction. error={}", e.to_string());
+            eprintln!("Could not begin transaction. error={}", e);
             Error::StorageFailure
         })?;
 
@@ -165,14 +375,18 @@ impl Storage for PostgresStorage {
         );
 
         let event_id = announcement.oracle_event.event_id.clone();
+        let unlisted = self.unstage_unlisted(&event_id);
+        let event_metadata = self.unstage_metadata(&event_id);
+        let event_options = self.unstage_options(&event_id);
+        let settlement_delay = self.unstage_settlement_delay(&event_id);
 
         sqlx::query(
             r#"
             INSERT INTO events (
                 event_id, announcement_signature, oracle_event,
-                name, is_enum
+                name, is_enum, maturity, announced, announce_at, settlement_delay
             )
-            VALUES ($1, $2, $3, $4, $5)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
             "#,
         )
         .bind(event_id.clone())
@@ -180,39 +394,76 @@ impl Storage for PostgresStorage {
         .bind(announcement.oracle_event.encode())
         .bind(&announcement.oracle_event.event_id)
         .bind(is_enum)
+        .bind(announcement.oracle_event.event_maturity_epoch as i64)
+        .bind(unlisted.is_none())
+        .bind(unlisted.flatten())
+        .bind(settlement_delay.map(|delay| delay as i32))
         .execute(&mut *tx)
         .await
         .map_err(|e| {
-            eprintln!("Could not execute query. error={}", e.to_string());
+            if e.as_database_error()
+                .is_some_and(|e| e.is_unique_violation())
+            {
+                log::error!("Event id is already taken. event_id={}", event_id);
+                return Error::InvalidArgument;
+            }
+            eprintln!("Could not execute query. error={}", e);
             Error::StorageFailure
         })?;
 
-        for (index, nonce) in indexes
-            .into_iter()
-            .zip(announcement.oracle_event.oracle_nonces)
-        {
-            sqlx::query(
-                r#"
-                INSERT INTO event_nonces (
-                    id, event_id, index, nonce
-                )
-                VALUES ($1, $2, $3, $4)
-                "#,
-            )
-            .bind(index as i32)
-            .bind(event_id.clone())
-            .bind(index as i32)
-            .bind(&nonce.serialize())
-            .execute(&mut *tx)
+        insert_nonces(
+            &mut tx,
+            &event_id,
+            &indexes,
+            &announcement.oracle_event.oracle_nonces,
+            &[],
+        )
+        .await
+        .map_err(|e| {
+            eprintln!("Could not execute query for nonces. error={}", e);
+            Error::StorageFailure
+        })?;
+
+        if let Some(event_metadata) = event_metadata {
+            metadata::insert_event_metadata(&mut tx, &event_id, &event_metadata)
+                .await
+                .map_err(|e| {
+                    log::error!(
+                        "Could not save metadata with the announcement. event_id={} error={}",
+                        event_id,
+                        e
+                    );
+                    Error::StorageFailure
+                })?;
+        }
+
+        if let Some((options, params)) = event_options {
+            events::insert_event_options(&mut tx, &event_id, &options, &params)
+                .await
+                .map_err(|e| {
+                    log::error!(
+                        "Could not save outcome options with the announcement. event_id={} error={}",
+                        event_id,
+                        e
+                    );
+                    Error::StorageFailure
+                })?;
+        }
+
+        let announced = Notification::EventAnnounced {
+            event_id: event_id.clone(),
+            maturity: announcement.oracle_event.event_maturity_epoch,
+        };
+        outbox::enqueue(&mut tx, &event_id, &announced)
             .await
             .map_err(|e| {
-                eprintln!(
-                    "Could not execute query for nonces. error={}",
-                    e.to_string()
+                log::error!(
+                    "Could not queue the announcement. event_id={} error={}",
+                    event_id,
+                    e
                 );
                 Error::StorageFailure
             })?;
-        }
 
         tx.commit().await.map_err(|_| Error::StorageFailure)?;
         Ok(event_id)
@@ -225,11 +476,9 @@ impl Storage for PostgresStorage {
     ) -> Result<OracleEventData, Error> {
         let mut tx = self.pool.begin().await.map_err(|_| Error::StorageFailure)?;
 
-        let row = match sqlx::query(
+        let Some(event) = sqlx::query_as::<Postgres, EventRow>(
             r#"
-            SELECT 
-                event_id, announcement_signature, oracle_event,
-                announcement_event_id, attestation_event_id
+            SELECT event_id, announcement_signature, oracle_event
             FROM events
             WHERE event_id = $1
             "#,
@@ -238,16 +487,11 @@ impl Storage for PostgresStorage {
         .fetch_optional(&mut *tx)
         .await
         .map_err(|_| Error::StorageFailure)?
-        {
-            Some(e) => e,
-            None => return Err(Error::StorageFailure),
+        else {
+            return Err(Error::StorageFailure);
         };
 
-        let event_id: String = row.get("event_id");
-        let announcement_signature: Vec<u8> = row.get("announcement_signature");
-        let oracle_event: Vec<u8> = row.get("oracle_event");
-
-        let row = sqlx::query(
+        let nonces = sqlx::query_as::<Postgres, (i32, i32)>(
             r#"
             SELECT id, index
             FROM event_nonces
@@ -255,53 +499,85 @@ impl Storage for PostgresStorage {
             ORDER BY index
             "#,
         )
-        .bind(event_id.clone())
+        .bind(&event.event_id)
         .fetch_all(&mut *tx)
         .await
         .map_err(|_| Error::StorageFailure)?;
 
-        let nonces = row
-            .iter()
-            .map(|row| {
-                let id: i32 = row.get("id");
-                let index: i32 = row.get("index");
-                (id, index)
-            })
-            .collect::<Vec<_>>();
-
         if nonces.len() != signatures.len() {
             return Err(Error::StorageFailure);
         }
 
-        let mut indexes = Vec::with_capacity(signatures.len());
-        for ((id, index), (outcome, sig)) in nonces.iter().zip(signatures.iter()) {
-            sqlx::query(
-                r#"
-                UPDATE event_nonces
-                SET outcome = $1, signature = $2
-                WHERE id = $3
-                "#,
-            )
-            .bind(outcome)
-            .bind(sig.encode())
-            .bind(id)
-            .execute(&mut *tx)
-            .await
-            .map_err(|_| Error::StorageFailure)?;
+        sqlx::query(
+            r#"
+            UPDATE event_nonces
+            SET outcome = signed.outcome, signature = signed.signature
+            FROM UNNEST($1::INTEGER[], $2::TEXT[], $3::BYTEA[]) AS signed(id, outcome, signature)
+            WHERE event_nonces.id = signed.id
+            "#,
+        )
+        .bind(nonces.iter().map(|(id, _)| *id).collect::<Vec<_>>())
+        .bind(
+            signatures
+                .iter()
+                .map(|(outcome, _)| outcome.clone())
+                .collect::<Vec<_>>(),
+        )
+        .bind(
+            signatures
+                .iter()
+                .map(|(_, sig)| sig.encode())
+                .collect::<Vec<_>>(),
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| Error::StorageFailure)?;
+        let indexes = nonces
+            .iter()
+            .map(|(_, index)| *index as u32)
+            .collect::<Vec<_>>();
+
+        if let Some(record) = self.unstage_attestation(&event_id) {
+            record.insert(&mut tx).await.map_err(|e| {
+                log::error!(
+                    "Could not save attestation with the signatures. event_id={} error={}",
+                    event_id,
+                    e
+                );
+                Error::StorageFailure
+            })?;
+        }
 
-            indexes.push(*index as u32);
+        if let Some((outcome, source)) = self.unstage_signing(&event_id) {
+            audit::record_signing(&mut tx, &event_id, outcome, &indexes, source)
+                .await
+                .map_err(|e| {
+                    log::error!(
+                        "Could not record signing audit entry. event_id={} error={}",
+                        event_id,
+                        e
+                    );
+                    Error::StorageFailure
+                })?;
         }
 
-        let oracle_event = to_oracle_event(&oracle_event);
+        let announcement = event.announcement(self.oracle_public_key)?;
+        if let Some(signed) = signed_notification(&announcement, &signatures) {
+            outbox::enqueue(&mut tx, &event_id, &signed)
+                .await
+                .map_err(|e| {
+                    log::error!(
+                        "Could not queue the attestation. event_id={} error={}",
+                        event_id,
+                        e
+                    );
+                    Error::StorageFailure
+                })?;
+        }
 
         let data = OracleEventData {
-            event_id: event_id.clone(),
-            announcement: OracleAnnouncement {
-                announcement_signature: Signature::from_slice(&announcement_signature)
-                    .map_err(|_| Error::StorageFailure)?,
-                oracle_public_key: self.oracle_public_key,
-                oracle_event,
-            },
+            announcement,
+            event_id: event.event_id,
             indexes,
             signatures,
         };
@@ -313,7 +589,7 @@ impl Storage for PostgresStorage {
     async fn get_event(&self, event_id: String) -> Result<Option<OracleEventData>, Error> {
         let mut tx = self.pool.begin().await.map_err(|_| Error::StorageFailure)?;
 
-        let row = match sqlx::query(
+        let Some(event) = sqlx::query_as::<Postgres, EventRow>(
             r#"
             SELECT 
                 event_id, announcement_signature, oracle_event
@@ -327,16 +603,12 @@ impl Storage for PostgresStorage {
         .map_err(|e| {
             log::error!("Could not retrieve event. error={}", e.to_string());
             Error::StorageFailure
-        })? {
-            Some(e) => e,
-            None => return Ok(None),
+        })?
+        else {
+            return Ok(None);
         };
 
-        let event_id: String = row.get("event_id");
-        let announcement_signature: Vec<u8> = row.get("announcement_signature");
-        let oracle_event: Vec<u8> = row.get("oracle_event");
-
-        let row = sqlx::query(
+        let nonces = sqlx::query_as::<Postgres, NonceRow>(
             r#"
             SELECT index, outcome, signature
             FROM event_nonces
@@ -344,44 +616,15 @@ impl Storage for PostgresStorage {
             ORDER BY index
             "#,
         )
-        .bind(event_id.clone())
+        .bind(&event.event_id)
         .fetch_all(&mut *tx)
         .await
         .map_err(|_| Error::StorageFailure)?;
-
-        let nonces = row
-            .iter()
-            .map(|row| {
-                let index: i32 = row.get("index");
-                let outcome: Option<String> = row.get("outcome");
-                let signature: Option<Vec<u8>> = row.get("signature");
-                (index, outcome, signature)
-            })
-            .collect::<Vec<_>>();
-
-        let indexes = nonces.iter().map(|(index, _, _)| *index as u32).collect();
-
-        let signatures = nonces
-            .into_iter()
-            .filter_map(|(_, outcome, sig)| {
-                if let (Some(outcome), Some(sig)) = (outcome, sig) {
-                    Some((outcome, Signature::from_slice(&sig).ok()?))
-                } else {
-                    None
-                }
-            })
-            .collect();
-
-        let oracle_event = to_oracle_event(&oracle_event);
+        let (indexes, signatures) = NonceRow::split(nonces);
 
         let data = OracleEventData {
-            event_id: event_id.clone(),
-            announcement: OracleAnnouncement {
-                announcement_signature: Signature::from_slice(&announcement_signature)
-                    .map_err(|_| Error::StorageFailure)?,
-                oracle_public_key: self.oracle_public_key,
-                oracle_event,
-            },
+            announcement: event.announcement(self.oracle_public_key)?,
+            event_id: event.event_id,
             indexes,
             signatures,
         };
@@ -391,7 +634,160 @@ impl Storage for PostgresStorage {
     }
 }
 
-fn to_oracle_event(oracle_event: &Vec<u8>) -> OracleEvent {
-    let mut cursor = kormir::lightning::io::Cursor::new(&oracle_event);
-    OracleEvent::read(&mut cursor).expect("invalid oracle event")
+/// Store `oracle_public_key` on first use of the database and refuse any other key after, so
+/// the oracle never announces under a key the stored events were not announced under.
+pub async fn ensure_public_key(
+    pool: &PgPool,
+    oracle_public_key: &XOnlyPublicKey,
+) -> anyhow::Result<()> {
+    let stored =
+        sqlx::query_scalar::<Postgres, Vec<u8>>("SELECT oracle_public_key FROM oracle_metadata")
+            .fetch_optional(pool)
+            .await?;
+    let stored = match stored {
+        Some(stored) => stored,
+        // Read-only mirrors of an initialized database never get here
+        None => {
+            sqlx::query_scalar::<Postgres, Vec<u8>>(
+                r#"
+            INSERT INTO oracle_metadata (oracle_public_key) VALUES ($1)
+            ON CONFLICT (id) DO UPDATE SET id = oracle_metadata.id
+            RETURNING oracle_public_key
+            "#,
+            )
+            .bind(oracle_public_key.serialize().to_vec())
+            .fetch_one(pool)
+            .await?
+        }
+    };
+    if stored != oracle_public_key.serialize() {
+        return Err(anyhow::anyhow!(
+            "Database belongs to another oracle key. configured={} stored={}",
+            oracle_public_key,
+            hex::encode(stored)
+        ));
+    }
+    Ok(())
+}
+
+/// Insert the nonces of an event in one statement, with the attestation's outcomes and
+/// signatures if it is signed. Fails when a nonce is repeated or stored for another event, two
+/// signatures with one nonce reveal the oracle key.
+pub(crate) async fn insert_nonces(
+    conn: &mut PgConnection,
+    event_id: &str,
+    indexes: &[u32],
+    nonces: &[XOnlyPublicKey],
+    signatures: &[(String, Signature)],
+) -> anyhow::Result<()> {
+    let indexes = indexes.iter().map(|i| *i as i32).collect::<Vec<_>>();
+    let nonces = nonces
+        .iter()
+        .map(|nonce| nonce.serialize().to_vec())
+        .collect::<Vec<_>>();
+    if nonces.iter().collect::<HashSet<_>>().len() != nonces.len() {
+        return Err(anyhow::anyhow!(
+            "Event repeats a nonce, refusing to store it. event_id={}",
+            event_id
+        ));
+    }
+    let reused = sqlx::query_scalar::<Postgres, String>(
+        "SELECT event_id FROM event_nonces WHERE nonce = ANY($1) AND event_id <> $2 LIMIT 1",
+    )
+    .bind(&nonces)
+    .bind(event_id)
+    .fetch_optional(&mut *conn)
+    .await?;
+    if let Some(other_event_id) = reused {
+        return Err(anyhow::anyhow!(
+            "Nonce is already stored for another event, refusing to reuse it. event_id={} other_event_id={}",
+            event_id,
+            other_event_id
+        ));
+    }
+    let outcomes = (0..nonces.len())
+        .map(|i| signatures.get(i).map(|(outcome, _)| outcome.clone()))
+        .collect::<Vec<_>>();
+    let signatures = (0..nonces.len())
+        .map(|i| signatures.get(i).map(|(_, signature)| signature.encode()))
+        .collect::<Vec<_>>();
+    sqlx::query(
+        r#"
+        INSERT INTO event_nonces (id, event_id, index, nonce, outcome, signature)
+        SELECT index, $1, index, nonce, outcome, signature
+        FROM UNNEST($2::INTEGER[], $3::BYTEA[], $4::TEXT[], $5::BYTEA[])
+            AS nonces(index, nonce, outcome, signature)
+        "#,
+    )
+    .bind(event_id)
+    .bind(&indexes)
+    .bind(&nonces)
+    .bind(&outcomes)
+    .bind(&signatures)
+    .execute(&mut *conn)
+    .await?;
+    // Imported and recovered indexes may lie past the sequence, move it so they are not handed out again
+    sqlx::query(
+        r#"
+        SELECT setval('event_nonce_index_seq', MAX(index))
+        FROM UNNEST($1::INTEGER[]) AS nonces(index)
+        HAVING MAX(index) >= (SELECT last_value FROM event_nonce_index_seq)
+        "#,
+    )
+    .bind(&indexes)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// The message announcing the attestation of a numeric event, with the value its digits spell.
+fn signed_notification(
+    announcement: &OracleAnnouncement,
+    signatures: &[(String, Signature)],
+) -> Option<Notification> {
+    let EventDescriptor::DigitDecompositionEvent(descriptor) =
+        &announcement.oracle_event.event_descriptor
+    else {
+        return None;
+    };
+    let outcomes = signatures
+        .iter()
+        .map(|(outcome, _)| outcome.clone())
+        .collect::<Vec<_>>();
+    let outcome = digits::decode_digits(&outcomes, descriptor.base, descriptor.is_signed).ok()?;
+    Some(Notification::EventSigned {
+        event_id: announcement.oracle_event.event_id.clone(),
+        outcome,
+    })
+}
+
+/// Decode a stored oracle event, a storage failure if it does not parse.
+pub(crate) fn to_oracle_event(oracle_event: &[u8]) -> Result<OracleEvent, Error> {
+    let mut cursor = kormir::lightning::io::Cursor::new(oracle_event);
+    OracleEvent::read(&mut cursor).map_err(|e| {
+        log::error!("Could not read oracle event. error={:?}", e);
+        Error::StorageFailure
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mempool::{MempoolClient, BASE_URL};
+    use crate::test_util::setup_ernest_oracle;
+
+    #[tokio::test]
+    async fn processes_sharing_a_database_get_distinct_indexes() {
+        let mempool = MempoolClient::new(BASE_URL.to_string());
+        let oracle = setup_ernest_oracle(mempool).await;
+        let storage = &oracle.oracle.storage;
+        let other = PostgresStorage::new(storage.pool.clone(), storage.oracle_public_key, false)
+            .await
+            .unwrap();
+
+        let mut indexes = storage.get_next_nonce_indexes(5).await.unwrap();
+        indexes.extend(other.get_next_nonce_indexes(5).await.unwrap());
+        indexes.extend(storage.get_next_nonce_indexes(5).await.unwrap());
+        assert_eq!(indexes.iter().collect::<HashSet<_>>().len(), 15);
+    }
 }
@@ -1,7 +1,12 @@
-use crate::mempool::MempoolClient;
+use crate::events::EventType;
+use crate::mempool::{Aggregation, FeePercentile, MempoolClient};
 use crate::oracle::ErnestOracle;
+use crate::parlay::contract::SCORING_VERSION;
 use crate::parlay::parameter::ParlayParameter;
+use crate::routes::CreateEvent;
+use crate::smoothing::Smoothing;
 use crate::storage::PostgresStorage;
+use crate::units::unit_for;
 use bitcoin::key::{Keypair, Secp256k1};
 use bitcoin::secp256k1::SecretKey;
 use serde::{Deserialize, Serialize};
@@ -23,12 +28,18 @@ pub async fn setup_ernest_oracle(mempool: MempoolClient) -> ErnestOracle {
     let key_pair = Keypair::from_secret_key(&secp, &secret_key);
     let pubkey = key_pair.x_only_public_key();
 
-    let storage = PostgresStorage::new(pool.clone(), pubkey.0, true)
+    let storage = PostgresStorage::new(pool.clone(), pubkey.0, false)
         .await
         .expect("Failed to create PostgresStorage");
     ErnestOracle::new(storage, pool, key_pair, mempool).expect("Failed to create ErnestOracle")
 }
 
+/// A client of the mempool and esplora APIs `server` mocks.
+pub fn mock_mempool_client(server: &MockServer) -> MempoolClient {
+    MempoolClient::new(format!("{}/api/v1", server.uri()))
+        .with_esplora_url(format!("{}/api", server.uri()))
+}
+
 pub async fn setup_mock_server() -> MockServer {
     let mock_server = MockServer::start().await;
 
@@ -68,6 +79,19 @@ pub async fn setup_mock_server() -> MockServer {
         .mount(&mock_server)
         .await;
 
+    // Mock block rewards endpoint
+    Mock::given(method("GET"))
+        .and(path("/api/v1/mining/blocks/rewards/3m"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+            {
+                "avgHeight": 735644,
+                "timestamp": 1652119111,
+                "avgRewards": 649212890
+            }
+        ])))
+        .mount(&mock_server)
+        .await;
+
     // Mock fee rate endpoint
     Mock::given(method("GET"))
         .and(path("/api/v1/mining/blocks/fee-rates/3m"))
@@ -87,9 +111,92 @@ pub async fn setup_mock_server() -> MockServer {
         .mount(&mock_server)
         .await;
 
+    // Mock difficulty adjustment endpoint
+    Mock::given(method("GET"))
+        .and(path("/api/v1/difficulty-adjustment"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "progressPercent": 44.39,
+            "difficultyChange": -2.47,
+            "estimatedRetargetDate": 1627762478,
+            "remainingBlocks": 1121,
+            "remainingTime": 665977,
+            "previousRetarget": -4.8,
+            "previousTime": 1627000000,
+            "nextRetargetHeight": 741888,
+            "timeAvg": 302328,
+            "adjustedTimeAvg": 302328,
+            "timeOffset": 0,
+            "expectedBlocks": 1129.5
+        })))
+        .mount(&mock_server)
+        .await;
+
+    // Mock tip height endpoint
+    Mock::given(method("GET"))
+        .and(path("/api/blocks/tip/height"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("840001"))
+        .mount(&mock_server)
+        .await;
+
     mock_server
 }
 
+/// A single event with the options a test sets, the rest left to their default as in
+/// [`CreateEvent::single`].
+#[derive(Debug, Clone)]
+pub struct SingleEvent {
+    pub event_type: EventType,
+    pub maturity: u32,
+    pub percentile: Option<FeePercentile>,
+    pub aggregation: Option<Aggregation>,
+    pub smoothing: Option<Smoothing>,
+    pub precision: Option<i32>,
+    pub nb_digits: Option<u16>,
+    pub description: Option<String>,
+    pub tags: Vec<String>,
+    pub announce_at: Option<u32>,
+    pub settlement_delay: Option<u32>,
+    pub event_id: Option<String>,
+}
+
+impl SingleEvent {
+    pub fn new(event_type: EventType, maturity: u32) -> Self {
+        Self {
+            event_type,
+            maturity,
+            percentile: None,
+            aggregation: None,
+            smoothing: None,
+            precision: None,
+            nb_digits: None,
+            description: None,
+            tags: vec![],
+            announce_at: None,
+            settlement_delay: None,
+            event_id: None,
+        }
+    }
+}
+
+impl From<SingleEvent> for CreateEvent {
+    fn from(event: SingleEvent) -> Self {
+        CreateEvent::Single {
+            event_type: event.event_type,
+            maturity: event.maturity,
+            percentile: event.percentile,
+            aggregation: event.aggregation,
+            smoothing: event.smoothing,
+            precision: event.precision,
+            nb_digits: event.nb_digits,
+            description: event.description,
+            tags: event.tags,
+            announce_at: event.announce_at,
+            settlement_delay: event.settlement_delay,
+            event_id: event.event_id,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TestVectors {
     pub test_vectors: Vec<TestVector>,
@@ -99,8 +206,37 @@ pub struct TestVectors {
 pub struct TestVector {
     pub name: String,
     pub contract: Contract,
-    pub mock_inputs: HashMap<String, i64>,
+    /// Leg outcomes in the unit of their data type, keyed by [`mock_input_key`].
+    pub mock_inputs: HashMap<String, f64>,
     pub expected: Expected,
+    /// Scoring versions the vector holds for, every supported version when empty.
+    #[serde(default)]
+    pub scoring_versions: Vec<u32>,
+}
+
+impl TestVector {
+    pub fn scoring_versions(&self) -> Vec<u32> {
+        if self.scoring_versions.is_empty() {
+            (1..=SCORING_VERSION).collect()
+        } else {
+            self.scoring_versions.clone()
+        }
+    }
+
+    /// Outcome of every leg of the contract, in order.
+    pub fn outcomes(&self) -> Vec<f64> {
+        self.contract
+            .parameters
+            .iter()
+            .map(|p| self.mock_inputs[mock_input_key(&p.data_type)])
+            .collect()
+    }
+
+    /// How close intermediate values have to be to the expected ones, the resolution the
+    /// contract attests at.
+    pub fn tolerance(&self) -> f64 {
+        1.0 / self.contract.max_normalized_value as f64
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -119,6 +255,21 @@ pub struct Expected {
     pub attestation_value: u64,
 }
 
+/// Key of a data type in a test vector's `mock_inputs`.
+pub fn mock_input_key(data_type: &EventType) -> &'static str {
+    match data_type {
+        EventType::Hashrate => "hashrate",
+        EventType::FeeRate => "fee-rate",
+        EventType::BlockFees => "block-fees",
+        EventType::Difficulty => "difficulty",
+        EventType::NextDifficultyChange => "next-difficulty-change",
+        EventType::BlocksUntilHalving => "blocks-until-halving",
+        EventType::DifficultyChangePercent => "difficulty-change-percent",
+        EventType::AvgBlockInterval => "avg-block-interval",
+        EventType::CoinbaseValue => "coinbase-value",
+    }
+}
+
 pub async fn setup_mock_server_from_test_vectors(test_vector: TestVector) -> MockServer {
     // Read the test vectors file
     let mock_server = MockServer::start().await;
@@ -129,6 +280,7 @@ pub async fn setup_mock_server_from_test_vectors(test_vector: TestVector) -> Moc
     for (data_type, value) in &test_vector.mock_inputs {
         match data_type.as_str() {
             "hashrate" => {
+                let value = value * unit_for(&EventType::Hashrate).divisor();
                 Mock::given(method("GET"))
                     .and(path("/api/v1/mining/hashrate/3m"))
                     .respond_with(ResponseTemplate::new(200).set_body_json(json!({
@@ -152,13 +304,14 @@ pub async fn setup_mock_server_from_test_vectors(test_vector: TestVector) -> Moc
                     .await;
             }
             "block-fees" => {
+                let value = value * unit_for(&EventType::BlockFees).divisor();
                 Mock::given(method("GET"))
                     .and(path("/api/v1/mining/blocks/fees/3m"))
                     .respond_with(ResponseTemplate::new(200).set_body_json(json!([
                         {
                             "avgHeight": 735644,
                             "timestamp": 1652119111,
-                            "avgFees": value
+                            "avgFees": value as i64
                         }
                     ])))
                     .mount(&mock_server)
@@ -0,0 +1,87 @@
+//! Postgres NOTIFY triggers for the watcher.
+//!
+//! Creating an event or requesting a signing sends a notification the watcher LISTENs for, so
+//! events created by another process (e.g. the admin CLI) are scheduled right away and signing
+//! requests are served without waiting for the next tick. Every signing still goes through the
+//! watcher, notifications only wake it.
+
+use sqlx::postgres::{PgListener, PgNotification};
+use sqlx::PgPool;
+
+pub const EVENT_CREATED_CHANNEL: &str = "ernest_oracle_event_created";
+pub const SIGN_REQUESTED_CHANNEL: &str = "ernest_oracle_sign_requested";
+
+/// A notification the watcher acts on, carrying the event id.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Trigger {
+    EventCreated(String),
+    SignRequested(String),
+}
+
+impl Trigger {
+    pub fn from_notification(notification: &PgNotification) -> Option<Self> {
+        let event_id = notification.payload().to_string();
+        match notification.channel() {
+            EVENT_CREATED_CHANNEL => Some(Trigger::EventCreated(event_id)),
+            SIGN_REQUESTED_CHANNEL => Some(Trigger::SignRequested(event_id)),
+            _ => None,
+        }
+    }
+}
+
+async fn notify(pool: &PgPool, channel: &str, event_id: &str) -> anyhow::Result<()> {
+    sqlx::query("SELECT pg_notify($1, $2)")
+        .bind(channel)
+        .bind(event_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+pub async fn notify_event_created(pool: &PgPool, event_id: &str) -> anyhow::Result<()> {
+    notify(pool, EVENT_CREATED_CHANNEL, event_id).await
+}
+
+/// Ask the watcher to sign `event_id` now, it is signed once it has matured.
+pub async fn request_signing(pool: &PgPool, event_id: &str) -> anyhow::Result<()> {
+    notify(pool, SIGN_REQUESTED_CHANNEL, event_id).await
+}
+
+pub async fn listen(pool: &PgPool) -> anyhow::Result<PgListener> {
+    let mut listener = PgListener::connect_with(pool).await?;
+    listener
+        .listen_all([EVENT_CREATED_CHANNEL, SIGN_REQUESTED_CHANNEL])
+        .await?;
+    Ok(listener)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn notifications_reach_listeners() {
+        let pool =
+            PgPool::connect(&std::env::var("DATABASE_URL").expect("$DATABASE_URL is not set"))
+                .await
+                .unwrap();
+        let mut listener = listen(&pool).await.unwrap();
+        let event_id = uuid::Uuid::new_v4().to_string();
+
+        request_signing(&pool, &event_id).await.unwrap();
+        // Other tests may create events concurrently
+        let received = tokio::time::timeout(std::time::Duration::from_secs(10), async {
+            loop {
+                let notification = listener.recv().await.unwrap();
+                if let Some(trigger @ Trigger::SignRequested(_)) =
+                    Trigger::from_notification(&notification)
+                {
+                    return trigger;
+                }
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(received, Trigger::SignRequested(event_id));
+    }
+}
@@ -0,0 +1,112 @@
+//! Units every event type is reported and attested in.
+//!
+//! Upstream APIs return raw values (hashes per second, raw difficulty) which are scaled into
+//! the units below before anything else sees them. Parlay thresholds and ranges are expressed
+//! in the same units, so a hashrate threshold of `700` means 700 EH/s.
+
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumIter, EnumString};
+
+use crate::events::EventType;
+
+#[derive(
+    Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq, EnumIter, Display, EnumString,
+)]
+#[serde(rename_all = "camelCase")]
+#[strum(serialize_all = "camelCase")]
+pub enum Unit {
+    /// Exahashes per second, raw values are hashes per second.
+    ExahashPerSecond,
+    /// Difficulty in trillions.
+    Tera,
+    Sats,
+    SatsPerVbyte,
+    Percent,
+    Blocks,
+    Seconds,
+}
+
+impl Unit {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Unit::ExahashPerSecond => "EH/s",
+            Unit::Tera => "T",
+            Unit::Sats => "sats",
+            Unit::SatsPerVbyte => "sat/vB",
+            Unit::Percent => "%",
+            Unit::Blocks => "blocks",
+            Unit::Seconds => "s",
+        }
+    }
+
+    /// Number of raw upstream units in one of this unit.
+    pub fn divisor(&self) -> f64 {
+        match self {
+            Unit::ExahashPerSecond => 1e18,
+            Unit::Tera => 1e12,
+            Unit::Sats | Unit::SatsPerVbyte | Unit::Percent | Unit::Blocks | Unit::Seconds => 1.0,
+        }
+    }
+
+    pub fn from_raw(&self, raw: f64) -> f64 {
+        raw / self.divisor()
+    }
+}
+
+/// The unit table, one entry per event type.
+pub fn unit_for(event_type: &EventType) -> Unit {
+    match event_type {
+        EventType::Hashrate => Unit::ExahashPerSecond,
+        EventType::FeeRate => Unit::SatsPerVbyte,
+        EventType::BlockFees => Unit::Sats,
+        EventType::Difficulty => Unit::Tera,
+        EventType::NextDifficultyChange => Unit::Percent,
+        EventType::BlocksUntilHalving => Unit::Blocks,
+        EventType::DifficultyChangePercent => Unit::Percent,
+        EventType::AvgBlockInterval => Unit::Seconds,
+        EventType::CoinbaseValue => Unit::Sats,
+    }
+}
+
+/// Unit string written into announcements, e.g. `hashrate (EH/s)`.
+pub fn announcement_unit(event_type: &EventType) -> String {
+    format!("{} ({})", event_type, unit_for(event_type).symbol())
+}
+
+/// Event type of an announcement unit string. Events announced before units were recorded
+/// only carry the event type.
+pub fn event_type_from_unit(unit: &str) -> anyhow::Result<EventType> {
+    let event_type = unit
+        .split_once(" (")
+        .map_or(unit, |(event_type, _)| event_type);
+    Ok(EventType::from_str(event_type)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use strum::IntoEnumIterator;
+
+    #[test]
+    fn announcement_units_roundtrip() {
+        for event_type in EventType::iter() {
+            let unit = announcement_unit(&event_type);
+            assert_eq!(event_type_from_unit(&unit).unwrap(), event_type);
+        }
+        assert_eq!(announcement_unit(&EventType::FeeRate), "feeRate (sat/vB)");
+        assert_eq!(
+            event_type_from_unit("hashrate").unwrap(),
+            EventType::Hashrate
+        );
+        assert!(event_type_from_unit("parlay").is_err());
+    }
+
+    #[test]
+    fn scales_raw_values() {
+        assert_eq!(Unit::ExahashPerSecond.from_raw(7e20), 700.0);
+        assert_eq!(Unit::Tera.from_raw(8.5e13), 85.0);
+        assert_eq!(Unit::SatsPerVbyte.from_raw(12.0), 12.0);
+    }
+}
@@ -0,0 +1,256 @@
+//! Daily usage per API key and the quotas it is counted against.
+//!
+//! Keys are identified as in [`crate::limits`], hashed and with one shared allowance for
+//! requests without a key. Usage is counted per UTC day and a quota is consumed before the work
+//! it covers is done, so concurrent requests cannot overshoot it.
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{prelude::FromRow, PgPool, Postgres};
+use strum_macros::Display;
+
+use crate::limits::hash_api_key;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Quotas {
+    pub events_per_day: Option<i64>,
+    /// Signings requested through `/api/sign-event` and `/api/sign-now`
+    pub signings_per_day: Option<i64>,
+    pub requests_per_day: Option<i64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+#[strum(serialize_all = "snake_case")]
+pub enum UsageKind {
+    EventsCreated,
+    SigningsRequested,
+    Requests,
+}
+
+impl Quotas {
+    pub fn quota(&self, kind: UsageKind) -> Option<i64> {
+        match kind {
+            UsageKind::EventsCreated => self.events_per_day,
+            UsageKind::SigningsRequested => self.signings_per_day,
+            UsageKind::Requests => self.requests_per_day,
+        }
+    }
+}
+
+/// A request over the daily quota of its key.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuotaExceeded {
+    pub kind: UsageKind,
+    pub quota: i64,
+    pub resets_at: DateTime<Utc>,
+}
+
+impl std::fmt::Display for QuotaExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Daily quota exceeded. usage={} quota={} resets_at={}",
+            self.kind,
+            self.quota,
+            self.resets_at.to_rfc3339()
+        )
+    }
+}
+
+impl std::error::Error for QuotaExceeded {}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct DailyUsage {
+    pub events_created: i64,
+    pub signings_requested: i64,
+    pub requests: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Usage {
+    pub day: NaiveDate,
+    #[serde(flatten)]
+    pub usage: DailyUsage,
+    pub quotas: Quotas,
+    pub resets_at: DateTime<Utc>,
+}
+
+fn today() -> NaiveDate {
+    Utc::now().date_naive()
+}
+
+fn resets_at(day: NaiveDate) -> DateTime<Utc> {
+    day.succ_opt()
+        .unwrap_or(day)
+        .and_hms_opt(0, 0, 0)
+        .expect("midnight is a valid time")
+        .and_utc()
+}
+
+/// Count one use of `kind` against the quota of `api_key`, failing with [`QuotaExceeded`] when
+/// it is used up.
+pub async fn consume(
+    pool: &PgPool,
+    quotas: &Quotas,
+    api_key: Option<&str>,
+    kind: UsageKind,
+) -> anyhow::Result<()> {
+    consume_many(pool, quotas, api_key, kind, 1).await
+}
+
+/// Count `uses` of `kind` at once, none are counted when they do not all fit the quota.
+pub async fn consume_many(
+    pool: &PgPool,
+    quotas: &Quotas,
+    api_key: Option<&str>,
+    kind: UsageKind,
+    uses: i64,
+) -> anyhow::Result<()> {
+    let day = today();
+    let quota = quotas.quota(kind);
+    let exceeded = QuotaExceeded {
+        kind,
+        quota: quota.unwrap_or_default(),
+        resets_at: resets_at(day),
+    };
+    if quota.is_some_and(|quota| quota < uses) {
+        return Err(exceeded.into());
+    }
+    // The update is skipped when the uses do not fit the quota, which returns no row
+    let consumed = sqlx::query_scalar::<Postgres, i64>(&format!(
+        r#"
+        INSERT INTO api_key_usage (api_key, day, {kind}) VALUES ($1, $2, $4)
+        ON CONFLICT (api_key, day) DO UPDATE SET {kind} = api_key_usage.{kind} + $4
+        WHERE $3::BIGINT IS NULL OR api_key_usage.{kind} + $4 <= $3
+        RETURNING {kind}
+        "#,
+    ))
+    .bind(hash_api_key(api_key))
+    .bind(day)
+    .bind(quota)
+    .bind(uses)
+    .fetch_optional(pool)
+    .await?;
+    match consumed {
+        Some(_) => Ok(()),
+        None => Err(exceeded.into()),
+    }
+}
+
+/// Give back a use of `kind` consumed for work that failed.
+pub async fn refund(pool: &PgPool, api_key: Option<&str>, kind: UsageKind) -> anyhow::Result<()> {
+    refund_many(pool, api_key, kind, 1).await
+}
+
+/// Give back `uses` of `kind` consumed at once with [`consume_many`].
+pub async fn refund_many(
+    pool: &PgPool,
+    api_key: Option<&str>,
+    kind: UsageKind,
+    uses: i64,
+) -> anyhow::Result<()> {
+    sqlx::query(&format!(
+        "UPDATE api_key_usage SET {kind} = GREATEST({kind} - $3, 0) WHERE api_key = $1 AND day = $2",
+    ))
+    .bind(hash_api_key(api_key))
+    .bind(today())
+    .bind(uses)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Today's usage of `api_key` and its quotas.
+pub async fn usage(pool: &PgPool, quotas: &Quotas, api_key: Option<&str>) -> anyhow::Result<Usage> {
+    let day = today();
+    let usage = sqlx::query_as::<Postgres, DailyUsage>(
+        r#"
+        SELECT events_created, signings_requested, requests FROM api_key_usage
+        WHERE api_key = $1 AND day = $2
+        "#,
+    )
+    .bind(hash_api_key(api_key))
+    .bind(day)
+    .fetch_optional(pool)
+    .await?
+    .unwrap_or(DailyUsage {
+        events_created: 0,
+        signings_requested: 0,
+        requests: 0,
+    });
+    Ok(Usage {
+        day,
+        usage,
+        quotas: quotas.clone(),
+        resets_at: resets_at(day),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mempool::{MempoolClient, BASE_URL};
+    use crate::test_util::setup_ernest_oracle;
+
+    #[tokio::test]
+    async fn enforces_daily_quotas() {
+        let mempool = MempoolClient::new(BASE_URL.to_string());
+        let oracle = setup_ernest_oracle(mempool).await;
+        let pool = &oracle.oracle.storage.pool;
+        let api_key = uuid::Uuid::new_v4().to_string();
+        let quotas = Quotas {
+            events_per_day: Some(0),
+            signings_per_day: Some(2),
+            requests_per_day: None,
+        };
+        let consume = |kind| consume(pool, &quotas, Some(&api_key), kind);
+
+        consume(UsageKind::SigningsRequested).await.unwrap();
+        consume(UsageKind::SigningsRequested).await.unwrap();
+        let error = consume(UsageKind::SigningsRequested).await.unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<QuotaExceeded>().unwrap().kind,
+            UsageKind::SigningsRequested
+        );
+        assert!(consume(UsageKind::EventsCreated).await.is_err());
+        for _ in 0..3 {
+            consume(UsageKind::Requests).await.unwrap();
+        }
+        let quotas = Quotas {
+            events_per_day: Some(3),
+            ..quotas.clone()
+        };
+        let consume_many = |uses| {
+            consume_many(
+                pool,
+                &quotas,
+                Some(&api_key),
+                UsageKind::EventsCreated,
+                uses,
+            )
+        };
+        consume_many(2).await.unwrap();
+        assert!(consume_many(2).await.is_err());
+        assert!(consume_many(4).await.is_err());
+        refund_many(pool, Some(&api_key), UsageKind::EventsCreated, 2)
+            .await
+            .unwrap();
+
+        refund(pool, Some(&api_key), UsageKind::SigningsRequested)
+            .await
+            .unwrap();
+        let usage = usage(pool, &quotas, Some(&api_key)).await.unwrap();
+        assert_eq!(
+            usage.usage,
+            DailyUsage {
+                events_created: 0,
+                signings_requested: 1,
+                requests: 3,
+            }
+        );
+        assert!(usage.resets_at > Utc::now());
+    }
+}
@@ -0,0 +1,270 @@
+//! Field level checks of create requests.
+//!
+//! Every invalid field is reported, named by its JSON path (e.g. `parameters[1].weight`), so
+//! frontends can show the errors next to the form fields they belong to.
+
+use serde::{Deserialize, Serialize};
+
+use crate::event_ids;
+use crate::events::{EventParams, MAX_NB_DIGITS, MAX_PRECISION, MIN_NB_DIGITS, MIN_PRECISION};
+use crate::routes::CreateEvent;
+use crate::smoothing::MAX_WINDOW;
+
+/// Furthest an event may mature in the future.
+pub const MAX_MATURITY_HORIZON_SECS: u32 = 10 * 365 * 24 * 60 * 60;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+/// A request with invalid fields.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationErrors(pub Vec<FieldError>);
+
+impl std::fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let fields = self
+            .0
+            .iter()
+            .map(|error| format!("{}: {}", error.field, error.message))
+            .collect::<Vec<_>>();
+        write!(f, "Invalid request. {}", fields.join(", "))
+    }
+}
+
+impl std::error::Error for ValidationErrors {}
+
+#[derive(Default)]
+struct Errors(Vec<FieldError>);
+
+impl Errors {
+    fn add(&mut self, field: impl Into<String>, message: impl Into<String>) {
+        self.0.push(FieldError {
+            field: field.into(),
+            message: message.into(),
+        });
+    }
+}
+
+fn check_maturity(errors: &mut Errors, field: &str, maturity: u32, now: u32) {
+    if maturity <= now {
+        errors.add(field, "must be in the future");
+    } else if maturity - now > MAX_MATURITY_HORIZON_SECS {
+        errors.add(
+            field,
+            format!(
+                "must be at most {} seconds in the future",
+                MAX_MATURITY_HORIZON_SECS
+            ),
+        );
+    }
+}
+
+/// Check `event` as created at `now`.
+pub fn validate_create_event(event: &CreateEvent, now: u32) -> Result<(), ValidationErrors> {
+    let mut errors = Errors::default();
+    if let Some(event_id) = event.event_id() {
+        if let Err(e) = event_ids::validate(event_id) {
+            errors.add("eventId", e.to_string());
+        }
+    }
+    match event {
+        CreateEvent::Single {
+            maturity,
+            percentile,
+            aggregation,
+            smoothing,
+            precision,
+            nb_digits,
+            ..
+        } => {
+            check_maturity(&mut errors, "maturity", *maturity, now);
+            if let Some(smoothing) = smoothing {
+                if !(2..=MAX_WINDOW).contains(&smoothing.window()) {
+                    errors.add(
+                        "smoothing.window",
+                        format!("must be between 2 and {}", MAX_WINDOW),
+                    );
+                }
+                // Snapshots are only taken with the default options
+                if percentile.is_some() || aggregation.is_some() {
+                    errors.add(
+                        "smoothing",
+                        "is not supported with a percentile or aggregation",
+                    );
+                }
+            }
+            if nb_digits.is_some_and(|n| !(MIN_NB_DIGITS..=MAX_NB_DIGITS).contains(&n)) {
+                errors.add(
+                    "nbDigits",
+                    format!("must be between {} and {}", MIN_NB_DIGITS, MAX_NB_DIGITS),
+                );
+            }
+            if precision.is_some_and(|p| !(MIN_PRECISION..=MAX_PRECISION).contains(&p)) {
+                errors.add(
+                    "precision",
+                    format!("must be between {} and {}", MIN_PRECISION, MAX_PRECISION),
+                );
+            }
+        }
+        CreateEvent::Parlay {
+            parameters,
+            max_normalized_value,
+            event_maturity_epoch,
+            ..
+        } => {
+            check_maturity(
+                &mut errors,
+                "eventMaturityEpoch",
+                *event_maturity_epoch,
+                now,
+            );
+            if parameters.is_empty() {
+                errors.add("parameters", "must have at least one leg");
+            }
+            if *max_normalized_value == Some(0) {
+                errors.add("maxNormalizedValue", "must be positive");
+            }
+            for (i, parameter) in parameters.iter().enumerate() {
+                let field = |name: &str| format!("parameters[{}].{}", i, name);
+                if !parameter.weight.is_finite() || parameter.weight <= 0.0 {
+                    errors.add(field("weight"), "must be a positive number");
+                }
+                if !parameter.threshold.is_finite() {
+                    errors.add(field("threshold"), "must be a finite number");
+                }
+                if !parameter.range.is_finite() || parameter.range <= 0.0 {
+                    errors.add(field("range"), "must be a positive number");
+                    continue;
+                }
+                if !parameter.threshold.is_finite() {
+                    continue;
+                }
+                if !(parameter.threshold + parameter.range).is_finite() {
+                    // e.g. both near f64::MAX
+                    errors.add(
+                        field("range"),
+                        "threshold plus range must be a finite number",
+                    );
+                    continue;
+                }
+                // Other oracles' legs are in their own unit and digits
+                if parameter.external.is_some() {
+                    continue;
+                }
+                // A strike outside of what an event of the data type can attest is a typo
+                let params = EventParams::from(parameter.data_type.clone());
+                let max_value = params.value(params.max_outcome());
+                let min_value = if params.is_signed { -max_value } else { 0.0 };
+                if !(min_value..=max_value).contains(&parameter.threshold) {
+                    errors.add(
+                        field("threshold"),
+                        format!(
+                            "must be between {} and {}, the {} values the oracle can attest",
+                            min_value, max_value, parameter.data_type
+                        ),
+                    );
+                }
+            }
+        }
+    }
+    match errors.0.is_empty() {
+        true => Ok(()),
+        false => Err(ValidationErrors(errors.0)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        events::EventType,
+        mempool::Aggregation,
+        parlay::{
+            contract::{CombinationMethod, ScoreMode},
+            parameter::{ParlayParameter, TransformationFunction},
+        },
+        smoothing::Smoothing,
+        test_util::SingleEvent,
+    };
+
+    const NOW: u32 = 1_750_000_000;
+
+    fn leg(threshold: f64, range: f64, weight: f64) -> ParlayParameter {
+        ParlayParameter {
+            data_type: EventType::Hashrate,
+            threshold,
+            range,
+            is_above_threshold: true,
+            transformation: TransformationFunction::Linear,
+            weight,
+            percentile: None,
+            aggregation: None,
+            period: None,
+            event_id: None,
+            external: None,
+        }
+    }
+
+    fn parlay(parameters: Vec<ParlayParameter>, event_maturity_epoch: u32) -> CreateEvent {
+        CreateEvent::Parlay {
+            parameters,
+            combination_method: CombinationMethod::Multiply,
+            max_normalized_value: None,
+            event_maturity_epoch,
+            score_mode: ScoreMode::default(),
+            description: None,
+            tags: vec![],
+            announce_at: None,
+            settlement_delay: None,
+            event_id: None,
+        }
+    }
+
+    fn fields(event: &CreateEvent) -> Vec<String> {
+        match validate_create_event(event, NOW) {
+            Ok(()) => vec![],
+            Err(errors) => errors.0.into_iter().map(|error| error.field).collect(),
+        }
+    }
+
+    #[test]
+    fn reports_every_invalid_field() {
+        assert!(fields(&parlay(vec![leg(700.0, 100.0, 1.0)], NOW + 60)).is_empty());
+        assert_eq!(
+            fields(&parlay(
+                vec![
+                    leg(700.0, 100.0, f64::NAN),
+                    leg(700.0, -1.0, 1.0),
+                    leg(20_000.0, 1000.0, 1.0),
+                ],
+                NOW,
+            )),
+            [
+                "eventMaturityEpoch",
+                "parameters[0].weight",
+                "parameters[1].range",
+                "parameters[2].threshold",
+            ]
+        );
+        assert_eq!(
+            fields(&parlay(vec![], NOW + MAX_MATURITY_HORIZON_SECS + 1)),
+            ["eventMaturityEpoch", "parameters"]
+        );
+
+        let smoothed = |window, aggregation| {
+            CreateEvent::from(SingleEvent {
+                aggregation,
+                smoothing: Some(Smoothing::MovingAverage { window }),
+                ..SingleEvent::new(EventType::Hashrate, NOW + 60)
+            })
+        };
+        assert!(fields(&smoothed(7, None)).is_empty());
+        assert_eq!(
+            fields(&smoothed(1, Some(Aggregation::Max))),
+            ["smoothing.window", "smoothing"]
+        );
+    }
+}
@@ -0,0 +1,222 @@
+//! Verifying attestations before settling on them.
+//!
+//! A wallet holds the announcement a contract was priced on and receives an attestation from the
+//! oracle, or from anyone relaying it. [`verify_attestation`] checks the attestation against that
+//! announcement alone, digit by digit, and only then decodes the value the digits spell.
+
+use bitcoin::{
+    hashes::{sha256, Hash},
+    key::Secp256k1,
+    secp256k1::Message,
+    XOnlyPublicKey,
+};
+use dlc_messages::oracle_msgs::{EventDescriptor, OracleAnnouncement, OracleAttestation};
+
+use crate::digits::decode_digits;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifyError {
+    /// The announcement is not signed by the key it announces
+    InvalidAnnouncement,
+    /// The announcement is for an enum event
+    NotNumeric,
+    EventMismatch {
+        announced: String,
+        attested: String,
+    },
+    /// The attestation is signed by `attested`, not the announcing oracle
+    OracleMismatch {
+        attested: XOnlyPublicKey,
+    },
+    /// Outcomes, signatures or nonces do not match the announced digits, sign included
+    DigitCount {
+        expected: usize,
+        outcomes: usize,
+        signatures: usize,
+    },
+    /// The signature of the digit at `index` does not use the announced nonce
+    NonceMismatch {
+        index: usize,
+    },
+    /// The signature of the digit at `index` does not sign its outcome
+    InvalidSignature {
+        index: usize,
+    },
+    /// The outcomes are not digits of the announced base
+    InvalidOutcome(String),
+}
+
+impl std::fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VerifyError::InvalidAnnouncement => write!(f, "Invalid announcement signature"),
+            VerifyError::NotNumeric => write!(f, "Announcement is not a numeric event"),
+            VerifyError::EventMismatch {
+                announced,
+                attested,
+            } => write!(
+                f,
+                "Attestation is for another event. announced={} attested={}",
+                announced, attested
+            ),
+            VerifyError::OracleMismatch { attested } => write!(
+                f,
+                "Attestation is from another oracle. attested={}",
+                attested
+            ),
+            VerifyError::DigitCount {
+                expected,
+                outcomes,
+                signatures,
+            } => write!(
+                f,
+                "Attestation does not have the announced digits. expected={} outcomes={} signatures={}",
+                expected, outcomes, signatures
+            ),
+            VerifyError::NonceMismatch { index } => write!(
+                f,
+                "Attestation signature does not use the announced nonce. index={}",
+                index
+            ),
+            VerifyError::InvalidSignature { index } => {
+                write!(f, "Invalid attestation signature. index={}", index)
+            }
+            VerifyError::InvalidOutcome(e) => write!(f, "Invalid attestation outcome. error={}", e),
+        }
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+/// Verify `attestation` against `announcement` and return the attested value, the number the
+/// signed digits spell before scaling by the announced precision.
+pub fn verify_attestation(
+    announcement: &OracleAnnouncement,
+    attestation: &OracleAttestation,
+) -> Result<i64, VerifyError> {
+    let secp = Secp256k1::verification_only();
+    announcement
+        .validate(&secp)
+        .map_err(|_| VerifyError::InvalidAnnouncement)?;
+    let EventDescriptor::DigitDecompositionEvent(descriptor) =
+        &announcement.oracle_event.event_descriptor
+    else {
+        return Err(VerifyError::NotNumeric);
+    };
+    if attestation.event_id != announcement.oracle_event.event_id {
+        return Err(VerifyError::EventMismatch {
+            announced: announcement.oracle_event.event_id.clone(),
+            attested: attestation.event_id.clone(),
+        });
+    }
+    if attestation.oracle_public_key != announcement.oracle_public_key {
+        return Err(VerifyError::OracleMismatch {
+            attested: attestation.oracle_public_key,
+        });
+    }
+
+    // Announcement validation checked the nonces against the descriptor
+    let nonces = &announcement.oracle_event.oracle_nonces;
+    if attestation.outcomes.len() != nonces.len() || attestation.signatures.len() != nonces.len() {
+        return Err(VerifyError::DigitCount {
+            expected: nonces.len(),
+            outcomes: attestation.outcomes.len(),
+            signatures: attestation.signatures.len(),
+        });
+    }
+    for (index, ((signature, outcome), nonce)) in attestation
+        .signatures
+        .iter()
+        .zip(&attestation.outcomes)
+        .zip(nonces)
+        .enumerate()
+    {
+        if signature.serialize()[..32] != nonce.serialize() {
+            return Err(VerifyError::NonceMismatch { index });
+        }
+        let message = Message::from_digest(sha256::Hash::hash(outcome.as_bytes()).to_byte_array());
+        secp.verify_schnorr(signature, &message, &announcement.oracle_public_key)
+            .map_err(|_| VerifyError::InvalidSignature { index })?;
+    }
+
+    decode_digits(&attestation.outcomes, descriptor.base, descriptor.is_signed)
+        .map_err(|e| VerifyError::InvalidOutcome(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::secp256k1::SecretKey;
+    use kormir::storage::MemoryStorage;
+
+    async fn test_oracle(key: u8) -> kormir::Oracle<MemoryStorage> {
+        let secret_key = SecretKey::from_slice(&[key; 32]).unwrap();
+        kormir::Oracle::from_signing_key(MemoryStorage::default(), secret_key).unwrap()
+    }
+
+    #[tokio::test]
+    async fn verifies_numeric_attestations() {
+        let oracle = test_oracle(3).await;
+        let announcement = oracle
+            .create_numeric_event("hashrate".to_string(), 20, false, 0, "eh/s".to_string(), 0)
+            .await
+            .unwrap();
+        let attestation = oracle
+            .sign_numeric_event("hashrate".to_string(), 725)
+            .await
+            .unwrap();
+        assert_eq!(verify_attestation(&announcement, &attestation), Ok(725));
+
+        let mut tampered = attestation.clone();
+        tampered.outcomes[19] = "0".to_string();
+        assert_eq!(
+            verify_attestation(&announcement, &tampered),
+            Err(VerifyError::InvalidSignature { index: 19 })
+        );
+
+        let mut swapped = attestation.clone();
+        swapped.signatures.swap(0, 1);
+        swapped.outcomes.swap(0, 1);
+        assert_eq!(
+            verify_attestation(&announcement, &swapped),
+            Err(VerifyError::NonceMismatch { index: 0 })
+        );
+
+        let mut truncated = attestation.clone();
+        truncated.outcomes.pop();
+        truncated.signatures.pop();
+        assert!(matches!(
+            verify_attestation(&announcement, &truncated),
+            Err(VerifyError::DigitCount { expected: 20, .. })
+        ));
+
+        // Another oracle signing an event of the same id
+        let other = test_oracle(4).await;
+        other
+            .create_numeric_event("hashrate".to_string(), 20, false, 0, "eh/s".to_string(), 0)
+            .await
+            .unwrap();
+        let forged = other
+            .sign_numeric_event("hashrate".to_string(), 725)
+            .await
+            .unwrap();
+        assert!(matches!(
+            verify_attestation(&announcement, &forged),
+            Err(VerifyError::OracleMismatch { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn verifies_signed_events() {
+        let oracle = test_oracle(3).await;
+        let announcement = oracle
+            .create_numeric_event("change".to_string(), 8, true, 0, "%".to_string(), 0)
+            .await
+            .unwrap();
+        let attestation = oracle
+            .sign_numeric_event("change".to_string(), -12)
+            .await
+            .unwrap();
+        assert_eq!(verify_attestation(&announcement, &attestation), Ok(-12));
+    }
+}
@@ -0,0 +1,114 @@
+//! Volatility of the sampled metric history.
+//!
+//! Returns are taken between consecutive samples, so with the hourly sampler the volatility is
+//! an hourly one. Values that are not strictly positive (e.g. signed difficulty changes) use
+//! absolute differences instead of log returns.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::history::MetricPoint;
+
+/// Samples per rolling window when none is requested, one day of hourly samples.
+pub const DEFAULT_VOLATILITY_WINDOW: usize = 24;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct VolatilityPoint {
+    pub at: DateTime<Utc>,
+    pub volatility: f64,
+}
+
+/// Change between two consecutive samples.
+pub fn change(from: f64, to: f64) -> f64 {
+    if from > 0.0 && to > 0.0 {
+        (to / from).ln()
+    } else {
+        to - from
+    }
+}
+
+/// Sample standard deviation, `None` with fewer than two values.
+pub fn std_dev(values: &[f64]) -> Option<f64> {
+    if values.len() < 2 {
+        return None;
+    }
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    let variance =
+        values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (values.len() - 1) as f64;
+    Some(variance.sqrt())
+}
+
+/// Changes between consecutive samples, stamped with the later sample.
+pub fn returns(points: &[MetricPoint]) -> Vec<(DateTime<Utc>, f64)> {
+    points
+        .windows(2)
+        .map(|pair| (pair[1].sampled_at, change(pair[0].value, pair[1].value)))
+        .collect()
+}
+
+/// Volatility over the whole series.
+pub fn volatility(points: &[MetricPoint]) -> Option<f64> {
+    let returns = returns(points)
+        .into_iter()
+        .map(|(_, r)| r)
+        .collect::<Vec<_>>();
+    std_dev(&returns)
+}
+
+/// Volatility of each trailing `window` of returns.
+pub fn rolling_volatility(points: &[MetricPoint], window: usize) -> Vec<VolatilityPoint> {
+    let returns = returns(points);
+    if window < 2 {
+        return Vec::new();
+    }
+    returns
+        .windows(window)
+        .filter_map(|window| {
+            let values = window.iter().map(|(_, r)| *r).collect::<Vec<_>>();
+            Some(VolatilityPoint {
+                at: window.last()?.0,
+                volatility: std_dev(&values)?,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points(values: &[f64]) -> Vec<MetricPoint> {
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| MetricPoint {
+                value: *value,
+                sampled_at: DateTime::from_timestamp(i as i64 * 3600, 0).unwrap(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn constant_series_has_no_volatility() {
+        assert_eq!(volatility(&points(&[5.0, 5.0, 5.0])), Some(0.0));
+        assert_eq!(volatility(&points(&[5.0, 5.0])), None);
+    }
+
+    #[test]
+    fn rolling_windows() {
+        let series = points(&[1.0, 2.0, 1.0, 2.0, 4.0]);
+        let rolling = rolling_volatility(&series, 3);
+        assert_eq!(rolling.len(), 2);
+        assert_eq!(rolling[0].at.timestamp(), 3 * 3600);
+        // ln 2, -ln 2, ln 2
+        let expected = std_dev(&[2f64.ln(), -(2f64.ln()), 2f64.ln()]).unwrap();
+        assert!((rolling[0].volatility - expected).abs() < 1e-12);
+    }
+
+    #[test]
+    fn signed_values_use_differences() {
+        assert_eq!(change(-1.0, 2.0), 3.0);
+        assert!((change(1.0, std::f64::consts::E) - 1.0).abs() < 1e-12);
+    }
+}
@@ -1,29 +1,266 @@
-use kormir::EventDescriptor;
-use std::{sync::Arc, time::Duration};
-use tokio::sync::watch;
+use kormir::{storage::Storage, EventDescriptor, OracleAttestation, OracleEvent};
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Reverse,
+    collections::BinaryHeap,
+    sync::{
+        atomic::{AtomicI64, AtomicU32, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+use tokio::sync::{watch, Notify};
 
-use crate::{attestation, events::EventType, OracleServerState};
+use crate::{
+    attestation::{AttestationDataOutcome, AttestationRecord},
+    audit::SigningSource,
+    events::EventType,
+    history::PREFETCH_LEAD_SECS,
+    lock::EventLock,
+    oracle::SingleEventOutcome,
+    quorum::SourcesDisagree,
+    triggers::{self, Trigger},
+    units, OracleServerState,
+};
+
+/// Longest the watcher sleeps between ticks. Failed signings are retried and events created by
+/// other processes (e.g. the admin CLI) are picked up at this pace.
+pub const WATCHER_INTERVAL_SECS: u64 = 60;
+
+/// Upcoming times the watcher has to act at, maturities and scheduled announcements, as unix
+/// timestamps. Creating an event wakes the watcher so it can sleep until the new earliest time.
+#[derive(Debug, Default)]
+pub struct MaturitySchedule {
+    times: Mutex<BinaryHeap<Reverse<i64>>>,
+    changed: Notify,
+}
+
+impl MaturitySchedule {
+    pub fn schedule(&self, at: i64) {
+        self.times.lock().unwrap().push(Reverse(at));
+        self.changed.notify_one();
+    }
+
+    pub fn extend(&self, times: impl IntoIterator<Item = i64>) {
+        self.times
+            .lock()
+            .unwrap()
+            .extend(times.into_iter().map(Reverse));
+        self.changed.notify_one();
+    }
+
+    /// The earliest scheduled time.
+    pub fn next(&self) -> Option<i64> {
+        self.times.lock().unwrap().peek().map(|Reverse(at)| *at)
+    }
+
+    /// Drop every time up to `now`, returning whether any was due.
+    pub fn pop_due(&self, now: i64) -> bool {
+        let mut times = self.times.lock().unwrap();
+        let mut due = false;
+        while times.peek().is_some_and(|Reverse(at)| *at <= now) {
+            times.pop();
+            due = true;
+        }
+        due
+    }
+
+    /// How long to sleep from `now` until the next tick.
+    pub fn sleep_duration(&self, now: i64) -> Duration {
+        let max = WATCHER_INTERVAL_SECS as i64;
+        let secs = self
+            .next()
+            .map(|at| (at - now).clamp(0, max))
+            .unwrap_or(max);
+        Duration::from_secs(secs as u64)
+    }
+}
+
+/// Liveness of the signing loop, updated at the end of every tick.
+#[derive(Debug, Default)]
+pub struct WatcherHealth {
+    last_run: AtomicI64,
+    signed_last_run: AtomicU32,
+    backlog: AtomicU64,
+    source_error: Mutex<Option<SourceError>>,
+}
+
+/// The last failure of a data source to answer.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceError {
+    pub source: String,
+    pub error: String,
+    pub at: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatcherHealthReport {
+    pub last_run: Option<i64>,
+    pub healthy: bool,
+    /// Events signed by the last tick
+    #[serde(default)]
+    pub signed_last_run: u32,
+    /// Matured events left unsigned after the last tick, held ones included
+    #[serde(default)]
+    pub backlog: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_source_error: Option<SourceError>,
+}
+
+impl WatcherHealth {
+    pub fn record_run(&self) {
+        self.last_run
+            .store(chrono::Utc::now().timestamp(), Ordering::SeqCst);
+    }
+
+    /// Record a tick that signed `signed` events and left `backlog` matured ones unsigned, the
+    /// previous backlog is kept when it could not be counted.
+    pub fn record_tick(&self, signed: u32, backlog: Option<u64>) {
+        self.signed_last_run.store(signed, Ordering::SeqCst);
+        if let Some(backlog) = backlog {
+            self.backlog.store(backlog, Ordering::SeqCst);
+        }
+        self.record_run();
+    }
+
+    pub fn record_source_error(&self, source: &str, error: &str) {
+        *self.source_error.lock().unwrap() = Some(SourceError {
+            source: source.to_string(),
+            error: error.to_string(),
+            at: chrono::Utc::now().timestamp(),
+        });
+    }
+
+    pub fn last_run(&self) -> Option<i64> {
+        match self.last_run.load(Ordering::SeqCst) {
+            0 => None,
+            last_run => Some(last_run),
+        }
+    }
+
+    /// The watcher is considered healthy when it has completed a tick within two intervals.
+    pub fn report(&self) -> WatcherHealthReport {
+        let last_run = self.last_run();
+        let now = chrono::Utc::now().timestamp();
+        let healthy = last_run
+            .map(|last_run| now - last_run <= 2 * WATCHER_INTERVAL_SECS as i64)
+            .unwrap_or(false);
+        WatcherHealthReport {
+            last_run,
+            healthy,
+            signed_last_run: self.signed_last_run.load(Ordering::SeqCst),
+            backlog: self.backlog.load(Ordering::SeqCst),
+            last_source_error: self.source_error.lock().unwrap().clone(),
+        }
+    }
+}
 
 pub async fn sign_matured_events_loop(
     state: Arc<OracleServerState>,
     mut stop_signal: watch::Receiver<bool>,
 ) {
-    let mut timer = tokio::time::interval(Duration::from_secs(60));
+    match state.oracle.pending_schedule().await {
+        Ok(times) => state.schedule.extend(times),
+        Err(e) => log::error!("Failed to load pending maturities. error={}", e),
+    }
+    // Without a listener the watcher still signs on schedule, triggers only make it faster
+    let mut listener = match triggers::listen(&state.oracle.oracle.storage.pool).await {
+        Ok(listener) => Some(listener),
+        Err(e) => {
+            log::error!("Failed to listen for signing triggers. error={}", e);
+            None
+        }
+    };
+    sign_matured_events(state.clone()).await;
     loop {
+        let now = chrono::Utc::now().timestamp();
+        let sleep = tokio::time::sleep(state.schedule.sleep_duration(now));
+        let notification = async {
+            match listener.as_mut() {
+                Some(listener) => listener.recv().await,
+                None => std::future::pending().await,
+            }
+        };
         tokio::select! {
             _ = stop_signal.changed() => {
                 if *stop_signal.borrow() {
                     break;
                 }
             }
-            _ = timer.tick() => {
+            // Recompute the sleep when an earlier time is scheduled
+            _ = state.schedule.changed.notified() => {}
+            notification = notification => match notification {
+                Ok(notification) => {
+                    if let Some(trigger) = Trigger::from_notification(&notification) {
+                        handle_trigger(state.clone(), trigger).await;
+                    }
+                }
+                Err(e) => log::error!("Failed to receive signing trigger. error={}", e),
+            },
+            _ = sleep => {
+                state.schedule.pop_due(chrono::Utc::now().timestamp());
                 sign_matured_events(state.clone()).await;
             }
         }
     }
 }
 
-async fn sign_parlay_events(state: Arc<OracleServerState>) {
+async fn handle_trigger(state: Arc<OracleServerState>, trigger: Trigger) {
+    match trigger {
+        Trigger::EventCreated(event_id) => match state.oracle.settles_at(&event_id).await {
+            Ok(settles_at) => state.schedule.schedule(settles_at),
+            Err(e) => log::error!(
+                "Could not schedule created event. event_id={} error={:?}",
+                event_id,
+                e
+            ),
+        },
+        Trigger::SignRequested(event_id) => sign_requested_event(state, event_id).await,
+    }
+}
+
+/// Sign a single event on request, if it has matured.
+async fn sign_requested_event(state: Arc<OracleServerState>, event_id: String) {
+    let event = match state
+        .oracle
+        .oracle
+        .storage
+        .get_event(event_id.clone())
+        .await
+    {
+        Ok(Some(event)) => event,
+        Ok(None) => return log::warn!("Requested signing of unknown event. event_id={}", event_id),
+        Err(e) => {
+            return log::error!(
+                "Could not load requested event. event_id={} error={:?}",
+                event_id,
+                e
+            )
+        }
+    };
+    if let Err(e) = state.oracle.ensure_due(&event_id).await {
+        return log::warn!(
+            "Requested signing of an event that is not due. event_id={} error={}",
+            event_id,
+            e
+        );
+    }
+    let oracle_event = event.announcement.oracle_event;
+    let is_parlay = matches!(
+        &oracle_event.event_descriptor,
+        EventDescriptor::DigitDecompositionEvent(descriptor) if descriptor.unit == "parlay"
+    );
+    if is_parlay {
+        sign_parlay_event(&state, event_id).await;
+    } else {
+        sign_single_event(&state, event_id, oracle_event).await;
+    }
+}
+
+/// Sign the matured parlays, returning how many were signed.
+async fn sign_parlay_events(state: Arc<OracleServerState>) -> u32 {
     let unsiged_matured_parlay_events = match state
         .oracle
         .get_matured_unsigned_event_ids_by_type("parlay")
@@ -32,88 +269,364 @@ async fn sign_parlay_events(state: Arc<OracleServerState>) {
         Ok(events) => events,
         Err(e) => {
             log::error!("Failed to get matured unsigned parlay events. error={}", e);
-            return;
+            return 0;
         }
     };
 
+    let mut signed = 0;
     for (event_id, _) in unsiged_matured_parlay_events {
-        if let Err(error) = state.oracle.attest_parlay_contract(event_id.clone()).await {
-            log::error!(
-                "Failed to attest parlay contract. event_id={} error={}",
-                event_id,
-                error
-            );
-            continue;
-        }
+        signed += sign_parlay_event(&state, event_id).await as u32;
+    }
+    signed
+}
+
+/// Sign a matured parlay, returning whether it was signed.
+async fn sign_parlay_event(state: &OracleServerState, event_id: String) -> bool {
+    let Some(lock) = lock_event(state, &event_id).await else {
+        return false;
+    };
+    if let Err(error) = attest_parlay_event(state, &event_id, SigningSource::Watcher).await {
+        log::error!(
+            "Failed to attest parlay contract. event_id={} error={}",
+            event_id,
+            error
+        );
+        return false;
     }
+    release_event(lock).await;
+    true
 }
 
-async fn sign_single_events(state: Arc<OracleServerState>) {
-    let unsiged_matured_single_events = state
+/// Sign the matured single events, returning how many were signed.
+async fn sign_single_events(state: Arc<OracleServerState>) -> u32 {
+    let unsiged_matured_single_events = match state
         .oracle
         .get_matured_unsigned_event_ids_by_type("single")
         .await
-        .unwrap();
+    {
+        Ok(events) => events,
+        Err(e) => {
+            log::error!("Failed to get matured unsigned single events. error={}", e);
+            return 0;
+        }
+    };
 
+    let mut signed = 0;
     for (event_id, oracle_event) in unsiged_matured_single_events {
-        let unit = match &oracle_event.event_descriptor {
-            EventDescriptor::DigitDecompositionEvent(descriptor) => descriptor.unit.clone(),
-            EventDescriptor::EnumEvent(_) => continue,
-        };
-        let Ok(outcome) = EventType::outcome_from_str(&unit, &state.mempool).await else {
-            return log::error!("Could not sign for event. event_id={}", event_id);
-        };
-        if let Err(e) = state
-            .oracle
-            .oracle
-            .sign_numeric_event(event_id.clone(), outcome)
-            .await
-        {
-            return log::error!(
-                "Could not sign for event. error={} event_id={} outcome={}",
-                e.to_string(),
-                event_id,
-                outcome
-            );
+        signed += sign_single_event(&state, event_id, oracle_event).await as u32;
+    }
+    signed
+}
+
+/// Sign a matured single event, returning whether it was signed.
+async fn sign_single_event(
+    state: &OracleServerState,
+    event_id: String,
+    oracle_event: OracleEvent,
+) -> bool {
+    if let EventDescriptor::EnumEvent(_) = oracle_event.event_descriptor {
+        return false;
+    }
+    let Some(lock) = lock_event(state, &event_id).await else {
+        return false;
+    };
+    let Ok(event_type) = single_event_type(&oracle_event) else {
+        log::error!("Could not sign for event. event_id={}", event_id);
+        return false;
+    };
+    if let Err(e) = attest_single_event(state, &event_id, &event_type, SigningSource::Watcher).await
+    {
+        log::error!(
+            "Could not sign for event. error={} event_id={}",
+            e,
+            event_id
+        );
+        return false;
+    }
+    release_event(lock).await;
+    true
+}
+
+/// Sign `event_id` now, single or parlay, through the same steps as the watcher. A signed event
+/// returns its attestation, after waiting for a concurrent signer to finish, so repeated
+/// requests never sign twice.
+pub async fn sign_event(
+    state: &OracleServerState,
+    event_id: &str,
+    source: SigningSource,
+) -> anyhow::Result<OracleAttestation> {
+    let Some(lock) = state.oracle.lock_event(event_id).await? else {
+        return Err(anyhow::anyhow!(
+            "Event is being signed, try again later. event_id={}",
+            event_id
+        ));
+    };
+    if let Some(attestation) = state.oracle.signed_attestation(event_id).await? {
+        lock.release().await?;
+        return Ok(attestation);
+    }
+    let storage = &state.oracle.oracle.storage;
+    let attestation = match storage.event_type(event_id).await?.as_deref() {
+        Some("parlay") => attest_parlay_event(state, event_id, source).await?,
+        _ => {
+            let event = storage
+                .get_event(event_id.to_string())
+                .await?
+                .ok_or(anyhow::anyhow!(
+                    "Event does not exist. event_id={}",
+                    event_id
+                ))?;
+            let event_type = single_event_type(&event.announcement.oracle_event)?;
+            attest_single_event(state, event_id, &event_type, source).await?
+        }
+    };
+    lock.release().await?;
+    Ok(attestation)
+}
+
+/// The metric a single event attests, from the unit of its descriptor.
+fn single_event_type(oracle_event: &OracleEvent) -> anyhow::Result<EventType> {
+    match &oracle_event.event_descriptor {
+        EventDescriptor::DigitDecompositionEvent(descriptor) => {
+            units::event_type_from_unit(&descriptor.unit)
         }
+        EventDescriptor::EnumEvent(_) => Err(anyhow::anyhow!("Cannot sign enum descriptor.")),
+    }
+}
 
-        if let Err(e) = attestation::save_attestation_outcome(
-            &state.oracle.oracle.storage.pool,
-            event_id.clone(),
-            outcome as f64,
-            outcome as u64,
+/// Evaluate and sign a parlay under the caller's lock, holding it when its sources disagree.
+async fn attest_parlay_event(
+    state: &OracleServerState,
+    event_id: &str,
+    source: SigningSource,
+) -> anyhow::Result<OracleAttestation> {
+    let attestation = match state
+        .oracle
+        .attest_parlay_contract(event_id.to_string(), source)
+        .await
+    {
+        Ok(attestation) => attestation,
+        Err(error) => {
+            hold_on_disagreement(state, event_id, &error).await;
+            return Err(error);
+        }
+    };
+    Ok(attestation)
+}
+
+/// Fetch the outcome of a single event and sign it under the caller's lock, recording source
+/// failures and holding the event when its sources disagree.
+async fn attest_single_event(
+    state: &OracleServerState,
+    event_id: &str,
+    event_type: &EventType,
+    source: SigningSource,
+) -> anyhow::Result<OracleAttestation> {
+    let outcome = match state
+        .oracle
+        .single_event_outcome(event_id, event_type)
+        .await
+    {
+        Ok(outcome) => {
+            state.alerts.source_succeeded(state.mempool.base_url());
+            outcome
+        }
+        Err(e) if e.downcast_ref::<SourcesDisagree>().is_some() => {
+            hold_on_disagreement(state, event_id, &e).await;
+            return Err(e);
+        }
+        Err(e) => {
+            state
+                .alerts
+                .source_failed(state.mempool.base_url(), &e.to_string());
+            state
+                .watcher
+                .record_source_error(state.mempool.base_url(), &e.to_string());
+            return Err(e);
+        }
+    };
+    let SingleEventOutcome {
+        outcome,
+        provenance,
+    } = outcome;
+    let receipt = state.oracle.provenance_receipt(event_id, &provenance);
+    let attestation = state
+        .oracle
+        .sign_and_record(
+            AttestationRecord {
+                event_id: event_id.to_string(),
+                combined_score: outcome as f64,
+                attested_value: outcome,
+                clamped: false,
+                outcomes: vec![AttestationDataOutcome {
+                    event_id: event_id.to_string(),
+                    data_type: event_type.to_string(),
+                    normalized_value: outcome as f64,
+                    original_value: provenance.value,
+                    transformed_value: outcome as f64,
+                }],
+                provenance: vec![(event_type.to_string(), provenance, receipt)],
+            },
+            source,
         )
         .await
-        {
-            return log::error!(
-                "Could not save attestation outcome. error={} event_id={} outcome={}",
-                e.to_string(),
-                event_id,
-                outcome
+        .map_err(|e| anyhow::anyhow!("Could not sign. outcome={} error={}", outcome, e))?;
+
+    log::info!("Signed event. event_id={} outcome={}", event_id, outcome);
+    Ok(attestation)
+}
+
+/// Hold `event_id` and alert the operator when its data sources disagree, so it is only signed
+/// once someone looked at the sources.
+async fn hold_on_disagreement(state: &OracleServerState, event_id: &str, error: &anyhow::Error) {
+    let Some(disagree) = error.downcast_ref::<SourcesDisagree>() else {
+        return;
+    };
+    let reason = disagree.to_string();
+    if let Err(e) = state.oracle.set_hold(event_id, true, Some(&reason)).await {
+        log::error!(
+            "Could not hold event with disagreeing sources. event_id={} error={}",
+            event_id,
+            e
+        );
+    }
+    state.alerts.sources_disagree(event_id, &reason);
+}
+
+/// Take the signing lock of an unsigned event, skipping events another replica is signing or
+/// has signed.
+async fn lock_event(state: &OracleServerState, event_id: &str) -> Option<EventLock> {
+    match state.oracle.lock_unsigned_event(event_id).await {
+        Ok(Some(lock)) => Some(lock),
+        Ok(None) => {
+            log::debug!(
+                "Event is signed or locked by another signer. event_id={}",
+                event_id
             );
+            None
         }
-        if let Err(e) = attestation::save_attestation_data_outcome(
-            &state.oracle.oracle.storage.pool,
-            event_id.clone(),
-            unit,
-            outcome as f64,
-            outcome as f64,
-        )
+        Err(e) => {
+            log::error!("Could not lock event. event_id={} error={}", event_id, e);
+            None
+        }
+    }
+}
+
+async fn release_event(lock: EventLock) {
+    let event_id = lock.event_id().to_string();
+    if let Err(e) = lock.release().await {
+        log::error!(
+            "Could not release event lock. event_id={} error={}",
+            event_id,
+            e
+        );
+    }
+}
+
+/// Publish announcements whose `announce_at` has passed.
+async fn publish_scheduled_announcements(state: Arc<OracleServerState>) {
+    match state
+        .oracle
+        .oracle
+        .storage
+        .publish_due_announcements()
         .await
-        {
-            return log::error!(
-                "Could not save attestation data outcome. error={} event_id={} outcome={}",
-                e.to_string(),
+    {
+        Ok(event_ids) => {
+            // Their outbox messages are delivered now that they are announced
+            for event_id in event_ids {
+                log::info!("Published scheduled announcement. event_id={}", event_id);
+            }
+        }
+        Err(e) => log::error!("Failed to publish scheduled announcements. error={}", e),
+    }
+}
+
+/// Fetch the values of events settling within [`PREFETCH_LEAD_SECS`], so signing them does not
+/// wait on mempool.
+async fn prefetch_outcomes(state: &OracleServerState) {
+    let now = chrono::Utc::now().timestamp();
+    let events = match state
+        .oracle
+        .unsigned_events_due_by(now + PREFETCH_LEAD_SECS)
+        .await
+    {
+        Ok(events) => events,
+        Err(e) => return log::error!("Failed to get events to prefetch. error={}", e),
+    };
+    for (event_id, _) in events
+        .into_iter()
+        .filter(|(_, settles_at)| *settles_at > now)
+    {
+        if let Err(e) = state.oracle.prefetch_outcomes(&event_id).await {
+            log::warn!(
+                "Could not prefetch event values, they are fetched when signing. event_id={} error={}",
                 event_id,
-                outcome
+                e
             );
         }
-
-        return log::info!("Signed event. event_id={} outcome={}", event_id, outcome);
     }
 }
 
 async fn sign_matured_events(state: Arc<OracleServerState>) {
-    sign_parlay_events(state.clone()).await;
-    sign_single_events(state.clone()).await;
+    publish_scheduled_announcements(state.clone()).await;
+    let signed = sign_parlay_events(state.clone()).await + sign_single_events(state.clone()).await;
+    prefetch_outcomes(&state).await;
+    let now = chrono::Utc::now().timestamp();
+    state.alerts.check_overdue(&state.oracle, now).await;
+    let backlog = match state.oracle.pending_events(now).await {
+        Ok(pending) => Some(pending.len() as u64),
+        Err(e) => {
+            log::error!("Could not count pending events. error={}", e);
+            None
+        }
+    };
+    state.watcher.record_tick(signed, backlog);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schedule_sleeps_until_earliest_time() {
+        let schedule = MaturitySchedule::default();
+        let max = Duration::from_secs(WATCHER_INTERVAL_SECS);
+        assert_eq!(schedule.sleep_duration(1000), max);
+
+        schedule.extend([1030, 1010]);
+        assert_eq!(schedule.sleep_duration(1000), Duration::from_secs(10));
+        schedule.schedule(1005);
+        assert_eq!(schedule.sleep_duration(1000), Duration::from_secs(5));
+        assert_eq!(schedule.sleep_duration(1020), Duration::ZERO);
+
+        assert!(!schedule.pop_due(1000));
+        assert!(schedule.pop_due(1010));
+        assert_eq!(schedule.next(), Some(1030));
+        schedule.schedule(5000);
+        assert_eq!(schedule.sleep_duration(1000), Duration::from_secs(30));
+        assert!(schedule.pop_due(5000));
+        assert_eq!(schedule.sleep_duration(5000), max);
+    }
+
+    #[test]
+    fn watcher_health_report() {
+        let health = WatcherHealth::default();
+        let report = health.report();
+        assert_eq!(report.last_run, None);
+        assert!(!report.healthy);
+
+        health.record_run();
+        let report = health.report();
+        assert!(report.last_run.is_some());
+        assert!(report.healthy);
+
+        health.record_source_error("https://mempool.space/api/v1", "timed out");
+        health.record_tick(2, Some(5));
+        health.record_tick(0, None);
+        let report = health.report();
+        assert_eq!(report.signed_last_run, 0);
+        assert_eq!(report.backlog, 5);
+        assert_eq!(report.last_source_error.unwrap().error, "timed out");
+    }
 }
@@ -0,0 +1,69 @@
+use std::collections::HashMap;
+
+use ernest_oracle::{
+    events::EventType,
+    harness::{HarnessConfig, OracleHarness},
+    mock_data::{MockDataConfig, MockValue},
+    series::CreateSeries,
+};
+
+#[tokio::test]
+async fn creates_matures_and_signs() {
+    let oracle = OracleHarness::start(HarnessConfig {
+        binary: env!("CARGO_BIN_EXE_oracle").into(),
+        database_url: std::env::var("DATABASE_URL").expect("DATABASE_URL is not set"),
+        mock_data: MockDataConfig {
+            values: HashMap::from([(EventType::Hashrate, MockValue::Constant { value: 650.0 })]),
+            ..Default::default()
+        },
+    })
+    .await
+    .unwrap();
+
+    let announcement = oracle.create_single(EventType::Hashrate, 2).await.unwrap();
+    let event_id = &announcement.oracle_event.event_id;
+    let attestation = oracle.attestation(event_id).await.unwrap();
+    assert_eq!(attestation.event_id, *event_id);
+    assert_eq!(
+        attestation.oracle_public_key,
+        announcement.oracle_public_key
+    );
+
+    let outcome = oracle
+        .client
+        .get_attestation_outcome(event_id)
+        .await
+        .unwrap();
+    // Hundredths of an EH/s
+    assert_eq!(outcome.attested_value, 65_000);
+
+    // Events of a series get a creation receipt like those created one by one
+    let manifest = oracle
+        .client
+        .create_series(&CreateSeries {
+            event_type: EventType::Hashrate,
+            first_maturity: chrono::Utc::now().timestamp() as u32 + 3600,
+            interval_secs: 3600,
+            count: 2,
+            percentile: None,
+            aggregation: None,
+            precision: None,
+            nb_digits: None,
+            description: None,
+            tags: vec![],
+            announce_at: None,
+            settlement_delay: None,
+        })
+        .await
+        .unwrap();
+    for entry in &manifest.events {
+        let receipt = oracle
+            .client
+            .get_creation_receipt(&entry.event_id)
+            .await
+            .unwrap();
+        assert!(receipt.verify(&announcement.oracle_public_key));
+    }
+
+    oracle.stop().await.unwrap();
+}